@@ -1,7 +1,7 @@
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 fn main() {
@@ -27,4 +27,50 @@ fn main() {
             .as_slice(),
     )
     .unwrap();
+
+    write_runtime_files(&out_dir);
+}
+
+/// Lists our runtime files (and includes their contents) in
+/// `runtime_files.rs`, so they are embedded in the binary (see
+/// `ui::resources`).
+fn write_runtime_files(out_dir: &str) {
+    let root =
+        Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("runtime");
+    let mut files = vec![];
+    collect_files(&root, &mut files);
+    files.sort();
+
+    let mut data = String::new();
+    data.push_str("const RUNTIME_FILES: &[(&str, &[u8])] = &[\n");
+    for file in files {
+        // Always with forward slashes, which work on all platforms.
+        let name = file
+            .strip_prefix(&root)
+            .unwrap()
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        data.push_str(&format!(
+            "    ({:?}, include_bytes!({:?})),\n",
+            name, file
+        ));
+    }
+    data.push_str("];\n");
+
+    let mut f =
+        File::create(Path::new(out_dir).join("runtime_files.rs")).unwrap();
+    f.write_all(data.as_bytes()).unwrap();
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
 }
@@ -0,0 +1,38 @@
+" Cell widths that were set before gnvim's (e.g. in init.vim). They take
+" precedence over the ones from gnvim's preferences.
+let s:user = v:null
+
+" Sets the cell widths from gnvim's preferences (a list like the argument of
+" setcellwidths()) together with the user's own ones, and tells gnvim the
+" result.
+function! gnvim#cell_widths#set(widths)
+    if exists('*setcellwidths')
+        if s:user is v:null
+            let s:user = exists('*getcellwidths') ? getcellwidths() : []
+        endif
+
+        let l:widths = copy(s:user)
+        for l:w in a:widths
+            let l:overlap = filter(copy(s:user),
+                        \ {_, u -> u[0] <= l:w[1] && l:w[0] <= u[1]})
+            if empty(l:overlap)
+                call add(l:widths, l:w)
+            endif
+        endfor
+
+        try
+            call setcellwidths(sort(l:widths, {a, b -> a[0] - b[0]}))
+        catch
+            echoerr 'Gnvim: failed to set the cell widths: ' . v:exception
+        endtry
+    endif
+
+    call gnvim#cell_widths#update()
+endfunction
+
+" Tells gnvim the current cell widths and 'ambiwidth'.
+function! gnvim#cell_widths#update()
+    let l:widths = exists('*getcellwidths') ? getcellwidths() : []
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'CellWidths', l:widths,
+                \ &ambiwidth ==# 'double')
+endfunction
@@ -0,0 +1,3 @@
+function! gnvim#cmdline#set_entry_mode(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'CmdlineSetEntryMode', a:enable ? 1 : 0)
+endfunction
@@ -0,0 +1,11 @@
+function! gnvim#cursor#set_crosshair(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'CursorSetCrosshair', a:enable ? 1 : 0)
+endfunction
+
+function! gnvim#cursor#set_beacon(rows)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'CursorSetBeacon', a:rows)
+endfunction
+
+function! gnvim#cursor#set_mode_flash(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'CursorSetModeFlash', a:enable ? 1 : 0)
+endfunction
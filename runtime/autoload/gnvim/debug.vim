@@ -0,0 +1,15 @@
+function! gnvim#debug#rpc_trace(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'RpcTrace', a:enable ? 1 : 0)
+endfunction
+
+function! gnvim#debug#show_console()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'ShowDebugConsole')
+endfunction
+
+function! gnvim#debug#profile(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'Profile', a:enable ? 1 : 0)
+endfunction
+
+function! gnvim#debug#perf_hud(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'PerfHud', a:enable ? 1 : 0)
+endfunction
@@ -0,0 +1,12 @@
+function! gnvim#gesture#set(gesture, input)
+    if a:gesture !~# '^[LRUD]\+$'
+        echoerr 'Gnvim: invalid gesture "' . a:gesture . '"'
+        return
+    endif
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'GestureSet', a:gesture, a:input)
+endfunction
+
+function! gnvim#gesture#remove(gesture)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'GestureSet', a:gesture, '')
+endfunction
@@ -0,0 +1,11 @@
+function! gnvim#grid#set_rounded_selection(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'RoundedSelection', a:enable ? 1 : 0)
+endfunction
+
+function! gnvim#grid#show_damage(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'GridShowDamage', a:enable ? 1 : 0)
+endfunction
+
+function! gnvim#grid#set_dithering(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'GridSetDithering', a:enable ? 1 : 0)
+endfunction
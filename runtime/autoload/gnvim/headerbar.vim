@@ -0,0 +1,31 @@
+" Shows the current file's path in the headerbar (with --headerbar), unless
+" the breadcrumb is disabled with g:gnvim_headerbar_breadcrumb. Without a
+" path, nvim's title is shown.
+function! gnvim#headerbar#update()
+    let path = ''
+    if get(g:, 'gnvim_headerbar_breadcrumb', 1) && empty(&buftype)
+        let path = expand('%:p')
+    endif
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'HeaderbarPath', path)
+endfunction
+
+" Opens `dir` (clicked in the headerbar's breadcrumb) with g:GnvimBrowse,
+" if the user has set it, or in netrw in a floating window.
+function! gnvim#headerbar#browse(dir)
+    if exists('g:GnvimBrowse')
+        call g:GnvimBrowse(a:dir)
+        return
+    endif
+
+    let width = min([&columns - 4, 80])
+    let height = min([&lines - 4, 20])
+    let buf = nvim_create_buf(v:false, v:true)
+    call nvim_open_win(buf, v:true, {
+                \ 'relative': 'editor',
+                \ 'row': 1,
+                \ 'col': (&columns - width) / 2,
+                \ 'width': width,
+                \ 'height': height,
+                \ })
+    execute 'edit ' . fnameescape(a:dir)
+endfunction
@@ -0,0 +1,61 @@
+" Returns the (zero based) screen cells of window info's text area, as
+" [top, bot, left, right], where bot and right are exclusive.
+function! s:rect(info)
+    let top = a:info.winrow - 1
+    let left = a:info.wincol - 1
+    return [top, top + a:info.height, left, left + a:info.width]
+endfunction
+
+" Returns the rect (see s:rect) of the window at screen cell (row, col), or
+" an empty list if there's no window there. Floating windows are listed
+" after the others, so they win over the windows under them.
+function! s:window_at(row, col)
+    let rect = []
+    for info in getwininfo()
+        if info.tabnr != tabpagenr()
+            continue
+        endif
+
+        let r = s:rect(info)
+        if a:row >= r[0] && a:row < r[1] && a:col >= r[2] && a:col < r[3]
+            let rect = r
+        endif
+    endfor
+    return rect
+endfunction
+
+function! s:show(id, path, row, col, rect, max_cols, max_rows)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'ImageShow', a:id,
+                \ fnamemodify(expand(a:path), ':p'), a:row, a:col,
+                \ a:max_cols, a:max_rows, a:rect)
+endfunction
+
+" Shows image at screen cell (row, col), zero based. The image is scaled
+" down to max_cols x max_rows cells (zero meaning no limit), and clipped to
+" the window it's in.
+function! gnvim#image#show(id, path, row, col, ...)
+    call s:show(a:id, a:path, a:row, a:col, s:window_at(a:row, a:col),
+                \ get(a:, 1, 0), get(a:, 2, 0))
+endfunction
+
+" Like gnvim#image#show, but at line lnum and column col of the current
+" window. Returns 0 if the position isn't visible.
+function! gnvim#image#show_at(id, path, lnum, col, ...)
+    let pos = screenpos(win_getid(), a:lnum, a:col)
+    if pos.row == 0
+        return 0
+    endif
+
+    call s:show(a:id, a:path, pos.row - 1, pos.col - 1,
+                \ s:rect(getwininfo(win_getid())[0]),
+                \ get(a:, 1, 0), get(a:, 2, 0))
+    return 1
+endfunction
+
+function! gnvim#image#hide(id)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'ImageHide', a:id)
+endfunction
+
+function! gnvim#image#clear()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'ImageClear')
+endfunction
@@ -0,0 +1,56 @@
+function! gnvim#input#set_hook(function)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'InputHookSet', a:function)
+endfunction
+
+function! gnvim#input#clear_hook()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'InputHookSet', '')
+endfunction
+
+" Called by gnvim for every input while the input hook is set. Returns the
+" input passed through the hook, which gnvim sends to nvim. If the hook fails
+" or took more than 50ms, it is removed. The time can only be checked after
+" the hook has returned, so a slow hook still blocks nvim for that one call.
+function! gnvim#input#process(function, input)
+    let start = reltime()
+    try
+        let res = eval(a:function . '(a:input)')
+    catch
+        call s:remove_hook(a:function, v:exception)
+        let res = a:input
+    endtry
+
+    if type(res) != v:t_string
+        let res = a:input
+    endif
+
+    if reltimefloat(reltime(start)) > 0.05
+        call s:remove_hook(a:function, 'took more than 50ms')
+    endif
+
+    return res
+endfunction
+
+function! s:remove_hook(function, reason)
+    call gnvim#input#clear_hook()
+    echom 'Gnvim: removed input hook ' . a:function . ' (' . a:reason . ')'
+endfunction
+
+function! gnvim#input#set_option_as_meta(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'InputSetOptionAsMeta', a:enable)
+endfunction
+
+" Feeds sequence to nvim as if it was typed. Options: delay (between the
+" keys, in milliseconds) and show (if the keys are shown on the screen).
+function! gnvim#input#simulate(sequence, ...)
+    let opts = get(a:, 1, {})
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'InputSimulate', a:sequence,
+                \ get(opts, 'delay', 100), get(opts, 'show', 1))
+endfunction
+
+function! gnvim#input#stop_simulation()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'InputSimulateStop')
+endfunction
+
+function! gnvim#input#key_display(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'InputKeyDisplay', a:enable ? 1 : 0)
+endfunction
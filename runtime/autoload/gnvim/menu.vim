@@ -0,0 +1,16 @@
+function! gnvim#menu#update_mousemodel()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'MenuSetMouseModel', &mousemodel)
+endfunction
+
+function! gnvim#menu#add(label, command)
+    if empty(a:command)
+        echoerr 'Gnvim: empty menu command'
+        return
+    endif
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'MenuSetEntry', a:label, a:command)
+endfunction
+
+function! gnvim#menu#remove(label)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'MenuSetEntry', a:label, '')
+endfunction
@@ -0,0 +1,3 @@
+function! gnvim#mouse#update()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'SetMouse', &mouse)
+endfunction
@@ -0,0 +1,34 @@
+function! gnvim#notify#send(msg, level)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'DesktopNotify', a:msg, a:level)
+endfunction
+
+function! gnvim#notify#set_level(level)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'DesktopNotifySetLevel', a:level)
+endfunction
+
+" Passes the messages of vim.notify() to gnvim, in addition to the original
+" handler. vim.notify() can be called from fast callbacks (e.g. the ones of
+" vim.loop), where vimscript functions and variables aren't available, so
+" the channel is read here and the message is sent with vim.rpcnotify().
+function! gnvim#notify#enable(enable)
+    if a:enable
+        lua << trim EOF
+            if not _G.gnvim_orig_notify then
+                local channel = vim.g.gnvim_channel_id
+                _G.gnvim_orig_notify = vim.notify
+                vim.notify = function(msg, level, opts)
+                    vim.rpcnotify(channel, 'Gnvim', 'DesktopNotify',
+                        tostring(msg), level or vim.log.levels.INFO)
+                    return _G.gnvim_orig_notify(msg, level, opts)
+                end
+            end
+        EOF
+    else
+        lua << trim EOF
+            if _G.gnvim_orig_notify then
+                vim.notify = _G.gnvim_orig_notify
+                _G.gnvim_orig_notify = nil
+            end
+        EOF
+    endif
+endfunction
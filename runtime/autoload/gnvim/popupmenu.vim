@@ -10,3 +10,11 @@ function! gnvim#popupmenu#toggle_details()
     call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'CompletionMenuToggleInfo')
     return ''
 endfunction
+
+function! gnvim#popupmenu#set_max_rows(rows)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'PopupmenuSetMaxRows', a:rows)
+endfunction
+
+function! gnvim#popupmenu#set_ghost_text(enable)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'PopupmenuSetGhostText', a:enable)
+endfunction
@@ -0,0 +1,3 @@
+function! gnvim#preferences#show()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'ShowPreferences')
+endfunction
@@ -0,0 +1,110 @@
+" Highlight attributes that gnvim understands (see nvim_get_hl_by_name()).
+let s:hl_keys = ['foreground', 'background', 'special', 'reverse', 'italic',
+            \ 'bold', 'underline', 'undercurl']
+
+" Collects the lines from line1 to line2 of the current buffer as chunks of
+" text and their highlight. The highlights come from the syntax, treesitter,
+" semantic tokens and the extmarks (see vim.inspect_pos()).
+lua << trim EOF
+    function _G.gnvim_print_chunks(line1, line2, hl_keys)
+        local buf = vim.api.nvim_get_current_buf()
+        local ts = vim.bo[buf].tabstop
+        local hls, ids = {}, {}
+        local keys = {}
+        for _, key in ipairs(hl_keys) do
+            keys[key] = true
+        end
+
+        local function hl_index(group)
+            if ids[group] == nil then
+                local attrs = {}
+                local ok, hl = pcall(vim.api.nvim_get_hl_by_name, group, true)
+                if ok then
+                    for key, value in pairs(hl) do
+                        if keys[key] then
+                            attrs[key] = value
+                        end
+                    end
+                end
+                table.insert(hls, attrs)
+                ids[group] = #hls - 1
+            end
+            return ids[group]
+        end
+
+        local function group_at(row, col)
+            if not vim.inspect_pos then
+                local id = vim.fn.synIDtrans(vim.fn.synID(row + 1, col + 1, 1))
+                return vim.fn.synIDattr(id, 'name')
+            end
+
+            -- The later lists have a higher priority.
+            local pos = vim.inspect_pos(buf, row, col)
+            local group = ''
+            for _, item in ipairs(pos.syntax) do
+                group = item.hl_group_link or item.hl_group
+            end
+            for _, item in ipairs(pos.treesitter) do
+                group = item.hl_group_link or item.hl_group
+            end
+            for _, list in ipairs({ pos.semantic_tokens, pos.extmarks }) do
+                for _, item in ipairs(list) do
+                    if item.opts and item.opts.hl_group then
+                        group = item.opts.hl_group
+                    end
+                end
+            end
+            return group
+        end
+
+        hl_index('Normal')
+        hl_index('LineNr')
+
+        local lines = {}
+        local rows = vim.api.nvim_buf_get_lines(buf, line1 - 1, line2, false)
+        for i, line in ipairs(rows) do
+            local row = line1 + i - 2
+            local chunks = {}
+            local vcol = 0
+            for c = 0, vim.str_utfindex(line) - 1 do
+                local s = vim.str_byteindex(line, c)
+                local e = vim.str_byteindex(line, c + 1)
+                local text = line:sub(s + 1, e)
+                if text == '\t' then
+                    text = string.rep(' ', ts - vcol % ts)
+                end
+                vcol = vcol + vim.fn.strdisplaywidth(text)
+
+                local hl = hl_index(group_at(row, s))
+                local last = chunks[#chunks]
+                if last and last[2] == hl then
+                    last[1] = last[1] .. text
+                else
+                    table.insert(chunks, { text, hl })
+                end
+            end
+            table.insert(lines, chunks)
+        end
+
+        return { hls, lines }
+    end
+EOF
+
+" Prints the lines from line1 to line2 of the current buffer. If a PDF file
+" is given, the lines are written to it instead of showing the print dialog.
+function! gnvim#print#buffer(line1, line2, ...)
+    let pdf = get(a:, 1, '')
+    if pdf !=# ''
+        let pdf = fnamemodify(expand(pdf), ':p')
+    endif
+
+    let title = expand('%:~:.')
+    if title ==# ''
+        let title = '[No Name]'
+    endif
+
+    let [hls, lines] = v:lua.gnvim_print_chunks(a:line1, a:line2, s:hl_keys)
+    let first_lnum = get(g:, 'gnvim_print_line_numbers', &number) ? a:line1 : 0
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'Print', title, pdf,
+                \ first_lnum, hls, lines)
+endfunction
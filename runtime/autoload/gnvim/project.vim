@@ -0,0 +1,3 @@
+function! gnvim#project#update()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'ProjectDirChanged', getcwd())
+endfunction
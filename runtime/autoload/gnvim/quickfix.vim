@@ -0,0 +1,80 @@
+" Id and changedtick of the lists we've last sent, to tell when they change.
+let s:sent = {}
+
+function! gnvim#quickfix#update(loclist)
+    if a:loclist
+        let l:list = getloclist(0, {'items': 1, 'title': 1})
+    else
+        let l:list = getqflist({'items': 1, 'title': 1})
+    endif
+    let s:sent[a:loclist] = s:tick(a:loclist)
+
+    let l:items = []
+    for l:item in get(l:list, 'items', [])
+        let l:filename = l:item.valid ? bufname(l:item.bufnr) : ''
+        call add(l:items, [l:filename, l:item.lnum, l:item.col, l:item.type,
+                    \ l:item.text])
+    endfor
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'QuickfixSet',
+                \ a:loclist, get(l:list, 'title', ''), l:items)
+endfunction
+
+function! gnvim#quickfix#show(loclist)
+    call gnvim#quickfix#update(a:loclist)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'QuickfixShow', 1)
+endfunction
+
+function! gnvim#quickfix#hide()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'QuickfixShow', 0)
+endfunction
+
+function! gnvim#quickfix#enable(enable)
+    augroup GnvimQuickfix
+        autocmd!
+        if a:enable
+            autocmd QuickFixCmdPost [^l]* call gnvim#quickfix#update(0)
+            autocmd QuickFixCmdPost l* call gnvim#quickfix#update(1)
+            autocmd WinEnter * call s:sync()
+            autocmd FileType qf call s:replace_window()
+        endif
+    augroup END
+
+    " Lists set without a command (e.g. with setqflist(), which is how
+    " diagnostics are usually put to the list) have no autocmd, so we check
+    " for changes with a timer.
+    if exists('s:timer')
+        call timer_stop(s:timer)
+        unlet s:timer
+    endif
+    if a:enable
+        let s:timer = timer_start(500, {-> s:sync()}, {'repeat': -1})
+    endif
+endfunction
+
+" Returns the id and changedtick of the current quickfix list, or the
+" current window's location list.
+function! s:tick(loclist)
+    let l:what = {'id': 0, 'changedtick': 0}
+    return a:loclist ? getloclist(0, l:what) : getqflist(l:what)
+endfunction
+
+" Sends the lists that have changed since they were last sent.
+function! s:sync()
+    for l:loclist in [0, 1]
+        if s:tick(l:loclist) != get(s:sent, l:loclist, {})
+            call gnvim#quickfix#update(l:loclist)
+        endif
+    endfor
+endfunction
+
+" Shows the panel instead of the quickfix (or location list) window, which
+" is being opened.
+function! s:replace_window()
+    let l:loclist = get(getwininfo(win_getid())[0], 'loclist', 0)
+    call gnvim#quickfix#show(l:loclist)
+
+    " The window can't be closed while it's being opened.
+    let l:cmd = l:loclist ? 'lclose' : 'cclose'
+    call timer_start(0, {-> execute(l:cmd)})
+endfunction
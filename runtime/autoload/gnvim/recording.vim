@@ -0,0 +1,6 @@
+" Shows (or with an empty register, hides) the recording indicator, unless
+" it's disabled with g:gnvim_recording_indicator.
+function! gnvim#recording#update(register)
+    let register = get(g:, 'gnvim_recording_indicator', 1) ? a:register : ''
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'RecordingSet', register)
+endfunction
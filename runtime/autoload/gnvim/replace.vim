@@ -0,0 +1,28 @@
+function! gnvim#replace#preview(pattern, replacement)
+    let l:matches = []
+    let l:lnum = 1
+    for l:line in getline(1, '$')
+        if l:line =~ a:pattern
+            let l:replaced = substitute(l:line, a:pattern, a:replacement, 'g')
+            call add(l:matches, [l:lnum, l:line, l:replaced])
+        endif
+        let l:lnum += 1
+    endfor
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'ReplacePreview',
+                \ bufnr('%'), b:changedtick, a:pattern, a:replacement,
+                \ l:matches)
+endfunction
+
+function! gnvim#replace#apply(bufnr, changedtick, pattern, replacement, lnums)
+    if getbufvar(a:bufnr, 'changedtick') != a:changedtick
+        echoerr 'Gnvim: buffer has changed since the preview, not replacing'
+        return
+    endif
+
+    for l:lnum in a:lnums
+        let l:line = getbufline(a:bufnr, l:lnum)[0]
+        let l:replaced = substitute(l:line, a:pattern, a:replacement, 'g')
+        call setbufline(a:bufnr, l:lnum, l:replaced)
+    endfor
+endfunction
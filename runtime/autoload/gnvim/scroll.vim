@@ -0,0 +1,31 @@
+" Remembers the view that is left, for gnvim#scroll#enter.
+function! gnvim#scroll#leave()
+    let s:left = {'win': win_getid(), 'topline': line('w0')}
+endfunction
+
+" Animates the current window's view from the view that was left, if it's
+" enabled with g:gnvim_scroll_restore. Without `tab`, only when the buffer
+" was switched in the same window.
+function! gnvim#scroll#enter(tab)
+    if !exists('s:left')
+        return
+    endif
+    let l:left = s:left
+    unlet s:left
+
+    if !get(g:, 'gnvim_scroll_restore', 0)
+                \ || (!a:tab && l:left.win != win_getid())
+                \ || nvim_win_get_config(0).relative != ''
+        return
+    endif
+
+    let l:rows = line('w0') - l:left.topline
+    if l:rows == 0
+        return
+    endif
+
+    let [l:row, l:col] = win_screenpos(0)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'ScrollRestore',
+                \ l:row - 1, l:row - 1 + winheight(0),
+                \ l:col - 1, l:col - 1 + winwidth(0), l:rows)
+endfunction
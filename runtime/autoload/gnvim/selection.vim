@@ -0,0 +1,93 @@
+" Text of the current selection that was copied last, so that the same
+" selection isn't copied again every time the cursor stops.
+let s:copied = ''
+
+" Time (in ms) after the last cursor move before the selection is copied.
+" Getting the text takes time with large selections, too much to do it on
+" every move.
+let s:delay = 100
+let s:timer = -1
+
+" Called when the cursor moves, copies the selection once the cursor stops.
+function! gnvim#selection#moved()
+    if empty(get(g:, 'gnvim_copy_on_select', ''))
+                \ || mode() !~# "^[vV\<C-v>]"
+        return
+    endif
+
+    call timer_stop(s:timer)
+    let s:timer = timer_start(s:delay, {_ -> gnvim#selection#changed()})
+endfunction
+
+" Copies the visual selection to the selection set in g:gnvim_copy_on_select
+" ('primary' or 'clipboard'), if the selection has changed.
+function! gnvim#selection#changed()
+    let target = get(g:, 'gnvim_copy_on_select', '')
+    let mode = mode()
+    if empty(target) || mode !~# "^[vV\<C-v>]"
+        " Next selection is copied even if it's the same as the last one.
+        let s:copied = ''
+        return
+    endif
+
+    " With $, the block extends to the end of each line.
+    let to_end = mode ==# "\<C-v>" && getcurpos()[4] >= 2147483647
+    call s:copy(target, gnvim#selection#text(
+                \ mode, getpos('v')[1:2], getpos('.')[1:2], to_end))
+endfunction
+
+" Called when the visual `mode` ends. Copies the final selection (from the
+" '< and '> marks), in case the cursor didn't stop before that.
+function! gnvim#selection#ended(mode)
+    call timer_stop(s:timer)
+
+    let target = get(g:, 'gnvim_copy_on_select', '')
+    if !empty(target)
+        let to_end = a:mode ==# "\<C-v>" && getcurpos()[4] >= 2147483647
+        call s:copy(target, gnvim#selection#text(
+                    \ a:mode, getpos("'<")[1:2], getpos("'>")[1:2], to_end))
+    endif
+
+    let s:copied = ''
+endfunction
+
+function! s:copy(target, text)
+    if a:text ==# s:copied
+        return
+    endif
+
+    let s:copied = a:text
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'CopySelection',
+                \ a:target, a:text)
+endfunction
+
+" Returns the text between the positions `start` and `end` ([lnum, col], col
+" being the byte index) of the current buffer, selected in the visual `mode`.
+" If `to_end` is set, a blockwise selection extends to the end of the lines.
+function! gnvim#selection#text(mode, start, end, to_end)
+    let [start, end] = sort([a:start, a:end],
+                \ {a, b -> a[0] == b[0] ? a[1] - b[1] : a[0] - b[0]})
+    let lines = getline(start[0], end[0])
+
+    if a:mode ==# 'V'
+        return join(lines, "\n")
+    elseif a:mode ==# 'v'
+        " The end column is the first byte of the last character.
+        let last = matchstr(lines[-1], '.', end[1] - 1)
+        let lines[-1] = strpart(lines[-1], 0, end[1] - 1 + len(last))
+        let lines[0] = strpart(lines[0], start[1] - 1)
+        return join(lines, "\n")
+    endif
+
+    " Blockwise selection is by the screen columns.
+    let start_vcol = virtcol(a:start)
+    let end_vcol = virtcol(a:end)
+    let left = min([start_vcol, end_vcol])
+    let pattern = '\%>' . (left - 1) . 'v.*'
+    if !a:to_end
+        let right = max([start_vcol, end_vcol])
+        let pattern .= '\%<' . (right + 2) . 'v'
+    endif
+
+    return join(map(lines, {_, line -> matchstr(line, pattern)}), "\n")
+endfunction
@@ -0,0 +1,30 @@
+function! gnvim#session#save()
+    if !exists('g:gnvim_session_file') || !get(g:, 'gnvim_session', 1)
+        return
+    endif
+
+    " Don't replace the previous session with an empty one.
+    let l:buffers = filter(getbufinfo({'buflisted': 1}), '!empty(v:val.name)')
+    if empty(l:buffers)
+        return
+    endif
+
+    " Options (e.g. mappings) come from the user's config, leave them out.
+    let l:ssop = &sessionoptions
+    set sessionoptions-=options sessionoptions+=curdir
+    execute 'mksession! ' . fnameescape(g:gnvim_session_file)
+    let &sessionoptions = l:ssop
+
+    " The font (e.g. after zooming) is restored too.
+    call writefile(['let &guifont = ' . string(&guifont)],
+                \ g:gnvim_session_file, 'a')
+endfunction
+
+function! gnvim#session#restore()
+    if !exists('g:gnvim_session_file') || !filereadable(g:gnvim_session_file)
+        echoerr 'Gnvim: no session to restore'
+        return
+    endif
+
+    execute 'source ' . fnameescape(g:gnvim_session_file)
+endfunction
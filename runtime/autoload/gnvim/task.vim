@@ -0,0 +1,22 @@
+function! gnvim#task#begin(name)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'TaskBegin', a:name)
+endfunction
+
+function! gnvim#task#update(progress)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'TaskUpdate',
+                \ max([0, float2nr(a:progress)]))
+endfunction
+
+" Status is "success" or "failure", or an exit code (zero is success).
+function! gnvim#task#end(status)
+    if type(a:status) == v:t_number
+        let l:status = a:status == 0 ? 'success' : 'failure'
+    elseif index(['success', 'failure'], a:status) >= 0
+        let l:status = a:status
+    else
+        echoerr 'Gnvim: invalid task status "' . a:status . '"'
+        return
+    endif
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'TaskEnd', l:status)
+endfunction
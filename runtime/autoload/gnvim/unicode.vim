@@ -0,0 +1,6 @@
+" Shows the popover for entering a character by its codepoint or name, at
+" the cursor. The character is inserted at the cursor.
+function! gnvim#unicode#input()
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'UnicodeInputShow',
+                \ screenrow() - 1, screencol() - 1)
+endfunction
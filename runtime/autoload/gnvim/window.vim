@@ -0,0 +1,60 @@
+function! gnvim#window#new()
+    let l:address = v:servername
+
+    " Gnvim can't connect to named pipes, so on Windows we'll listen on a
+    " (local) TCP address for the new window.
+    if has('win32')
+        let l:address = serverstart('127.0.0.1:0')
+    endif
+
+    if l:address == ''
+        echoerr 'Gnvim: nvim has no server address (see :h v:servername)'
+        return
+    endif
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'NewWindow', l:address)
+endfunction
+
+function! gnvim#window#set_max_fps(fps)
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'SetMaxFps', a:fps)
+endfunction
+
+let s:snaps = ['left', 'right', 'top', 'bottom',
+            \ 'top-left', 'top-right', 'bottom-left', 'bottom-right']
+
+function! gnvim#window#snap(snap)
+    if index(s:snaps, a:snap) < 0
+        echoerr 'Gnvim: invalid snap "' . a:snap . '"'
+        return
+    endif
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'WindowSnap', a:snap)
+endfunction
+
+function! gnvim#window#complete_snap(arglead, cmdline, cursorpos)
+    return filter(copy(s:snaps), 'v:val =~# "^" . a:arglead')
+endfunction
+
+" Animates a property of the window. See :h gnvim#window#animate.
+function! gnvim#window#animate(opts)
+    let l:ToList = {val -> type(val) == v:t_list ? val : [val]}
+    let l:relative = has_key(a:opts, 'by')
+    let l:to = l:relative ? a:opts.by : get(a:opts, 'to', [])
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'WindowAnimate',
+                \ get(a:opts, 'property', ''),
+                \ l:ToList(get(a:opts, 'from', [])),
+                \ l:ToList(l:to),
+                \ l:relative ? 1 : 0,
+                \ get(a:opts, 'duration', 300),
+                \ get(a:opts, 'easing', 'ease-out'))
+endfunction
+
+function! gnvim#window#set_wm_class(name)
+    if a:name == ''
+        echoerr 'Gnvim: empty window class'
+        return
+    endif
+
+    call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'SetWmClass', a:name)
+endfunction
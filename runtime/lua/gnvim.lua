@@ -0,0 +1,37 @@
+-- Lua API of gnvim, see |gnvim-lua|.
+--
+-- The functions only send notifications with vim.rpcnotify(), so unlike
+-- the vimscript functions they can be called from fast callbacks (e.g. the
+-- ones of vim.loop, or a job's on_exit). vim.g isn't available there, so the
+-- channel is read when the module is loaded.
+local M = {}
+
+local channel = vim.g.gnvim_channel_id
+
+local function send(event, ...)
+    vim.rpcnotify(channel, 'Gnvim', event, ...)
+end
+
+-- Starts showing the progress of a task, see |gnvim-task|.
+function M.task_begin(name)
+    send('TaskBegin', name)
+end
+
+-- Sets the progress of the current task, in percents.
+function M.task_update(progress)
+    send('TaskUpdate', math.max(0, math.floor(progress)))
+end
+
+-- Finishes the current task. Status is "success" or "failure", or an exit
+-- code (zero is success).
+function M.task_end(status)
+    if type(status) == 'number' then
+        status = status == 0 and 'success' or 'failure'
+    elseif status ~= 'success' and status ~= 'failure' then
+        error('Gnvim: invalid task status "' .. tostring(status) .. '"')
+    end
+
+    send('TaskEnd', status)
+end
+
+return M
@@ -3,6 +3,10 @@ if has("g:gnvim_runtime_loaded")
 endif
 
 let g:gnvim_runtime_loaded = 1
+" Gnvim checks that the runtime matches the binary (see RUNTIME_VERSION in
+" src/ui/runtime_check.rs).
+let g:gnvim_runtime_version = 1
+let g:gnvim_runtime_path = expand('<sfile>:p:h:h')
 
 function! gnvim#get_hl_term(group, term)
     " Store output of group to variable
@@ -34,6 +38,8 @@ function! gnvim#set_gui_colors()
                 \ 'wildmenu_fg': gnvim#get_hl_term('Pmenu', 'guifg'),
                 \ 'wildmenusel_bg': gnvim#get_hl_term('PmenuSel', 'guibg'),
                 \ 'wildmenusel_fg': gnvim#get_hl_term('PmenuSel', 'guifg'),
+                \
+                \ 'visual_bg': gnvim#get_hl_term('Visual', 'guibg'),
                 \}
 
     call rpcnotify(g:gnvim_channel_id, 'Gnvim', 'SetGuiColors', colors)
@@ -45,4 +51,75 @@ augroup GnvimColors
     autocmd VimEnter * call gnvim#set_gui_colors()
 augroup END
 
+augroup GnvimMouseModel
+    autocmd!
+    autocmd OptionSet mousemodel call gnvim#menu#update_mousemodel()
+    autocmd VimEnter * call gnvim#menu#update_mousemodel()
+augroup END
+
+augroup GnvimMouse
+    autocmd!
+    autocmd OptionSet mouse call gnvim#mouse#update()
+    autocmd VimEnter * call gnvim#mouse#update()
+augroup END
+
+augroup GnvimProject
+    autocmd!
+    autocmd DirChanged * call gnvim#project#update()
+    autocmd VimEnter * call gnvim#project#update()
+augroup END
+
+" RecordingEnter and RecordingLeave are new in nvim 0.7.
+augroup GnvimRecording
+    autocmd!
+    if exists('##RecordingEnter')
+        autocmd RecordingEnter * call gnvim#recording#update(reg_recording())
+        autocmd RecordingLeave * call gnvim#recording#update('')
+    endif
+augroup END
+
+" ModeChanged is new in nvim 0.6. Before it, the selection is only copied
+" once the cursor stops.
+augroup GnvimCopyOnSelect
+    autocmd!
+    autocmd CursorMoved * call gnvim#selection#moved()
+    if exists('##ModeChanged')
+        autocmd ModeChanged *:[vV\x16]* call gnvim#selection#changed()
+        autocmd ModeChanged [vV\x16]*:[^vV\x16]*
+                    \ call gnvim#selection#ended(v:event.old_mode[0])
+    endif
+augroup END
+
+augroup GnvimHeaderbar
+    autocmd!
+    autocmd BufEnter,BufFilePost,TermOpen * call gnvim#headerbar#update()
+augroup END
+
+augroup GnvimScrollRestore
+    autocmd!
+    autocmd BufLeave,TabLeave * call gnvim#scroll#leave()
+    autocmd BufEnter * call gnvim#scroll#enter(0)
+    autocmd TabEnter * call gnvim#scroll#enter(1)
+augroup END
+
+augroup GnvimCellWidths
+    autocmd!
+    autocmd VimEnter * call gnvim#cell_widths#set(get(g:, 'gnvim_cell_widths', []))
+    autocmd OptionSet ambiwidth call gnvim#cell_widths#update()
+augroup END
+
+augroup GnvimSession
+    autocmd!
+    autocmd VimLeavePre * call gnvim#session#save()
+augroup END
+
 inoremap <expr> <C-s> gnvim#popupmenu#toggle_details()
+
+command! GnvimNewWindow call gnvim#window#new()
+command! -nargs=1 -complete=customlist,gnvim#window#complete_snap GnvimSnap
+            \ call gnvim#window#snap(<q-args>)
+command! GnvimDebugConsole call gnvim#debug#show_console()
+command! GnvimPreferences call gnvim#preferences#show()
+command! GnvimRestoreSession call gnvim#session#restore()
+command! -range=% -nargs=? -complete=file GnvimPrint
+            \ call gnvim#print#buffer(<line1>, <line2>, <q-args>)
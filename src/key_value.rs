@@ -0,0 +1,38 @@
+/// Splits `pair` at its first `=` into a key and a value, both trimmed.
+/// Returns `None` if there is no `=`.
+///
+/// This is the format of our state files (e.g. the window geometry and the
+/// trusted directories), and of the lines of the project settings.
+pub fn split(pair: &str) -> Option<(&str, &str)> {
+    let mut split = pair.splitn(2, '=');
+    match (split.next(), split.next()) {
+        (Some(key), Some(value)) => Some((key.trim(), value.trim())),
+        _ => None,
+    }
+}
+
+/// Returns the key=value pairs of the lines of `data`, skipping the lines
+/// that are not pairs.
+pub fn lines<'a>(data: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+    data.lines().filter_map(split)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split("width=800"), Some(("width", "800")));
+        assert_eq!(split(" font_size = 14 "), Some(("font_size", "14")));
+        assert_eq!(split("a=b=c"), Some(("a", "b=c")));
+        assert_eq!(split("key="), Some(("key", "")));
+        assert_eq!(split("no pair"), None);
+    }
+
+    #[test]
+    fn test_lines() {
+        let pairs: Vec<_> = lines("x=1\n\ngarbage\ny = 2\n").collect();
+        assert_eq!(pairs, vec![("x", "1"), ("y", "2")]);
+    }
+}
@@ -2,9 +2,12 @@
 
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "webkit-preview")]
 extern crate ammonia;
+#[cfg(feature = "webkit-preview")]
 extern crate pulldown_cmark;
 extern crate structopt;
+#[cfg(feature = "webkit-preview")]
 extern crate syntect;
 
 extern crate cairo;
@@ -16,15 +19,21 @@ extern crate gtk;
 extern crate neovim_lib;
 extern crate pango;
 extern crate pangocairo;
+#[cfg(feature = "webkit-preview")]
 extern crate webkit2gtk;
 
 use gio::prelude::*;
 
 use neovim_lib::neovim::{Neovim, UiAttachOptions};
 use neovim_lib::session::Session as NeovimSession;
-use neovim_lib::NeovimApi;
-
-use std::process::Command;
+use neovim_lib::{NeovimApi, NeovimApiAsync};
+
+use std::cell::RefCell;
+use std::env;
+use std::io;
+use std::net::SocketAddr;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
 use std::sync::mpsc::channel;
 use std::sync::{Arc, Mutex};
 
@@ -32,12 +41,22 @@ use structopt::StructOpt;
 
 include!(concat!(env!("OUT_DIR"), "/gnvim_version.rs"));
 
+/// Process creation flag that keeps nvim from getting a console window.
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+mod key_value;
 mod nvim_bridge;
+mod paths;
+mod profiler;
+mod rpc_trace;
+mod startup;
 mod thread_guard;
 mod ui;
+mod watchdog;
 
 /// Gnvim is a graphical UI for neovim.
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Debug, Clone)]
 #[structopt(
     name = "gnvim",
     raw(version = "VERSION"),
@@ -52,13 +71,10 @@ struct Options {
     #[structopt(long = "nvim", name = "BIN", default_value = "nvim")]
     nvim_path: String,
 
-    /// Path for gnvim runtime files.
-    #[structopt(
-        long = "gnvim-rtp",
-        default_value = "/usr/local/share/gnvim/runtime",
-        env = "GNVIM_RUNTIME_PATH"
-    )]
-    gnvim_rtp: String,
+    /// Path for gnvim runtime files. By default, the runtime files that are
+    /// embedded in gnvim are used.
+    #[structopt(long = "gnvim-rtp", env = "GNVIM_RUNTIME_PATH")]
+    gnvim_rtp: Option<String>,
 
     /// Files to open.
     #[structopt(value_name = "FILES")]
@@ -79,21 +95,132 @@ struct Options {
     /// Disables externalized tab line
     #[structopt(long = "disable-ext-tabline")]
     disable_ext_tabline: bool,
-}
 
-fn build(app: &gtk::Application, opts: &Options) {
-    let (tx, rx) = channel();
+    /// Limits the frame rate (e.g. to save battery). Zero uses the
+    /// limit from the preferences, which is no limit by default.
+    #[structopt(long = "max-fps", name = "FPS", default_value = "0")]
+    max_fps: u32,
+
+    /// Where the window is placed: "restore" (where it was when gnvim was
+    /// last closed), "pointer" (at the pointer), "center" (centered on the
+    /// monitor under the pointer) or "project" (where the window of the
+    /// current directory's project was). Overrides the preferences.
+    #[structopt(long = "placement", name = "PLACEMENT")]
+    placement: Option<ui::Placement>,
+
+    /// Uses a headerbar, which shows the current file's path, instead of the
+    /// system's title bar.
+    #[structopt(long = "headerbar")]
+    headerbar: bool,
+
+    /// Derives the window's class (WM_CLASS on X11, app id on wayland) from
+    /// the project of the current directory (e.g. "gnvim-myproject"), for
+    /// window manager rules.
+    #[structopt(long = "class-from-cwd")]
+    class_from_cwd: bool,
+
+    /// Restores the session of the project from the last time gnvim was
+    /// closed in it.
+    #[structopt(long = "restore")]
+    restore: bool,
+
+    /// Traces the rpc messages from nvim. The trace can be viewed with
+    /// :GnvimDebugConsole.
+    #[structopt(long = "rpc-trace")]
+    rpc_trace: bool,
+
+    /// Profiles the frames. The profile is written to gnvim's state
+    /// directory on exit, in the chrome tracing format.
+    #[structopt(long = "profile")]
+    profile: bool,
+}
 
-    let bridge = nvim_bridge::NvimBridge::new(tx);
+/// Checks for problems with gnvim.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "gnvim doctor")]
+struct DoctorOptions {
+    /// Shows the startup times of the latest launches, and which phases of
+    /// the latest launch were slower than usual (e.g. after updating nvim
+    /// or changing the config).
+    #[structopt(long = "startup")]
+    startup: bool,
+}
 
+/// Starts nvim and attaches our first window to it. The nvim instance is
+/// stored in `primary`. If nvim fails to start, the error (and what nvim
+/// wrote to its stderr) is shown to the user, who can retry without their
+/// config.
+fn build(
+    app: &gtk::Application,
+    opts: &Options,
+    primary: Rc<RefCell<Option<Arc<Mutex<Neovim>>>>>,
+) {
     let mut cmd = Command::new(&opts.nvim_path);
     cmd.arg("--embed")
         .arg("--cmd")
         .arg("let g:gnvim=1")
         .arg("--cmd")
         .arg("set termguicolors")
+        // Makes nvim to send us the title (and icon) of the window, which
+        // by default contain the current file and its modified state.
         .arg("--cmd")
-        .arg(format!("let &rtp.=',{}'", opts.gnvim_rtp));
+        .arg("set title icon")
+        .arg("--cmd")
+        .arg(format!(
+            "let &rtp.=',{}'",
+            ui::runtime_path(opts.gnvim_rtp.as_ref().map(String::as_str))
+        ))
+        // Tells us which runtime nvim loaded, even when it's not ours (or
+        // it's missing), so a mismatch with the binary can be shown.
+        .arg("--cmd")
+        .arg(
+            "autocmd VimEnter * call rpcnotify(g:gnvim_channel_id, 'Gnvim', \
+             'RuntimeVersion', get(g:, 'gnvim_runtime_version', 0), \
+             get(g:, 'gnvim_runtime_path', ''))",
+        );
+
+    // Font from the preferences. Set before the user's config, so that
+    // guifont set in the user's config takes precedence.
+    if let Some(font) = ui::default_guifont() {
+        cmd.arg("--cmd")
+            .arg(format!("let &guifont='{}'", font.replace("'", "''")));
+    }
+
+    // Cell widths from the preferences. Applied (see gnvim#cell_widths#set)
+    // after the user's config, whose widths take precedence.
+    if let Some(widths) = ui::default_cell_widths() {
+        cmd.arg("--cmd")
+            .arg(format!("let g:gnvim_cell_widths = {}", widths));
+    }
+
+    // Scroll animation from the preferences, unless the user's config sets
+    // it. The variable is read when a buffer is entered, after the config.
+    if ui::default_scroll_restore() {
+        cmd.arg("--cmd").arg("let g:gnvim_scroll_restore = 1");
+    }
+
+    // Nvim saves the session here when it exits.
+    if let Some(path) = ui::session::path() {
+        cmd.arg("--cmd").arg(format!(
+            "let g:gnvim_session_file='{}'",
+            path.to_string_lossy().replace("'", "''")
+        ));
+    }
+    if opts.restore {
+        match ui::session::saved() {
+            Some(path) => {
+                cmd.arg("-S").arg(path);
+            }
+            None => eprintln!("No session to restore"),
+        }
+    }
+
+    // Don't flash a console window for nvim.
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
 
     // Pass arguments from cli to nvim.
     for arg in opts.nvim_args.iter() {
@@ -110,16 +237,79 @@ fn build(app: &gtk::Application, opts: &Options) {
         println!("nvim cmd: {:?}", cmd);
     }
 
-    let mut session = NeovimSession::new_child_cmd(&mut cmd).unwrap();
+    // Nvim's stderr (e.g. errors from the user's config) is shown if
+    // nvim fails to start.
+    let (stdio, stderr) = ui::startup_error::Stderr::capture();
+    cmd.stderr(stdio);
+
+    let session = NeovimSession::new_child_cmd(&mut cmd);
+    // Close our end of nvim's stderr, so that we see when nvim closes it.
+    cmd.stderr(Stdio::null());
+
+    let res = session
+        .map_err(|err| format!("Failed to run {}: {}", opts.nvim_path, err))
+        .and_then(|session| {
+            startup::mark(startup::Phase::Spawn);
+            attach(app, session, opts, true)
+        });
+
+    match res {
+        Ok(nvim) => *primary.borrow_mut() = Some(nvim),
+        Err(err) => {
+            // The time spent in the dialog isn't part of the startup.
+            startup::discard();
+
+            // Nvim's output is already on our stderr.
+            let output = stderr.output();
+            eprintln!("{}", err);
+
+            let mut opts = opts.clone();
+            if !opts.nvim_args.iter().any(|arg| arg == "--clean") {
+                opts.nvim_args.push(String::from("--clean"));
+            }
+            let app_ref = app.clone();
+            ui::startup_error::show(app, &err, &output, move || {
+                build(&app_ref, &opts, primary.clone());
+            });
+        }
+    }
+}
+
+/// Attaches a new UI (e.g. window) to nvim through `session`. `primary`
+/// tells if this is the first UI of the nvim instance (e.g. the one that
+/// started nvim). Returns the nvim instance the UI is attached to, or an
+/// error if nvim doesn't respond to us (e.g. if it has exited).
+fn attach(
+    app: &gtk::Application,
+    mut session: NeovimSession,
+    opts: &Options,
+    primary: bool,
+) -> Result<Arc<Mutex<Neovim>>, String> {
+    let (tx, rx) = channel();
+
+    let trace = Arc::new(Mutex::new(rpc_trace::RpcTrace::new(opts.rpc_trace)));
+    let bridge = nvim_bridge::NvimBridge::new(tx, trace.clone());
     session.start_event_loop_handler(bridge);
 
     let mut nvim = Neovim::new(session);
-    nvim.subscribe("Gnvim")
-        .expect("Failed to subscribe to 'Gnvim' events");
-
-    let api_info = nvim.get_api_info().expect("Failed to get API info");
-    nvim.set_var("gnvim_channel_id", api_info[0].clone())
-        .expect("Failed to set g:gnvim_channel_id");
+    nvim.subscribe("Gnvim").map_err(|err| {
+        format!("Failed to subscribe to 'Gnvim' events: {}", err)
+    })?;
+
+    let api_info = nvim
+        .get_api_info()
+        .map_err(|err| format!("Failed to get API info: {}", err))?;
+    let channel = api_info[0].as_u64().unwrap_or(0);
+
+    // Only the primary UI receives the gnvim events (that are sent to
+    // g:gnvim_channel_id).
+    if primary {
+        startup::set_nvim_version(&api_info);
+        nvim.set_var("gnvim_channel_id", api_info[0].clone())
+            .map_err(|err| {
+                format!("Failed to set g:gnvim_channel_id: {}", err)
+            })?;
+    }
 
     let mut ui_opts = UiAttachOptions::new();
     ui_opts.set_rgb(true);
@@ -129,28 +319,200 @@ fn build(app: &gtk::Application, opts: &Options) {
     ui_opts.set_cmdline_external(!opts.disable_ext_cmdline);
 
     ui_opts.set_wildmenu_external(true);
-    nvim.ui_attach(80, 30, &ui_opts)
-        .expect("Failed to attach UI");
 
-    let ui = ui::UI::init(app, rx, Arc::new(Mutex::new(nvim)));
+    // Restore the geometry before attaching, so we can tell nvim the right
+    // grid size from the start.
+    let placement = opts
+        .placement
+        .or_else(ui::default_placement)
+        .unwrap_or_default();
+    let geometry = if placement == ui::Placement::Project {
+        let dir = env::current_dir().unwrap_or_default();
+        ui::WindowGeometry::load_project(&ui::project_dir(&dir))
+    } else {
+        ui::WindowGeometry::load()
+    };
+    nvim.ui_attach(geometry.cols as i64, geometry.rows as i64, &ui_opts)
+        .map_err(|err| format!("Failed to attach UI: {}", err))?;
+    if primary {
+        startup::mark(startup::Phase::Attach);
+    }
+
+    let nvim = Arc::new(Mutex::new(nvim));
+    let ui = ui::UI::init(
+        app,
+        rx,
+        nvim.clone(),
+        geometry,
+        placement,
+        opts.headerbar,
+        trace,
+    );
+    // Overrides the frame rate cap from the preferences.
+    if opts.max_fps > 0 {
+        ui.set_max_fps(opts.max_fps);
+    }
+
+    if primary {
+        ui.confirm_quit_on_close();
+        ui::session::save_on_logout(app, nvim.clone());
+
+        // Unless we were asked to open something else, offer to continue
+        // where the user left off.
+        if !opts.restore
+            && opts.open_files.is_empty()
+            && opts.nvim_args.is_empty()
+        {
+            ui.offer_session_restore();
+        }
+    } else {
+        ui.detach_on_close(channel);
+    }
+
+    // New windows connect to the nvim instance over its server address.
+    let app_ref = app.clone();
+    let opts_ref = opts.clone();
+    ui.connect_new_window(move |address| {
+        let res = connect(address)
+            .map_err(|err| {
+                format!("Failed to connect to nvim at {}: {}", address, err)
+            })
+            .and_then(|session| attach(&app_ref, session, &opts_ref, false));
+        if let Err(err) = res {
+            eprintln!("{}", err);
+        }
+    });
+
     ui.start();
+
+    Ok(nvim)
+}
+
+/// Connects to nvim's server `address`, which is either a TCP address
+/// (e.g. "127.0.0.1:6666") or a unix socket.
+fn connect(address: &str) -> io::Result<NeovimSession> {
+    if address.parse::<SocketAddr>().is_ok() {
+        NeovimSession::new_tcp(address)
+    } else {
+        connect_socket(address)
+    }
+}
+
+#[cfg(unix)]
+fn connect_socket(path: &str) -> io::Result<NeovimSession> {
+    NeovimSession::new_unix_socket(path)
+}
+
+/// Neovim-lib can't connect to named pipes, which is what nvim listens on
+/// by default on Windows. `gnvim#window#new` uses a TCP address instead.
+#[cfg(not(unix))]
+fn connect_socket(path: &str) -> io::Result<NeovimSession> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        format!("{} is not a TCP address", path),
+    ))
+}
+
+/// Opens `paths` in `nvim`. Used for files that are opened with us while
+/// we're already running (e.g. with "Open With" on macOS).
+fn open_files(nvim: &Arc<Mutex<Neovim>>, paths: &[String]) {
+    let mut nvim = nvim.lock().unwrap();
+    for path in paths {
+        nvim.command_async(&format!(
+            "execute 'drop ' . fnameescape('{}')",
+            path.replace("'", "''")
+        ))
+        .cb(|res| {
+            if let Err(err) = res {
+                eprintln!("Failed to open file: {}", err);
+            }
+        })
+        .call();
+    }
+}
+
+/// Runs `gnvim doctor`.
+fn doctor(opts: DoctorOptions) {
+    if opts.startup {
+        print!("{}", startup::report());
+    } else {
+        eprintln!("Nothing to check, see `gnvim doctor --help`");
+    }
 }
 
 fn main() {
+    startup::start(VERSION);
+
+    // Doctor is handled before our regular options, which would take it as
+    // a file to open.
+    if env::args()
+        .nth(1)
+        .map(|arg| arg == "doctor")
+        .unwrap_or(false)
+    {
+        doctor(DoctorOptions::from_iter(env::args().skip(1)));
+        return;
+    }
+
     let opts = Options::from_args();
+    profiler::set_enabled(opts.profile);
 
     let mut flags = gio::ApplicationFlags::empty();
     flags.insert(gio::ApplicationFlags::NON_UNIQUE);
     flags.insert(gio::ApplicationFlags::HANDLES_OPEN);
-    let app =
-        gtk::Application::new("com.github.vhakulinen.gnvim", flags).unwrap();
+    let mut app_id = String::from("com.github.vhakulinen.gnvim");
+    if opts.class_from_cwd {
+        let dir = env::current_dir().unwrap_or_default();
+        let project = ui::project_name(&dir);
+        // WM_CLASS's instance name is the program name, and its class is
+        // the program class.
+        glib::set_prgname(Some(&format!("gnvim-{}", project)));
+        gdk::set_program_class(&format!("gnvim-{}", project));
+        app_id = format!("{}.{}", app_id, project);
+    }
+    let app = gtk::Application::new(app_id.as_str(), flags).unwrap();
 
     glib::set_application_name("GNvim");
     gtk::Window::set_default_icon_name("gnvim");
 
+    // Nvim of our first window. Files that are opened with us after we've
+    // started are opened in it.
+    let primary: Rc<RefCell<Option<Arc<Mutex<Neovim>>>>> =
+        Rc::new(RefCell::new(None));
+
+    let opts_ref = opts.clone();
+    let primary_ref = primary.clone();
     app.connect_activate(move |app| {
-        build(app, &opts);
+        ui::set_default_icon();
+        build(app, &opts_ref, primary_ref.clone());
+    });
+
+    // On macOS, files opened with us (e.g. from Finder) come through here,
+    // instead of the command line, and we might not be running yet.
+    app.connect_open(move |app, files, _| {
+        let paths: Vec<String> = files
+            .iter()
+            .filter_map(|file| file.get_path())
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+
+        let nvim = primary.borrow().clone();
+        match nvim {
+            Some(ref nvim) => open_files(nvim, &paths),
+            None => {
+                let mut opts = opts.clone();
+                opts.open_files.extend(paths);
+                build(app, &opts, primary.clone());
+            }
+        }
     });
 
     app.run(&vec![]);
+
+    if profiler::is_enabled() {
+        match profiler::export() {
+            Ok(path) => println!("Profile written to {}", path.display()),
+            Err(err) => eprintln!("Failed to write the profile: {}", err),
+        }
+    }
 }
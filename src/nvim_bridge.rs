@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::slice;
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 
 use neovim_lib::{neovim_api::Tabpage, Handler, RequestHandler, Value};
 
+use profiler;
+use rpc_trace::{Kind, RpcTrace};
 use ui::color::{Color, Highlight};
 
 macro_rules! unwrap_str {
@@ -56,6 +60,20 @@ macro_rules! try_u64 {
     };
 }
 
+macro_rules! try_i64 {
+    ($val:expr, $msg:expr) => {
+        $val.as_i64()
+            .ok_or(format!("Value is not an i64: {}", $msg))?
+    };
+}
+
+macro_rules! try_array {
+    ($val:expr, $msg:expr) => {
+        $val.as_array()
+            .ok_or(format!("Value is not an array: {}", $msg))?
+    };
+}
+
 macro_rules! try_map {
     ($val:expr, $msg:expr) => {
         $val.as_map()
@@ -232,6 +250,7 @@ pub struct CmdlineShow {
 
 pub enum RedrawEvent {
     SetTitle(String),
+    SetIcon(String),
 
     GridLine(Vec<GridLineSegment>),
     /// grid, width, height
@@ -240,6 +259,8 @@ pub enum RedrawEvent {
     GridCursorGoto(u64, u64, u64),
     /// grid
     GridClear(u64),
+    /// grid
+    GridDestroy(u64),
     /// grid, [top, bot, left, right], rows, cols
     GridScroll(u64, [u64; 4], i64, i64),
 
@@ -281,10 +302,12 @@ impl fmt::Display for RedrawEvent {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
             RedrawEvent::SetTitle(..) => write!(fmt, "SetTitle"),
+            RedrawEvent::SetIcon(..) => write!(fmt, "SetIcon"),
             RedrawEvent::GridLine(..) => write!(fmt, "GridLine"),
             RedrawEvent::GridResize(..) => write!(fmt, "GridResize"),
             RedrawEvent::GridCursorGoto(..) => write!(fmt, "GridCursorGoto"),
             RedrawEvent::GridClear(..) => write!(fmt, "GridClear"),
+            RedrawEvent::GridDestroy(..) => write!(fmt, "GridDestroy"),
             RedrawEvent::GridScroll(..) => write!(fmt, "GridScroll"),
             RedrawEvent::DefaultColorsSet(..) => {
                 write!(fmt, "DefaultColorsSet")
@@ -333,6 +356,142 @@ pub enum GnvimEvent {
 
     PopupmenuWidth(u64),
     PopupmenuWidthDetails(u64),
+    PopupmenuMaxRows(u64),
+    PopupmenuGhostText(bool),
+
+    /// Enable or disable editing the cmdline in a GTK entry.
+    CmdlineEntryMode(bool),
+
+    /// Show the preview of a search and replace.
+    ReplacePreview(ReplacePreview),
+
+    /// Nvim's current directory changed (or nvim started), so the project's
+    /// settings need to be (re)loaded.
+    ProjectDirChanged(String),
+
+    /// Set the contents of the quickfix panel.
+    QuickfixSet(QuickfixList),
+    /// Show or hide the quickfix panel.
+    QuickfixShow(bool),
+
+    /// Print a buffer (or write it to a PDF file).
+    Print(PrintJob),
+
+    /// Open a new window, which is attached to nvim with the given
+    /// (server) address.
+    NewWindow(String),
+    /// Snap the window to a half or a quarter of the screen (e.g.
+    /// "top-left").
+    WindowSnap(String),
+    /// Animate a property (e.g. the opacity) of the window.
+    WindowAnimate(WindowAnimation),
+
+    /// Set the function that all keyboard input is passed through. `None`
+    /// removes the hook.
+    InputHookSet(Option<String>),
+    /// Use the Option key as Meta on macOS, instead of composing characters.
+    OptionAsMeta(bool),
+    /// Feed keys (sequence in nvim's key notation, delay between the keys in
+    /// milliseconds and if the keys are shown on the screen) to nvim as if
+    /// they were typed.
+    InputSimulate(String, u64, bool),
+    /// Stop the running input simulation.
+    InputSimulateStop,
+    /// Show or hide the keys we send to nvim on the screen.
+    KeyDisplay(bool),
+
+    /// Map a mouse gesture (e.g. "LU") to input. Empty input removes the
+    /// mapping.
+    GestureSet(String, String),
+
+    /// Current value of 'mousemodel', which tells if we show our context
+    /// menu.
+    MouseModel(String),
+    /// Current value of 'mouse', which tells if nvim takes the mouse.
+    Mouse(String),
+    /// Add an entry (label and command) to the context menu. Empty command
+    /// removes the entry.
+    MenuEntrySet(String, String),
+
+    /// Enable or disable rounded corners of the selection.
+    RoundedSelection(bool),
+
+    /// Show or hide the outlines of the areas that are redrawn on flush.
+    ShowDamage(bool),
+    /// Enable or disable dithering of the blended areas of the grids.
+    Dithering(bool),
+
+    /// Enable or disable the crosshair that follows the cursor.
+    CursorCrosshair(bool),
+    /// Set the minimum jump (in rows) that shows the cursor beacon. Zero
+    /// disables the beacon.
+    CursorBeacon(u64),
+    /// Animate the view of the window at the area (top, bot, left, right)
+    /// after its buffer was switched, and the view moved by the rows.
+    ScrollRestore([u64; 4], i64),
+    /// Enable or disable the flash on mode changes.
+    CursorModeFlash(bool),
+
+    /// Show an image: id, path, row, col, the max size in cells (cols and
+    /// rows, zero meaning no limit) and the cells of the window it's clipped
+    /// to (top, bot, left, right).
+    ImageShow(u64, String, u64, u64, u64, u64, Option<[u64; 4]>),
+    /// Hide the image with the id.
+    ImageHide(u64),
+    /// Hide all images.
+    ImageClear,
+
+    /// Register that a macro is being recorded to, empty when not
+    /// recording.
+    RecordingSet(String),
+
+    /// Copy text to a selection, "primary" or "clipboard".
+    CopySelection(String, String),
+
+    /// Show the unicode input popover at the cell (row, col).
+    UnicodeInputShow(u64, u64),
+
+    /// Set the path shown in the headerbar. Empty path shows the title.
+    HeaderbarPath(String),
+
+    /// Version and the path of the runtime files that nvim loaded. Zero
+    /// version and empty path when nvim didn't load our runtime.
+    RuntimeVersion(u64, String),
+
+    /// Start showing the progress of a task, with the task's name.
+    TaskBegin(String),
+    /// Set the progress of the current task, in percents.
+    TaskUpdate(u64),
+    /// Finish the current task, with its status ("success" or "failure").
+    TaskEnd(String),
+
+    /// Cell widths set with `setcellwidths()` (first, last, width), and if
+    /// 'ambiwidth' is "double".
+    CellWidths(Vec<(u32, u32, usize)>, bool),
+
+    /// Set the window's class (WM_CLASS).
+    SetWmClass(String),
+
+    /// Set the frame rate cap. Zero removes the cap.
+    SetMaxFps(u64),
+
+    /// Show a message (and its level) as a desktop notification.
+    DesktopNotify(String, u64),
+    /// Set the minimum level of the messages that are shown as desktop
+    /// notifications.
+    DesktopNotifyLevel(u64),
+
+    /// Enable or disable the rpc trace.
+    RpcTrace(bool),
+    /// Enable or disable profiling of the frames. Disabling writes the
+    /// profile.
+    Profile(bool),
+    /// Show the debug console (e.g. the rpc trace).
+    ShowDebugConsole,
+    /// Show or hide the performance metrics overlay.
+    PerfHud(bool),
+    /// Show the preferences window.
+    ShowPreferences,
 
     Unknown(String),
 }
@@ -370,12 +529,87 @@ pub struct CmdlineColors {
     pub border: Option<Color>,
 }
 
+/// Line matching the pattern of a `ReplacePreview`.
+#[derive(Clone)]
+pub struct ReplaceMatch {
+    pub lnum: u64,
+    pub line: String,
+    /// The line after the replacement.
+    pub replaced: String,
+}
+
+/// Preview of a search and replace in a buffer.
+#[derive(Clone)]
+pub struct ReplacePreview {
+    pub bufnr: u64,
+    /// The buffer's `b:changedtick` when the matches were collected.
+    pub changedtick: u64,
+    pub pattern: String,
+    pub replacement: String,
+    pub matches: Vec<ReplaceMatch>,
+}
+
+/// Buffer (or a part of it) to print, with its highlights.
+#[derive(Clone)]
+pub struct PrintJob {
+    /// Title of the document, shown in the page headers.
+    pub title: String,
+    /// PDF file to write. If empty, the print dialog is shown.
+    pub pdf: String,
+    /// Number of the first line, if the line numbers are printed.
+    pub first_lnum: Option<u64>,
+    /// Highlights of the chunks. The first one is the Normal group and the
+    /// second the line numbers.
+    pub hls: Vec<Highlight>,
+    /// Lines as chunks of text and their highlight (index to `hls`).
+    pub lines: Vec<Vec<(String, usize)>>,
+}
+
+/// Animation of a window property (see `ui::animation`).
+#[derive(Debug, Clone)]
+pub struct WindowAnimation {
+    /// "opacity", "size" or "position".
+    pub property: String,
+    /// Start values. Empty for the current values.
+    pub from: Vec<f64>,
+    pub to: Vec<f64>,
+    /// If `to` is relative to `from`.
+    pub relative: bool,
+    /// Duration in milliseconds.
+    pub duration: u64,
+    pub easing: String,
+}
+
+/// Entry of a `QuickfixList`.
+#[derive(Clone)]
+pub struct QuickfixItem {
+    /// Empty for entries that aren't recognized (see `:h getqflist()`'s
+    /// valid), e.g. continuation lines of a multiline message.
+    pub filename: String,
+    pub lnum: u64,
+    pub col: u64,
+    /// Type of the entry, e.g. "E" for errors.
+    pub kind: String,
+    pub text: String,
+}
+
+/// Quickfix or location list.
+#[derive(Clone)]
+pub struct QuickfixList {
+    /// If the list is the location list of the current window.
+    pub loclist: bool,
+    pub title: String,
+    pub items: Vec<QuickfixItem>,
+}
+
 #[derive(Default)]
 pub struct SetGuiColors {
     pub pmenu: PmenuColors,
     pub tabline: TablineColors,
     pub cmdline: CmdlineColors,
     pub wildmenu: WildmenuColors,
+    /// Background of the visual selection.
+    pub visual_bg: Option<Color>,
 }
 
 pub enum Request {
@@ -400,16 +634,20 @@ pub struct NvimBridge {
     request_tx: Sender<Result<Value, Value>>,
     /// Receiving end of `request_tx`.
     request_rx: Receiver<Result<Value, Value>>,
+
+    /// Trace of the messages we receive (and respond to).
+    trace: Arc<Mutex<RpcTrace>>,
 }
 
 impl NvimBridge {
-    pub fn new(tx: Sender<Message>) -> Self {
+    pub fn new(tx: Sender<Message>, trace: Arc<Mutex<RpcTrace>>) -> Self {
         let (request_tx, request_rx) = channel();
 
         NvimBridge {
             tx,
             request_tx,
             request_rx,
+            trace,
         }
     }
 }
@@ -420,7 +658,13 @@ impl RequestHandler for NvimBridge {
         name: &str,
         args: Vec<Value>,
     ) -> Result<Value, Value> {
-        match name {
+        let start =
+            self.trace
+                .lock()
+                .unwrap()
+                .record(Kind::Request, name, &args, None);
+
+        let res = match name {
             "Gnvim" => match parse_request(args) {
                 Ok(msg) => {
                     self.tx
@@ -434,12 +678,33 @@ impl RequestHandler for NvimBridge {
                 println!("Unknown request: {}", name);
                 Err("Unkown request".into())
             }
-        }
+        };
+
+        let value = match res {
+            Ok(ref value) | Err(ref value) => value,
+        };
+        self.trace.lock().unwrap().record(
+            Kind::Response,
+            name,
+            slice::from_ref(value),
+            Some(start.elapsed()),
+        );
+
+        res
     }
 }
 
 impl Handler for NvimBridge {
     fn handle_notify(&mut self, name: &str, args: Vec<Value>) {
+        let _span = profiler::span("rpc", name);
+
+        self.trace.lock().unwrap().record(
+            Kind::Notification,
+            name,
+            &args,
+            None,
+        );
+
         if let Some(notify) = parse_notify(name, args) {
             self.tx.send(Message::Notify(notify)).unwrap();
         } else {
@@ -492,8 +757,16 @@ fn parse_redraw_event(args: Vec<Value>) -> Vec<RedrawEvent> {
                     let title = unwrap_str!(args[0]);
                     RedrawEvent::SetTitle(title.to_string())
                 }
+                "set_icon" => {
+                    let args = unwrap_array!(args[1]);
+                    let icon = unwrap_str!(args[0]);
+                    RedrawEvent::SetIcon(icon.to_string())
+                }
                 "grid_line" => {
                     let mut lines = vec![];
+                    // Cells without a hl id use the most recently seen one,
+                    // which may be from the previous line of this event.
+                    let mut hl_id = 0;
 
                     for entry in unwrap_array!(args)[1..].into_iter() {
                         let entry = unwrap_array!(entry);
@@ -505,23 +778,17 @@ fn parse_redraw_event(args: Vec<Value>) -> Vec<RedrawEvent> {
                         for entry in unwrap_array!(entry[3]) {
                             let entry = unwrap_array!(entry);
                             let text = unwrap_str!(entry[0]);
-                            let hl_id = if entry.len() >= 2 {
-                                entry[1].as_u64()
-                            } else {
-                                None
-                            };
+                            if let Some(id) =
+                                entry.get(1).and_then(Value::as_u64)
+                            {
+                                hl_id = id;
+                            }
                             let repeat = if entry.len() >= 3 {
                                 unwrap_u64!(entry[2])
                             } else {
                                 1
                             };
 
-                            let hl_id = if let Some(hl_id) = hl_id {
-                                hl_id
-                            } else {
-                                cells.last().unwrap().hl_id
-                            };
-
                             cells.push(Cell {
                                 hl_id,
                                 repeat,
@@ -560,6 +827,11 @@ fn parse_redraw_event(args: Vec<Value>) -> Vec<RedrawEvent> {
                     let id = unwrap_u64!(args[0]);
                     RedrawEvent::GridClear(id)
                 }
+                "grid_destroy" => {
+                    let args = unwrap_array!(args[1]);
+                    let id = unwrap_u64!(args[0]);
+                    RedrawEvent::GridDestroy(id)
+                }
                 "grid_scroll" => {
                     let args = unwrap_array!(args[1]);
 
@@ -793,6 +1065,132 @@ fn parse_redraw_event(args: Vec<Value>) -> Vec<RedrawEvent> {
         .collect()
 }
 
+fn parse_replace_preview(args: &[Value]) -> Result<ReplacePreview, String> {
+    let bufnr = try_u64!(args.get(0).ok_or("bufnr missing")?, "bufnr");
+    let changedtick =
+        try_u64!(args.get(1).ok_or("changedtick missing")?, "changedtick");
+    let pattern = try_str!(args.get(2).ok_or("pattern missing")?, "pattern");
+    let replacement =
+        try_str!(args.get(3).ok_or("replacement missing")?, "replacement");
+
+    let mut matches = vec![];
+    for m in try_array!(args.get(4).ok_or("matches missing")?, "matches") {
+        let m = try_array!(m, "match");
+        matches.push(ReplaceMatch {
+            lnum: try_u64!(m.get(0).ok_or("lnum missing")?, "lnum"),
+            line: try_str!(m.get(1).ok_or("line missing")?, "line").to_string(),
+            replaced: try_str!(m.get(2).ok_or("replaced missing")?, "replaced")
+                .to_string(),
+        });
+    }
+
+    Ok(ReplacePreview {
+        bufnr,
+        changedtick,
+        pattern: pattern.to_string(),
+        replacement: replacement.to_string(),
+        matches,
+    })
+}
+
+fn parse_quickfix_list(args: &[Value]) -> Result<QuickfixList, String> {
+    let loclist = try_u64!(args.get(0).ok_or("loclist missing")?, "loclist");
+    let title = try_str!(args.get(1).ok_or("title missing")?, "title");
+
+    let mut items = vec![];
+    for item in try_array!(args.get(2).ok_or("items missing")?, "items") {
+        let item = try_array!(item, "item");
+        items.push(QuickfixItem {
+            filename: try_str!(
+                item.get(0).ok_or("filename missing")?,
+                "filename"
+            )
+            .to_string(),
+            lnum: try_u64!(item.get(1).ok_or("lnum missing")?, "lnum"),
+            col: try_u64!(item.get(2).ok_or("col missing")?, "col"),
+            kind: try_str!(item.get(3).ok_or("type missing")?, "type")
+                .to_string(),
+            text: try_str!(item.get(4).ok_or("text missing")?, "text")
+                .to_string(),
+        });
+    }
+
+    Ok(QuickfixList {
+        loclist: loclist != 0,
+        title: title.to_string(),
+        items,
+    })
+}
+
+fn parse_window_animation(args: &[Value]) -> Result<WindowAnimation, String> {
+    let numbers = |val: &Value, msg: &str| -> Result<Vec<f64>, String> {
+        try_array!(val, msg)
+            .iter()
+            .map(|n| {
+                n.as_f64().ok_or(format!("Value is not a number: {}", msg))
+            })
+            .collect()
+    };
+
+    let property = try_str!(args.get(0).ok_or("property missing")?, "property");
+    let from = numbers(args.get(1).ok_or("from missing")?, "from")?;
+    let to = numbers(args.get(2).ok_or("to missing")?, "to")?;
+    let relative = try_u64!(args.get(3).ok_or("relative missing")?, "relative");
+    let duration = try_u64!(args.get(4).ok_or("duration missing")?, "duration");
+    let easing = try_str!(args.get(5).ok_or("easing missing")?, "easing");
+
+    Ok(WindowAnimation {
+        property: property.to_string(),
+        from,
+        to,
+        relative: relative != 0,
+        duration,
+        easing: easing.to_string(),
+    })
+}
+
+fn parse_print_job(args: &[Value]) -> Result<PrintJob, String> {
+    let title = try_str!(args.get(0).ok_or("title missing")?, "title");
+    let pdf = try_str!(args.get(1).ok_or("pdf missing")?, "pdf");
+    let first_lnum =
+        try_u64!(args.get(2).ok_or("first lnum missing")?, "first lnum");
+
+    let mut hls = vec![];
+    for hl in try_array!(args.get(3).ok_or("hls missing")?, "hls") {
+        hls.push(Highlight::from_map_val(try_map!(hl, "hl")));
+    }
+    if hls.len() < 2 {
+        return Err(String::from("normal and line number hls missing"));
+    }
+
+    let mut lines = vec![];
+    for line in try_array!(args.get(4).ok_or("lines missing")?, "lines") {
+        let mut chunks = vec![];
+        for chunk in try_array!(line, "line") {
+            let chunk = try_array!(chunk, "chunk");
+            let text = try_str!(chunk.get(0).ok_or("text missing")?, "text");
+            let hl = try_u64!(chunk.get(1).ok_or("hl missing")?, "hl") as usize;
+            if hl >= hls.len() {
+                return Err(format!("Unknown hl: {}", hl));
+            }
+            chunks.push((text.to_string(), hl));
+        }
+        lines.push(chunks);
+    }
+
+    Ok(PrintJob {
+        title: title.to_string(),
+        pdf: pdf.to_string(),
+        first_lnum: if first_lnum > 0 {
+            Some(first_lnum)
+        } else {
+            None
+        },
+        hls,
+        lines,
+    })
+}
+
 fn parse_gnvim_event(args: Vec<Value>) -> Result<GnvimEvent, String> {
     let cmd = try_str!(args.get(0).ok_or("No command given")?, "cmd");
     let res = match cmd {
@@ -829,6 +1227,8 @@ fn parse_gnvim_event(args: Vec<Value>) -> Result<GnvimEvent, String> {
                     "wildmenu_fg" => colors.wildmenu.fg = color,
                     "wildmenusel_bg" => colors.wildmenu.sel_bg = color,
                     "wildmenusel_fg" => colors.wildmenu.sel_fg = color,
+
+                    "visual_bg" => colors.visual_bg = color,
                     _ => {
                         println!(
                             "Unknown SetGuiColor: {}",
@@ -875,6 +1275,289 @@ fn parse_gnvim_event(args: Vec<Value>) -> Result<GnvimEvent, String> {
                 try_u64!(args.get(1).ok_or("width missing")?, "pmenu width");
             GnvimEvent::PopupmenuWidthDetails(w)
         }
+        "PopupmenuSetMaxRows" => {
+            let rows =
+                try_u64!(args.get(1).ok_or("rows missing")?, "pmenu max rows");
+            GnvimEvent::PopupmenuMaxRows(rows)
+        }
+        "PopupmenuSetGhostText" => {
+            let enabled = try_u64!(
+                args.get(1).ok_or("enabled missing")?,
+                "pmenu ghost text"
+            );
+            GnvimEvent::PopupmenuGhostText(enabled != 0)
+        }
+        "ReplacePreview" => GnvimEvent::ReplacePreview(parse_replace_preview(
+            args.get(1..).unwrap_or_default(),
+        )?),
+        "ProjectDirChanged" => {
+            let dir = try_str!(args.get(1).ok_or("dir missing")?, "dir");
+            GnvimEvent::ProjectDirChanged(dir.to_string())
+        }
+        "QuickfixSet" => GnvimEvent::QuickfixSet(parse_quickfix_list(
+            args.get(1..).unwrap_or_default(),
+        )?),
+        "Print" => GnvimEvent::Print(parse_print_job(
+            args.get(1..).unwrap_or_default(),
+        )?),
+        "QuickfixShow" => {
+            let show =
+                try_u64!(args.get(1).ok_or("show missing")?, "quickfix show");
+            GnvimEvent::QuickfixShow(show != 0)
+        }
+        "CmdlineSetEntryMode" => {
+            let enabled = try_u64!(
+                args.get(1).ok_or("enabled missing")?,
+                "cmdline entry mode"
+            );
+            GnvimEvent::CmdlineEntryMode(enabled != 0)
+        }
+        "NewWindow" => {
+            let address = try_str!(
+                args.get(1).ok_or("address missing")?,
+                "server address"
+            );
+            GnvimEvent::NewWindow(address.to_string())
+        }
+        "WindowAnimate" => GnvimEvent::WindowAnimate(parse_window_animation(
+            args.get(1..).unwrap_or_default(),
+        )?),
+        "WindowSnap" => {
+            let snap = try_str!(args.get(1).ok_or("snap missing")?, "snap");
+            GnvimEvent::WindowSnap(snap.to_string())
+        }
+        "InputSetOptionAsMeta" => {
+            let enabled = try_u64!(
+                args.get(1).ok_or("enabled missing")?,
+                "option as meta"
+            );
+            GnvimEvent::OptionAsMeta(enabled != 0)
+        }
+        "InputHookSet" => {
+            let function = try_str!(
+                args.get(1).ok_or("function missing")?,
+                "input hook function"
+            );
+            if function.is_empty() {
+                GnvimEvent::InputHookSet(None)
+            } else {
+                GnvimEvent::InputHookSet(Some(function.to_string()))
+            }
+        }
+        "InputSimulate" => {
+            let sequence =
+                try_str!(args.get(1).ok_or("sequence missing")?, "sequence");
+            let delay = try_u64!(args.get(2).ok_or("delay missing")?, "delay");
+            let show = try_u64!(args.get(3).ok_or("show missing")?, "show");
+            GnvimEvent::InputSimulate(sequence.to_string(), delay, show != 0)
+        }
+        "InputSimulateStop" => GnvimEvent::InputSimulateStop,
+        "InputKeyDisplay" => {
+            let enabled =
+                try_u64!(args.get(1).ok_or("enabled missing")?, "key display");
+            GnvimEvent::KeyDisplay(enabled != 0)
+        }
+        "GestureSet" => {
+            let gesture =
+                try_str!(args.get(1).ok_or("gesture missing")?, "gesture");
+            let input =
+                try_str!(args.get(2).ok_or("input missing")?, "gesture input");
+            GnvimEvent::GestureSet(gesture.to_string(), input.to_string())
+        }
+        "MenuSetMouseModel" => {
+            let mousemodel = try_str!(
+                args.get(1).ok_or("mousemodel missing")?,
+                "mousemodel"
+            );
+            GnvimEvent::MouseModel(mousemodel.to_string())
+        }
+        "SetMouse" => {
+            let mouse = try_str!(args.get(1).ok_or("mouse missing")?, "mouse");
+            GnvimEvent::Mouse(mouse.to_string())
+        }
+        "MenuSetEntry" => {
+            let label =
+                try_str!(args.get(1).ok_or("label missing")?, "menu label");
+            let command =
+                try_str!(args.get(2).ok_or("command missing")?, "menu command");
+            GnvimEvent::MenuEntrySet(label.to_string(), command.to_string())
+        }
+        "RoundedSelection" => {
+            let rounded = try_u64!(
+                args.get(1).ok_or("rounded missing")?,
+                "rounded selection"
+            );
+            GnvimEvent::RoundedSelection(rounded != 0)
+        }
+        "GridShowDamage" => {
+            let show =
+                try_u64!(args.get(1).ok_or("show missing")?, "show damage");
+            GnvimEvent::ShowDamage(show != 0)
+        }
+        "GridSetDithering" => {
+            let enabled =
+                try_u64!(args.get(1).ok_or("enabled missing")?, "dithering");
+            GnvimEvent::Dithering(enabled != 0)
+        }
+        "CursorSetCrosshair" => {
+            let enabled =
+                try_u64!(args.get(1).ok_or("enabled missing")?, "crosshair");
+            GnvimEvent::CursorCrosshair(enabled != 0)
+        }
+        "ScrollRestore" => {
+            let top = try_u64!(args.get(1).ok_or("top missing")?, "top");
+            let bot = try_u64!(args.get(2).ok_or("bot missing")?, "bot");
+            let left = try_u64!(args.get(3).ok_or("left missing")?, "left");
+            let right = try_u64!(args.get(4).ok_or("right missing")?, "right");
+            let rows = try_i64!(args.get(5).ok_or("rows missing")?, "rows");
+            GnvimEvent::ScrollRestore([top, bot, left, right], rows)
+        }
+        "CursorSetBeacon" => {
+            let rows = try_u64!(args.get(1).ok_or("rows missing")?, "beacon");
+            GnvimEvent::CursorBeacon(rows)
+        }
+        "CursorSetModeFlash" => {
+            let enabled =
+                try_u64!(args.get(1).ok_or("enabled missing")?, "mode flash");
+            GnvimEvent::CursorModeFlash(enabled != 0)
+        }
+        "ImageShow" => {
+            let id = try_u64!(args.get(1).ok_or("id missing")?, "image id");
+            let path =
+                try_str!(args.get(2).ok_or("path missing")?, "image path");
+            let row = try_u64!(args.get(3).ok_or("row missing")?, "image row");
+            let col = try_u64!(args.get(4).ok_or("col missing")?, "image col");
+            let max_cols = try_u64!(
+                args.get(5).ok_or("max cols missing")?,
+                "image max cols"
+            );
+            let max_rows = try_u64!(
+                args.get(6).ok_or("max rows missing")?,
+                "image max rows"
+            );
+            // Older runtime files don't send the window's rect.
+            let clip = match args.get(7) {
+                Some(rect) => {
+                    let rect = try_array!(rect, "image clip");
+                    if rect.len() == 4 {
+                        let mut clip = [0; 4];
+                        for (c, v) in clip.iter_mut().zip(rect) {
+                            *c = try_u64!(v, "image clip");
+                        }
+                        Some(clip)
+                    } else {
+                        None
+                    }
+                }
+                None => None,
+            };
+            GnvimEvent::ImageShow(
+                id,
+                path.to_string(),
+                row,
+                col,
+                max_cols,
+                max_rows,
+                clip,
+            )
+        }
+        "ImageHide" => {
+            let id = try_u64!(args.get(1).ok_or("id missing")?, "image id");
+            GnvimEvent::ImageHide(id)
+        }
+        "ImageClear" => GnvimEvent::ImageClear,
+        "RecordingSet" => {
+            let register =
+                try_str!(args.get(1).ok_or("register missing")?, "register");
+            GnvimEvent::RecordingSet(register.to_string())
+        }
+        "CopySelection" => {
+            let target =
+                try_str!(args.get(1).ok_or("target missing")?, "target");
+            let text = try_str!(args.get(2).ok_or("text missing")?, "text");
+            GnvimEvent::CopySelection(target.to_string(), text.to_string())
+        }
+        "UnicodeInputShow" => {
+            let row = try_u64!(args.get(1).ok_or("row missing")?, "row");
+            let col = try_u64!(args.get(2).ok_or("col missing")?, "col");
+            GnvimEvent::UnicodeInputShow(row, col)
+        }
+        "HeaderbarPath" => {
+            let path = try_str!(args.get(1).ok_or("path missing")?, "path");
+            GnvimEvent::HeaderbarPath(path.to_string())
+        }
+        "RuntimeVersion" => {
+            let version =
+                try_u64!(args.get(1).ok_or("version missing")?, "version");
+            let path = try_str!(args.get(2).ok_or("path missing")?, "path");
+            GnvimEvent::RuntimeVersion(version, path.to_string())
+        }
+        "TaskBegin" => {
+            let name = try_str!(args.get(1).ok_or("name missing")?, "name");
+            GnvimEvent::TaskBegin(name.to_string())
+        }
+        "TaskUpdate" => {
+            let progress =
+                try_u64!(args.get(1).ok_or("progress missing")?, "progress");
+            GnvimEvent::TaskUpdate(progress)
+        }
+        "TaskEnd" => {
+            let status =
+                try_str!(args.get(1).ok_or("status missing")?, "status");
+            GnvimEvent::TaskEnd(status.to_string())
+        }
+        "CellWidths" => {
+            let mut widths = vec![];
+            for w in try_array!(args.get(1).ok_or("widths missing")?, "widths")
+            {
+                let w = try_array!(w, "width");
+                widths.push((
+                    try_u64!(w.get(0).ok_or("first missing")?, "first") as u32,
+                    try_u64!(w.get(1).ok_or("last missing")?, "last") as u32,
+                    try_u64!(w.get(2).ok_or("width missing")?, "width")
+                        as usize,
+                ));
+            }
+            let ambiwidth =
+                try_u64!(args.get(2).ok_or("ambiwidth missing")?, "ambiwidth");
+            GnvimEvent::CellWidths(widths, ambiwidth != 0)
+        }
+        "SetWmClass" => {
+            let name = try_str!(args.get(1).ok_or("name missing")?, "name");
+            GnvimEvent::SetWmClass(name.to_string())
+        }
+        "SetMaxFps" => {
+            let fps = try_u64!(args.get(1).ok_or("fps missing")?, "max fps");
+            GnvimEvent::SetMaxFps(fps)
+        }
+        "DesktopNotify" => {
+            let msg = try_str!(args.get(1).ok_or("msg missing")?, "notify msg");
+            let level =
+                try_u64!(args.get(2).ok_or("level missing")?, "notify level");
+            GnvimEvent::DesktopNotify(msg.to_string(), level)
+        }
+        "DesktopNotifySetLevel" => {
+            let level =
+                try_u64!(args.get(1).ok_or("level missing")?, "notify level");
+            GnvimEvent::DesktopNotifyLevel(level)
+        }
+        "RpcTrace" => {
+            let enabled =
+                try_u64!(args.get(1).ok_or("enabled missing")?, "rpc trace");
+            GnvimEvent::RpcTrace(enabled != 0)
+        }
+        "Profile" => {
+            let enabled =
+                try_u64!(args.get(1).ok_or("enabled missing")?, "profile");
+            GnvimEvent::Profile(enabled != 0)
+        }
+        "ShowDebugConsole" => GnvimEvent::ShowDebugConsole,
+        "PerfHud" => {
+            let enabled =
+                try_u64!(args.get(1).ok_or("enabled missing")?, "perf hud");
+            GnvimEvent::PerfHud(enabled != 0)
+        }
+        "ShowPreferences" => GnvimEvent::ShowPreferences,
         _ => GnvimEvent::Unknown(String::from(cmd)),
     };
 
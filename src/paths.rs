@@ -0,0 +1,45 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Returns gnvim's state directory, creating it if it doesn't exist yet.
+///
+/// The directory is `$XDG_STATE_HOME/gnvim`, falling back to
+/// `~/.local/state/gnvim` (`%LOCALAPPDATA%\gnvim` on Windows).
+pub fn state_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| {
+            env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".local").join("state"))
+        })
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+
+    let dir = base.join("gnvim");
+    fs::create_dir_all(&dir).ok()?;
+
+    Some(dir)
+}
+
+/// Error for when there is no state directory (see `state_dir`) to write
+/// to.
+pub fn no_state_dir() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "No state directory available")
+}
+
+/// Returns gnvim's config directory, `$XDG_CONFIG_HOME/gnvim` (falling back
+/// to `~/.config/gnvim`, or `%APPDATA%\gnvim` on Windows). The directory
+/// might not exist.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+        })
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+
+    Some(base.join("gnvim"))
+}
@@ -0,0 +1,275 @@
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use paths;
+use rpc_trace::{as_micros, escape_json};
+
+/// How many of the latest spans are kept in the profile.
+const CAPACITY: usize = 100_000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref PROFILER: Mutex<Profiler> = Mutex::new(Profiler::new());
+}
+
+thread_local! {
+    /// Id of the current thread in the profile, zero if not assigned yet.
+    static THREAD_ID: Cell<u32> = Cell::new(0);
+}
+
+/// Single timed part of a frame.
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    name: String,
+    /// Category of the span, e.g. "rpc" or "render".
+    cat: &'static str,
+    tid: u32,
+    /// Relative to the start of the profile.
+    start: Duration,
+    dur: Duration,
+    /// For the frame spans, the number of the frame.
+    frame: Option<u64>,
+}
+
+/// Timings of the frames: what happens between receiving redraw events
+/// from nvim and rendering them, in every thread. The latest spans are
+/// kept in a ring buffer, and they can be written in the chrome tracing
+/// format, which chrome://tracing and Perfetto (ui.perfetto.dev) can show.
+struct Profiler {
+    start: Instant,
+    spans: VecDeque<Span>,
+    /// Names of the threads, by their ids.
+    threads: HashMap<u32, String>,
+    frame: u64,
+}
+
+impl Profiler {
+    fn new() -> Self {
+        Profiler {
+            start: Instant::now(),
+            spans: VecDeque::new(),
+            threads: HashMap::new(),
+            frame: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.start = Instant::now();
+        self.spans.clear();
+        self.frame = 0;
+    }
+
+    /// Returns the id of the current thread. Threads without a name (e.g.
+    /// neovim-lib's) are named by the category of their first span.
+    fn thread_id(&mut self, cat: &str) -> u32 {
+        let next = self.threads.len() as u32 + 1;
+        let threads = &mut self.threads;
+        THREAD_ID.with(|id| {
+            if id.get() == 0 {
+                let name = thread::current().name().unwrap_or(cat).to_string();
+                threads.insert(next, name);
+                id.set(next);
+            }
+            id.get()
+        })
+    }
+
+    fn record(
+        &mut self,
+        cat: &'static str,
+        name: &str,
+        start: Instant,
+        frame: Option<u64>,
+    ) {
+        // Spans from before the profile was (re)started are dropped.
+        if start < self.start {
+            return;
+        }
+
+        if self.spans.len() == CAPACITY {
+            self.spans.pop_front();
+        }
+
+        let tid = self.thread_id(cat);
+        self.spans.push_back(Span {
+            name: name.to_string(),
+            cat,
+            tid,
+            start: start.duration_since(self.start),
+            dur: start.elapsed(),
+            frame,
+        });
+    }
+
+    /// Returns the profile in the chrome tracing (JSON object) format.
+    fn to_json(&self) -> String {
+        let mut events: Vec<String> = self
+            .threads
+            .iter()
+            .map(|(tid, name)| {
+                format!(
+                    "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\
+                     \"tid\":{},\"args\":{{\"name\":\"{}\"}}}}",
+                    tid,
+                    escape_json(name),
+                )
+            })
+            .collect();
+
+        events.extend(self.spans.iter().map(|s| {
+            format!(
+                "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{},\
+                 \"dur\":{},\"pid\":1,\"tid\":{}{}}}",
+                escape_json(&s.name),
+                s.cat,
+                as_micros(s.start),
+                as_micros(s.dur),
+                s.tid,
+                s.frame
+                    .map(|f| format!(",\"args\":{{\"frame\":{}}}", f))
+                    .unwrap_or_default(),
+            )
+        }));
+
+        format!(
+            "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n{}\n]}}",
+            events.join(",\n")
+        )
+    }
+}
+
+/// Measures a span from its creation until it's dropped.
+pub struct SpanGuard {
+    cat: &'static str,
+    name: String,
+    start: Instant,
+    frame: Option<u64>,
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        PROFILER
+            .lock()
+            .unwrap()
+            .record(self.cat, &self.name, self.start, self.frame);
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    if enabled && !is_enabled() {
+        PROFILER.lock().unwrap().clear();
+    }
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Starts a span in category `cat`, if profiling is enabled. The span ends
+/// when the returned guard is dropped.
+pub fn span(cat: &'static str, name: &str) -> Option<SpanGuard> {
+    if !is_enabled() {
+        return None;
+    }
+
+    Some(SpanGuard {
+        cat,
+        name: name.to_string(),
+        start: Instant::now(),
+        frame: None,
+    })
+}
+
+/// Starts the span of a new frame (see `span`). The spans of the frame's
+/// stages are nested in it.
+pub fn frame() -> Option<SpanGuard> {
+    if !is_enabled() {
+        return None;
+    }
+
+    let frame = {
+        let mut profiler = PROFILER.lock().unwrap();
+        profiler.frame += 1;
+        profiler.frame
+    };
+
+    Some(SpanGuard {
+        cat: "frame",
+        name: String::from("frame"),
+        start: Instant::now(),
+        frame: Some(frame),
+    })
+}
+
+/// Records a span that started at `start` and ends now (e.g. time spent
+/// waiting), if profiling is enabled.
+pub fn record(cat: &'static str, name: &str, start: Instant) {
+    if is_enabled() {
+        PROFILER.lock().unwrap().record(cat, name, start, None);
+    }
+}
+
+/// Writes the profile to gnvim's state directory. Returns the path of the
+/// written file.
+pub fn export() -> io::Result<PathBuf> {
+    let dir = paths::state_dir().ok_or_else(paths::no_state_dir)?;
+
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("profile-{}.json", secs));
+
+    fs::write(&path, PROFILER.lock().unwrap().to_json())?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let mut profiler = Profiler::new();
+        profiler.record("render", "draw", Instant::now(), Some(3));
+        profiler.spans[0].start = Duration::from_millis(1);
+        profiler.spans[0].dur = Duration::from_micros(250);
+        let tid = profiler.spans[0].tid;
+        profiler.threads.insert(tid, String::from("ui"));
+
+        assert_eq!(
+            profiler.to_json(),
+            format!(
+                "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n\
+                 {{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\
+                 \"tid\":{0},\"args\":{{\"name\":\"ui\"}}}},\n\
+                 {{\"name\":\"draw\",\"cat\":\"render\",\"ph\":\"X\",\
+                 \"ts\":1000,\"dur\":250,\"pid\":1,\"tid\":{0},\
+                 \"args\":{{\"frame\":3}}}}\n]}}",
+                tid
+            )
+        );
+    }
+
+    #[test]
+    fn test_record_before_start() {
+        let mut profiler = Profiler::new();
+        profiler.record("rpc", "redraw", Instant::now(), None);
+        assert_eq!(profiler.spans.len(), 1);
+
+        profiler.clear();
+        profiler.start += Duration::from_secs(60);
+        profiler.record("rpc", "redraw", Instant::now(), None);
+        assert!(profiler.spans.is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use neovim_lib::Value;
+
+use paths;
+
+/// How many of the latest messages are kept in the trace.
+const CAPACITY: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Request from nvim.
+    Request,
+    /// Our response to a request from nvim.
+    Response,
+    /// Notification from nvim.
+    Notification,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kind::Request => write!(fmt, "request"),
+            Kind::Response => write!(fmt, "response"),
+            Kind::Notification => write!(fmt, "notification"),
+        }
+    }
+}
+
+/// Single traced message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// When the message was seen, relative to the start of the trace.
+    pub time: Duration,
+    pub kind: Kind,
+    pub method: String,
+    /// Size of the message's arguments (or result), in bytes when encoded
+    /// as msgpack.
+    pub size: usize,
+    /// For responses, how long it took to respond to the request.
+    pub latency: Option<Duration>,
+}
+
+/// Trace of the msgpack-rpc messages between nvim and us. The latest
+/// messages are kept in a ring buffer.
+///
+/// Only the messages that go through our handlers are traced: the requests
+/// we make go directly through neovim-lib's session and are not seen here.
+pub struct RpcTrace {
+    enabled: bool,
+    start: Instant,
+    entries: VecDeque<Entry>,
+}
+
+impl RpcTrace {
+    pub fn new(enabled: bool) -> Self {
+        RpcTrace {
+            enabled,
+            start: Instant::now(),
+            entries: VecDeque::with_capacity(CAPACITY),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Records a message, if the trace is enabled. Returns the time the
+    /// message was recorded at, which can be used to calculate the latency
+    /// of a response.
+    pub fn record(
+        &mut self,
+        kind: Kind,
+        method: &str,
+        args: &[Value],
+        latency: Option<Duration>,
+    ) -> Instant {
+        let now = Instant::now();
+        if !self.enabled {
+            return now;
+        }
+
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(Entry {
+            time: now.duration_since(self.start),
+            kind,
+            method: method.to_string(),
+            size: args.iter().map(encoded_size).sum(),
+            latency,
+        });
+
+        now
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    /// Returns the trace as a JSON array.
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|e| {
+                format!(
+                    "{{\"time_us\":{},\"kind\":\"{}\",\"method\":\"{}\",\
+                     \"size\":{},\"latency_us\":{}}}",
+                    as_micros(e.time),
+                    e.kind,
+                    escape_json(&e.method),
+                    e.size,
+                    e.latency
+                        .map(|l| as_micros(l).to_string())
+                        .unwrap_or(String::from("null")),
+                )
+            })
+            .collect();
+
+        format!("[{}]", entries.join(",\n"))
+    }
+
+    /// Writes the trace as JSON to gnvim's state directory. Returns the
+    /// path of the written file.
+    pub fn export(&self) -> io::Result<PathBuf> {
+        let dir = paths::state_dir().ok_or_else(paths::no_state_dir)?;
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = dir.join(format!("rpc-trace-{}.json", secs));
+
+        fs::write(&path, self.to_json())?;
+
+        Ok(path)
+    }
+}
+
+pub(crate) fn as_micros(d: Duration) -> u64 {
+    d.as_secs() * 1_000_000 + d.subsec_micros() as u64
+}
+
+pub fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Returns the size of the msgpack header for a string, binary, array or
+/// map of `len` elements.
+fn header_size(len: usize, fix_max: usize) -> usize {
+    if len <= fix_max {
+        1
+    } else if len <= 0xffff {
+        3
+    } else {
+        5
+    }
+}
+
+/// Returns the size of `value` when encoded as msgpack.
+fn encoded_size(value: &Value) -> usize {
+    match value {
+        Value::Nil | Value::Boolean(_) => 1,
+        Value::Integer(i) => match (i.as_i64(), i.as_u64()) {
+            (Some(i), _) if i >= -32 && i <= 127 => 1,
+            (Some(i), _) if i >= -128 && i <= 255 => 2,
+            (Some(i), _) if i >= -32768 && i <= 65535 => 3,
+            (Some(i), _) if i >= -2_147_483_648 && i <= 4_294_967_295 => 5,
+            _ => 9,
+        },
+        Value::F32(_) => 5,
+        Value::F64(_) => 9,
+        Value::String(s) => {
+            let len = s.as_bytes().len();
+            // Strings have one byte header up to 255 bytes (str8).
+            let header = if len <= 31 {
+                1
+            } else if len <= 0xff {
+                2
+            } else {
+                header_size(len, 0)
+            };
+            header + len
+        }
+        Value::Binary(b) => {
+            let header = if b.len() <= 0xff {
+                2
+            } else {
+                header_size(b.len(), 0)
+            };
+            header + b.len()
+        }
+        Value::Array(arr) => {
+            header_size(arr.len(), 15)
+                + arr.iter().map(encoded_size).sum::<usize>()
+        }
+        Value::Map(map) => {
+            header_size(map.len(), 15)
+                + map
+                    .iter()
+                    .map(|(k, v)| encoded_size(k) + encoded_size(v))
+                    .sum::<usize>()
+        }
+        Value::Ext(_, data) => 6 + data.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut trace = RpcTrace::new(false);
+        trace.record(Kind::Notification, "redraw", &[], None);
+        assert_eq!(trace.entries().count(), 0);
+
+        trace.set_enabled(true);
+        for _ in 0..CAPACITY + 10 {
+            trace.record(Kind::Notification, "redraw", &[], None);
+        }
+        trace.record(
+            Kind::Response,
+            "Gnvim",
+            &[Value::from(1)],
+            Some(Duration::from_millis(2)),
+        );
+        assert_eq!(trace.entries().count(), CAPACITY);
+
+        let last = trace.entries().last().unwrap();
+        assert_eq!(last.kind, Kind::Response);
+        assert_eq!(last.size, 1);
+        assert_eq!(last.latency, Some(Duration::from_millis(2)));
+
+        trace.clear();
+        assert_eq!(trace.entries().count(), 0);
+    }
+
+    #[test]
+    fn test_encoded_size() {
+        assert_eq!(encoded_size(&Value::from(1)), 1);
+        assert_eq!(encoded_size(&Value::from(200)), 2);
+        assert_eq!(encoded_size(&Value::from(-1000)), 3);
+        assert_eq!(encoded_size(&Value::from(1u64 << 40)), 9);
+        assert_eq!(encoded_size(&Value::from("foo")), 4);
+        assert_eq!(
+            encoded_size(&Value::from(vec![Value::from("a"), Value::Nil])),
+            4
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut trace = RpcTrace::new(true);
+        trace.record(Kind::Request, "a\"b", &[], None);
+        trace.entries[0].time = Duration::from_millis(1);
+
+        assert_eq!(
+            trace.to_json(),
+            "[{\"time_us\":1000,\"kind\":\"request\",\"method\":\"a\\\"b\",\
+             \"size\":0,\"latency_us\":null}]"
+        );
+    }
+}
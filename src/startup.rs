@@ -0,0 +1,510 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use neovim_lib::Value;
+
+use key_value;
+use paths;
+
+const FILE_NAME: &str = "startup";
+/// How many launches are kept.
+const MAX_LAUNCHES: usize = 50;
+/// How many launches are shown in the report.
+const REPORT_LAUNCHES: usize = 10;
+/// How many of the previous launches the latest one is compared to.
+const BASELINE: usize = 5;
+/// A phase is slower than usual if it takes this much longer (in
+/// milliseconds)...
+const REGRESSION_MS: u64 = 50;
+/// ...and this many times as long as usual.
+const REGRESSION_RATIO: f64 = 1.5;
+
+/// Set once the launch is recorded (or discarded), after which the phases
+/// are ignored.
+static DONE: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref CURRENT: Mutex<Option<(Instant, Launch)>> = Mutex::new(None);
+}
+
+/// Phases of gnvim's startup, in order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// Nvim's process is spawned.
+    Spawn,
+    /// Our UI is attached to nvim.
+    Attach,
+    /// First redraw batch from nvim is flushed.
+    FirstFlush,
+    /// First frame is drawn after the first flush.
+    FirstFrame,
+}
+
+const PHASES: [Phase; 4] = [
+    Phase::Spawn,
+    Phase::Attach,
+    Phase::FirstFlush,
+    Phase::FirstFrame,
+];
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Spawn => "spawn",
+            Phase::Attach => "attach",
+            Phase::FirstFlush => "flush",
+            Phase::FirstFrame => "frame",
+        }
+    }
+}
+
+/// Timings of a single launch.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Launch {
+    /// When gnvim was started, in seconds since the unix epoch.
+    time: u64,
+    gnvim: String,
+    nvim: String,
+    /// Modification time of nvim's config (init.vim or init.lua), in seconds
+    /// since the unix epoch. Zero if there is no config.
+    config: u64,
+    /// Milliseconds from gnvim's start to the end of each phase.
+    phases: [Option<u64>; 4],
+}
+
+impl Launch {
+    /// Returns how long (in milliseconds) `phase` itself took.
+    fn duration(&self, phase: Phase) -> Option<u64> {
+        let i = phase as usize;
+        let prev = if i == 0 { Some(0) } else { self.phases[i - 1] };
+        Some(self.phases[i]?.saturating_sub(prev?))
+    }
+
+    fn total(&self) -> Option<u64> {
+        self.phases[PHASES.len() - 1]
+    }
+
+    /// Parses a launch from space separated `key=value` pairs.
+    fn parse(line: &str) -> Option<Self> {
+        let mut launch = Launch::default();
+
+        for pair in line.split_whitespace() {
+            let (key, value) = match key_value::split(pair) {
+                Some(pair) => pair,
+                None => continue,
+            };
+
+            match key {
+                "time" => launch.time = value.parse().ok()?,
+                "gnvim" => launch.gnvim = value.to_string(),
+                "nvim" => launch.nvim = value.to_string(),
+                "config" => launch.config = value.parse().unwrap_or(0),
+                _ => {
+                    if let Some(i) = PHASES.iter().position(|p| p.name() == key)
+                    {
+                        launch.phases[i] = value.parse().ok();
+                    }
+                }
+            }
+        }
+
+        if launch.time == 0 {
+            return None;
+        }
+
+        Some(launch)
+    }
+
+    fn serialize(&self) -> String {
+        let mut line = format!(
+            "time={} gnvim={} nvim={} config={}",
+            self.time, self.gnvim, self.nvim, self.config
+        );
+        for (phase, value) in PHASES.iter().zip(self.phases.iter()) {
+            if let Some(value) = value {
+                line.push_str(&format!(" {}={}", phase.name(), value));
+            }
+        }
+
+        line
+    }
+}
+
+/// Starts recording the startup of this launch. Should be called as early
+/// as possible.
+pub fn start(gnvim_version: &str) {
+    let launch = Launch {
+        time: unix_time(SystemTime::now()),
+        gnvim: gnvim_version.to_string(),
+        config: config_mtime(),
+        ..Launch::default()
+    };
+
+    *CURRENT.lock().unwrap() = Some((Instant::now(), launch));
+}
+
+/// Sets the version of nvim from nvim's api info.
+pub fn set_nvim_version(api_info: &[Value]) {
+    let version = api_info
+        .get(1)
+        .and_then(|meta| map_get(meta, "version"))
+        .map(|version| {
+            ["major", "minor", "patch"]
+                .iter()
+                .map(|key| {
+                    map_get(version, key)
+                        .and_then(Value::as_u64)
+                        .unwrap_or(0)
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+                .join(".")
+        });
+
+    if let Some(version) = version {
+        if let Some((_, ref mut launch)) = *CURRENT.lock().unwrap() {
+            launch.nvim = version;
+        }
+    }
+}
+
+/// Marks `phase` done. Only the first time each phase is done counts, and
+/// only after the phases before it are done. Once the first frame is
+/// drawn, the launch is saved to gnvim's state directory.
+pub fn mark(phase: Phase) {
+    if DONE.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let mut current = CURRENT.lock().unwrap();
+    let (start, launch) = match *current {
+        Some((start, ref mut launch)) => (start, launch),
+        None => return,
+    };
+
+    let i = phase as usize;
+    if launch.phases[i].is_some() || (i > 0 && launch.phases[i - 1].is_none()) {
+        return;
+    }
+
+    let elapsed = start.elapsed();
+    launch.phases[i] =
+        Some(elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis()));
+
+    if phase == Phase::FirstFrame {
+        DONE.store(true, Ordering::SeqCst);
+        if let Err(err) = save(launch) {
+            eprintln!("Failed to save the startup times: {}", err);
+        }
+    }
+}
+
+/// Stops recording this launch without saving it. Used when the startup is
+/// interrupted (e.g. by an error dialog), so that the launch doesn't look
+/// like a regression.
+pub fn discard() {
+    DONE.store(true, Ordering::SeqCst);
+}
+
+fn save(launch: &Launch) -> io::Result<()> {
+    let path = path().ok_or_else(paths::no_state_dir)?;
+
+    let mut launches = load();
+    launches.push(launch.clone());
+    let skip = launches.len().saturating_sub(MAX_LAUNCHES);
+
+    let data: String = launches[skip..]
+        .iter()
+        .map(|launch| launch.serialize() + "\n")
+        .collect();
+    fs::write(path, data)
+}
+
+fn load() -> Vec<Launch> {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|data| data.lines().filter_map(Launch::parse).collect())
+        .unwrap_or_default()
+}
+
+fn path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Returns the modification time of nvim's config.
+fn config_mtime() -> u64 {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+        })
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .map(|base| base.join("nvim"));
+
+    dir.and_then(|dir| {
+        ["init.lua", "init.vim"]
+            .iter()
+            .filter_map(|name| fs::metadata(dir.join(name)).ok())
+            .filter_map(|meta| meta.modified().ok())
+            .next()
+    })
+    .map(unix_time)
+    .unwrap_or(0)
+}
+
+fn map_get<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    value
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .map(|(_, v)| v)
+}
+
+/// Returns what changed between the `prev` and `next` launches.
+fn changes(prev: &Launch, next: &Launch) -> Vec<String> {
+    let mut changes = vec![];
+    if prev.gnvim != next.gnvim {
+        changes.push(format!("gnvim {} -> {}", prev.gnvim, next.gnvim));
+    }
+    if prev.nvim != next.nvim {
+        changes.push(format!("nvim {} -> {}", prev.nvim, next.nvim));
+    }
+    if prev.config != next.config {
+        changes.push(String::from("config changed"));
+    }
+
+    changes
+}
+
+/// Returns the phases of the latest launch that took much longer than they
+/// usually do (e.g. the median of the launches before it), with the
+/// duration of the latest launch and the usual duration.
+fn regressions(launches: &[Launch]) -> Vec<(Phase, u64, u64)> {
+    let (latest, previous) = match launches.split_last() {
+        Some(split) => split,
+        None => return vec![],
+    };
+    let baseline = &previous[previous.len().saturating_sub(BASELINE)..];
+
+    let mut regressions = vec![];
+    for phase in PHASES.iter() {
+        let mut durations: Vec<u64> =
+            baseline.iter().filter_map(|l| l.duration(*phase)).collect();
+        let latest = match latest.duration(*phase) {
+            Some(latest) if !durations.is_empty() => latest,
+            _ => continue,
+        };
+
+        durations.sort_unstable();
+        let usual = durations[durations.len() / 2];
+        if latest >= usual + REGRESSION_MS
+            && latest as f64 >= usual as f64 * REGRESSION_RATIO
+        {
+            regressions.push((*phase, latest, usual));
+        }
+    }
+
+    regressions
+}
+
+/// Returns how long ago `time` was from `now` (both in seconds since the
+/// unix epoch).
+fn ago(time: u64, now: u64) -> String {
+    let secs = now.saturating_sub(time);
+    if secs < 60 * 60 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 60 * 60 * 24 {
+        format!("{}h ago", secs / (60 * 60))
+    } else {
+        format!("{}d ago", secs / (60 * 60 * 24))
+    }
+}
+
+fn format_ms(ms: Option<u64>) -> String {
+    ms.map(|ms| ms.to_string())
+        .unwrap_or_else(|| String::from("-"))
+}
+
+/// Returns the report of `launches` (oldest first), as of `now`.
+fn format_report(launches: &[Launch], now: u64) -> String {
+    if launches.is_empty() {
+        return String::from(
+            "No startup times recorded yet. They are recorded every time \
+             gnvim starts.\n",
+        );
+    }
+
+    let mut report = String::from(
+        "Startup times (in milliseconds) of the latest launches:\n\n",
+    );
+    let header = format!(
+        "{:<10} {:<8} {:<8} {:>6} {:>6} {:>6} {:>6} {:>6}",
+        "", "gnvim", "nvim", "spawn", "attach", "flush", "frame", "total"
+    );
+    report.push_str(&header);
+    report.push('\n');
+
+    let skip = launches.len().saturating_sub(REPORT_LAUNCHES);
+    for (i, launch) in launches.iter().enumerate().skip(skip) {
+        let changes = if i > 0 {
+            changes(&launches[i - 1], launch).join(", ")
+        } else {
+            String::new()
+        };
+
+        let row = format!(
+            "{:<10} {:<8} {:<8} {:>6} {:>6} {:>6} {:>6} {:>6}  {}",
+            ago(launch.time, now),
+            launch.gnvim,
+            launch.nvim,
+            format_ms(launch.duration(Phase::Spawn)),
+            format_ms(launch.duration(Phase::Attach)),
+            format_ms(launch.duration(Phase::FirstFlush)),
+            format_ms(launch.duration(Phase::FirstFrame)),
+            format_ms(launch.total()),
+            changes,
+        );
+        report.push_str(row.trim_end());
+        report.push('\n');
+    }
+
+    let regressions = regressions(launches);
+    report.push('\n');
+    if regressions.is_empty() {
+        report.push_str("The latest launch was not slower than usual.\n");
+        return report;
+    }
+
+    report.push_str("The latest launch was slower than usual:\n");
+    for (phase, latest, usual) in regressions {
+        report.push_str(&format!(
+            "  {} took {}ms, usually {}ms\n",
+            phase.name(),
+            latest,
+            usual
+        ));
+    }
+
+    // Changes since the previous launch likely explain the regression.
+    let prev = &launches[launches.len().saturating_sub(2)];
+    let changes = changes(prev, &launches[launches.len() - 1]);
+    if !changes.is_empty() {
+        report.push_str(&format!(
+            "Changed since the previous launch: {}\n",
+            changes.join(", ")
+        ));
+    }
+
+    report
+}
+
+/// Returns the report of the recorded startup times, for
+/// `gnvim doctor --startup`.
+pub fn report() -> String {
+    format_report(&load(), unix_time(SystemTime::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn launch(phases: [u64; 4]) -> Launch {
+        Launch {
+            time: 1000,
+            gnvim: String::from("0.1.0"),
+            nvim: String::from("0.4.3"),
+            config: 10,
+            phases: [
+                Some(phases[0]),
+                Some(phases[1]),
+                Some(phases[2]),
+                Some(phases[3]),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_parse_serialize() {
+        let launch = launch([10, 50, 200, 210]);
+        assert_eq!(Launch::parse(&launch.serialize()), Some(launch));
+
+        let launch = Launch::parse("time=5 nvim=0.4.3 attach=foo bar").unwrap();
+        assert_eq!(launch.nvim, "0.4.3");
+        assert_eq!(launch.phases, [None; 4]);
+
+        assert_eq!(Launch::parse("gnvim=0.1.0 spawn=10"), None);
+    }
+
+    #[test]
+    fn test_duration() {
+        let mut launch = launch([10, 50, 200, 210]);
+        assert_eq!(launch.duration(Phase::Spawn), Some(10));
+        assert_eq!(launch.duration(Phase::FirstFlush), Some(150));
+        assert_eq!(launch.total(), Some(210));
+
+        launch.phases[1] = None;
+        assert_eq!(launch.duration(Phase::Attach), None);
+        assert_eq!(launch.duration(Phase::FirstFlush), None);
+    }
+
+    #[test]
+    fn test_regressions() {
+        let mut launches = vec![
+            launch([10, 50, 200, 210]),
+            launch([12, 55, 190, 200]),
+            launch([11, 45, 210, 220]),
+        ];
+        assert_eq!(regressions(&launches), vec![]);
+
+        // Attach took 300ms instead of ~40ms.
+        launches.push(launch([10, 310, 460, 470]));
+        assert_eq!(regressions(&launches), vec![(Phase::Attach, 300, 40)]);
+
+        // Small differences are not regressions.
+        launches.push(launch([30, 80, 230, 240]));
+        assert_eq!(regressions(&launches), vec![]);
+
+        assert_eq!(regressions(&launches[..1]), vec![]);
+        assert_eq!(regressions(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_changes() {
+        let prev = launch([0; 4]);
+        let mut next = prev.clone();
+        assert!(changes(&prev, &next).is_empty());
+
+        next.nvim = String::from("0.5.0");
+        next.config = 20;
+        assert_eq!(
+            changes(&prev, &next),
+            vec!["nvim 0.4.3 -> 0.5.0", "config changed"]
+        );
+    }
+
+    #[test]
+    fn test_format_report() {
+        let mut launches =
+            vec![launch([10, 50, 200, 210]), launch([12, 55, 190, 200])];
+        let mut slow = launch([10, 400, 550, 560]);
+        slow.gnvim = String::from("0.2.0");
+        launches.push(slow);
+
+        let report = format_report(&launches, 1000 + 2 * 60 * 60);
+        assert!(report.contains("2h ago"));
+        assert!(report.contains("attach took 390ms, usually 43ms"));
+        assert!(report.contains("Changed since the previous launch: gnvim"));
+    }
+}
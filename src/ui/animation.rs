@@ -0,0 +1,220 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use gdk::prelude::*;
+use gtk;
+use gtk::prelude::*;
+
+use nvim_bridge::WindowAnimation;
+
+/// Window property that can be animated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Property {
+    /// Opacity of the window, from 0 to 1. Requires a compositor.
+    Opacity,
+    /// Width and height of the window.
+    Size,
+    /// Position (x, y) of the window. Not supported on Wayland, where
+    /// windows can't move themselves.
+    Position,
+}
+
+impl Property {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "opacity" => Some(Property::Opacity),
+            "size" => Some(Property::Size),
+            "position" => Some(Property::Position),
+            _ => None,
+        }
+    }
+
+    /// Number of values the property has.
+    fn len(self) -> usize {
+        match self {
+            Property::Opacity => 1,
+            Property::Size | Property::Position => 2,
+        }
+    }
+
+    fn get(self, window: &gtk::Window) -> Vec<f64> {
+        match self {
+            Property::Opacity => vec![window.get_opacity()],
+            Property::Size => {
+                let (w, h) = window.get_size();
+                vec![w as f64, h as f64]
+            }
+            Property::Position => {
+                let (x, y) = window.get_position();
+                vec![x as f64, y as f64]
+            }
+        }
+    }
+
+    fn set(self, window: &gtk::Window, value: &[f64]) {
+        match self {
+            Property::Opacity => window.set_opacity(value[0].max(0.0).min(1.0)),
+            Property::Size => {
+                window.resize(value[0].round() as i32, value[1].round() as i32)
+            }
+            Property::Position => {
+                window.move_(value[0].round() as i32, value[1].round() as i32)
+            }
+        }
+    }
+}
+
+/// How the animation progresses over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Oscillates between the start and the end values with a decreasing
+    /// amplitude, and ends where it started (e.g. for shake-on-error).
+    Shake,
+}
+
+impl Easing {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "linear" => Some(Easing::Linear),
+            "ease-in" => Some(Easing::EaseIn),
+            "ease-out" => Some(Easing::EaseOut),
+            "ease-in-out" => Some(Easing::EaseInOut),
+            "shake" => Some(Easing::Shake),
+            _ => None,
+        }
+    }
+
+    /// Returns the progress of the animation (0 being the start value and
+    /// 1 the end value) at time `t`, from 0 to 1.
+    fn apply(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::Shake => (t * PI * 6.0).sin() * (1.0 - t),
+        }
+    }
+}
+
+/// Returns the value between `from` and `to` at time `t` (from 0 to 1).
+fn interpolate(from: &[f64], to: &[f64], easing: Easing, t: f64) -> Vec<f64> {
+    let p = easing.apply(t.max(0.0).min(1.0));
+    from.iter().zip(to).map(|(a, b)| a + (b - a) * p).collect()
+}
+
+/// Animations of the window's properties. Each property has at most one
+/// animation running: a new animation replaces the running one. The values
+/// are updated on the window's frame clock.
+#[derive(Default)]
+pub struct Animations {
+    /// Tick callbacks of the running animations.
+    running: Rc<RefCell<HashMap<Property, u32>>>,
+}
+
+impl Animations {
+    /// Starts `anim` on `window`. Fails if the animation's property or
+    /// easing is unknown, or it has a wrong number of values.
+    pub fn animate(
+        &self,
+        window: &gtk::Window,
+        anim: &WindowAnimation,
+    ) -> Result<(), String> {
+        let property = Property::from_str(&anim.property)
+            .ok_or(format!("Unknown property: {}", anim.property))?;
+        let easing = Easing::from_str(&anim.easing)
+            .ok_or(format!("Unknown easing: {}", anim.easing))?;
+
+        let from = if anim.from.is_empty() {
+            property.get(window)
+        } else {
+            anim.from.clone()
+        };
+        let n = property.len();
+        if from.len() != n || anim.to.len() != n {
+            return Err(format!(
+                "Animation of {} needs {} value(s)",
+                anim.property, n
+            ));
+        }
+
+        let to: Vec<f64> = if anim.relative {
+            from.iter().zip(&anim.to).map(|(a, b)| a + b).collect()
+        } else {
+            anim.to.clone()
+        };
+
+        if let Some(id) = self.running.borrow_mut().remove(&property) {
+            window.remove_tick_callback(id);
+        }
+
+        property.set(window, &from);
+
+        let running = self.running.clone();
+        let duration = anim.duration.max(1) as f64 * 1000.0;
+        let mut start = None;
+        let id = window.add_tick_callback(move |window, clock| {
+            let now = clock.get_frame_time() as f64;
+            let t = (now - *start.get_or_insert(now)) / duration;
+
+            property.set(window, &interpolate(&from, &to, easing, t));
+
+            if t >= 1.0 {
+                running.borrow_mut().remove(&property);
+                return gtk::Continue(false);
+            }
+
+            gtk::Continue(true)
+        });
+
+        self.running.borrow_mut().insert(property, id);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing_endpoints() {
+        let easings = [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ];
+        for easing in easings.iter() {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-9);
+            assert!((easing.apply(0.5) - 0.5).abs() <= 0.4);
+        }
+
+        // Shake starts and ends at the start value.
+        assert!(Easing::Shake.apply(0.0).abs() < 1e-9);
+        assert!(Easing::Shake.apply(1.0).abs() < 1e-9);
+        assert!(Easing::Shake.apply(1.0 / 12.0) > 0.5);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let from = [0.0, 100.0];
+        let to = [10.0, 50.0];
+        assert_eq!(interpolate(&from, &to, Easing::Linear, 0.5), [5.0, 75.0]);
+        assert_eq!(interpolate(&from, &to, Easing::Linear, 2.0), [10.0, 50.0]);
+        assert_eq!(interpolate(&from, &to, Easing::EaseIn, -1.0), [0.0, 100.0]);
+    }
+}
@@ -0,0 +1,258 @@
+mod tables;
+
+use neovim_lib::Value;
+
+/// Widths of the characters, in cells, as nvim has them: the widths from
+/// the Unicode data (which is probably newer than nvim's), `'ambiwidth'`
+/// and the overrides set with `setcellwidths()`. Nvim tells us where each
+/// character is, but we need the widths for our own text (e.g. the ghost
+/// text), and to tell which characters are likely to be drawn at a
+/// different width than nvim gave them (see `is_isolated`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CellWidths {
+    /// Ranges (first, last) of characters and their widths, sorted.
+    overrides: Vec<(u32, u32, usize)>,
+    /// If `'ambiwidth'` is "double".
+    ambiwidth_double: bool,
+}
+
+impl CellWidths {
+    pub fn new(
+        mut overrides: Vec<(u32, u32, usize)>,
+        ambiwidth_double: bool,
+    ) -> Self {
+        overrides.sort();
+
+        CellWidths {
+            overrides,
+            ambiwidth_double,
+        }
+    }
+
+    /// Returns the width of `c`.
+    pub fn width(&self, c: char) -> usize {
+        let c = c as u32;
+
+        if let Some(width) = self.overridden(c) {
+            return width;
+        }
+
+        if c < 0x7f {
+            return if c < 0x20 { 0 } else { 1 };
+        }
+
+        if contains(tables::ZERO, c) {
+            0
+        } else if contains(tables::WIDE, c)
+            || (self.ambiwidth_double && contains(tables::AMBIGUOUS, c))
+        {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Returns the width of `text`.
+    pub fn str_width(&self, text: &str) -> usize {
+        text.chars().map(|c| self.width(c)).sum()
+    }
+
+    /// If the cell with `text` is shaped on its own, so that its glyphs
+    /// don't move the glyphs after it. That's the case when the width of
+    /// its glyphs is likely to differ from the one cell that it has: wide
+    /// characters, characters with overridden widths and the private use
+    /// area, where the icon fonts are.
+    pub fn is_isolated(&self, text: &str) -> bool {
+        let c = match text.chars().next() {
+            Some(c) => c,
+            None => return false,
+        };
+        if (c as u32) < 0x7f {
+            return false;
+        }
+
+        self.width(c) != 1
+            || is_private_use(c as u32)
+            || self.overridden(c as u32).is_some()
+    }
+
+    /// Returns the width of `c`, if it's overridden.
+    fn overridden(&self, c: u32) -> Option<usize> {
+        find(&self.overrides, c, |&(first, last, _)| (first, last))
+            .map(|&(_, _, width)| width)
+    }
+}
+
+/// Parses the overrides in the `cell-widths` setting. The overrides are
+/// separated by commas, and each is a codepoint (in hex) or a range of
+/// them, and the width: e.g. "e000-f8ff:2, 1f600:1".
+pub fn parse_overrides(text: &str) -> Result<Vec<(u32, u32, usize)>, String> {
+    let mut overrides = vec![];
+
+    for item in text.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let mut parts = item.splitn(2, ':');
+        let range = parts.next().unwrap_or_default();
+        let width = parts
+            .next()
+            .and_then(|width| width.trim().parse::<usize>().ok())
+            .filter(|&width| width == 1 || width == 2)
+            .ok_or_else(|| format!("Invalid width in \"{}\" (1 or 2)", item))?;
+
+        let mut bounds = range.splitn(2, '-');
+        let parse = |hex: Option<&str>| {
+            hex.map(|hex| hex.trim().trim_start_matches("U+"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        };
+        let first = parse(bounds.next())
+            .ok_or_else(|| format!("Invalid codepoint in \"{}\"", item))?;
+        let last = match bounds.next() {
+            Some(last) => parse(Some(last))
+                .ok_or_else(|| format!("Invalid codepoint in \"{}\"", item))?,
+            None => first,
+        };
+
+        // Same limit as in setcellwidths().
+        if first < 0x80 || last < first {
+            return Err(format!("Invalid range in \"{}\"", item));
+        }
+
+        overrides.push((first, last, width));
+    }
+
+    overrides.sort();
+    if overrides.windows(2).any(|w| w[0].1 >= w[1].0) {
+        return Err(String::from("Overlapping ranges"));
+    }
+
+    Ok(overrides)
+}
+
+/// Returns the overrides as the argument of `setcellwidths()`.
+pub fn overrides_to_value(overrides: &[(u32, u32, usize)]) -> Value {
+    Value::Array(
+        overrides
+            .iter()
+            .map(|&(first, last, width)| {
+                Value::Array(vec![
+                    Value::from(first),
+                    Value::from(last),
+                    Value::from(width as u64),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// Returns the overrides as a vim list, in the format of `setcellwidths()`.
+pub fn overrides_to_vim(overrides: &[(u32, u32, usize)]) -> String {
+    let items: Vec<String> = overrides
+        .iter()
+        .map(|&(first, last, width)| {
+            format!("[0x{:x}, 0x{:x}, {}]", first, last, width)
+        })
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Returns the entry of the sorted `ranges` that contains `c`.
+fn find<T, F>(ranges: &[T], c: u32, range: F) -> Option<&T>
+where
+    F: Fn(&T) -> (u32, u32),
+{
+    ranges
+        .binary_search_by(|entry| {
+            let (first, last) = range(entry);
+            if last < c {
+                ::std::cmp::Ordering::Less
+            } else if first > c {
+                ::std::cmp::Ordering::Greater
+            } else {
+                ::std::cmp::Ordering::Equal
+            }
+        })
+        .ok()
+        .map(|i| &ranges[i])
+}
+
+fn contains(table: &[(u32, u32)], c: u32) -> bool {
+    find(table, c, |&range| range).is_some()
+}
+
+fn is_private_use(c: u32) -> bool {
+    (0xe000..=0xf8ff).contains(&c)
+        || (0xf0000..=0xffffd).contains(&c)
+        || (0x100000..=0x10fffd).contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_sorted(table: &[(u32, u32)]) -> bool {
+        table.iter().all(|&(first, last)| first <= last)
+            && table.windows(2).all(|w| w[0].1 < w[1].0)
+    }
+
+    #[test]
+    fn test_tables_sorted() {
+        assert!(is_sorted(tables::WIDE));
+        assert!(is_sorted(tables::AMBIGUOUS));
+        assert!(is_sorted(tables::ZERO));
+    }
+
+    #[test]
+    fn test_width() {
+        let widths = CellWidths::default();
+        assert_eq!(widths.width('a'), 1);
+        assert_eq!(widths.width('ä'), 1);
+        assert_eq!(widths.width('\u{301}'), 0);
+        assert_eq!(widths.width('漢'), 2);
+        assert_eq!(widths.width('😀'), 2);
+        // New in Unicode 15.
+        assert_eq!(widths.width('\u{1fae8}'), 2);
+        assert_eq!(widths.width('\u{e0a0}'), 1);
+        assert_eq!(widths.width('·'), 1);
+        assert_eq!(widths.str_width("a漢\u{301}"), 3);
+
+        let widths = CellWidths::new(vec![(0xe000, 0xf8ff, 2)], true);
+        assert_eq!(widths.width('\u{e0a0}'), 2);
+        assert_eq!(widths.width('·'), 2);
+        assert_eq!(widths.width('a'), 1);
+    }
+
+    #[test]
+    fn test_is_isolated() {
+        let widths = CellWidths::new(vec![(0x2665, 0x2665, 1)], false);
+        assert!(!widths.is_isolated("a"));
+        assert!(!widths.is_isolated("ä"));
+        assert!(!widths.is_isolated(""));
+        assert!(widths.is_isolated("漢"));
+        assert!(widths.is_isolated("\u{e0a0}"));
+        assert!(widths.is_isolated("\u{2665}"));
+    }
+
+    #[test]
+    fn test_parse_overrides() {
+        assert_eq!(parse_overrides(""), Ok(vec![]));
+        assert_eq!(
+            parse_overrides("1f600:1, e000-f8ff:2"),
+            Ok(vec![(0xe000, 0xf8ff, 2), (0x1f600, 0x1f600, 1)])
+        );
+        assert_eq!(parse_overrides("U+2665:2"), Ok(vec![(0x2665, 0x2665, 2)]));
+        assert!(parse_overrides("e000:3").is_err());
+        assert!(parse_overrides("e000").is_err());
+        assert!(parse_overrides("41:2").is_err());
+        assert!(parse_overrides("f000-e000:2").is_err());
+        assert!(parse_overrides("zz:2").is_err());
+        assert!(parse_overrides("e000-e0ff:2,e0a0:1").is_err());
+    }
+
+    #[test]
+    fn test_overrides_to_vim() {
+        assert_eq!(overrides_to_vim(&[]), "[]");
+        assert_eq!(
+            overrides_to_vim(&[(0xe000, 0xf8ff, 2), (0x1f600, 0x1f600, 1)]),
+            "[[0xe000, 0xf8ff, 2], [0x1f600, 0x1f600, 1]]"
+        );
+    }
+}
@@ -0,0 +1,655 @@
+// Unicode 17.0.0 character widths, generated from EastAsianWidth.txt and
+// DerivedGeneralCategory.txt of the Unicode Character Database. The ranges
+// are sorted and don't overlap.
+
+/// Wide (W) and fullwidth (F) characters, two cells. Includes the emoji
+/// that are shown as emoji by default.
+pub const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2630, 0x2637),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x268a, 0x268f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x2e99),
+    (0x2e9b, 0x2ef3),
+    (0x2f00, 0x2fd5),
+    (0x2ff0, 0x3029),
+    (0x3030, 0x303e),
+    (0x3041, 0x3096),
+    (0x309b, 0x30ff),
+    (0x3105, 0x312f),
+    (0x3131, 0x3163),
+    (0x3165, 0x318e),
+    (0x3190, 0x31e5),
+    (0x31ef, 0x321e),
+    (0x3220, 0x3247),
+    (0x3250, 0xa48c),
+    (0xa490, 0xa4c6),
+    (0xa960, 0xa97c),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe52),
+    (0xfe54, 0xfe66),
+    (0xfe68, 0xfe6b),
+    (0xff01, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x16fe3),
+    (0x16ff2, 0x16ff6),
+    (0x17000, 0x18cd5),
+    (0x18cff, 0x18d1e),
+    (0x18d80, 0x18df2),
+    (0x1aff0, 0x1aff3),
+    (0x1aff5, 0x1affb),
+    (0x1affd, 0x1affe),
+    (0x1b000, 0x1b122),
+    (0x1b132, 0x1b132),
+    (0x1b150, 0x1b152),
+    (0x1b155, 0x1b155),
+    (0x1b164, 0x1b167),
+    (0x1b170, 0x1b2fb),
+    (0x1d300, 0x1d356),
+    (0x1d360, 0x1d376),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f1e6, 0x1f202),
+    (0x1f210, 0x1f23b),
+    (0x1f240, 0x1f248),
+    (0x1f250, 0x1f251),
+    (0x1f260, 0x1f265),
+    (0x1f300, 0x1f320),
+    (0x1f32d, 0x1f335),
+    (0x1f337, 0x1f37c),
+    (0x1f37e, 0x1f393),
+    (0x1f3a0, 0x1f3ca),
+    (0x1f3cf, 0x1f3d3),
+    (0x1f3e0, 0x1f3f0),
+    (0x1f3f4, 0x1f3f4),
+    (0x1f3f8, 0x1f43e),
+    (0x1f440, 0x1f440),
+    (0x1f442, 0x1f4fc),
+    (0x1f4ff, 0x1f53d),
+    (0x1f54b, 0x1f54e),
+    (0x1f550, 0x1f567),
+    (0x1f57a, 0x1f57a),
+    (0x1f595, 0x1f596),
+    (0x1f5a4, 0x1f5a4),
+    (0x1f5fb, 0x1f64f),
+    (0x1f680, 0x1f6c5),
+    (0x1f6cc, 0x1f6cc),
+    (0x1f6d0, 0x1f6d2),
+    (0x1f6d5, 0x1f6d8),
+    (0x1f6dc, 0x1f6df),
+    (0x1f6eb, 0x1f6ec),
+    (0x1f6f4, 0x1f6fc),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f7f0, 0x1f7f0),
+    (0x1f90c, 0x1f93a),
+    (0x1f93c, 0x1f945),
+    (0x1f947, 0x1f9ff),
+    (0x1fa70, 0x1fa7c),
+    (0x1fa80, 0x1fa8a),
+    (0x1fa8e, 0x1fac6),
+    (0x1fac8, 0x1fac8),
+    (0x1facd, 0x1fadc),
+    (0x1fadf, 0x1faea),
+    (0x1faef, 0x1faf8),
+    (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
+
+/// Ambiguous (A) characters, two cells with `'ambiwidth'` set to
+/// "double".
+pub const AMBIGUOUS: &[(u32, u32)] = &[
+    (0xa1, 0xa1),
+    (0xa4, 0xa4),
+    (0xa7, 0xa8),
+    (0xaa, 0xaa),
+    (0xad, 0xae),
+    (0xb0, 0xb4),
+    (0xb6, 0xba),
+    (0xbc, 0xbf),
+    (0xc6, 0xc6),
+    (0xd0, 0xd0),
+    (0xd7, 0xd8),
+    (0xde, 0xe1),
+    (0xe6, 0xe6),
+    (0xe8, 0xea),
+    (0xec, 0xed),
+    (0xf0, 0xf0),
+    (0xf2, 0xf3),
+    (0xf7, 0xfa),
+    (0xfc, 0xfc),
+    (0xfe, 0xfe),
+    (0x101, 0x101),
+    (0x111, 0x111),
+    (0x113, 0x113),
+    (0x11b, 0x11b),
+    (0x126, 0x127),
+    (0x12b, 0x12b),
+    (0x131, 0x133),
+    (0x138, 0x138),
+    (0x13f, 0x142),
+    (0x144, 0x144),
+    (0x148, 0x14b),
+    (0x14d, 0x14d),
+    (0x152, 0x153),
+    (0x166, 0x167),
+    (0x16b, 0x16b),
+    (0x1ce, 0x1ce),
+    (0x1d0, 0x1d0),
+    (0x1d2, 0x1d2),
+    (0x1d4, 0x1d4),
+    (0x1d6, 0x1d6),
+    (0x1d8, 0x1d8),
+    (0x1da, 0x1da),
+    (0x1dc, 0x1dc),
+    (0x251, 0x251),
+    (0x261, 0x261),
+    (0x2c4, 0x2c4),
+    (0x2c7, 0x2c7),
+    (0x2c9, 0x2cb),
+    (0x2cd, 0x2cd),
+    (0x2d0, 0x2d0),
+    (0x2d8, 0x2db),
+    (0x2dd, 0x2dd),
+    (0x2df, 0x2df),
+    (0x391, 0x3a1),
+    (0x3a3, 0x3a9),
+    (0x3b1, 0x3c1),
+    (0x3c3, 0x3c9),
+    (0x401, 0x401),
+    (0x410, 0x44f),
+    (0x451, 0x451),
+    (0x2010, 0x2010),
+    (0x2013, 0x2016),
+    (0x2018, 0x2019),
+    (0x201c, 0x201d),
+    (0x2020, 0x2022),
+    (0x2024, 0x2027),
+    (0x2030, 0x2030),
+    (0x2032, 0x2033),
+    (0x2035, 0x2035),
+    (0x203b, 0x203b),
+    (0x203e, 0x203e),
+    (0x2074, 0x2074),
+    (0x207f, 0x207f),
+    (0x2081, 0x2084),
+    (0x20ac, 0x20ac),
+    (0x2103, 0x2103),
+    (0x2105, 0x2105),
+    (0x2109, 0x2109),
+    (0x2113, 0x2113),
+    (0x2116, 0x2116),
+    (0x2121, 0x2122),
+    (0x2126, 0x2126),
+    (0x212b, 0x212b),
+    (0x2153, 0x2154),
+    (0x215b, 0x215e),
+    (0x2160, 0x216b),
+    (0x2170, 0x2179),
+    (0x2189, 0x2189),
+    (0x2190, 0x2199),
+    (0x21b8, 0x21b9),
+    (0x21d2, 0x21d2),
+    (0x21d4, 0x21d4),
+    (0x21e7, 0x21e7),
+    (0x2200, 0x2200),
+    (0x2202, 0x2203),
+    (0x2207, 0x2208),
+    (0x220b, 0x220b),
+    (0x220f, 0x220f),
+    (0x2211, 0x2211),
+    (0x2215, 0x2215),
+    (0x221a, 0x221a),
+    (0x221d, 0x2220),
+    (0x2223, 0x2223),
+    (0x2225, 0x2225),
+    (0x2227, 0x222c),
+    (0x222e, 0x222e),
+    (0x2234, 0x2237),
+    (0x223c, 0x223d),
+    (0x2248, 0x2248),
+    (0x224c, 0x224c),
+    (0x2252, 0x2252),
+    (0x2260, 0x2261),
+    (0x2264, 0x2267),
+    (0x226a, 0x226b),
+    (0x226e, 0x226f),
+    (0x2282, 0x2283),
+    (0x2286, 0x2287),
+    (0x2295, 0x2295),
+    (0x2299, 0x2299),
+    (0x22a5, 0x22a5),
+    (0x22bf, 0x22bf),
+    (0x2312, 0x2312),
+    (0x2460, 0x24e9),
+    (0x24eb, 0x254b),
+    (0x2550, 0x2573),
+    (0x2580, 0x258f),
+    (0x2592, 0x2595),
+    (0x25a0, 0x25a1),
+    (0x25a3, 0x25a9),
+    (0x25b2, 0x25b3),
+    (0x25b6, 0x25b7),
+    (0x25bc, 0x25bd),
+    (0x25c0, 0x25c1),
+    (0x25c6, 0x25c8),
+    (0x25cb, 0x25cb),
+    (0x25ce, 0x25d1),
+    (0x25e2, 0x25e5),
+    (0x25ef, 0x25ef),
+    (0x2605, 0x2606),
+    (0x2609, 0x2609),
+    (0x260e, 0x260f),
+    (0x261c, 0x261c),
+    (0x261e, 0x261e),
+    (0x2640, 0x2640),
+    (0x2642, 0x2642),
+    (0x2660, 0x2661),
+    (0x2663, 0x2665),
+    (0x2667, 0x266a),
+    (0x266c, 0x266d),
+    (0x266f, 0x266f),
+    (0x269e, 0x269f),
+    (0x26bf, 0x26bf),
+    (0x26c6, 0x26cd),
+    (0x26cf, 0x26d3),
+    (0x26d5, 0x26e1),
+    (0x26e3, 0x26e3),
+    (0x26e8, 0x26e9),
+    (0x26eb, 0x26f1),
+    (0x26f4, 0x26f4),
+    (0x26f6, 0x26f9),
+    (0x26fb, 0x26fc),
+    (0x26fe, 0x26ff),
+    (0x273d, 0x273d),
+    (0x2776, 0x277f),
+    (0x2b56, 0x2b59),
+    (0x3248, 0x324f),
+    (0xe000, 0xf8ff),
+    (0xfffd, 0xfffd),
+    (0x1f100, 0x1f10a),
+    (0x1f110, 0x1f12d),
+    (0x1f130, 0x1f169),
+    (0x1f170, 0x1f18d),
+    (0x1f18f, 0x1f190),
+    (0x1f19b, 0x1f1ac),
+    (0xf0000, 0xffffd),
+    (0x100000, 0x10fffd),
+];
+
+/// Combining marks and the other characters that don't take a cell of
+/// their own.
+pub const ZERO: &[(u32, u32)] = &[
+    (0x300, 0x36f),
+    (0x483, 0x489),
+    (0x591, 0x5bd),
+    (0x5bf, 0x5bf),
+    (0x5c1, 0x5c2),
+    (0x5c4, 0x5c5),
+    (0x5c7, 0x5c7),
+    (0x610, 0x61a),
+    (0x61c, 0x61c),
+    (0x64b, 0x65f),
+    (0x670, 0x670),
+    (0x6d6, 0x6dc),
+    (0x6df, 0x6e4),
+    (0x6e7, 0x6e8),
+    (0x6ea, 0x6ed),
+    (0x711, 0x711),
+    (0x730, 0x74a),
+    (0x7a6, 0x7b0),
+    (0x7eb, 0x7f3),
+    (0x7fd, 0x7fd),
+    (0x816, 0x819),
+    (0x81b, 0x823),
+    (0x825, 0x827),
+    (0x829, 0x82d),
+    (0x859, 0x85b),
+    (0x897, 0x89f),
+    (0x8ca, 0x8e1),
+    (0x8e3, 0x903),
+    (0x93a, 0x93c),
+    (0x93e, 0x94f),
+    (0x951, 0x957),
+    (0x962, 0x963),
+    (0x981, 0x983),
+    (0x9bc, 0x9bc),
+    (0x9be, 0x9c4),
+    (0x9c7, 0x9c8),
+    (0x9cb, 0x9cd),
+    (0x9d7, 0x9d7),
+    (0x9e2, 0x9e3),
+    (0x9fe, 0x9fe),
+    (0xa01, 0xa03),
+    (0xa3c, 0xa3c),
+    (0xa3e, 0xa42),
+    (0xa47, 0xa48),
+    (0xa4b, 0xa4d),
+    (0xa51, 0xa51),
+    (0xa70, 0xa71),
+    (0xa75, 0xa75),
+    (0xa81, 0xa83),
+    (0xabc, 0xabc),
+    (0xabe, 0xac5),
+    (0xac7, 0xac9),
+    (0xacb, 0xacd),
+    (0xae2, 0xae3),
+    (0xafa, 0xaff),
+    (0xb01, 0xb03),
+    (0xb3c, 0xb3c),
+    (0xb3e, 0xb44),
+    (0xb47, 0xb48),
+    (0xb4b, 0xb4d),
+    (0xb55, 0xb57),
+    (0xb62, 0xb63),
+    (0xb82, 0xb82),
+    (0xbbe, 0xbc2),
+    (0xbc6, 0xbc8),
+    (0xbca, 0xbcd),
+    (0xbd7, 0xbd7),
+    (0xc00, 0xc04),
+    (0xc3c, 0xc3c),
+    (0xc3e, 0xc44),
+    (0xc46, 0xc48),
+    (0xc4a, 0xc4d),
+    (0xc55, 0xc56),
+    (0xc62, 0xc63),
+    (0xc81, 0xc83),
+    (0xcbc, 0xcbc),
+    (0xcbe, 0xcc4),
+    (0xcc6, 0xcc8),
+    (0xcca, 0xccd),
+    (0xcd5, 0xcd6),
+    (0xce2, 0xce3),
+    (0xcf3, 0xcf3),
+    (0xd00, 0xd03),
+    (0xd3b, 0xd3c),
+    (0xd3e, 0xd44),
+    (0xd46, 0xd48),
+    (0xd4a, 0xd4d),
+    (0xd57, 0xd57),
+    (0xd62, 0xd63),
+    (0xd81, 0xd83),
+    (0xdca, 0xdca),
+    (0xdcf, 0xdd4),
+    (0xdd6, 0xdd6),
+    (0xdd8, 0xddf),
+    (0xdf2, 0xdf3),
+    (0xe31, 0xe31),
+    (0xe34, 0xe3a),
+    (0xe47, 0xe4e),
+    (0xeb1, 0xeb1),
+    (0xeb4, 0xebc),
+    (0xec8, 0xece),
+    (0xf18, 0xf19),
+    (0xf35, 0xf35),
+    (0xf37, 0xf37),
+    (0xf39, 0xf39),
+    (0xf3e, 0xf3f),
+    (0xf71, 0xf84),
+    (0xf86, 0xf87),
+    (0xf8d, 0xf97),
+    (0xf99, 0xfbc),
+    (0xfc6, 0xfc6),
+    (0x102b, 0x103e),
+    (0x1056, 0x1059),
+    (0x105e, 0x1060),
+    (0x1062, 0x1064),
+    (0x1067, 0x106d),
+    (0x1071, 0x1074),
+    (0x1082, 0x108d),
+    (0x108f, 0x108f),
+    (0x109a, 0x109d),
+    (0x1160, 0x11ff),
+    (0x135d, 0x135f),
+    (0x1712, 0x1715),
+    (0x1732, 0x1734),
+    (0x1752, 0x1753),
+    (0x1772, 0x1773),
+    (0x17b4, 0x17d3),
+    (0x17dd, 0x17dd),
+    (0x180b, 0x180f),
+    (0x1885, 0x1886),
+    (0x18a9, 0x18a9),
+    (0x1920, 0x192b),
+    (0x1930, 0x193b),
+    (0x1a17, 0x1a1b),
+    (0x1a55, 0x1a5e),
+    (0x1a60, 0x1a7c),
+    (0x1a7f, 0x1a7f),
+    (0x1ab0, 0x1add),
+    (0x1ae0, 0x1aeb),
+    (0x1b00, 0x1b04),
+    (0x1b34, 0x1b44),
+    (0x1b6b, 0x1b73),
+    (0x1b80, 0x1b82),
+    (0x1ba1, 0x1bad),
+    (0x1be6, 0x1bf3),
+    (0x1c24, 0x1c37),
+    (0x1cd0, 0x1cd2),
+    (0x1cd4, 0x1ce8),
+    (0x1ced, 0x1ced),
+    (0x1cf4, 0x1cf4),
+    (0x1cf7, 0x1cf9),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x2028, 0x202e),
+    (0x2060, 0x206f),
+    (0x20d0, 0x20f0),
+    (0x2cef, 0x2cf1),
+    (0x2d7f, 0x2d7f),
+    (0x2de0, 0x2dff),
+    (0x302a, 0x302f),
+    (0x3099, 0x309a),
+    (0x3164, 0x3164),
+    (0xa66f, 0xa672),
+    (0xa674, 0xa67d),
+    (0xa69e, 0xa69f),
+    (0xa6f0, 0xa6f1),
+    (0xa802, 0xa802),
+    (0xa806, 0xa806),
+    (0xa80b, 0xa80b),
+    (0xa823, 0xa827),
+    (0xa82c, 0xa82c),
+    (0xa880, 0xa881),
+    (0xa8b4, 0xa8c5),
+    (0xa8e0, 0xa8f1),
+    (0xa8ff, 0xa8ff),
+    (0xa926, 0xa92d),
+    (0xa947, 0xa953),
+    (0xa980, 0xa983),
+    (0xa9b3, 0xa9c0),
+    (0xa9e5, 0xa9e5),
+    (0xaa29, 0xaa36),
+    (0xaa43, 0xaa43),
+    (0xaa4c, 0xaa4d),
+    (0xaa7b, 0xaa7d),
+    (0xaab0, 0xaab0),
+    (0xaab2, 0xaab4),
+    (0xaab7, 0xaab8),
+    (0xaabe, 0xaabf),
+    (0xaac1, 0xaac1),
+    (0xaaeb, 0xaaef),
+    (0xaaf5, 0xaaf6),
+    (0xabe3, 0xabea),
+    (0xabec, 0xabed),
+    (0xd7b0, 0xd7ff),
+    (0xfb1e, 0xfb1e),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xfeff, 0xfeff),
+    (0xffa0, 0xffa0),
+    (0xfff0, 0xfffb),
+    (0x101fd, 0x101fd),
+    (0x102e0, 0x102e0),
+    (0x10376, 0x1037a),
+    (0x10a01, 0x10a03),
+    (0x10a05, 0x10a06),
+    (0x10a0c, 0x10a0f),
+    (0x10a38, 0x10a3a),
+    (0x10a3f, 0x10a3f),
+    (0x10ae5, 0x10ae6),
+    (0x10d24, 0x10d27),
+    (0x10d69, 0x10d6d),
+    (0x10eab, 0x10eac),
+    (0x10efa, 0x10eff),
+    (0x10f46, 0x10f50),
+    (0x10f82, 0x10f85),
+    (0x11000, 0x11002),
+    (0x11038, 0x11046),
+    (0x11070, 0x11070),
+    (0x11073, 0x11074),
+    (0x1107f, 0x11082),
+    (0x110b0, 0x110ba),
+    (0x110c2, 0x110c2),
+    (0x11100, 0x11102),
+    (0x11127, 0x11134),
+    (0x11145, 0x11146),
+    (0x11173, 0x11173),
+    (0x11180, 0x11182),
+    (0x111b3, 0x111c0),
+    (0x111c9, 0x111cc),
+    (0x111ce, 0x111cf),
+    (0x1122c, 0x11237),
+    (0x1123e, 0x1123e),
+    (0x11241, 0x11241),
+    (0x112df, 0x112ea),
+    (0x11300, 0x11303),
+    (0x1133b, 0x1133c),
+    (0x1133e, 0x11344),
+    (0x11347, 0x11348),
+    (0x1134b, 0x1134d),
+    (0x11357, 0x11357),
+    (0x11362, 0x11363),
+    (0x11366, 0x1136c),
+    (0x11370, 0x11374),
+    (0x113b8, 0x113c0),
+    (0x113c2, 0x113c2),
+    (0x113c5, 0x113c5),
+    (0x113c7, 0x113ca),
+    (0x113cc, 0x113d0),
+    (0x113d2, 0x113d2),
+    (0x113e1, 0x113e2),
+    (0x11435, 0x11446),
+    (0x1145e, 0x1145e),
+    (0x114b0, 0x114c3),
+    (0x115af, 0x115b5),
+    (0x115b8, 0x115c0),
+    (0x115dc, 0x115dd),
+    (0x11630, 0x11640),
+    (0x116ab, 0x116b7),
+    (0x1171d, 0x1172b),
+    (0x1182c, 0x1183a),
+    (0x11930, 0x11935),
+    (0x11937, 0x11938),
+    (0x1193b, 0x1193e),
+    (0x11940, 0x11940),
+    (0x11942, 0x11943),
+    (0x119d1, 0x119d7),
+    (0x119da, 0x119e0),
+    (0x119e4, 0x119e4),
+    (0x11a01, 0x11a0a),
+    (0x11a33, 0x11a39),
+    (0x11a3b, 0x11a3e),
+    (0x11a47, 0x11a47),
+    (0x11a51, 0x11a5b),
+    (0x11a8a, 0x11a99),
+    (0x11b60, 0x11b67),
+    (0x11c2f, 0x11c36),
+    (0x11c38, 0x11c3f),
+    (0x11c92, 0x11ca7),
+    (0x11ca9, 0x11cb6),
+    (0x11d31, 0x11d36),
+    (0x11d3a, 0x11d3a),
+    (0x11d3c, 0x11d3d),
+    (0x11d3f, 0x11d45),
+    (0x11d47, 0x11d47),
+    (0x11d8a, 0x11d8e),
+    (0x11d90, 0x11d91),
+    (0x11d93, 0x11d97),
+    (0x11ef3, 0x11ef6),
+    (0x11f00, 0x11f01),
+    (0x11f03, 0x11f03),
+    (0x11f34, 0x11f3a),
+    (0x11f3e, 0x11f42),
+    (0x11f5a, 0x11f5a),
+    (0x13430, 0x13440),
+    (0x13447, 0x13455),
+    (0x1611e, 0x1612f),
+    (0x16af0, 0x16af4),
+    (0x16b30, 0x16b36),
+    (0x16f4f, 0x16f4f),
+    (0x16f51, 0x16f87),
+    (0x16f8f, 0x16f92),
+    (0x16fe4, 0x16fe4),
+    (0x16ff0, 0x16ff1),
+    (0x1bc9d, 0x1bc9e),
+    (0x1bca0, 0x1bca3),
+    (0x1cf00, 0x1cf2d),
+    (0x1cf30, 0x1cf46),
+    (0x1d165, 0x1d169),
+    (0x1d16d, 0x1d182),
+    (0x1d185, 0x1d18b),
+    (0x1d1aa, 0x1d1ad),
+    (0x1d242, 0x1d244),
+    (0x1da00, 0x1da36),
+    (0x1da3b, 0x1da6c),
+    (0x1da75, 0x1da75),
+    (0x1da84, 0x1da84),
+    (0x1da9b, 0x1da9f),
+    (0x1daa1, 0x1daaf),
+    (0x1e000, 0x1e006),
+    (0x1e008, 0x1e018),
+    (0x1e01b, 0x1e021),
+    (0x1e023, 0x1e024),
+    (0x1e026, 0x1e02a),
+    (0x1e08f, 0x1e08f),
+    (0x1e130, 0x1e136),
+    (0x1e2ae, 0x1e2ae),
+    (0x1e2ec, 0x1e2ef),
+    (0x1e4ec, 0x1e4ef),
+    (0x1e5ee, 0x1e5ef),
+    (0x1e6e3, 0x1e6e3),
+    (0x1e6e6, 0x1e6e6),
+    (0x1e6ee, 0x1e6ef),
+    (0x1e6f5, 0x1e6f5),
+    (0x1e8d0, 0x1e8d6),
+    (0x1e944, 0x1e94a),
+    (0xe0000, 0xe0fff),
+];
@@ -1,5 +1,9 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
+use gdk;
 use gtk;
 use gtk::prelude::*;
 
@@ -8,10 +12,13 @@ use neovim_lib::neovim::Neovim;
 use nvim_bridge;
 use ui::common::calc_line_space;
 use ui::font::{Font, FontUnit};
+use ui::input_queue::InputQueue;
 use ui::ui::HlDefs;
 use ui::wildmenu::Wildmenu;
 
 const MAX_WIDTH: i32 = 650;
+/// How many of the latest texts sent from `CmdlineEntry` are remembered.
+const MAX_SENT: usize = 32;
 
 struct CmdlineBlock {
     frame: gtk::Frame,
@@ -394,14 +401,236 @@ impl CmdlineInput {
     }
 }
 
+/// Keeps track of the text `CmdlineEntry` sends to nvim.
+#[derive(Default)]
+struct EntrySync {
+    /// Texts we've sent to nvim that it hasn't echoed back yet, oldest
+    /// first. Nvim echoes these back to us with `cmdline_show`, in order,
+    /// possibly after we've already changed the text again.
+    sent: VecDeque<String>,
+    /// Set while we're setting the entry's text ourselves.
+    updating: bool,
+}
+
+impl EntrySync {
+    fn push(&mut self, text: String) {
+        if self.sent.len() == MAX_SENT {
+            self.sent.pop_front();
+        }
+        self.sent.push_back(text);
+    }
+
+    /// Returns true if `text` (from `cmdline_show`) is the echo of a text
+    /// we've sent. Nvim handles our texts in order, so the texts sent
+    /// before the echoed one won't be echoed anymore, and are forgotten
+    /// along with it.
+    fn acknowledge(&mut self, text: &str) -> bool {
+        match self.sent.iter().position(|sent| sent == text) {
+            Some(i) => {
+                self.sent.drain(..=i);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Editable alternative for `CmdlineInput` (see `Cmdline::set_entry_mode`).
+///
+/// The entry is edited like any other GTK entry (cursor movement,
+/// selection, IME, etc.), and its text is sent to nvim as it changes. Keys
+/// that nvim needs to act on (e.g. enter, escape and completion) are
+/// passed to nvim.
+struct CmdlineEntry {
+    frame: gtk::Frame,
+    prompt: gtk::Label,
+    entry: gtk::Entry,
+    css_provider: gtk::CssProvider,
+
+    sync: Rc<RefCell<EntrySync>>,
+    /// Level from the latest `cmdline_show`.
+    current_level: u64,
+}
+
+impl CmdlineEntry {
+    fn new(input_queue: InputQueue) -> Self {
+        let css_provider = gtk::CssProvider::new();
+
+        let prompt = gtk::Label::new(None);
+        let entry = gtk::Entry::new();
+        entry.set_has_frame(false);
+        entry.set_hexpand(true);
+
+        let box_ = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        box_.pack_start(&prompt, false, false, 0);
+        box_.pack_start(&entry, true, true, 0);
+
+        let frame = gtk::Frame::new(None);
+        frame.add(&box_);
+
+        add_css_provider!(&css_provider, frame, prompt, entry);
+
+        let sync = Rc::new(RefCell::new(EntrySync::default()));
+
+        let sync_ref = sync.clone();
+        let queue_ref = input_queue.clone();
+        entry.connect_changed(move |entry| {
+            let mut sync = sync_ref.borrow_mut();
+            if sync.updating {
+                return;
+            }
+
+            // Replace nvim's cmdline with our text.
+            let text = entry.get_text().unwrap_or_default();
+            queue_ref.send(format!("<C-e><C-u>{}", text.replace("<", "<lt>")));
+            sync.push(text);
+        });
+
+        entry.connect_key_press_event(move |entry, e| {
+            let keyname = gdk::keyval_name(e.get_keyval());
+            let key = match keyname.as_ref().map(|name| name.as_str()) {
+                Some("Escape") => "<Esc>",
+                Some("Return") | Some("KP_Enter") => "<CR>",
+                Some("Tab") => "<Tab>",
+                Some("ISO_Left_Tab") => "<S-Tab>",
+                Some("Up") => "<Up>",
+                Some("Down") => "<Down>",
+                _ => return Inhibit(false),
+            };
+
+            // Move nvim's cursor to ours first, so that e.g. the completion
+            // happens where our cursor is.
+            let text = entry.get_text().unwrap_or_default();
+            let after = text
+                .chars()
+                .count()
+                .saturating_sub(entry.get_position() as usize);
+            input_queue.send(format!("<C-e>{}{}", "<Left>".repeat(after), key));
+
+            Inhibit(true)
+        });
+
+        CmdlineEntry {
+            frame,
+            prompt,
+            entry,
+            css_provider,
+            sync,
+            current_level: 0,
+        }
+    }
+
+    fn widget(&self) -> gtk::Widget {
+        self.frame.clone().upcast()
+    }
+
+    fn set_text(&mut self, content: &nvim_bridge::CmdlineShow) {
+        self.prompt.set_text(&format!(
+            "{}{}{}",
+            content.firstc,
+            " ".repeat(content.indent as usize),
+            content.prompt
+        ));
+
+        // New cmdline (e.g. from <C-r>=), forget what we sent to the
+        // previous one.
+        if content.level != self.current_level {
+            self.current_level = content.level;
+            self.reset();
+        }
+
+        let text: String =
+            content.content.iter().map(|c| c.1.as_str()).collect();
+
+        // If this is just nvim echoing our own text back, keep what we
+        // have: the user might have already changed the text again.
+        if self.sync.borrow_mut().acknowledge(&text) {
+            return;
+        }
+
+        let pos = text
+            .get(..content.pos as usize)
+            .unwrap_or(&text)
+            .chars()
+            .count();
+
+        self.sync.borrow_mut().updating = true;
+        self.entry.set_text(&text);
+        self.entry.set_position(pos as i32);
+        self.sync.borrow_mut().updating = false;
+
+        self.entry.grab_focus_without_selecting();
+    }
+
+    /// Forgets the text we've sent.
+    fn reset(&self) {
+        self.sync.borrow_mut().sent.clear();
+    }
+
+    fn set_colors(
+        &self,
+        colors: &nvim_bridge::CmdlineColors,
+        hl_defs: &HlDefs,
+    ) {
+        let css = if gtk::get_minor_version() < 20 {
+            format!(
+                "GtkFrame {{
+                    border: none;
+                    padding: 5px;
+                    background: #{bg};
+                    border-radius: 0;
+                }}
+
+                GtkLabel, GtkEntry {{
+                    color: #{fg};
+                    background: #{bg};
+                    border: none;
+                    box-shadow: none;
+                }}",
+                fg = colors.fg.unwrap_or(hl_defs.default_fg).to_hex(),
+                bg = colors.bg.unwrap_or(hl_defs.default_bg).to_hex()
+            )
+        } else {
+            format!(
+                "frame {{
+                    padding: 5px;
+                    background: #{bg};
+                }}
+
+                frame > border {{
+                    border: none;
+                }}
+
+                label, entry {{
+                    caret-color: #{fg};
+                    color: #{fg};
+                    background: #{bg};
+                    border: none;
+                    box-shadow: none;
+                    padding: 0;
+                    min-height: 0;
+                }}",
+                fg = colors.fg.unwrap_or(hl_defs.default_fg).to_hex(),
+                bg = colors.bg.unwrap_or(hl_defs.default_bg).to_hex()
+            )
+        };
+        CssProviderExt::load_from_data(&self.css_provider, css.as_bytes())
+            .unwrap();
+    }
+}
+
 pub struct Cmdline {
     css_provider: gtk::CssProvider,
     fixed: gtk::Fixed,
 
     input: CmdlineInput,
+    entry: CmdlineEntry,
     block: CmdlineBlock,
     wildmenu: Wildmenu,
 
+    /// If `entry` is used instead of `input`.
+    entry_mode: bool,
+
     /// If the block should be shown or not.
     show_block: bool,
     /// If the wildmenu should be shown or not.
@@ -414,16 +643,22 @@ pub struct Cmdline {
 }
 
 impl Cmdline {
-    pub fn new(parent: &gtk::Overlay, nvim: Arc<Mutex<Neovim>>) -> Self {
+    pub fn new(
+        parent: &gtk::Overlay,
+        nvim: Arc<Mutex<Neovim>>,
+        input_queue: InputQueue,
+    ) -> Self {
         let css_provider = gtk::CssProvider::new();
 
-        // Inner box contains cmdline block and input.
+        // Inner box contains cmdline block and input (or entry).
         let inner_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
         let input = CmdlineInput::new();
+        let entry = CmdlineEntry::new(input_queue);
         let block = CmdlineBlock::new();
         inner_box.pack_start(&block.widget(), true, true, 0);
         inner_box.pack_start(&input.widget(), true, true, 0);
+        inner_box.pack_start(&entry.widget(), true, true, 0);
 
         // Frame will contain inner_box. This is so we can add some padding
         // around them without adding padding around the wildmenu.
@@ -436,6 +671,7 @@ impl Cmdline {
         let box_ = gtk::Box::new(gtk::Orientation::Vertical, 0);
         box_.pack_start(&frame, true, true, 0);
         box_.pack_start(&wildmenu.widget(), true, true, 0);
+        gtk::WidgetExt::set_name(&box_, "gnvim-cmdline");
 
         add_css_provider!(&css_provider, box_, frame, inner_box);
 
@@ -459,8 +695,10 @@ impl Cmdline {
             css_provider,
             fixed,
             input,
+            entry,
             block,
             wildmenu,
+            entry_mode: false,
             show_block: false,
             show_wildmenu: false,
             font: Font::default(),
@@ -474,6 +712,7 @@ impl Cmdline {
         hl_defs: &HlDefs,
     ) {
         self.input.set_colors(&colors, hl_defs);
+        self.entry.set_colors(&colors, hl_defs);
         self.block.set_colors(&colors, hl_defs);
         self.colors = colors;
 
@@ -543,6 +782,18 @@ impl Cmdline {
 
     pub fn hide(&self) {
         self.fixed.hide();
+        self.entry.reset();
+    }
+
+    /// Sets if the cmdline is edited in a GTK entry, instead of being just
+    /// a view of nvim's cmdline.
+    /// The entry of the entry mode, which handles its own key presses.
+    pub fn entry_widget(&self) -> gtk::Widget {
+        self.entry.entry.clone().upcast()
+    }
+
+    pub fn set_entry_mode(&mut self, enabled: bool) {
+        self.entry_mode = enabled;
     }
 
     pub fn show(
@@ -550,8 +801,16 @@ impl Cmdline {
         content: &nvim_bridge::CmdlineShow,
         hl_defs: &HlDefs,
     ) {
-        self.input.set_text(content, hl_defs);
-        self.fixed.show_all();
+        if self.entry_mode {
+            self.fixed.show_all();
+            self.input.widget().hide();
+            // The entry can only grab the focus once it is visible.
+            self.entry.set_text(content);
+        } else {
+            self.input.set_text(content, hl_defs);
+            self.fixed.show_all();
+            self.entry.widget().hide();
+        }
 
         if !self.show_block {
             self.block.hide();
@@ -563,7 +822,10 @@ impl Cmdline {
     }
 
     pub fn show_special_char(&mut self, ch: String, shift: bool, level: u64) {
-        self.input.show_special_char(ch, shift, level);
+        // The entry has a cursor of its own.
+        if !self.entry_mode {
+            self.input.show_special_char(ch, shift, level);
+        }
     }
 
     pub fn set_line_space(&self, space: i64) {
@@ -586,7 +848,10 @@ impl Cmdline {
     }
 
     pub fn set_pos(&mut self, pos: u64, level: u64) {
-        self.input.set_cursor(pos as usize, level);
+        // The entry has a cursor of its own.
+        if !self.entry_mode {
+            self.input.set_cursor(pos as usize, level);
+        }
     }
 
     pub fn show_block(&mut self, lines: &Vec<(u64, String)>, hl_defs: &HlDefs) {
@@ -55,7 +55,7 @@ impl Highlight {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Color {
     pub r: f64,
     pub g: f64,
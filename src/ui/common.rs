@@ -55,6 +55,31 @@ pub fn get_preferred_vertical_position(
     return (y, height);
 }
 
+/// Calculate the preferred height and y-position, preferring the position
+/// below `pos`. If there is not enough room below, the position above `pos`
+/// is used if there is more room there.
+pub fn get_preferred_vertical_position_below(
+    area: &gdk::Rectangle,
+    pos: &gdk::Rectangle,
+    mut height: i32,
+) -> (i32, i32) {
+    let mut y = pos.y + pos.height;
+    let max_below = area.y + area.height - y;
+
+    if height > max_below {
+        let max_above = pos.y - area.y;
+
+        if max_above > max_below {
+            height = height.min(max_above);
+            y = pos.y - height;
+        } else {
+            height = max_below;
+        }
+    }
+
+    (y, height)
+}
+
 #[cfg(test)]
 mod test {
 
@@ -154,6 +179,93 @@ mod test {
         assert_eq!(h, 30);
     }
 
+    #[test]
+    fn test_get_preferred_vertical_position_below1() {
+        // Case 1: there is room below the `pos`.
+        let area = gdk::Rectangle {
+            x: 0,
+            y: 0,
+            width: 300,
+            height: 300,
+        };
+        let pos = gdk::Rectangle {
+            x: 0,
+            y: 30,
+            width: 300,
+            height: 15,
+        };
+        let height = 100;
+        let (y, h) = get_preferred_vertical_position_below(&area, &pos, height);
+        assert_eq!(y, 45);
+        assert_eq!(h, 100);
+    }
+
+    #[test]
+    fn test_get_preferred_vertical_position_below2() {
+        // Case 2: there is not enough room below the `pos`, but there is
+        // above it.
+        let area = gdk::Rectangle {
+            x: 0,
+            y: 0,
+            width: 300,
+            height: 300,
+        };
+        let pos = gdk::Rectangle {
+            x: 0,
+            y: 250,
+            width: 300,
+            height: 15,
+        };
+        let height = 100;
+        let (y, h) = get_preferred_vertical_position_below(&area, &pos, height);
+        assert_eq!(y, 150);
+        assert_eq!(h, 100);
+    }
+
+    #[test]
+    fn test_get_preferred_vertical_position_below3() {
+        // Case 3: there is not enough room anywhere, but more room above the
+        // `pos`. We should go above, but truncate our height.
+        let area = gdk::Rectangle {
+            x: 0,
+            y: 0,
+            width: 300,
+            height: 100,
+        };
+        let pos = gdk::Rectangle {
+            x: 0,
+            y: 60,
+            width: 300,
+            height: 15,
+        };
+        let height = 200;
+        let (y, h) = get_preferred_vertical_position_below(&area, &pos, height);
+        assert_eq!(y, 0);
+        assert_eq!(h, 60);
+    }
+
+    #[test]
+    fn test_get_preferred_vertical_position_below4() {
+        // Case 4: there is not enough room anywhere, but more room below the
+        // `pos`. We should stay below, but truncate our height.
+        let area = gdk::Rectangle {
+            x: 0,
+            y: 0,
+            width: 300,
+            height: 100,
+        };
+        let pos = gdk::Rectangle {
+            x: 0,
+            y: 20,
+            width: 300,
+            height: 15,
+        };
+        let height = 200;
+        let (y, h) = get_preferred_vertical_position_below(&area, &pos, height);
+        assert_eq!(y, 35);
+        assert_eq!(h, 65);
+    }
+
     #[test]
     fn test_get_preferred_horizontal_position1() {
         // Case 1: Everything fits.
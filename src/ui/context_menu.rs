@@ -0,0 +1,260 @@
+use std::sync::{Arc, Mutex};
+
+use gdk;
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::neovim::Neovim;
+use neovim_lib::{NeovimApiAsync, Value};
+
+use ui::input_queue::InputQueue;
+
+/// Built in actions of the context menu.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Cut,
+    Copy,
+    Paste,
+    SelectAll,
+    OpenFile,
+}
+
+impl Action {
+    const ALL: [Action; 5] = [
+        Action::Cut,
+        Action::Copy,
+        Action::Paste,
+        Action::SelectAll,
+        Action::OpenFile,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Action::Cut => "Cut",
+            Action::Copy => "Copy",
+            Action::Paste => "Paste",
+            Action::SelectAll => "Select All",
+            Action::OpenFile => "Open File Under Cursor",
+        }
+    }
+
+    /// Returns the input that performs the action in `mode` (e.g. "normal",
+    /// see `mode_change`), or `None` if the action is not available in the
+    /// mode.
+    pub fn input(self, mode: &str) -> Option<&'static str> {
+        match (self, mode) {
+            (Action::Cut, "visual") => Some("\"+x"),
+            (Action::Copy, "visual") => Some("\"+y"),
+            (Action::Paste, "normal") => Some("\"+gP"),
+            (Action::Paste, "visual") => Some("\"+P"),
+            (Action::Paste, "insert") | (Action::Paste, "replace") => {
+                Some("<C-r><C-o>+")
+            }
+            (Action::Paste, "cmdline_normal") => Some("<C-r>+"),
+            (Action::SelectAll, _) => Some("<C-\\><C-n>ggVG"),
+            (Action::OpenFile, "normal") => Some("gf"),
+            (Action::OpenFile, "visual") => Some("gf"),
+            (Action::OpenFile, _) => Some("<C-\\><C-n>gf"),
+            _ => None,
+        }
+    }
+}
+
+/// Context menu, shown on right click when `'mousemodel'` is "popup" (or
+/// "popup_setpos"). Has our built in actions (see `Action`), followed by
+/// the user's entries.
+pub struct ContextMenu {
+    popover: gtk::Popover,
+    box_: gtk::Box,
+
+    nvim: Arc<Mutex<Neovim>>,
+    input_queue: InputQueue,
+
+    /// If the menu is used, e.g. when `'mousemodel'` is "popup".
+    enabled: bool,
+    /// Name of the current mode (from `mode_change`).
+    mode: String,
+    /// User's entries: label and the command to run.
+    entries: Vec<(String, String)>,
+    /// Where the right button was pressed, relative to the widget the menu
+    /// was created for.
+    pointer: (f64, f64),
+}
+
+impl ContextMenu {
+    pub fn new(
+        relative_to: &gtk::Widget,
+        nvim: Arc<Mutex<Neovim>>,
+        input_queue: InputQueue,
+    ) -> Self {
+        let popover = gtk::Popover::new(Some(relative_to));
+        popover.set_position(gtk::PositionType::Bottom);
+
+        let box_ = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        box_.set_border_width(6);
+        popover.add(&box_);
+
+        ContextMenu {
+            popover,
+            box_,
+            nvim,
+            input_queue,
+            enabled: false,
+            mode: String::from("normal"),
+            entries: vec![],
+            pointer: (0.0, 0.0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables the menu if `mousemodel` (e.g. nvim's `'mousemodel'`) is
+    /// "popup" or "popup_setpos".
+    pub fn set_mousemodel(&mut self, mousemodel: &str) {
+        self.enabled = mousemodel.starts_with("popup");
+    }
+
+    pub fn set_mode(&mut self, mode: &str) {
+        self.mode = mode.to_string();
+    }
+
+    /// Returns the input that performs `action` in the current mode. Used
+    /// by the macOS menu bar.
+    #[cfg(target_os = "macos")]
+    pub fn action_input(&self, action: Action) -> Option<&'static str> {
+        action.input(&self.mode)
+    }
+
+    /// Adds an entry which runs `command`, replacing any previous entry
+    /// with the same `label`. Empty `command` removes the entry.
+    pub fn set_entry(&mut self, label: String, command: String) {
+        self.entries.retain(|(l, _)| *l != label);
+
+        if !command.is_empty() {
+            self.entries.push((label, command));
+        }
+    }
+
+    /// Remembers where the right button was pressed, for `popup`.
+    pub fn set_pointer(&mut self, pos: (f64, f64)) {
+        self.pointer = pos;
+    }
+
+    /// Shows the menu for a right click at the cell (row, col), at the
+    /// pointer (see `set_pointer`).
+    ///
+    /// Unless there is a selection, nvim's cursor is moved to the clicked
+    /// cell first (like with `'mousemodel'` "popup_setpos"), so that the
+    /// actions apply there.
+    pub fn popup(&self, row: u64, col: u64) {
+        match self.mode.as_str() {
+            "normal" | "insert" | "replace" => self.move_cursor(row, col),
+            _ => {}
+        }
+
+        for child in self.box_.get_children() {
+            self.box_.remove(&child);
+        }
+
+        for action in Action::ALL.iter() {
+            let button = self.add_button(action.label());
+
+            match action.input(&self.mode) {
+                Some(input) => {
+                    let queue = self.input_queue.clone();
+                    button.connect_clicked(move |_| {
+                        queue.send(input.to_string());
+                    });
+                }
+                None => button.set_sensitive(false),
+            }
+        }
+
+        if !self.entries.is_empty() {
+            let sep = gtk::Separator::new(gtk::Orientation::Horizontal);
+            self.box_.pack_start(&sep, false, false, 3);
+        }
+
+        for (label, command) in self.entries.iter() {
+            let button = self.add_button(label);
+
+            let nvim = self.nvim.clone();
+            let command = command.clone();
+            button.connect_clicked(move |_| {
+                let mut nvim = nvim.lock().unwrap();
+                nvim.command_async(&command)
+                    .cb(|res| {
+                        if let Err(err) = res {
+                            println!("Failed to run menu command: {}", err);
+                        }
+                    })
+                    .call();
+            });
+        }
+
+        self.popover.set_pointing_to(&gdk::Rectangle {
+            x: self.pointer.0 as i32,
+            y: self.pointer.1 as i32,
+            width: 1,
+            height: 1,
+        });
+        self.popover.show_all();
+    }
+
+    /// Moves nvim's cursor to the cell (row, col) with a left click.
+    fn move_cursor(&self, row: u64, col: u64) {
+        let mut nvim = self.nvim.lock().unwrap();
+        for action in ["press", "release"].iter() {
+            nvim.session
+                .call_async::<Value>(
+                    "nvim_input_mouse",
+                    vec![
+                        Value::from("left"),
+                        Value::from(*action),
+                        Value::from(""),
+                        Value::from(0),
+                        Value::from(row),
+                        Value::from(col),
+                    ],
+                )
+                .cb(|res| {
+                    if let Err(err) = res {
+                        println!("Failed to move the cursor: {}", err);
+                    }
+                })
+                .call();
+        }
+    }
+
+    fn add_button(&self, label: &str) -> gtk::Button {
+        let button = gtk::Button::new_with_label(label);
+        button.set_relief(gtk::ReliefStyle::None);
+        if let Some(child) = button.get_child() {
+            child.set_halign(gtk::Align::Start);
+        }
+
+        let popover = self.popover.clone();
+        button.connect_clicked(move |_| popover.hide());
+
+        self.box_.pack_start(&button, false, false, 0);
+
+        button
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_input() {
+        assert_eq!(Action::Copy.input("visual"), Some("\"+y"));
+        assert_eq!(Action::Copy.input("normal"), None);
+        assert_eq!(Action::Cut.input("insert"), None);
+        assert_eq!(Action::Paste.input("insert"), Some("<C-r><C-o>+"));
+        assert_eq!(Action::Paste.input("operator"), None);
+        assert_eq!(Action::OpenFile.input("insert"), Some("<C-\\><C-n>gf"));
+        assert_eq!(Action::SelectAll.input("normal"), Some("<C-\\><C-n>ggVG"));
+    }
+}
@@ -1,20 +1,29 @@
+#[cfg(feature = "webkit-preview")]
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::collections::HashSet;
+#[cfg(feature = "webkit-preview")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "webkit-preview")]
 use std::path::Path;
 use std::sync::Arc;
 
 use gtk;
 use gtk::prelude::*;
 
+#[cfg(feature = "webkit-preview")]
 use webkit2gtk as webkit;
+#[cfg(feature = "webkit-preview")]
 use webkit2gtk::{SettingsExt, WebViewExt};
 
+#[cfg(feature = "webkit-preview")]
 use ammonia;
+#[cfg(feature = "webkit-preview")]
 use pulldown_cmark as md;
 
+#[cfg(feature = "webkit-preview")]
 use syntect::dumps::from_binary;
+#[cfg(feature = "webkit-preview")]
 use syntect::highlighting::{Theme, ThemeSet};
+#[cfg(feature = "webkit-preview")]
 use syntect::parsing::SyntaxSet;
 
 use thread_guard::ThreadGuard;
@@ -23,12 +32,14 @@ use ui::common::{
     get_preferred_horizontal_position, get_preferred_vertical_position,
 };
 use ui::font::{Font, FontUnit};
+use ui::scroll_routing::ScrollArea;
 
 pub enum Gravity {
     Up,
     Down,
 }
 
+#[cfg(feature = "webkit-preview")]
 lazy_static! {
     /// Our custom ammonia builder to clean untrusted HTML.
     static ref AMMONIA: ammonia::Builder<'static> = {
@@ -75,12 +86,18 @@ impl Default for State {
 }
 
 /// Cursor tooltip to display markdown documents on given grid position.
-/// Internally uses `syntect` to do code highlighting.
+/// Internally uses `syntect` to do code highlighting. Without the
+/// `webkit-preview` feature, the document is shown as plain text.
 pub struct CursorTooltip {
     css_provider: gtk::CssProvider,
     frame: gtk::Frame,
     fixed: gtk::Fixed,
+    #[cfg(feature = "webkit-preview")]
     webview: webkit::WebView,
+    #[cfg(not(feature = "webkit-preview"))]
+    label: gtk::Label,
+    #[cfg(not(feature = "webkit-preview"))]
+    scrolled: gtk::ScrolledWindow,
     state: Arc<ThreadGuard<State>>,
 
     fg: Color,
@@ -88,11 +105,14 @@ pub struct CursorTooltip {
     font: Font,
 
     /// Our current syntax set.
+    #[cfg(feature = "webkit-preview")]
     syntax_set: SyntaxSet,
     /// Our current theme set.
+    #[cfg(feature = "webkit-preview")]
     theme_set: ThemeSet,
 
     /// Currently selected theme.
+    #[cfg(feature = "webkit-preview")]
     current_theme: Theme,
 }
 
@@ -100,11 +120,8 @@ impl CursorTooltip {
     pub fn new(parent: &gtk::Overlay) -> Self {
         let css_provider = gtk::CssProvider::new();
 
-        let context = webkit::WebContext::get_default().unwrap();
-        let webview = webkit::WebView::new_with_context(&context);
-
         let frame = gtk::Frame::new(None);
-        frame.add(&webview);
+        gtk::WidgetExt::set_name(&frame, "gnvim-cursor-tooltip");
 
         add_css_provider!(&css_provider, frame);
 
@@ -113,23 +130,26 @@ impl CursorTooltip {
 
         let state = Arc::new(ThreadGuard::new(State::default()));
 
-        let frame_ref = frame.clone();
-        let fixed_ref = fixed.clone();
-        let state_ref = state.clone();
-        webview.connect_load_changed(move |webview, e| match e {
-            webkit::LoadEvent::Finished => {
-                webview_load_finished(
-                    webview,
-                    frame_ref.clone(),
-                    fixed_ref.clone(),
-                    state_ref.clone(),
-                );
-            }
-            _ => {}
-        });
-
-        let settings = WebViewExt::get_settings(&webview).unwrap();
-        settings.set_enable_javascript(true);
+        #[cfg(feature = "webkit-preview")]
+        let webview = new_webview(&frame, &fixed, &state);
+
+        #[cfg(not(feature = "webkit-preview"))]
+        let (label, scrolled) = {
+            let label = gtk::Label::new(None);
+            label.set_line_wrap(true);
+            label.set_selectable(true);
+            label.set_xalign(0.0);
+            label.set_yalign(0.0);
+            add_css_provider!(&css_provider, label);
+
+            let scrolled = gtk::ScrolledWindow::new(None, None);
+            scrolled
+                .set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+            scrolled.add(&label);
+            frame.add(&scrolled);
+
+            (label, scrolled)
+        };
 
         parent.add_overlay(&fixed);
         parent.set_overlay_pass_through(&fixed, true);
@@ -142,25 +162,36 @@ impl CursorTooltip {
             state.available_area = alloc.clone();
         });
 
+        #[cfg(feature = "webkit-preview")]
         let syntax_set: SyntaxSet =
             from_binary(include_bytes!("../../sublime-syntaxes/all.pack"));
+        #[cfg(feature = "webkit-preview")]
         let theme_set = ThemeSet::load_defaults();
 
+        #[cfg(feature = "webkit-preview")]
         let current_theme = theme_set.themes["base16-ocean.dark"].clone();
 
         CursorTooltip {
             css_provider,
             frame,
             fixed,
+            #[cfg(feature = "webkit-preview")]
             webview,
+            #[cfg(not(feature = "webkit-preview"))]
+            label,
+            #[cfg(not(feature = "webkit-preview"))]
+            scrolled,
             state,
 
             fg: Color::default(),
             bg: Color::default(),
             font: Font::default(),
 
+            #[cfg(feature = "webkit-preview")]
             syntax_set,
+            #[cfg(feature = "webkit-preview")]
             theme_set,
+            #[cfg(feature = "webkit-preview")]
             current_theme,
         }
     }
@@ -168,42 +199,101 @@ impl CursorTooltip {
     pub fn set_colors(&mut self, fg: Color, bg: Color) {
         self.fg = fg;
         self.bg = bg;
+        self.load_css();
+    }
 
-        let css = format!(
+    fn load_css(&self) {
+        #[allow(unused_mut)]
+        let mut css = format!(
             "* {{
             border: 1px solid #{fg};
             border-radius: 0;
         }}",
-            fg = fg.to_hex()
+            fg = self.fg.to_hex()
         );
+
+        // Without webkit, the colors and the font of the document are ours
+        // to set.
+        #[cfg(not(feature = "webkit-preview"))]
+        {
+            let (selector, unit) = if gtk::get_minor_version() < 20 {
+                ("GtkLabel", FontUnit::Pixel)
+            } else {
+                ("label", FontUnit::Point)
+            };
+            css.push_str(&format!(
+                "{selector} {{
+                color: #{fg};
+                background-color: #{bg};
+                padding: 8px;
+            }}",
+                selector = selector,
+                fg = self.fg.to_hex(),
+                bg = self.bg.to_hex()
+            ));
+            css.push_str(&self.font.as_wild_css(unit));
+        }
+
         CssProviderExt::load_from_data(&self.css_provider, css.as_bytes())
             .unwrap();
     }
 
     /// Get list of available code highlighting styles.
+    #[cfg(feature = "webkit-preview")]
     pub fn get_styles(&self) -> Vec<String> {
         self.theme_set.themes.keys().cloned().collect()
     }
 
+    /// Get list of available code highlighting styles. Without webkit,
+    /// there is no code highlighting.
+    #[cfg(not(feature = "webkit-preview"))]
+    pub fn get_styles(&self) -> Vec<String> {
+        vec![]
+    }
+
     /// Set the current code highlighting style.
+    #[cfg(feature = "webkit-preview")]
     pub fn set_style(&mut self, style: &str) {
         if let Some(theme) = self.theme_set.themes.get(style) {
             self.current_theme = theme.clone();
         }
     }
 
+    #[cfg(not(feature = "webkit-preview"))]
+    pub fn set_style(&mut self, _style: &str) {}
+
     pub fn set_font(&mut self, font: Font) {
         self.font = font;
+        #[cfg(not(feature = "webkit-preview"))]
+        self.load_css();
     }
 
     pub fn hide(&self) {
         self.frame.hide();
     }
 
+    /// Returns our area for routing the wheel events (see `ScrollRouter`),
+    /// since we let the events pass through to the grid.
+    #[cfg(not(feature = "webkit-preview"))]
+    pub fn scroll_area(&self) -> ScrollArea {
+        ScrollArea::new(
+            self.frame.clone().upcast(),
+            vec![self.scrolled.clone()],
+        )
+    }
+
+    /// Returns our area for routing the wheel events. The webview scrolls
+    /// itself, so the area only keeps the events from nvim.
+    #[cfg(feature = "webkit-preview")]
+    pub fn scroll_area(&self) -> ScrollArea {
+        ScrollArea::new(self.frame.clone().upcast(), vec![])
+    }
+
     pub fn is_visible(&self) -> bool {
         self.frame.is_visible()
     }
 
+    #[cfg(feature = "webkit-preview")]
     pub fn load_style(&mut self, path: String) -> Result<(), &str> {
         let path = Path::new(&path);
         let theme =
@@ -219,7 +309,13 @@ impl CursorTooltip {
         Ok(())
     }
 
+    #[cfg(not(feature = "webkit-preview"))]
+    pub fn load_style(&mut self, _path: String) -> Result<(), &str> {
+        Err("Gnvim is built without webkit-preview")
+    }
+
     /// Parse markdown parser events into a form where we have syntax highlighting.
+    #[cfg(feature = "webkit-preview")]
     fn parse_events<'a>(&self, parser: md::Parser<'a>) -> Vec<md::Event<'a>> {
         let mut syntax = self.syntax_set.find_syntax_plain_text();
 
@@ -284,6 +380,7 @@ impl CursorTooltip {
         events
     }
 
+    #[cfg(feature = "webkit-preview")]
     pub fn show(&mut self, content: String) {
         // Parse the content (that should be markdown document).
         let mut opts = md::Options::empty();
@@ -354,6 +451,28 @@ impl CursorTooltip {
         self.webview.load_html(&all, None);
     }
 
+    #[cfg(not(feature = "webkit-preview"))]
+    pub fn show(&mut self, content: String) {
+        self.label.set_text(&content);
+
+        // Fit the tooltip to the content, like the webview does once it
+        // has loaded its content.
+        let (_, width) = self.label.get_preferred_width();
+        let width = width.min(MAX_WIDTH);
+        let (_, height) = self.label.get_preferred_height_for_width(width);
+        // Extra height coming from the frame's border.
+        let height = (height + 2).min(MAX_HEIGHT);
+
+        self.frame.show();
+        set_position(
+            &self.frame,
+            &self.fixed,
+            &self.state.borrow(),
+            width,
+            height,
+        );
+    }
+
     pub fn move_to(&mut self, rect: &gdk::Rectangle) {
         let mut state = self.state.borrow_mut();
         state.anchor = rect.clone();
@@ -414,9 +533,43 @@ fn set_position(
     frame.set_size_request(width, height);
 }
 
+/// Creates the webview for `frame`, which is resized to the webview's
+/// content when the content is loaded.
+#[cfg(feature = "webkit-preview")]
+fn new_webview(
+    frame: &gtk::Frame,
+    fixed: &gtk::Fixed,
+    state: &Arc<ThreadGuard<State>>,
+) -> webkit::WebView {
+    let context = webkit::WebContext::get_default().unwrap();
+    let webview = webkit::WebView::new_with_context(&context);
+    frame.add(&webview);
+
+    let frame_ref = frame.clone();
+    let fixed_ref = fixed.clone();
+    let state_ref = state.clone();
+    webview.connect_load_changed(move |webview, e| match e {
+        webkit::LoadEvent::Finished => {
+            webview_load_finished(
+                webview,
+                frame_ref.clone(),
+                fixed_ref.clone(),
+                state_ref.clone(),
+            );
+        }
+        _ => {}
+    });
+
+    let settings = WebViewExt::get_settings(&webview).unwrap();
+    settings.set_enable_javascript(true);
+
+    webview
+}
+
 /// Once the webview has loaded its content, we need to check how much
 /// height and width does the rendered content take. After this, we can set
 /// the size of the webview's container.
+#[cfg(feature = "webkit-preview")]
 fn webview_load_finished(
     webview: &webkit::WebView,
     frame: gtk::Frame,
@@ -481,6 +634,7 @@ fn webview_load_finished(
 /// Filters some HTML element attributes. Only allows `style` attribute
 /// for `span` element, with allowed CSS styles that are outputted by
 /// `syntect` HTML renderer.
+#[cfg(feature = "webkit-preview")]
 fn attribute_filter<'u>(
     element: &str,
     attribute: &str,
@@ -0,0 +1,172 @@
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use gtk;
+use gtk::prelude::*;
+use pango;
+
+use rpc_trace::RpcTrace;
+use ui::input_queue::QueueStats;
+
+/// Window that shows the rpc trace (see `RpcTrace`) and the state of our
+/// input queue.
+pub struct DebugConsole {
+    win: gtk::Window,
+    buffer: gtk::TextBuffer,
+    enabled: gtk::CheckButton,
+    trace: Arc<Mutex<RpcTrace>>,
+
+    queue_label: gtk::Label,
+    queue_stats: Arc<QueueStats>,
+}
+
+impl DebugConsole {
+    pub fn new(
+        parent: &gtk::ApplicationWindow,
+        trace: Arc<Mutex<RpcTrace>>,
+        queue_stats: Arc<QueueStats>,
+    ) -> Self {
+        let win = gtk::Window::new(gtk::WindowType::Toplevel);
+        win.set_title("Gnvim RPC trace");
+        win.set_transient_for(Some(parent));
+        win.set_default_size(800, 500);
+
+        let buffer = gtk::TextBuffer::new(None);
+        let view = gtk::TextView::new_with_buffer(&buffer);
+        view.set_editable(false);
+        view.set_cursor_visible(false);
+        view.set_monospace(true);
+
+        let scrolled = gtk::ScrolledWindow::new(None, None);
+        scrolled.add(&view);
+        scrolled.set_vexpand(true);
+
+        let enabled = gtk::CheckButton::new_with_label("Trace");
+        let refresh = gtk::Button::new_with_label("Refresh");
+        let clear = gtk::Button::new_with_label("Clear");
+        let export = gtk::Button::new_with_label("Export JSON");
+        let status = gtk::Label::new(None);
+        status.set_ellipsize(pango::EllipsizeMode::Start);
+
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        buttons.set_border_width(6);
+        buttons.pack_start(&enabled, false, false, 0);
+        buttons.pack_start(&refresh, false, false, 0);
+        buttons.pack_start(&clear, false, false, 0);
+        buttons.pack_start(&export, false, false, 0);
+        buttons.pack_end(&status, true, true, 0);
+
+        let queue_label = gtk::Label::new(None);
+        queue_label.set_halign(gtk::Align::Start);
+        queue_label.set_margin_start(6);
+        queue_label.set_margin_bottom(6);
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        content.pack_start(&buttons, false, false, 0);
+        content.pack_start(&queue_label, false, false, 0);
+        content.pack_start(&scrolled, true, true, 0);
+        win.add(&content);
+
+        // Keep the window around, so that it can be shown again.
+        win.connect_delete_event(|win, _| {
+            win.hide();
+            Inhibit(true)
+        });
+
+        let trace_ref = trace.clone();
+        enabled.connect_toggled(move |enabled| {
+            let mut trace = trace_ref.lock().unwrap();
+            trace.set_enabled(enabled.get_active());
+        });
+
+        let trace_ref = trace.clone();
+        let buffer_ref = buffer.clone();
+        let label_ref = queue_label.clone();
+        let stats_ref = queue_stats.clone();
+        refresh.connect_clicked(move |_| {
+            refresh_buffer(&buffer_ref, &trace_ref.lock().unwrap());
+            refresh_queue_label(&label_ref, &stats_ref);
+        });
+
+        let trace_ref = trace.clone();
+        let buffer_ref = buffer.clone();
+        clear.connect_clicked(move |_| {
+            let mut trace = trace_ref.lock().unwrap();
+            trace.clear();
+            refresh_buffer(&buffer_ref, &trace);
+        });
+
+        let trace_ref = trace.clone();
+        export.connect_clicked(move |_| {
+            match trace_ref.lock().unwrap().export() {
+                Ok(path) => {
+                    status.set_text(&format!("Exported to {}", path.display()))
+                }
+                Err(err) => {
+                    status.set_text(&format!("Failed to export: {}", err))
+                }
+            }
+        });
+
+        DebugConsole {
+            win,
+            buffer,
+            enabled,
+            trace,
+            queue_label,
+            queue_stats,
+        }
+    }
+
+    /// Shows the window with the latest trace.
+    pub fn show(&self) {
+        // The toggled handler locks the trace too, so don't hold the lock
+        // while setting the check button.
+        let enabled = self.trace.lock().unwrap().is_enabled();
+        self.enabled.set_active(enabled);
+        refresh_buffer(&self.buffer, &self.trace.lock().unwrap());
+        refresh_queue_label(&self.queue_label, &self.queue_stats);
+
+        self.win.show_all();
+        self.win.present();
+    }
+}
+
+fn refresh_queue_label(label: &gtk::Label, stats: &QueueStats) {
+    label.set_text(&format!(
+        "Input queue: {} pending, {} overflowed, {} calls",
+        stats.depth.load(Ordering::SeqCst),
+        stats.overflowed.load(Ordering::SeqCst),
+        stats.calls.load(Ordering::SeqCst),
+    ));
+}
+
+/// Fills `buffer` with the contents of `trace`, one message per line.
+fn refresh_buffer(buffer: &gtk::TextBuffer, trace: &RpcTrace) {
+    let mut text = format!(
+        "{:>12}  {:<12}  {:<24}  {:>10}  {:>10}\n",
+        "time (ms)", "kind", "method", "size (B)", "latency"
+    );
+
+    for entry in trace.entries() {
+        let latency = entry
+            .latency
+            .map(|l| format!("{:.3}ms", as_millis(l)))
+            .unwrap_or_default();
+
+        text.push_str(&format!(
+            "{:>12.3}  {:<12}  {:<24}  {:>10}  {:>10}\n",
+            as_millis(entry.time),
+            entry.kind.to_string(),
+            entry.method,
+            entry.size,
+            latency,
+        ));
+    }
+
+    buffer.set_text(&text);
+}
+
+fn as_millis(d: ::std::time::Duration) -> f64 {
+    d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1_000_000.0
+}
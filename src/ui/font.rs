@@ -2,6 +2,9 @@ use std::fmt;
 use std::fmt::Display;
 
 const DEFAULT_HEIGHT: usize = 14;
+/// Limits for `Font::zoom`.
+const MIN_HEIGHT: usize = 4;
+const MAX_HEIGHT: usize = 96;
 
 pub enum FontUnit {
     Pixel,
@@ -64,6 +67,62 @@ impl Font {
         Ok(font)
     }
 
+    /// Parses a pango font name (e.g. "Monospace Bold 12"), like the ones
+    /// used by `gtk::FontButton`.
+    pub fn from_font_name(font_name: &str) -> Result<Self, ()> {
+        let font_name = font_name.trim();
+        let (name, size) = match font_name.rfind(' ') {
+            Some(i) => (&font_name[..i], &font_name[i + 1..]),
+            None => (font_name, ""),
+        };
+
+        if name.is_empty() {
+            return Err(());
+        }
+
+        // Fractional sizes are rounded, because guifont doesn't support
+        // them.
+        let height = match size.parse::<f64>() {
+            Ok(h) if h.round() >= 1.0 => h.round() as usize,
+            // There was no size, so the size is part of the name.
+            Err(_) => {
+                return Ok(Font {
+                    name: font_name.to_string(),
+                    height: DEFAULT_HEIGHT,
+                })
+            }
+            _ => DEFAULT_HEIGHT,
+        };
+
+        Ok(Font {
+            name: name.to_string(),
+            height,
+        })
+    }
+
+    /// Returns self with the height changed by `step`, within sensible
+    /// limits.
+    pub fn zoom(&self, step: i64) -> Self {
+        let height = (self.height as i64 + step)
+            .max(MIN_HEIGHT as i64)
+            .min(MAX_HEIGHT as i64);
+
+        Font {
+            name: self.name.clone(),
+            height: height as usize,
+        }
+    }
+
+    /// Returns self as a value for nvim's `guifont` option.
+    pub fn as_guifont(&self) -> String {
+        format!("{}:h{}", self.name, self.height)
+    }
+
+    /// Returns self as a pango font name (see `from_font_name`).
+    pub fn as_font_name(&self) -> String {
+        format!("{} {}", self.name, self.height)
+    }
+
     /// Returns a CSS representation of self for a wild (`*`) CSS selector.
     /// On gtk version below 3.20 unit needs to be `FontUnit::Pixel` and
     /// with version 3.20 and up, unit needs to be `FontUnit::Point`. This is
@@ -159,4 +218,35 @@ mod tests {
         assert_eq!(f.name, "bar");
         assert_eq!(f.height, DEFAULT_HEIGHT);
     }
+
+    #[test]
+    fn test_from_font_name() {
+        let f = Font::from_font_name("Fira Code Bold 12").unwrap();
+        assert_eq!(f.name, "Fira Code Bold");
+        assert_eq!(f.height, 12);
+        assert_eq!(f.as_guifont(), "Fira Code Bold:h12");
+        assert_eq!(f.as_font_name(), "Fira Code Bold 12");
+
+        let f = Font::from_font_name("Monospace 10.6").unwrap();
+        assert_eq!(f.height, 11);
+
+        // Font without a size.
+        let f = Font::from_font_name("Noto Sans Mono").unwrap();
+        assert_eq!(f.name, "Noto Sans Mono");
+        assert_eq!(f.height, DEFAULT_HEIGHT);
+
+        let f = Font::from_font_name("Monospace 0").unwrap();
+        assert_eq!(f.height, DEFAULT_HEIGHT);
+
+        assert!(Font::from_font_name("").is_err());
+    }
+
+    #[test]
+    fn test_zoom() {
+        let f = Font::from_guifont("foo:h12").unwrap();
+        assert_eq!(f.zoom(2).as_guifont(), "foo:h14");
+        assert_eq!(f.zoom(-2).as_guifont(), "foo:h10");
+        assert_eq!(f.zoom(-100).height, MIN_HEIGHT);
+        assert_eq!(f.zoom(100).height, MAX_HEIGHT);
+    }
 }
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use gtk;
+use gtk::prelude::*;
+
+/// How many columns the pointer needs to move for a horizontal stroke.
+const COL_THRESHOLD: i64 = 4;
+/// How many rows the pointer needs to move for a vertical stroke.
+const ROW_THRESHOLD: i64 = 2;
+
+/// Direction of a single stroke in a mouse gesture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stroke {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Stroke {
+    /// Character used for the stroke in gesture mappings.
+    fn as_char(self) -> char {
+        match self {
+            Stroke::Left => 'L',
+            Stroke::Right => 'R',
+            Stroke::Up => 'U',
+            Stroke::Down => 'D',
+        }
+    }
+
+    /// Arrow used for the stroke in the on-screen feedback.
+    fn as_arrow(self) -> char {
+        match self {
+            Stroke::Left => '←',
+            Stroke::Right => '→',
+            Stroke::Up => '↑',
+            Stroke::Down => '↓',
+        }
+    }
+}
+
+/// Turns pointer positions (in grid cells) into strokes.
+pub struct Recognizer {
+    /// Position (row, col) where the latest stroke ended.
+    anchor: (i64, i64),
+    strokes: Vec<Stroke>,
+}
+
+impl Recognizer {
+    pub fn new(row: u64, col: u64) -> Self {
+        Recognizer {
+            anchor: (row as i64, col as i64),
+            strokes: vec![],
+        }
+    }
+
+    /// Updates the pointer position. Returns true if a new stroke was
+    /// recognized.
+    pub fn motion(&mut self, row: u64, col: u64) -> bool {
+        let drow = row as i64 - self.anchor.0;
+        let dcol = col as i64 - self.anchor.1;
+
+        // Cells are roughly twice as high as they are wide, so take that
+        // into account when deciding the direction.
+        let stroke = if dcol.abs() >= drow.abs() * 2 {
+            if dcol >= COL_THRESHOLD {
+                Some(Stroke::Right)
+            } else if dcol <= -COL_THRESHOLD {
+                Some(Stroke::Left)
+            } else {
+                None
+            }
+        } else if drow >= ROW_THRESHOLD {
+            Some(Stroke::Down)
+        } else if drow <= -ROW_THRESHOLD {
+            Some(Stroke::Up)
+        } else {
+            None
+        };
+
+        if let Some(stroke) = stroke {
+            self.anchor = (row as i64, col as i64);
+
+            // Continuing to the same direction is still the same stroke.
+            if self.strokes.last() != Some(&stroke) {
+                self.strokes.push(stroke);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Returns the gesture in the mapping notation (e.g. "LU").
+    pub fn gesture(&self) -> String {
+        self.strokes.iter().map(|s| s.as_char()).collect()
+    }
+
+    /// Returns the gesture as arrows (e.g. "←↑").
+    pub fn arrows(&self) -> String {
+        self.strokes.iter().map(|s| s.as_arrow()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strokes.is_empty()
+    }
+}
+
+/// Outcome of a finished gesture.
+pub enum GestureResult {
+    /// No strokes were made. The button press should be handled as a normal
+    /// click at the (row, col) where the gesture started.
+    Click(u64, u64),
+    /// Input mapped to the performed gesture.
+    Input(String),
+    /// The performed gesture has no mapping.
+    Unmapped,
+}
+
+/// Mouse gestures, performed while holding the right mouse button down.
+pub struct Gestures {
+    /// Gesture to input mappings.
+    mappings: HashMap<String, String>,
+    /// Currently active gesture and the (row, col) where it started.
+    current: Option<(Recognizer, (u64, u64))>,
+
+    css_provider: gtk::CssProvider,
+    /// Label to show the on-screen feedback of the current gesture.
+    label: gtk::Label,
+}
+
+impl Gestures {
+    pub fn new(parent: &gtk::Overlay) -> Self {
+        let css_provider = gtk::CssProvider::new();
+
+        let label = gtk::Label::new(None);
+        label.set_halign(gtk::Align::Center);
+        label.set_valign(gtk::Align::Center);
+        label.set_no_show_all(true);
+
+        add_css_provider!(&css_provider, label);
+
+        parent.add_overlay(&label);
+        parent.set_overlay_pass_through(&label, true);
+
+        let gestures = Gestures {
+            mappings: HashMap::new(),
+            current: None,
+            css_provider,
+            label,
+        };
+        gestures.set_style();
+
+        gestures
+    }
+
+    fn set_style(&self) {
+        let css = if gtk::get_minor_version() < 20 {
+            "GtkLabel {
+                padding: 12px;
+                font-size: 24px;
+                color: #ffffff;
+                background-color: rgba(0, 0, 0, 0.6);
+                border-radius: 6px;
+            }"
+        } else {
+            "label {
+                padding: 12px;
+                font-size: 24px;
+                color: #ffffff;
+                background-color: rgba(0, 0, 0, 0.6);
+                border-radius: 6px;
+            }"
+        };
+        CssProviderExt::load_from_data(&self.css_provider, css.as_bytes())
+            .unwrap();
+    }
+
+    /// Maps `gesture` (e.g. "LU") to `input`. Empty `input` removes the
+    /// mapping.
+    pub fn set_mapping(&mut self, gesture: String, input: String) {
+        if input.is_empty() {
+            self.mappings.remove(&gesture);
+        } else {
+            self.mappings.insert(gesture, input);
+        }
+    }
+
+    /// Gestures are only recognized when there is something mapped to them,
+    /// so that the right mouse button works normally otherwise.
+    pub fn is_enabled(&self) -> bool {
+        !self.mappings.is_empty()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.current.is_some()
+    }
+
+    pub fn begin(&mut self, row: u64, col: u64) {
+        self.current = Some((Recognizer::new(row, col), (row, col)));
+    }
+
+    pub fn motion(&mut self, row: u64, col: u64) {
+        let text = match self.current {
+            Some((ref mut recognizer, _)) => {
+                if !recognizer.motion(row, col) {
+                    return;
+                }
+
+                match self.mappings.get(&recognizer.gesture()) {
+                    Some(input) => {
+                        format!("{}  {}", recognizer.arrows(), input)
+                    }
+                    None => recognizer.arrows(),
+                }
+            }
+            None => return,
+        };
+
+        self.label.set_text(&text);
+        self.label.show();
+    }
+
+    pub fn end(&mut self) -> GestureResult {
+        self.label.hide();
+
+        match self.current.take() {
+            Some((ref recognizer, (row, col))) if recognizer.is_empty() => {
+                GestureResult::Click(row, col)
+            }
+            Some((recognizer, _)) => {
+                match self.mappings.get(&recognizer.gesture()) {
+                    Some(input) => GestureResult::Input(input.clone()),
+                    None => GestureResult::Unmapped,
+                }
+            }
+            None => GestureResult::Unmapped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recognizer_single_stroke() {
+        let mut r = Recognizer::new(10, 10);
+
+        // Small movements are not strokes.
+        assert_eq!(r.motion(10, 12), false);
+        assert_eq!(r.motion(11, 8), false);
+        assert!(r.is_empty());
+
+        assert_eq!(r.motion(10, 5), true);
+        assert_eq!(r.gesture(), "L");
+
+        // Continuing to the same direction doesn't add a stroke.
+        assert_eq!(r.motion(10, 0), false);
+        assert_eq!(r.gesture(), "L");
+        assert_eq!(r.arrows(), "←");
+    }
+
+    #[test]
+    fn test_recognizer_multiple_strokes() {
+        let mut r = Recognizer::new(10, 10);
+
+        assert_eq!(r.motion(10, 15), true);
+        assert_eq!(r.motion(7, 15), true);
+        assert_eq!(r.motion(7, 10), true);
+        assert_eq!(r.motion(10, 10), true);
+
+        assert_eq!(r.gesture(), "RULD");
+        assert_eq!(r.arrows(), "→↑←↓");
+    }
+
+    #[test]
+    fn test_recognizer_diagonal() {
+        let mut r = Recognizer::new(10, 10);
+
+        // Mostly horizontal movement is a horizontal stroke.
+        assert_eq!(r.motion(12, 16), true);
+        assert_eq!(r.gesture(), "R");
+
+        // Mostly vertical movement is a vertical stroke.
+        assert_eq!(r.motion(15, 18), true);
+        assert_eq!(r.gesture(), "RD");
+    }
+}
@@ -0,0 +1,157 @@
+use ui::cell_width::CellWidths;
+
+/// Ghost text shows the rest of the selected completion item inline at the
+/// cursor, when the item isn't inserted to the buffer (e.g. with
+/// `completeopt+=noinsert`).
+pub struct GhostText {
+    pub enabled: bool,
+
+    /// Words of the current completion items.
+    words: Vec<String>,
+    /// Column where the completed word starts.
+    col: u64,
+    /// Currently selected item, -1 if there is no selection.
+    selected: i64,
+    /// Widths of the characters, to map the line's text to its cells.
+    cell_widths: CellWidths,
+}
+
+impl Default for GhostText {
+    fn default() -> Self {
+        GhostText {
+            enabled: true,
+            words: vec![],
+            col: 0,
+            selected: -1,
+            cell_widths: CellWidths::default(),
+        }
+    }
+}
+
+impl GhostText {
+    /// Popupmenu is shown with `words`, completing a word that starts at
+    /// `col`.
+    pub fn show(&mut self, words: Vec<String>, col: u64, selected: i64) {
+        self.words = words;
+        self.col = col;
+        self.selected = selected;
+    }
+
+    pub fn select(&mut self, selected: i64) {
+        self.selected = selected;
+    }
+
+    pub fn set_cell_widths(&mut self, cell_widths: CellWidths) {
+        self.cell_widths = cell_widths;
+    }
+
+    pub fn hide(&mut self) {
+        self.words = vec![];
+        self.selected = -1;
+    }
+
+    /// Returns the ghost text to show at `cursor_col`, where `line` is the
+    /// contents of the cursor's row.
+    pub fn text(&self, line: &str, cursor_col: u64) -> Option<String> {
+        if !self.enabled || self.selected < 0 || cursor_col < self.col {
+            return None;
+        }
+
+        let word = self.words.get(self.selected as usize)?;
+
+        // Take the text of the cells between the word's start and the
+        // cursor. Characters can be wider than one cell, and zero width
+        // characters belong to the cell before them.
+        let mut typed = String::new();
+        let mut col = 0;
+        let mut cell = 0;
+        for c in line.chars() {
+            let width = self.cell_widths.width(c) as u64;
+            if width > 0 {
+                cell = col;
+                col += width;
+            }
+
+            if cell >= cursor_col {
+                break;
+            }
+            if cell >= self.col {
+                typed.push(c);
+            }
+        }
+
+        remaining(word, &typed)
+    }
+}
+
+/// Returns the part of `word` that is not yet `typed`. The typed part is
+/// compared case insensitively, because completion can be too.
+fn remaining(word: &str, typed: &str) -> Option<String> {
+    let mut word_chars = word.chars();
+
+    for c in typed.chars() {
+        match word_chars.next() {
+            Some(w) if w.to_lowercase().eq(c.to_lowercase()) => {}
+            _ => return None,
+        }
+    }
+
+    let rest: String = word_chars.collect();
+    if rest.is_empty() {
+        None
+    } else {
+        Some(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remaining() {
+        assert_eq!(remaining("foobar", "foo"), Some(String::from("bar")));
+        assert_eq!(remaining("foobar", ""), Some(String::from("foobar")));
+        assert_eq!(remaining("FooBar", "foob"), Some(String::from("ar")));
+        assert_eq!(remaining("foobar", "foobar"), None);
+        assert_eq!(remaining("foobar", "bar"), None);
+        assert_eq!(remaining("äöü", "ä"), Some(String::from("öü")));
+    }
+
+    #[test]
+    fn test_text() {
+        let mut ghost = GhostText::default();
+        ghost.show(vec!["println".to_string(), "print".to_string()], 4, -1);
+
+        // No selection, no ghost text.
+        assert_eq!(ghost.text("    pri", 7), None);
+
+        ghost.select(0);
+        assert_eq!(ghost.text("    pri", 7), Some(String::from("ntln")));
+
+        // Item is inserted to the buffer.
+        ghost.select(1);
+        assert_eq!(ghost.text("    print", 9), None);
+
+        ghost.enabled = false;
+        ghost.select(0);
+        assert_eq!(ghost.text("    pri", 7), None);
+
+        ghost.enabled = true;
+        ghost.hide();
+        assert_eq!(ghost.text("    pri", 7), None);
+    }
+
+    #[test]
+    fn test_text_wide_chars() {
+        let mut ghost = GhostText::default();
+
+        // Wide characters take two cells.
+        ghost.show(vec!["日本語".to_string()], 4, 0);
+        assert_eq!(ghost.text("日本日本", 6), Some(String::from("本語")));
+
+        // Combining characters don't take a cell of their own.
+        ghost.show(vec!["e\u{301}té".to_string()], 2, 0);
+        assert_eq!(ghost.text("a e\u{301}t", 4), Some(String::from("é")));
+    }
+}
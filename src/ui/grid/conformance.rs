@@ -0,0 +1,268 @@
+//! Conformance of our grid model with nvim's screen.
+//!
+//! The tests run nvim (`GNVIM_CONFORMANCE_NVIM`, or `nvim` from the path)
+//! headless, attach to it with each set of the UI extensions that we
+//! support, and after each step check that the rows we have built from the
+//! redraw events are what `screenstring()` tells is on nvim's screen. They
+//! need nvim, so they are ignored by default: `make conformance` runs them
+//! against each nvim release we support.
+
+use std::env;
+use std::process::Command;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use neovim_lib::neovim::{Neovim, UiAttachOptions};
+use neovim_lib::{NeovimApi, Session, Value};
+
+use nvim_bridge::{Message, Notify, NvimBridge, RedrawEvent};
+use rpc_trace::RpcTrace;
+use ui::grid::row::{self, Row};
+
+const COLS: u64 = 80;
+const ROWS: u64 = 24;
+
+/// How long nvim has to be quiet before its screen is checked.
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// The UI extensions to test with.
+#[derive(Debug, Clone, Copy)]
+struct Extensions {
+    popupmenu: bool,
+    tabline: bool,
+    cmdline: bool,
+    wildmenu: bool,
+}
+
+const EXTENSIONS: &[Extensions] = &[
+    Extensions {
+        popupmenu: false,
+        tabline: false,
+        cmdline: false,
+        wildmenu: false,
+    },
+    Extensions {
+        popupmenu: true,
+        tabline: false,
+        cmdline: false,
+        wildmenu: false,
+    },
+    Extensions {
+        popupmenu: false,
+        tabline: true,
+        cmdline: false,
+        wildmenu: false,
+    },
+    Extensions {
+        popupmenu: false,
+        tabline: false,
+        cmdline: true,
+        wildmenu: false,
+    },
+    Extensions {
+        popupmenu: false,
+        tabline: false,
+        cmdline: false,
+        wildmenu: true,
+    },
+    Extensions {
+        popupmenu: true,
+        tabline: true,
+        cmdline: true,
+        wildmenu: true,
+    },
+];
+
+#[derive(Debug)]
+enum Step {
+    /// Ex command, run through `nvim_command`.
+    Command(&'static str),
+    /// Keys, sent through `nvim_input`.
+    Input(&'static str),
+}
+
+const STEPS: &[Step] = &[
+    Step::Command("call setline(1, map(range(1, 100), 'v:val . \" line\"'))"),
+    Step::Input("50Gzt"),
+    Step::Input("<C-e><C-e><C-e>"),
+    Step::Input("<C-y>"),
+    Step::Input("<C-d>"),
+    Step::Input("gg"),
+    Step::Command("vsplit"),
+    Step::Input("<C-e><C-e>"),
+    Step::Command("split"),
+    Step::Command("wincmd w"),
+    Step::Input("<C-d>"),
+    Step::Command("only"),
+    Step::Command("call setline(1, ['漢字 wide', 'tab\there', 'ä é'])"),
+    Step::Command(
+        "call nvim_open_win(nvim_create_buf(v:false, v:true), v:false, \
+         {'relative': 'editor', 'row': 5, 'col': 10, 'width': 20, \
+         'height': 4, 'border': 'single'})",
+    ),
+    Step::Command("tabnew"),
+    Step::Command("call setline(1, ['foo', 'foobar', 'foobaz', ''])"),
+    Step::Input("Gofo<C-n>"),
+    Step::Input("<C-n>"),
+    Step::Input("<Esc>"),
+    Step::Command("tabprevious"),
+    Step::Input(":echo 'hello'<CR>"),
+    Step::Input(":colorscheme <Tab>"),
+    Step::Input("<Tab>"),
+    Step::Input("<Esc>"),
+];
+
+/// Headless grid (the default grid, without multigrid), built from the
+/// redraw events like `Grid` builds its rows.
+#[derive(Default)]
+struct Model {
+    rows: Vec<Row>,
+}
+
+impl Model {
+    fn handle(&mut self, event: RedrawEvent) {
+        match event {
+            RedrawEvent::GridResize(1, width, height) => {
+                self.rows = vec![Row::new(width as usize); height as usize];
+            }
+            RedrawEvent::GridClear(1) => {
+                self.rows.iter_mut().for_each(Row::clear);
+            }
+            RedrawEvent::GridLine(lines) => {
+                for line in lines.iter().filter(|line| line.grid == 1) {
+                    self.rows[line.row as usize].update(line);
+                }
+            }
+            RedrawEvent::GridScroll(1, reg, count, _) => {
+                row::scroll(&mut self.rows, reg, count);
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles the events from nvim until it has been quiet for `SETTLE`.
+    fn settle(&mut self, rx: &Receiver<Message>) {
+        loop {
+            match rx.recv_timeout(SETTLE) {
+                Ok(Message::Notify(Notify::RedrawEvent(events))) => {
+                    events.into_iter().for_each(|event| self.handle(event));
+                }
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => return,
+                Err(RecvTimeoutError::Disconnected) => {
+                    panic!("Nvim exited")
+                }
+            }
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| row.text().trim_end().to_string())
+            .collect()
+    }
+}
+
+/// Returns the rows of nvim's screen, as `screenstring()` has them.
+fn screen(nvim: &mut Neovim) -> Vec<String> {
+    // With the width of each cell, so the right halves of the double width
+    // characters can be skipped, whatever `screenstring()` has for them.
+    let value = nvim
+        .eval(
+            "map(range(1, &lines), {_, r -> map(range(1, &columns), \
+             {_, c -> [screenstring(r, c), strwidth(screenstring(r, c))]})})",
+        )
+        .expect("Failed to get the screen");
+
+    let rows = match value {
+        Value::Array(rows) => rows,
+        value => panic!("Unexpected screen: {:?}", value),
+    };
+
+    rows.into_iter()
+        .map(|cells| {
+            let cells = match cells {
+                Value::Array(cells) => cells,
+                value => panic!("Unexpected row: {:?}", value),
+            };
+
+            let mut line = String::new();
+            let mut skip = false;
+            for cell in cells {
+                if skip {
+                    skip = false;
+                    continue;
+                }
+
+                let cell = cell.as_array().cloned().unwrap_or_default();
+                line += cell.get(0).and_then(Value::as_str).unwrap_or("");
+                skip = cell.get(1).and_then(Value::as_u64) == Some(2);
+            }
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+fn run(path: &str, ext: Extensions) {
+    let mut cmd = Command::new(path);
+    cmd.args(&["--embed", "--clean", "-n"]);
+    let mut session = Session::new_child_cmd(&mut cmd)
+        .unwrap_or_else(|err| panic!("Failed to run {}: {}", path, err));
+
+    let (tx, rx) = channel();
+    let trace = Arc::new(Mutex::new(RpcTrace::new(false)));
+    session.start_event_loop_handler(NvimBridge::new(tx, trace));
+    let mut nvim = Neovim::new(session);
+
+    let mut opts = UiAttachOptions::new();
+    opts.set_rgb(true);
+    opts.set_linegrid_external(true);
+    opts.set_popupmenu_external(ext.popupmenu);
+    opts.set_tabline_external(ext.tabline);
+    opts.set_cmdline_external(ext.cmdline);
+    opts.set_wildmenu_external(ext.wildmenu);
+    nvim.ui_attach(COLS as i64, ROWS as i64, &opts)
+        .expect("Failed to attach");
+
+    let mut model = Model::default();
+    model.settle(&rx);
+
+    for step in STEPS {
+        match step {
+            Step::Command(cmd) => nvim.command(cmd).unwrap_or_else(|err| {
+                panic!("{:?} failed with {}: {}", step, path, err)
+            }),
+            Step::Input(keys) => {
+                nvim.input(keys).expect("Failed to send input");
+            }
+        }
+        model.settle(&rx);
+
+        let expected = screen(&mut nvim);
+        let lines = model.lines();
+        assert_eq!(lines.len(), expected.len(), "{} {:?}", path, ext);
+        for (row, (line, expected)) in lines.iter().zip(&expected).enumerate() {
+            assert_eq!(
+                line, expected,
+                "{} {:?}, after {:?}, row {}",
+                path, ext, step, row
+            );
+        }
+    }
+
+    // Nvim exits when the embedder goes away.
+    let _ = nvim.command("qa!");
+}
+
+#[test]
+#[ignore]
+fn test_conformance() {
+    let path =
+        env::var("GNVIM_CONFORMANCE_NVIM").unwrap_or_else(|_| "nvim".into());
+
+    for &ext in EXTENSIONS {
+        run(&path, ext);
+    }
+}
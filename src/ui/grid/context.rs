@@ -1,4 +1,5 @@
 use cairo;
+use gdk::ScreenExt;
 use gtk::DrawingArea;
 use pango;
 use pango::FontDescription;
@@ -7,8 +8,28 @@ use pangocairo;
 use gtk::prelude::*;
 use pango::prelude::*;
 
+use ui::cell_width::CellWidths;
 use ui::color::{Color, Highlight};
+use ui::grid::cursor_effects::CursorEffects;
+use ui::grid::damage::{Damage, Rect};
+use ui::grid::dither::Dither;
+use ui::grid::images::Images;
+use ui::grid::lru::LruCache;
 use ui::grid::row::Row;
+use ui::grid::scroll_restore::ScrollRestore;
+use ui::grid::text_selection::TextSelection;
+
+/// Max number of shaped texts in `Context::glyph_cache`.
+const GLYPH_CACHE_SIZE: usize = 4096;
+
+/// Shaped pango item: its font, glyphs and x offset from the start of the
+/// text.
+pub type ShapedItem = (pango::Font, pango::GlyphString, f64);
+
+/// Cache of shaped texts, keyed by the text and if it's bold and/or italic.
+/// Shaping (and font lookup) is the most expensive part of rendering, and
+/// the same texts are rendered over and over again (e.g. when scrolling).
+pub type GlyphCache = LruCache<(String, bool, bool), Vec<ShapedItem>>;
 
 /// Context is manipulated by Grid.
 pub struct Context {
@@ -18,6 +39,11 @@ pub struct Context {
     pub pango_context: pango::Context,
     /// Our cell metrics.
     pub cell_metrics: CellMetrics,
+    /// Shaped texts. Needs to be cleared when the font or the cell widths
+    /// change.
+    pub glyph_cache: GlyphCache,
+    /// Widths of the characters, as nvim has them.
+    pub cell_widths: CellWidths,
     /// Cell metrics to be updated.
     pub cell_metrics_update: Option<CellMetrics>,
 
@@ -41,8 +67,46 @@ pub struct Context {
     /// If the grid that this context belongs to is active or not.
     pub active: bool,
 
-    /// Areas to call queue_draw_area on the drawing area on flush.
-    pub queue_draw_area: Vec<(i32, i32, i32, i32)>,
+    /// Areas that have changed since the last flush. These are queued to be
+    /// drawn on flush.
+    pub damage: Damage,
+    /// If the damaged areas are outlined (for debugging).
+    pub show_damage: bool,
+    /// Areas that were damaged on the last flush, when `show_damage` is set.
+    pub shown_damage: Vec<Rect>,
+    /// Number of times the grid has been drawn.
+    pub frames: u64,
+
+    /// Background color of the selection (e.g. visual highlight). Cells
+    /// with this background are rendered as continuous regions.
+    pub selection_bg: Option<Color>,
+    /// If the selection is rendered with rounded corners.
+    pub rounded_selection: bool,
+
+    /// If nvim takes the mouse. If it doesn't, dragging with the left
+    /// button selects text (see `text_selection`).
+    pub mouse_enabled: bool,
+    /// Text selected with the mouse, when nvim doesn't take the mouse.
+    pub text_selection: Option<TextSelection>,
+
+    /// Ghost text drawn at the cursor and the row it was set at.
+    pub ghost_text: Option<(String, u64)>,
+    /// Colors (fg, bg) of the ghost text.
+    pub ghost_colors: (Color, Color),
+
+    /// Dither for the blended areas, if dithering is enabled.
+    pub dither: Option<Dither>,
+
+    /// Images shown on top of the grid.
+    pub images: Images,
+
+    /// Crosshair, beacon and mode flash of the cursor.
+    pub cursor_effects: CursorEffects,
+    /// Animation of a window's view when its buffer is switched.
+    pub scroll_restore: ScrollRestore,
+    /// If a tick callback is running the cursor effects' or the scroll
+    /// restore's animations.
+    pub effects_ticking: bool,
 }
 
 impl Context {
@@ -57,6 +121,10 @@ impl Context {
         let cairo_context = cairo::Context::new(&surface);
         let pango_context =
             pangocairo::functions::create_context(&cairo_context).unwrap();
+        pangocairo::functions::context_set_resolution(
+            &pango_context,
+            screen_resolution(da),
+        );
 
         let font_desc = FontDescription::from_string("Monospace 12");
         pango_context.set_font_description(&font_desc);
@@ -70,6 +138,8 @@ impl Context {
             cairo_context,
             pango_context,
             cell_metrics,
+            glyph_cache: LruCache::new(GLYPH_CACHE_SIZE),
+            cell_widths: CellWidths::default(),
             cell_metrics_update: None,
             rows: vec![],
 
@@ -82,7 +152,27 @@ impl Context {
             current_hl: Highlight::default(),
             active: false,
 
-            queue_draw_area: vec![],
+            damage: Damage::default(),
+            show_damage: false,
+            shown_damage: vec![],
+            frames: 0,
+
+            selection_bg: None,
+            rounded_selection: false,
+
+            mouse_enabled: true,
+            text_selection: None,
+
+            ghost_text: None,
+            ghost_colors: (Color::default(), Color::default()),
+
+            dither: None,
+
+            images: Images::default(),
+
+            cursor_effects: CursorEffects::default(),
+            scroll_restore: ScrollRestore::default(),
+            effects_ticking: false,
         }
     }
 
@@ -104,10 +194,15 @@ impl Context {
         self.cairo_context.restore();
 
         let pctx = pangocairo::functions::create_context(&ctx).unwrap();
+        pangocairo::functions::context_set_resolution(
+            &pctx,
+            screen_resolution(da),
+        );
         pctx.set_font_description(&self.cell_metrics.font_desc);
 
         self.cairo_context = ctx;
         self.pango_context = pctx;
+        self.glyph_cache.clear();
 
         self.cell_metrics.update(&self.pango_context);
     }
@@ -141,10 +236,21 @@ impl Context {
         if let Some(cm) = self.cell_metrics_update.take() {
             self.pango_context.set_font_description(&cm.font_desc);
             self.cell_metrics = cm;
+            self.glyph_cache.clear();
         }
     }
 }
 
+/// Returns the resolution (DPI) of `da`'s screen, which the fonts are
+/// scaled by. With fractional scaling, the resolution is scaled instead of
+/// the (integer) scale factor.
+fn screen_resolution(da: &DrawingArea) -> f64 {
+    da.get_screen()
+        .map(|screen| screen.get_resolution())
+        .filter(|res| *res > 0.0)
+        .unwrap_or(96.0)
+}
+
 /// Cell metrics tells the size (and other metrics) of the cells in a grid.
 #[derive(Default, Debug, Clone)]
 pub struct CellMetrics {
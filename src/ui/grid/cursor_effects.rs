@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use cairo;
+
+use ui::color::Color;
+use ui::grid::context::CellMetrics;
+
+/// How long the beacon is shown, in milliseconds.
+const BEACON_DURATION: u64 = 400;
+/// How many cells the beacon extends to the right of the cursor when it
+/// starts.
+const BEACON_CELLS: f64 = 12.0;
+/// How long the mode flash is shown, in milliseconds.
+const FLASH_DURATION: u64 = 200;
+
+/// Visual aids drawn around the cursor: a crosshair that follows the
+/// cursor, a beacon (a shrinking highlight) when the cursor jumps far and
+/// a flash of the grid when the mode changes. All of them are disabled by
+/// default.
+#[derive(Default)]
+pub struct CursorEffects {
+    /// If the cursor's row and column are highlighted.
+    pub crosshair: bool,
+    /// Minimum jump, in rows, that shows the beacon. Zero disables the
+    /// beacon.
+    pub beacon_rows: u64,
+    /// If the grid flashes when the mode changes.
+    pub mode_flash: bool,
+
+    beacon: Option<Instant>,
+    flash: Option<Instant>,
+}
+
+impl CursorEffects {
+    /// Tells that the cursor moved from row `from` to row `to`. Returns
+    /// true if the beacon was started.
+    pub fn cursor_moved(&mut self, from: u64, to: u64) -> bool {
+        let jump = if from > to { from - to } else { to - from };
+        if self.beacon_rows == 0 || jump < self.beacon_rows {
+            return false;
+        }
+
+        self.beacon = Some(Instant::now());
+        true
+    }
+
+    /// Tells that the mode changed. Returns true if the flash was started.
+    pub fn mode_changed(&mut self) -> bool {
+        if !self.mode_flash {
+            return false;
+        }
+
+        self.flash = Some(Instant::now());
+        true
+    }
+
+    /// Returns true if the beacon or the flash is still running.
+    pub fn is_animating(&self) -> bool {
+        progress(self.beacon, BEACON_DURATION).is_some()
+            || progress(self.flash, FLASH_DURATION).is_some()
+    }
+
+    /// Draws the effects for the cursor at cell `cursor` to `cr`, which is
+    /// `width` x `height`. Called before the cursor itself is drawn.
+    pub fn draw(
+        &self,
+        cr: &cairo::Context,
+        cm: &CellMetrics,
+        cursor: (u64, u64),
+        color: &Color,
+        width: f64,
+        height: f64,
+    ) {
+        let x = cursor.1 as f64 * cm.width;
+        let y = cursor.0 as f64 * cm.height;
+
+        cr.save();
+
+        if self.crosshair {
+            cr.set_source_rgba(color.r, color.g, color.b, 0.08);
+            cr.rectangle(0.0, y, width, cm.height);
+            cr.fill();
+            cr.rectangle(x, 0.0, cm.width, y);
+            cr.rectangle(x, y + cm.height, cm.width, height - y - cm.height);
+            cr.fill();
+        }
+
+        if let Some(p) = progress(self.beacon, BEACON_DURATION) {
+            let w = cm.width * (1.0 + BEACON_CELLS * (1.0 - p));
+            cr.set_source_rgba(color.r, color.g, color.b, 0.5 * (1.0 - p));
+            cr.rectangle(x, y, w, cm.height);
+            cr.fill();
+        }
+
+        if let Some(p) = progress(self.flash, FLASH_DURATION) {
+            cr.set_source_rgba(color.r, color.g, color.b, 0.15 * (1.0 - p));
+            cr.rectangle(0.0, 0.0, width, height);
+            cr.fill();
+        }
+
+        cr.restore();
+    }
+}
+
+/// Returns how far (0..1) an animation that started at `start` is, or
+/// `None` if it isn't running.
+pub fn progress(start: Option<Instant>, duration: u64) -> Option<f64> {
+    let elapsed = start?.elapsed();
+    let duration = Duration::from_millis(duration);
+    if elapsed >= duration {
+        return None;
+    }
+
+    let as_secs =
+        |d: Duration| d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9;
+    Some(as_secs(elapsed) / as_secs(duration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beacon_jump() {
+        let mut effects = CursorEffects::default();
+        assert!(!effects.cursor_moved(0, 50));
+
+        effects.beacon_rows = 10;
+        assert!(!effects.cursor_moved(20, 11));
+        assert!(!effects.is_animating());
+        assert!(effects.cursor_moved(20, 10));
+        assert!(effects.cursor_moved(10, 20));
+        assert!(effects.is_animating());
+    }
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(progress(None, 100), None);
+
+        let start = Instant::now() - Duration::from_millis(50);
+        let p = progress(Some(start), 100).unwrap();
+        assert!(p >= 0.5 && p < 1.0);
+
+        let start = Instant::now() - Duration::from_millis(150);
+        assert_eq!(progress(Some(start), 100), None);
+    }
+}
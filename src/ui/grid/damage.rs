@@ -0,0 +1,155 @@
+use std::mem;
+
+/// Rectangle in pixels: (x, y, width, height).
+pub type Rect = (i32, i32, i32, i32);
+
+/// Maximum number of separate rectangles we keep track of. If there are
+/// more, the damage is collapsed into its bounding box.
+const MAX_RECTS: usize = 32;
+
+/// Damage accumulates the areas of a grid that have changed between
+/// flushes, so that only those areas are queued to be drawn.
+///
+/// Neighbouring areas (e.g. cells on the same row, or whole rows after a
+/// scroll) are merged together, so that we don't end up queueing hundreds
+/// of small draws on each flush.
+#[derive(Default)]
+pub struct Damage {
+    rects: Vec<Rect>,
+}
+
+impl Damage {
+    /// Adds `rect` to the damaged area.
+    pub fn add(&mut self, rect: Rect) {
+        if rect.2 <= 0 || rect.3 <= 0 {
+            return;
+        }
+
+        let mut rect = rect;
+        loop {
+            if self.rects.iter().any(|r| contains(r, &rect)) {
+                return;
+            }
+
+            match self
+                .rects
+                .iter()
+                .position(|r| contains(&rect, r) || mergeable(r, &rect))
+            {
+                Some(i) => {
+                    let r = self.rects.swap_remove(i);
+                    rect = union(&r, &rect);
+                }
+                None => break,
+            }
+        }
+
+        if self.rects.len() >= MAX_RECTS {
+            rect = self.rects.drain(..).fold(rect, |a, b| union(&a, &b));
+        }
+
+        self.rects.push(rect);
+    }
+
+    /// Returns the damaged areas and clears the damage.
+    pub fn take(&mut self) -> Vec<Rect> {
+        mem::replace(&mut self.rects, vec![])
+    }
+}
+
+/// Returns the smallest rectangle of whole pixels that contains the area
+/// `(x, y, w, h)`. Cells are rarely whole pixels, and the edges drawn for
+/// them are rounded to the nearest pixel (see `render::snap_rect`), so
+/// truncating would leave out the last pixels of the area.
+pub fn enclosing(x: f64, y: f64, w: f64, h: f64) -> Rect {
+    let x1 = x.floor();
+    let y1 = y.floor();
+    let x2 = (x + w).ceil();
+    let y2 = (y + h).ceil();
+
+    (x1 as i32, y1 as i32, (x2 - x1) as i32, (y2 - y1) as i32)
+}
+
+/// Checks if `a` contains `b`.
+fn contains(a: &Rect, b: &Rect) -> bool {
+    a.0 <= b.0 && a.1 <= b.1 && a.0 + a.2 >= b.0 + b.2 && a.1 + a.3 >= b.1 + b.3
+}
+
+/// Checks if `a` and `b` can be merged without damaging any extra area:
+/// they're on the same rows (or columns) and touch or overlap each other.
+fn mergeable(a: &Rect, b: &Rect) -> bool {
+    let same_rows = a.1 == b.1 && a.3 == b.3;
+    let same_cols = a.0 == b.0 && a.2 == b.2;
+
+    (same_rows && a.0 <= b.0 + b.2 && b.0 <= a.0 + a.2)
+        || (same_cols && a.1 <= b.1 + b.3 && b.1 <= a.1 + a.3)
+}
+
+fn union(a: &Rect, b: &Rect) -> Rect {
+    let x = a.0.min(b.0);
+    let y = a.1.min(b.1);
+    let w = (a.0 + a.2).max(b.0 + b.2) - x;
+    let h = (a.1 + a.3).max(b.1 + b.3) - y;
+
+    (x, y, w, h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_damage_merge() {
+        let mut damage = Damage::default();
+
+        // Cells next to each other on the same row.
+        damage.add((0, 0, 10, 20));
+        damage.add((10, 0, 10, 20));
+        damage.add((20, 0, 10, 20));
+        // Contained in the existing damage.
+        damage.add((5, 0, 10, 20));
+        // Empty.
+        damage.add((100, 100, 0, 20));
+        assert_eq!(damage.take(), vec![(0, 0, 30, 20)]);
+        assert!(damage.take().is_empty());
+
+        // Whole rows (e.g. from a scroll).
+        damage.add((0, 0, 100, 20));
+        damage.add((0, 40, 100, 20));
+        damage.add((0, 20, 100, 20));
+        assert_eq!(damage.take(), vec![(0, 0, 100, 60)]);
+    }
+
+    #[test]
+    fn test_damage_separate() {
+        let mut damage = Damage::default();
+
+        damage.add((0, 0, 10, 20));
+        damage.add((50, 40, 10, 20));
+        assert_eq!(damage.take(), vec![(0, 0, 10, 20), (50, 40, 10, 20)]);
+
+        // Damage that contains existing damage replaces it.
+        damage.add((0, 0, 10, 20));
+        damage.add((50, 40, 10, 20));
+        damage.add((0, 0, 100, 100));
+        assert_eq!(damage.take(), vec![(0, 0, 100, 100)]);
+    }
+
+    #[test]
+    fn test_enclosing() {
+        assert_eq!(enclosing(0.0, 0.0, 10.0, 20.0), (0, 0, 10, 20));
+        assert_eq!(enclosing(10.4, 17.3, 8.4, 17.3), (10, 17, 9, 18));
+    }
+
+    #[test]
+    fn test_damage_max_rects() {
+        let mut damage = Damage::default();
+
+        for i in 0..MAX_RECTS as i32 + 1 {
+            damage.add((i * 20, i * 20, 10, 10));
+        }
+
+        let max = MAX_RECTS as i32 * 20;
+        assert_eq!(damage.take(), vec![(0, 0, max + 10, max + 10)]);
+    }
+}
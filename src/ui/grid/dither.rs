@@ -0,0 +1,140 @@
+use cairo;
+use cairo::prelude::*;
+use pango;
+use pangocairo;
+
+/// Size of the Bayer matrix (and our pattern), in pixels.
+const SIZE: usize = 8;
+/// Maximum change the dither makes to a color channel: half of one step of
+/// a 6 bit channel, which is what many laptop panels have.
+const STRENGTH: f64 = 0.5 / 64.0;
+
+/// Ordered dithering for the areas we blend (e.g. the fading cursor and
+/// the ghost text). Without it, the blended colors fall between the levels
+/// that 6 bit panels can show, and gradual changes are seen as bands.
+///
+/// The dither is a repeating pattern of slightly lighter and darker device
+/// pixels that is drawn on top of the blended pixels only, so that the
+/// opaque colors around them stay exact.
+pub struct Dither {
+    surface: cairo::ImageSurface,
+}
+
+impl Dither {
+    pub fn new() -> Option<Self> {
+        let surface = cairo::ImageSurface::create(
+            cairo::Format::ARgb32,
+            SIZE as i32,
+            SIZE as i32,
+        )
+        .ok()?;
+
+        {
+            let cr = cairo::Context::new(&surface);
+            cr.set_operator(cairo::Operator::Source);
+            for (y, row) in bayer(SIZE).iter().enumerate() {
+                for (x, &value) in row.iter().enumerate() {
+                    // Threshold in [-1, 1), lighter above zero and darker
+                    // below it.
+                    let t = (value as f64 + 0.5) / (SIZE * SIZE) as f64;
+                    let t = t * 2.0 - 1.0;
+                    let c = if t >= 0.0 { 1.0 } else { 0.0 };
+                    cr.set_source_rgba(c, c, c, t.abs() * STRENGTH);
+                    cr.rectangle(x as f64, y as f64, 1.0, 1.0);
+                    cr.fill();
+                }
+            }
+        }
+
+        Some(Dither { surface })
+    }
+
+    /// Dithers the area `(x, y, w, h)` of `cr`.
+    pub fn apply(&self, cr: &cairo::Context, x: f64, y: f64, w: f64, h: f64) {
+        cr.save();
+        self.set_source(cr);
+        cr.rectangle(x, y, w, h);
+        cr.fill();
+        cr.restore();
+    }
+
+    /// Dithers the glyphs of `layout`, drawn at `(x, y)` of `cr`. Used when
+    /// only the text is blended, and not its background.
+    pub fn apply_layout(
+        &self,
+        cr: &cairo::Context,
+        x: f64,
+        y: f64,
+        layout: &pango::Layout,
+    ) {
+        cr.save();
+        self.set_source(cr);
+        cr.move_to(x, y);
+        pangocairo::functions::layout_path(cr, layout);
+        cr.fill();
+        cr.restore();
+    }
+
+    /// Sets our pattern as the source of `cr`, with one pattern pixel per
+    /// device pixel. Otherwise, with a scale factor (or zoom) the pattern
+    /// would be scaled up, and seen as a grid instead of noise.
+    fn set_source(&self, cr: &cairo::Context) {
+        cr.set_source_surface(&self.surface, 0.0, 0.0);
+        let source = cr.get_source();
+        source.set_extend(cairo::Extend::Repeat);
+        source.set_filter(cairo::Filter::Nearest);
+
+        // The pattern's matrix maps user space to the pattern's space,
+        // which we want to be the device space.
+        let (sx, sy) = cr.user_to_device_distance(1.0, 1.0);
+        let (x0, y0) = cr.user_to_device(0.0, 0.0);
+        source.set_matrix(cairo::Matrix::new(sx, 0.0, 0.0, sy, x0, y0));
+    }
+}
+
+/// Returns the `size` x `size` Bayer (ordered dithering) matrix. `size`
+/// needs to be a power of two.
+fn bayer(size: usize) -> Vec<Vec<u32>> {
+    let mut m = vec![vec![0]];
+    while m.len() < size {
+        let n = m.len();
+        let mut next = vec![vec![0; n * 2]; n * 2];
+        for y in 0..n {
+            for x in 0..n {
+                let v = m[y][x] * 4;
+                next[y][x] = v;
+                next[y][x + n] = v + 2;
+                next[y + n][x] = v + 3;
+                next[y + n][x + n] = v + 1;
+            }
+        }
+        m = next;
+    }
+
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bayer() {
+        assert_eq!(bayer(2), vec![vec![0, 2], vec![3, 1]]);
+        assert_eq!(
+            bayer(4),
+            vec![
+                vec![0, 8, 2, 10],
+                vec![12, 4, 14, 6],
+                vec![3, 11, 1, 9],
+                vec![15, 7, 13, 5],
+            ]
+        );
+
+        // Every threshold is used exactly once.
+        let mut values: Vec<u32> =
+            bayer(SIZE).into_iter().flat_map(|row| row).collect();
+        values.sort();
+        assert_eq!(values, (0..(SIZE * SIZE) as u32).collect::<Vec<_>>());
+    }
+}
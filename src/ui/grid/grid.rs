@@ -1,22 +1,36 @@
 use std::fmt;
 use std::fmt::Display;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use cairo;
 use gdk;
-use gdk::{EventMask, ModifierType};
+use gdk::{EventMask, ModifierType, ScreenExt};
+use gdk_pixbuf;
 use gtk;
 use gtk::{DrawingArea, EventBox};
+use pango;
 use pango::FontDescription;
+use pango::LayoutExt;
+use pangocairo;
 
 use cairo::prelude::*;
 use gtk::prelude::*;
 
 use nvim_bridge::{GridLineSegment, ModeInfo};
+use profiler;
+use startup;
 use thread_guard::ThreadGuard;
-use ui::grid::context::Context;
+use ui::cell_width::CellWidths;
+use ui::color::Color;
+use ui::grid::context::{CellMetrics, Context};
+use ui::grid::damage;
+use ui::grid::dither::Dither;
+use ui::grid::images::{self, Anchor};
 use ui::grid::render;
 use ui::grid::row::Row;
+use ui::grid::text_selection::TextSelection;
+use ui::scroll_routing;
 use ui::ui::HlDefs;
 
 pub enum ScrollDirection {
@@ -86,6 +100,17 @@ impl Grid {
             false
         });
 
+        // When we're moved to a monitor with a different scale factor, our
+        // surface needs to be recreated for the new scale. Otherwise our
+        // content would be scaled (and blurry).
+        let ctx_ref = ctx.clone();
+        da.connect_property_scale_factor_notify(move |da| {
+            if let Some(ref mut ctx) = *ctx_ref.borrow_mut() {
+                ctx.update(&da);
+            }
+            da.queue_draw();
+        });
+
         let ctx_ref = ctx.clone();
         da.connect_draw(move |_, cr| {
             let ctx = ctx_ref.clone();
@@ -114,7 +139,9 @@ impl Grid {
         self.eb.clone().upcast()
     }
 
-    pub fn flush(&self, hl_defs: &HlDefs) {
+    /// Queues the damaged areas to be drawn. Returns the number of damaged
+    /// cells.
+    pub fn flush(&self, hl_defs: &HlDefs) -> usize {
         let mut ctx = self.context.borrow_mut();
         let ctx = ctx.as_mut().unwrap();
 
@@ -124,9 +151,130 @@ impl Grid {
         let hl = hl_defs.get(&leaf.hl_id()).unwrap();
         ctx.cursor_color = hl.foreground.unwrap_or(hl_defs.default_fg);
 
-        while let Some(area) = ctx.queue_draw_area.pop() {
+        let damage = ctx.damage.take();
+        for area in damage.iter() {
             self.da.queue_draw_area(area.0, area.1, area.2, area.3);
         }
+
+        // The new view of a switched buffer is here.
+        if ctx.scroll_restore.flushed() {
+            self.animate_effects(ctx);
+        }
+
+        if ctx.show_damage {
+            // Redraw the previously damaged areas too, so their outlines
+            // are cleared.
+            for area in ctx.shown_damage.iter() {
+                self.da.queue_draw_area(area.0, area.1, area.2, area.3);
+            }
+            ctx.shown_damage = damage.clone();
+        }
+
+        let cm = &ctx.cell_metrics;
+        let cell_area = cm.width * cm.height;
+        if cell_area <= 0.0 {
+            return 0;
+        }
+        damage
+            .iter()
+            .map(|&(_, _, w, h)| ((w * h) as f64 / cell_area).round() as usize)
+            .sum()
+    }
+
+    /// Returns how many times the grid has been drawn.
+    pub fn frame_count(&self) -> u64 {
+        let ctx = self.context.borrow();
+        ctx.as_ref().map(|ctx| ctx.frames).unwrap_or(0)
+    }
+
+    /// Sets if the areas that are redrawn on flush are outlined.
+    pub fn set_show_damage(&self, show: bool) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.show_damage = show;
+        ctx.shown_damage.clear();
+        self.da.queue_draw();
+    }
+
+    /// Sets if the blended areas (e.g. the fading cursor) are dithered.
+    pub fn set_dithering(&self, enabled: bool) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.dither = if enabled { Dither::new() } else { None };
+        self.da.queue_draw();
+    }
+
+    /// Shows image from `path` (with id `id`) at cell (`row`, `col`). The
+    /// image is scaled down to fit in `max_cols` x `max_rows` cells, zero
+    /// meaning no limit. The image is clipped to the cells `clip` (top, bot,
+    /// left, right), e.g. its nvim window, or to the grid if it's `None`.
+    /// Showing an image with an existing id replaces it. The image is loaded
+    /// at our scale factor, e.g. SVGs are rendered in full resolution on
+    /// HiDPI monitors.
+    pub fn show_image(
+        &self,
+        id: u64,
+        path: &str,
+        row: u64,
+        col: u64,
+        max_cols: u64,
+        max_rows: u64,
+        clip: Option<[u64; 4]>,
+    ) -> Result<(), String> {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        let (_, width, height) = gdk_pixbuf::Pixbuf::get_file_info(path)
+            .ok_or_else(|| format!("Failed to load image '{}'", path))?;
+
+        // Max size in pixels.
+        let size = |cells: u64, cell_size: f64| {
+            if cells == 0 {
+                None
+            } else {
+                Some((cells as f64 * cell_size).floor())
+            }
+        };
+        let (width, height) = images::fit(
+            (width, height),
+            (
+                size(max_cols, ctx.cell_metrics.width),
+                size(max_rows, ctx.cell_metrics.height),
+            ),
+        );
+
+        let scale = self.da.get_scale_factor();
+        let pixbuf = gdk_pixbuf::Pixbuf::new_from_file_at_scale(
+            path,
+            (width * scale as f64).round().max(1.0) as i32,
+            (height * scale as f64).round().max(1.0) as i32,
+            true,
+        )
+        .map_err(|err| format!("Failed to load image '{}': {}", path, err))?;
+
+        ctx.images
+            .show(id, Anchor { row, col }, clip, pixbuf, scale);
+        self.da.queue_draw();
+
+        Ok(())
+    }
+
+    pub fn hide_image(&self, id: u64) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.images.hide(id);
+        self.da.queue_draw();
+    }
+
+    pub fn clear_images(&self) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.images.clear();
+        self.da.queue_draw();
     }
 
     pub fn set_im_context(&mut self, im_context: &gtk::IMMulticontext) {
@@ -176,21 +324,56 @@ impl Grid {
             let ctx = ctx.borrow();
             let ctx = ctx.as_ref().unwrap();
 
-            let dir = match e.get_direction() {
-                gdk::ScrollDirection::Up => ScrollDirection::Up,
-                _ => ScrollDirection::Down,
+            let dir = match scroll_direction(e.get_direction()) {
+                Some(dir) => dir,
+                None => return Inhibit(false),
             };
 
             let pos = e.get_position();
-            let col = (pos.0 / ctx.cell_metrics.width).floor() as u64;
-            let row = (pos.1 / ctx.cell_metrics.height).floor() as u64;
+            let (row, col) = cell_at(&ctx.cell_metrics, pos.0, pos.1);
+
+            f(dir, row, col)
+        });
+    }
+
+    /// Connects `f` to the wheel events that `widget` (e.g. an overlay on
+    /// top of us) gets, with the cell under the pointer. `f` params are
+    /// the same as in `connect_scroll_events`.
+    pub fn connect_scroll_events_of<F: 'static>(
+        &self,
+        widget: &gtk::Widget,
+        f: F,
+    ) where
+        F: Fn(ScrollDirection, u64, u64) -> Inhibit,
+    {
+        let ctx = self.context.clone();
+        let eb = self.eb.clone();
+
+        widget.connect_scroll_event(move |_, e| {
+            let dir = match scroll_direction(e.get_direction()) {
+                Some(dir) => dir,
+                None => return Inhibit(false),
+            };
+            let (x, y) = match scroll_routing::widget_position(
+                eb.upcast_ref(),
+                e.get_root(),
+            ) {
+                Some(pos) => pos,
+                None => return Inhibit(false),
+            };
+
+            let ctx = ctx.borrow();
+            let ctx = ctx.as_ref().unwrap();
+            let (row, col) = cell_at(&ctx.cell_metrics, x, y);
 
             f(dir, row, col)
         });
     }
 
     /// Connects `f` to internal widget's motion events. `f` params are button,
-    /// row, col. `f` is only called when the cell under the pointer changes.
+    /// row, col. `f` is only called when the cell under the pointer changes,
+    /// and not for the left button when it's selecting text (see
+    /// `set_mouse_enabled`).
     pub fn connect_motion_events_for_drag<F: 'static>(&self, f: F)
     where
         F: Fn(MouseButton, u64, u64) -> Inhibit,
@@ -198,9 +381,9 @@ impl Grid {
         let ctx = self.context.clone();
         let drag_position = self.drag_position.clone();
 
-        self.eb.connect_motion_notify_event(move |_, e| {
-            let ctx = ctx.borrow();
-            let ctx = ctx.as_ref().unwrap();
+        self.eb.connect_motion_notify_event(move |eb, e| {
+            let mut ctx = ctx.borrow_mut();
+            let ctx = ctx.as_mut().unwrap();
             let mut drag_position = drag_position.borrow_mut();
 
             let button = match e.get_state() {
@@ -213,26 +396,35 @@ impl Grid {
             let col = (pos.0 / ctx.cell_metrics.width).floor() as u64;
             let row = (pos.1 / ctx.cell_metrics.height).floor() as u64;
 
-            if drag_position.0 != col || drag_position.1 != row {
-                *drag_position = (col, row);
-                f(button, row, col)
-            } else {
-                Inhibit(false)
+            if drag_position.0 == col && drag_position.1 == row {
+                return Inhibit(false);
             }
+            *drag_position = (col, row);
+
+            if let Some(ref mut selection) = ctx.text_selection {
+                if e.get_state().contains(ModifierType::BUTTON1_MASK) {
+                    selection.extend(row, col);
+                    eb.queue_draw();
+                    return Inhibit(false);
+                }
+            }
+
+            f(button, row, col)
         });
     }
 
     /// Connects `f` to internal widget's mouse button press event. `f` params
-    /// are button, row, col.
+    /// are button, row, col. When nvim doesn't take the mouse, the left
+    /// button starts a text selection instead of calling `f`.
     pub fn connect_mouse_button_press_events<F: 'static>(&self, f: F)
     where
         F: Fn(MouseButton, u64, u64) -> Inhibit,
     {
         let ctx = self.context.clone();
 
-        self.eb.connect_button_press_event(move |_, e| {
-            let ctx = ctx.borrow();
-            let ctx = ctx.as_ref().unwrap();
+        self.eb.connect_button_press_event(move |eb, e| {
+            let mut ctx = ctx.borrow_mut();
+            let ctx = ctx.as_mut().unwrap();
 
             let button = match e.get_button() {
                 3 => MouseButton::Right,
@@ -244,21 +436,28 @@ impl Grid {
             let col = (pos.0 / ctx.cell_metrics.width).floor() as u64;
             let row = (pos.1 / ctx.cell_metrics.height).floor() as u64;
 
+            if !ctx.mouse_enabled && e.get_button() == 1 {
+                ctx.text_selection = Some(TextSelection::new(row, col));
+                eb.queue_draw();
+                return Inhibit(false);
+            }
+
             f(button, row, col)
         });
     }
 
     /// Connects `f` to internal widget's mouse button release event. `f` params
-    /// are button, row, col.
+    /// are button, row, col. When the left button was selecting text, the
+    /// text is copied to the primary selection instead of calling `f`.
     pub fn connect_mouse_button_release_events<F: 'static>(&self, f: F)
     where
         F: Fn(MouseButton, u64, u64) -> Inhibit,
     {
         let ctx = self.context.clone();
 
-        self.eb.connect_button_release_event(move |_, e| {
-            let ctx = ctx.borrow();
-            let ctx = ctx.as_ref().unwrap();
+        self.eb.connect_button_release_event(move |eb, e| {
+            let mut ctx = ctx.borrow_mut();
+            let ctx = ctx.as_mut().unwrap();
 
             let button = match e.get_button() {
                 3 => MouseButton::Right,
@@ -270,11 +469,63 @@ impl Grid {
             let col = (pos.0 / ctx.cell_metrics.width).floor() as u64;
             let row = (pos.1 / ctx.cell_metrics.height).floor() as u64;
 
+            if e.get_button() == 1 {
+                if let Some(selection) = ctx.text_selection {
+                    if selection.is_empty() {
+                        // Just a click, which clears the selection.
+                        ctx.text_selection = None;
+                        eb.queue_draw();
+                    } else {
+                        let text = selection.text(&ctx.rows);
+                        gtk::Clipboard::get(&gdk::SELECTION_PRIMARY)
+                            .set_text(&text);
+                    }
+                    return Inhibit(false);
+                }
+            }
+
             f(button, row, col)
         });
     }
 
     /// Connects `f` to internal widget's resize events. `f` params are rows, cols.
+    /// Connects `f` to be called when our cell metrics change because the
+    /// scale factor or the screen's resolution (e.g. with fractional
+    /// scaling) changed. `f` gets our new size (rows, cols).
+    pub fn connect_scale_changed<F: 'static>(&self, f: F)
+    where
+        F: Fn(u64, u64),
+    {
+        let f = Rc::new(f);
+
+        // Connected after our own handler (see `new`), which updates the
+        // context.
+        let ctx = self.context.clone();
+        let f_ref = f.clone();
+        self.da.connect_property_scale_factor_notify(move |da| {
+            if let Some((rows, cols)) = calc_size(da, &ctx) {
+                f_ref(rows, cols);
+            }
+        });
+
+        let screen = match self.da.get_screen() {
+            Some(screen) => screen,
+            None => return,
+        };
+        let ctx = self.context.clone();
+        let da = self.da.clone();
+        screen.connect_property_resolution_notify(move |_| {
+            if let Some(ref mut ctx) = *ctx.borrow_mut() {
+                ctx.update(&da);
+            }
+            da.queue_draw();
+
+            if let Some((rows, cols)) = calc_size(&da, &ctx) {
+                f(rows, cols);
+            }
+        });
+    }
+
     pub fn connect_da_resize<F: 'static>(&self, f: F)
     where
         F: Fn(u64, u64) -> bool,
@@ -282,14 +533,7 @@ impl Grid {
         let ctx = self.context.clone();
 
         self.da.connect_configure_event(move |da, _| {
-            let ctx = ctx.borrow();
-            let ctx = ctx.as_ref().unwrap();
-
-            let w = da.get_allocated_width();
-            let h = da.get_allocated_height();
-            let cols = (w / ctx.cell_metrics.width as i32) as u64;
-            let rows = (h / ctx.cell_metrics.height as i32) as u64;
-
+            let (rows, cols) = calc_size(da, &ctx).unwrap();
             f(rows, cols)
         });
     }
@@ -322,12 +566,20 @@ impl Grid {
             );
             (x, y, cm.width, cm.height)
         };
-        ctx.queue_draw_area
-            .push((x as i32, y as i32, w as i32, h as i32));
+        ctx.damage.add(damage::enclosing(x, y, w, h));
 
+        let beacon = ctx.cursor_effects.cursor_moved(ctx.cursor.0, row);
         ctx.cursor.0 = row;
         ctx.cursor.1 = col;
 
+        // The crosshair spans the whole grid.
+        if ctx.cursor_effects.crosshair {
+            self.da.queue_draw();
+        }
+        if beacon {
+            self.animate_effects(ctx);
+        }
+
         // Mark the new cursor position to be drawn.
         let (x, y, w, h) = {
             let cm = &ctx.cell_metrics;
@@ -339,8 +591,7 @@ impl Grid {
             );
             (x, y, cm.width, cm.height)
         };
-        ctx.queue_draw_area
-            .push((x as i32, y as i32, w as i32, h as i32));
+        ctx.damage.add(damage::enclosing(x, y, w, h));
 
         if let Some(ref im_context) = self.im_context {
             let rect = gdk::Rectangle {
@@ -353,6 +604,16 @@ impl Grid {
         }
     }
 
+    /// Recreates our surface and clears the glyph cache, e.g. when the old
+    /// surface might have lost its content while the system was suspended.
+    /// Nvim needs to redraw the content after this.
+    pub fn reset_surface(&self) {
+        if let Some(ref mut ctx) = *self.context.borrow_mut() {
+            ctx.update(&self.da);
+        }
+        self.da.queue_draw();
+    }
+
     pub fn resize(&self, width: u64, height: u64) {
         let mut ctx = self.context.borrow_mut();
         let ctx = ctx.as_mut().unwrap();
@@ -374,6 +635,7 @@ impl Grid {
         for row in ctx.rows.iter_mut() {
             row.clear();
         }
+        ctx.images.clear();
 
         render::clear(&self.da, ctx, hl_defs)
     }
@@ -388,6 +650,11 @@ impl Grid {
         let mut ctx = self.context.borrow_mut();
         let mut ctx = ctx.as_mut().unwrap();
 
+        if !ctx.images.is_empty() {
+            ctx.images.scroll(reg, rows);
+            self.da.queue_draw();
+        }
+
         render::scroll(&mut ctx, hl_defs, reg, rows);
     }
 
@@ -418,11 +685,11 @@ impl Grid {
             (x, y, cm.width, cm.height)
         };
 
-        // Don't use the ctx.queue_draw_area, because those draws will only
+        // Don't use the ctx.damage, because those draws will only
         // happen once nvim sends 'flush' event. This draw needs to happen
         // on each tick so the cursor blinks.
-        self.da
-            .queue_draw_area(x as i32, y as i32, w as i32, h as i32);
+        let (x, y, w, h) = damage::enclosing(x, y, w, h);
+        self.da.queue_draw_area(x, y, w, h);
     }
 
     /// Sets line space. Actual change is postponed till the next call
@@ -448,6 +715,77 @@ impl Grid {
         ctx.cursor_cell_percentage = mode.cell_percentage;
     }
 
+    /// Flashes the grid, if the mode flash is enabled.
+    pub fn flash_mode(&self) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        if ctx.cursor_effects.mode_changed() {
+            self.animate_effects(ctx);
+        }
+    }
+
+    /// Sets if the cursor's row and column are highlighted.
+    pub fn set_crosshair(&self, enabled: bool) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.cursor_effects.crosshair = enabled;
+        self.da.queue_draw();
+    }
+
+    /// Sets the minimum jump (in rows) of the cursor that shows the
+    /// beacon. Zero disables the beacon.
+    pub fn set_beacon(&self, rows: u64) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.cursor_effects.beacon_rows = rows;
+    }
+
+    /// Sets if the grid flashes when the mode changes.
+    pub fn set_mode_flash(&self, enabled: bool) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.cursor_effects.mode_flash = enabled;
+    }
+
+    /// Animates the view of the window at `reg` (top, bot, left, right),
+    /// after its buffer was switched and the view moved by `rows`. `bg` is
+    /// drawn where the view slides away from.
+    pub fn scroll_restore(&self, reg: [u64; 4], rows: i64, bg: Color) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.scroll_restore.start(reg, rows, bg);
+    }
+
+    /// Redraws the grid on every frame until the cursor effects' and the
+    /// scroll restore's animations are done.
+    fn animate_effects(&self, ctx: &mut Context) {
+        if ctx.effects_ticking {
+            return;
+        }
+        ctx.effects_ticking = true;
+
+        let ctx_ref = self.context.clone();
+        self.da.add_tick_callback(move |da, _| {
+            da.queue_draw();
+
+            let mut ctx = ctx_ref.borrow_mut();
+            let ctx = ctx.as_mut().unwrap();
+            if ctx.cursor_effects.is_animating()
+                || ctx.scroll_restore.is_animating()
+            {
+                gtk::Continue(true)
+            } else {
+                ctx.effects_ticking = false;
+                gtk::Continue(false)
+            }
+        });
+    }
+
     pub fn set_busy(&self, busy: bool) {
         let mut ctx = self.context.borrow_mut();
         let ctx = ctx.as_mut().unwrap();
@@ -455,6 +793,95 @@ impl Grid {
         ctx.busy = busy;
     }
 
+    /// Sets how the selection is rendered. `bg` is the background color of
+    /// the selected cells. Call `redraw` to make the change visible.
+    pub fn set_selection_style(&self, bg: Option<Color>, rounded: bool) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        ctx.selection_bg = bg;
+        ctx.rounded_selection = rounded;
+    }
+
+    /// Sets if nvim takes the mouse. If it doesn't, dragging with the left
+    /// button selects text, which is copied to the primary selection.
+    pub fn set_mouse_enabled(&self, enabled: bool) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        if ctx.mouse_enabled == enabled {
+            return;
+        }
+        ctx.mouse_enabled = enabled;
+
+        if ctx.text_selection.take().is_some() {
+            self.da.queue_draw();
+        }
+    }
+
+    /// Sets the widths of the characters, as nvim has them. Call `redraw`
+    /// to make the change visible.
+    pub fn set_cell_widths(&self, cell_widths: CellWidths) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        if ctx.cell_widths != cell_widths {
+            ctx.cell_widths = cell_widths;
+            ctx.glyph_cache.clear();
+        }
+    }
+
+    /// Returns the contents of the cursor's row and the cursor's column.
+    pub fn cursor_line(&self) -> (String, u64) {
+        let ctx = self.context.borrow();
+        let ctx = ctx.as_ref().unwrap();
+
+        let text = ctx
+            .rows
+            .get(ctx.cursor.0 as usize)
+            .map(|row| row.text())
+            .unwrap_or_default();
+
+        (text, ctx.cursor.1)
+    }
+
+    /// Sets the ghost text that is drawn (dimmed) at the cursor.
+    pub fn set_ghost_text(&self, text: Option<String>, hl_defs: &HlDefs) {
+        let mut ctx = self.context.borrow_mut();
+        let ctx = ctx.as_mut().unwrap();
+
+        let row = ctx.cursor.0;
+        let old = ctx.ghost_text.take();
+        if old.is_none() && text.is_none() {
+            return;
+        }
+
+        // Redraw the rows where the ghost text was and where it will be.
+        let w = self.da.get_allocated_width();
+        let h = ctx.cell_metrics.height;
+        for r in old.iter().map(|old| old.1).chain(Some(row)) {
+            let y = r as f64 * h;
+            ctx.damage.add((0, y as i32, w, h.ceil() as i32));
+        }
+
+        ctx.ghost_text = text.map(|text| (text, row));
+        ctx.ghost_colors = (hl_defs.default_fg, hl_defs.default_bg);
+    }
+
+    /// Returns the text contents of the grid, one line per row.
+    pub fn text_snapshot(&self) -> String {
+        let ctx = self.context.borrow();
+        let ctx = ctx.as_ref().unwrap();
+
+        let mut text = String::new();
+        for row in ctx.rows.iter() {
+            text.push_str(&row.text());
+            text.push('\n');
+        }
+
+        text
+    }
+
     /// Calculates the current gird size. Returns (rows, cols).
     pub fn calc_size_for_new_metrics(&self) -> Option<(usize, usize)> {
         let ctx = self.context.borrow();
@@ -473,9 +900,28 @@ impl Grid {
     }
 }
 
+fn scroll_direction(dir: gdk::ScrollDirection) -> Option<ScrollDirection> {
+    match dir {
+        gdk::ScrollDirection::Up => Some(ScrollDirection::Up),
+        gdk::ScrollDirection::Down => Some(ScrollDirection::Down),
+        _ => None,
+    }
+}
+
+/// Returns the cell (row, col) at `x`, `y` (in our widget's coordinates).
+fn cell_at(cm: &CellMetrics, x: f64, y: f64) -> (u64, u64) {
+    let col = (x / cm.width).floor().max(0.0) as u64;
+    let row = (y / cm.height).floor().max(0.0) as u64;
+    (row, col)
+}
+
 /// Handler for grid's drawingarea's draw event. Draws the internal cairo
 /// context (`ctx`) surface to the `cr`.
 fn drawingarea_draw(cr: &cairo::Context, ctx: &mut Context) {
+    let _span = profiler::span("render", "draw");
+    ctx.frames += 1;
+    startup::mark(startup::Phase::FirstFrame);
+
     let surface = ctx.cairo_context.get_target();
     surface.flush();
 
@@ -484,6 +930,87 @@ fn drawingarea_draw(cr: &cairo::Context, ctx: &mut Context) {
     cr.paint();
     cr.restore();
 
+    ctx.scroll_restore.draw(cr, &surface, &ctx.cell_metrics);
+
+    if !ctx.images.is_empty() {
+        let cm = &ctx.cell_metrics;
+        let cols = ctx.rows.first().map(|row| row.len()).unwrap_or(0);
+        ctx.images.draw(
+            cr,
+            cm.width,
+            cm.height,
+            cols as f64 * cm.width,
+            ctx.rows.len() as f64 * cm.height,
+        );
+    }
+
+    if let Some(ref selection) = ctx.text_selection {
+        let cm = &ctx.cell_metrics;
+        let cols = ctx.rows.first().map(|row| row.len()).unwrap_or(0);
+        let color = ctx.selection_bg.unwrap_or(ctx.cursor_color);
+
+        cr.save();
+        cr.set_source_rgba(color.r, color.g, color.b, 0.5);
+        for (row, from, to) in selection.ranges(cols as u64) {
+            cr.rectangle(
+                from as f64 * cm.width,
+                row as f64 * cm.height,
+                (to - from) as f64 * cm.width,
+                cm.height,
+            );
+        }
+        cr.fill();
+        cr.restore();
+    }
+
+    if let Some((ref text, _)) = ctx.ghost_text {
+        let cm = &ctx.cell_metrics;
+        let (x, y) = render::get_coords(
+            cm.height,
+            cm.width,
+            ctx.cursor.0 as f64,
+            ctx.cursor.1 as f64,
+        );
+        let (fg, bg) = ctx.ghost_colors;
+        let w = ctx.cell_widths.str_width(text) as f64 * cm.width;
+
+        let layout = pangocairo::functions::create_layout(cr).unwrap();
+        layout.set_font_description(Some(&cm.font_desc));
+        layout.set_text(text);
+        let baseline = layout.get_baseline() as f64 / pango::SCALE as f64;
+
+        // The ghost text is drawn on top of whatever there is after the
+        // cursor.
+        cr.save();
+        cr.set_source_rgb(bg.r, bg.g, bg.b);
+        let (bx, by, bw, bh) = render::snap_rect(cr, x, y, w, cm.height);
+        cr.rectangle(bx, by, bw, bh);
+        cr.fill();
+
+        cr.set_source_rgba(fg.r, fg.g, fg.b, 0.5);
+        cr.move_to(x, y + cm.ascent - baseline);
+        pangocairo::functions::show_layout(cr, &layout);
+        cr.restore();
+
+        // Only the text is a blend, its background is opaque.
+        if let Some(ref dither) = ctx.dither {
+            dither.apply_layout(cr, x, y + cm.ascent - baseline, &layout);
+        }
+    }
+
+    {
+        let cm = &ctx.cell_metrics;
+        let cols = ctx.rows.first().map(|row| row.len()).unwrap_or(0);
+        ctx.cursor_effects.draw(
+            cr,
+            cm,
+            ctx.cursor,
+            &ctx.cursor_color,
+            cols as f64 * cm.width,
+            ctx.rows.len() as f64 * cm.height,
+        );
+    }
+
     // If we're not "busy", draw the cursor.
     if !ctx.busy {
         let (x, y, w, h) = {
@@ -502,8 +1029,13 @@ fn drawingarea_draw(cr: &cairo::Context, ctx: &mut Context) {
             alpha = 2.0 - alpha;
         }
 
+        // The cursor bar (e.g. in insert mode) is only a few pixels wide,
+        // so it needs to be on whole pixels to stay sharp.
+        let (x, y, w, h) =
+            render::snap_rect(cr, x, y, w * ctx.cursor_cell_percentage, h);
+
         cr.save();
-        cr.rectangle(x, y, w * ctx.cursor_cell_percentage, h);
+        cr.rectangle(x, y, w, h);
         cr.set_source_rgba(
             ctx.cursor_color.r,
             ctx.cursor_color.g,
@@ -512,5 +1044,45 @@ fn drawingarea_draw(cr: &cairo::Context, ctx: &mut Context) {
         );
         cr.fill();
         cr.restore();
+
+        // Only the partly transparent cursor is a blend.
+        if alpha > 0.0 && alpha < 1.0 {
+            if let Some(ref dither) = ctx.dither {
+                dither.apply(cr, x, y, w, h);
+            }
+        }
+    }
+
+    if ctx.show_damage {
+        cr.save();
+        cr.set_source_rgba(1.0, 0.0, 0.0, 0.8);
+        cr.set_line_width(1.0);
+        for &(x, y, w, h) in ctx.shown_damage.iter() {
+            cr.rectangle(
+                x as f64 + 0.5,
+                y as f64 + 0.5,
+                w as f64 - 1.0,
+                h as f64 - 1.0,
+            );
+        }
+        cr.stroke();
+        cr.restore();
     }
 }
+
+/// Returns how many rows and columns fit in `da` with the cell metrics of
+/// `ctx`.
+fn calc_size(
+    da: &DrawingArea,
+    ctx: &Arc<ThreadGuard<Option<Context>>>,
+) -> Option<(u64, u64)> {
+    let ctx = ctx.borrow();
+    let ctx = ctx.as_ref()?;
+
+    let w = da.get_allocated_width();
+    let h = da.get_allocated_height();
+    let cols = (w / ctx.cell_metrics.width as i32) as u64;
+    let rows = (h / ctx.cell_metrics.height as i32) as u64;
+
+    Some((rows, cols))
+}
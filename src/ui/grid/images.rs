@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use cairo;
+use gdk::prelude::*;
+use gdk_pixbuf;
+use gdk_pixbuf::prelude::*;
+
+/// Grid cell that an image's top left corner is anchored to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Anchor {
+    pub row: u64,
+    pub col: u64,
+}
+
+impl Anchor {
+    /// Moves the anchor along with the grid's contents when the region
+    /// `reg` (top, bot, left, right) is scrolled by `count` rows. Returns
+    /// false if the anchor is scrolled out of the region.
+    fn scroll(&mut self, reg: [u64; 4], count: i64) -> bool {
+        let [top, bot, left, right] = reg;
+        if self.row < top
+            || self.row >= bot
+            || self.col < left
+            || self.col >= right
+        {
+            return true;
+        }
+
+        let row = self.row as i64 - count;
+        if row < top as i64 || row >= bot as i64 {
+            return false;
+        }
+
+        self.row = row as u64;
+        true
+    }
+}
+
+/// Image shown on top of a grid. The pixbuf is in device pixels, `scale`
+/// times the size of the image on the grid, so that it's sharp on HiDPI
+/// monitors.
+struct Image {
+    anchor: Anchor,
+    /// Cells (top, bot, left, right) that the image is clipped to.
+    clip: Option<[u64; 4]>,
+    pixbuf: gdk_pixbuf::Pixbuf,
+    scale: i32,
+}
+
+/// Images shown on top of a grid (e.g. by plugins that preview images),
+/// keyed by an id that the plugin gives.
+#[derive(Default)]
+pub struct Images {
+    images: HashMap<u64, Image>,
+}
+
+impl Images {
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// Shows `pixbuf` at `anchor`, clipped to the cells `clip` (top, bot,
+    /// left, right) if it's given. `pixbuf` is rendered for the scale
+    /// factor `scale`.
+    pub fn show(
+        &mut self,
+        id: u64,
+        anchor: Anchor,
+        clip: Option<[u64; 4]>,
+        pixbuf: gdk_pixbuf::Pixbuf,
+        scale: i32,
+    ) {
+        self.images.insert(
+            id,
+            Image {
+                anchor,
+                clip,
+                pixbuf,
+                scale,
+            },
+        );
+    }
+
+    pub fn hide(&mut self, id: u64) {
+        self.images.remove(&id);
+    }
+
+    /// Removes all images, e.g. when the grid is cleared.
+    pub fn clear(&mut self) {
+        self.images.clear();
+    }
+
+    /// Scrolls the images along with the grid (see `Anchor::scroll`).
+    /// Images that are scrolled out of the region are removed, since nvim
+    /// doesn't tell us if their content ever comes back.
+    pub fn scroll(&mut self, reg: [u64; 4], count: i64) {
+        self.images
+            .retain(|_, image| image.anchor.scroll(reg, count));
+    }
+
+    /// Draws the images to `cr`, which is clipped to `width` x `height`.
+    pub fn draw(
+        &self,
+        cr: &cairo::Context,
+        cell_width: f64,
+        cell_height: f64,
+        width: f64,
+        height: f64,
+    ) {
+        cr.save();
+        cr.rectangle(0.0, 0.0, width, height);
+        cr.clip();
+
+        for image in self.images.values() {
+            let x = image.anchor.col as f64 * cell_width;
+            let y = image.anchor.row as f64 * cell_height;
+            let scale = image.scale as f64;
+
+            cr.save();
+            if let Some([top, bot, left, right]) = image.clip {
+                cr.rectangle(
+                    left as f64 * cell_width,
+                    top as f64 * cell_height,
+                    right.saturating_sub(left) as f64 * cell_width,
+                    bot.saturating_sub(top) as f64 * cell_height,
+                );
+                cr.clip();
+            }
+            cr.translate(x, y);
+            cr.scale(1.0 / scale, 1.0 / scale);
+            cr.set_source_pixbuf(&image.pixbuf, 0.0, 0.0);
+            cr.rectangle(
+                0.0,
+                0.0,
+                image.pixbuf.get_width() as f64,
+                image.pixbuf.get_height() as f64,
+            );
+            cr.fill();
+            cr.restore();
+        }
+
+        cr.restore();
+    }
+}
+
+/// Returns `size` (width, height) scaled down to fit in `max`, keeping the
+/// aspect ratio. `None` means no limit.
+pub fn fit(size: (i32, i32), max: (Option<f64>, Option<f64>)) -> (f64, f64) {
+    let (width, height) = (size.0 as f64, size.1 as f64);
+    let scale = |max: Option<f64>, size: f64| match max {
+        Some(max) if size > 0.0 => max / size,
+        _ => 1.0,
+    };
+    let scale = scale(max.0, width).min(scale(max.1, height)).min(1.0);
+
+    (width * scale, height * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor_scroll() {
+        let reg = [2, 10, 0, 80];
+
+        // Scrolling up moves the anchor up.
+        let mut anchor = Anchor { row: 5, col: 3 };
+        assert!(anchor.scroll(reg, 2));
+        assert_eq!(anchor, Anchor { row: 3, col: 3 });
+
+        // Out of the region.
+        assert!(!anchor.scroll(reg, 2));
+
+        // Scrolling down.
+        let mut anchor = Anchor { row: 5, col: 3 };
+        assert!(anchor.scroll(reg, -4));
+        assert_eq!(anchor.row, 9);
+        assert!(!anchor.scroll(reg, -1));
+
+        // Anchors outside of the region stay where they are.
+        let mut anchor = Anchor { row: 1, col: 3 };
+        assert!(anchor.scroll(reg, 5));
+        assert_eq!(anchor.row, 1);
+        let mut anchor = Anchor { row: 5, col: 90 };
+        assert!(anchor.scroll(reg, 1));
+        assert_eq!(anchor.row, 5);
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit((200, 100), (None, None)), (200.0, 100.0));
+        assert_eq!(fit((200, 100), (Some(100.0), None)), (100.0, 50.0));
+        assert_eq!(fit((200, 100), (Some(100.0), Some(20.0))), (40.0, 20.0));
+        // Small images are not scaled up.
+        assert_eq!(fit((20, 10), (Some(100.0), Some(100.0))), (20.0, 10.0));
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Simple least recently used cache.
+///
+/// When the cache is full, the least recently used quarter of the entries
+/// is evicted at once, so that we don't need to look for the oldest entry
+/// on each insert.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    /// Incremented on each access, used to tell which entries are the least
+    /// recently used ones.
+    tick: u64,
+    map: HashMap<K, (u64, V)>,
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity: capacity.max(1),
+            tick: 0,
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        self.map.get_mut(key).map(|entry| {
+            entry.0 = tick;
+            &mut entry.1
+        })
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.map.len() >= self.capacity && !self.map.contains_key(&key) {
+            self.evict();
+        }
+
+        self.tick += 1;
+        self.map.insert(key, (self.tick, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Removes the least recently used quarter of the entries.
+    fn evict(&mut self) {
+        let mut ticks: Vec<u64> = self.map.values().map(|e| e.0).collect();
+        ticks.sort();
+
+        let n = (self.capacity / 4).max(1);
+        if let Some(&threshold) = ticks.get(n - 1) {
+            self.map.retain(|_, entry| entry.0 > threshold);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_get_insert() {
+        let mut cache = LruCache::new(4);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("a", 3);
+
+        assert_eq!(cache.get_mut(&"a"), Some(&mut 3));
+        assert_eq!(cache.get_mut(&"b"), Some(&mut 2));
+        assert_eq!(cache.get_mut(&"c"), None);
+
+        cache.clear();
+        assert_eq!(cache.get_mut(&"a"), None);
+    }
+
+    #[test]
+    fn test_lru_evict() {
+        let mut cache = LruCache::new(4);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        cache.insert("d", 4);
+
+        // "a" is now more recently used than "b".
+        cache.get_mut(&"a");
+        cache.insert("e", 5);
+
+        assert_eq!(cache.get_mut(&"b"), None);
+        assert_eq!(cache.get_mut(&"c"), Some(&mut 3));
+        assert_eq!(cache.get_mut(&"a"), Some(&mut 1));
+        assert_eq!(cache.get_mut(&"e"), Some(&mut 5));
+    }
+}
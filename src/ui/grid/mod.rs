@@ -1,6 +1,15 @@
+#[cfg(test)]
+mod conformance;
 mod context;
+mod cursor_effects;
+mod damage;
+mod dither;
 mod grid;
+mod images;
+mod lru;
 mod render;
 mod row;
+mod scroll_restore;
+mod text_selection;
 
-pub use self::grid::Grid;
+pub use self::grid::{Grid, MouseButton};
@@ -1,3 +1,5 @@
+use std::f64::consts::PI;
+
 use cairo;
 use gtk::prelude::*;
 use gtk::DrawingArea;
@@ -6,36 +8,256 @@ use pango::Attribute;
 use pangocairo;
 
 use nvim_bridge::GridLineSegment;
-use ui::grid::context::{CellMetrics, Context};
-use ui::grid::row::Segment;
+use ui::cell_width::CellWidths;
+use ui::color::{Color, Highlight};
+use ui::grid::context::{CellMetrics, Context, GlyphCache, ShapedItem};
+use ui::grid::damage::{self, Damage};
+use ui::grid::row::{self, Leaf, Segment};
 use ui::ui::HlDefs;
 
-/// Renders `segments` to `cr`.
+/// Selection (e.g. visual highlight) on a single row. The selection is
+/// rendered as continuous regions instead of separate cells, optionally
+/// with rounded corners.
+struct RowSelection {
+    /// Background color of the selected cells.
+    bg: Color,
+    rounded: bool,
+    /// Selected column ranges (start, end) on the row.
+    runs: Vec<(usize, usize)>,
+    /// Selected column ranges on the rows above and below the row. Used to
+    /// decide which corners are rounded.
+    above: Vec<(usize, usize)>,
+    below: Vec<(usize, usize)>,
+}
+
+impl RowSelection {
+    fn is_selected(&self, bg: &Color) -> bool {
+        self.bg == *bg
+    }
+
+    fn draw(&self, cr: &cairo::Context, cm: &CellMetrics, row: usize) {
+        // Snap the edges to pixels, so that neighbouring rows don't leave
+        // antialiased seams between them.
+        let (sx, sy) = device_scale(cr);
+        let y1 = snap(row as f64 * cm.height, sy);
+        let y2 = snap((row + 1) as f64 * cm.height, sy);
+        let r = cm.width.min(cm.height) * 0.4;
+
+        cr.save();
+        cr.set_source_rgb(self.bg.r, self.bg.g, self.bg.b);
+
+        for &(start, end) in self.runs.iter() {
+            let x1 = snap(start as f64 * cm.width, sx);
+            let x2 = snap(end as f64 * cm.width, sx);
+
+            let rounded = |neighbour: &Vec<(usize, usize)>, col| {
+                self.rounded && !covers(neighbour, col)
+            };
+
+            cr.new_path();
+            if rounded(&self.above, start) {
+                cr.arc(x1 + r, y1 + r, r, PI, PI * 1.5);
+            } else {
+                cr.move_to(x1, y1);
+            }
+            if rounded(&self.above, end - 1) {
+                cr.arc(x2 - r, y1 + r, r, PI * 1.5, PI * 2.0);
+            } else {
+                cr.line_to(x2, y1);
+            }
+            if rounded(&self.below, end - 1) {
+                cr.arc(x2 - r, y2 - r, r, 0.0, PI * 0.5);
+            } else {
+                cr.line_to(x2, y2);
+            }
+            if rounded(&self.below, start) {
+                cr.arc(x1 + r, y2 - r, r, PI * 0.5, PI);
+            } else {
+                cr.line_to(x1, y2);
+            }
+            cr.close_path();
+            cr.fill();
+        }
+
+        cr.restore();
+    }
+}
+
+/// Returns the (fg, bg) colors of `hl`.
+fn get_colors(hl: &Highlight, hl_defs: &HlDefs) -> (Color, Color) {
+    if hl.reverse {
+        (
+            hl.background.unwrap_or(hl_defs.default_bg),
+            hl.foreground.unwrap_or(hl_defs.default_fg),
+        )
+    } else {
+        (
+            hl.foreground.unwrap_or(hl_defs.default_fg),
+            hl.background.unwrap_or(hl_defs.default_bg),
+        )
+    }
+}
+
+/// Returns the column ranges of `segments` that have `bg` as their
+/// background color.
+fn selection_runs(
+    segments: &[Segment],
+    hl_defs: &HlDefs,
+    bg: &Color,
+) -> Vec<(usize, usize)> {
+    let ranges = segments
+        .iter()
+        .filter(|seg| {
+            let hl = hl_defs.get(&seg.leaf.hl_id()).unwrap();
+            get_colors(hl, hl_defs).1 == *bg
+        })
+        .map(|seg| (seg.start, seg.start + seg.len))
+        .collect();
+
+    merge_runs(ranges)
+}
+
+/// Sorts `ranges` and merges the adjacent ones.
+fn merge_runs(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.sort();
+
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for (start, end) in ranges {
+        if let Some(last) = runs.last_mut() {
+            if last.1 >= start {
+                last.1 = last.1.max(end);
+                continue;
+            }
+        }
+
+        runs.push((start, end));
+    }
+
+    runs
+}
+
+/// Checks if `col` is in any of `runs`.
+fn covers(runs: &Vec<(usize, usize)>, col: usize) -> bool {
+    runs.iter().any(|&(start, end)| col >= start && col < end)
+}
+
+/// Returns the selection of `row`, if the selection rendering is enabled.
+fn row_selection(
+    context: &Context,
+    hl_defs: &HlDefs,
+    row: usize,
+) -> Option<RowSelection> {
+    let bg = context.selection_bg?;
+    let rounded = context.rounded_selection;
+
+    let runs_at = |row: Option<usize>| {
+        row.and_then(|row| context.rows.get(row))
+            .map(|row| selection_runs(&row.as_segments(), hl_defs, &bg))
+            .unwrap_or_default()
+    };
+
+    let (above, below) = if rounded {
+        (runs_at(row.checked_sub(1)), runs_at(Some(row + 1)))
+    } else {
+        (vec![], vec![])
+    };
+
+    Some(RowSelection {
+        bg,
+        rounded,
+        runs: runs_at(Some(row)),
+        above,
+        below,
+    })
+}
+
+/// Shapes the text of `leaf` with `pango_context`. Returns the font, glyphs
+/// and x offset of each pango item in the text. The cells that might not be
+/// drawn at the width nvim has for them (see `CellWidths::is_isolated`) are
+/// shaped on their own, so they don't move the rest of the text.
+fn shape(
+    pango_context: &pango::Context,
+    leaf: &Leaf,
+    hl: &Highlight,
+    cw: f64,
+    cell_widths: &CellWidths,
+) -> Vec<ShapedItem> {
+    let text = leaf.text();
+    let attrs = pango::AttrList::new();
+
+    if hl.bold {
+        let attr = Attribute::new_weight(pango::Weight::Bold).unwrap();
+        attrs.insert(attr);
+    }
+    if hl.italic {
+        let attr = Attribute::new_style(pango::Style::Italic).unwrap();
+        attrs.insert(attr);
+    }
+
+    let items = leaf
+        .runs(|cell| cell_widths.is_isolated(cell))
+        .into_iter()
+        .flat_map(|(start, end)| {
+            pango::itemize(
+                pango_context,
+                text,
+                start as i32,
+                (end - start) as i32,
+                &attrs,
+                None,
+            )
+        });
+
+    let mut shaped = vec![];
+    for item in items {
+        let a = item.analysis();
+        let item_offset = item.offset() as usize;
+        let mut glyphs = pango::GlyphString::new();
+
+        pango::shape(
+            &text[item_offset..item_offset + item.length() as usize],
+            &a,
+            &mut glyphs,
+        );
+
+        // Items start on the cell of their first character, which isn't
+        // the same as their character offset with double width characters
+        // and combining characters.
+        let x_offset = leaf.col_at(item_offset) as f64 * cw;
+        shaped.push((a.font(), glyphs, x_offset));
+    }
+
+    shaped
+}
+
+/// Renders `segments` to `cr`. If `selection` is given, `segments` needs
+/// to contain the whole row.
 fn put_segments(
     cr: &cairo::Context,
     pango_context: &pango::Context,
-    queue_draw_area: &mut Vec<(i32, i32, i32, i32)>,
+    damage: &mut Damage,
+    glyph_cache: &mut GlyphCache,
     cm: &CellMetrics,
+    cell_widths: &CellWidths,
     hl_defs: &HlDefs,
     segments: Vec<Segment>,
     row: usize,
+    selection: Option<&RowSelection>,
 ) {
     let cw = cm.width;
     let ch = cm.height;
 
-    for seg in segments {
+    // Draw the backgrounds first, so that the selection can be drawn on top
+    // of them before the text.
+    for seg in segments.iter() {
         let hl = hl_defs.get(&seg.leaf.hl_id()).unwrap();
+        let (_, bg) = get_colors(hl, hl_defs);
 
-        let (fg, bg) = if hl.reverse {
-            (
-                hl.background.unwrap_or(hl_defs.default_bg),
-                hl.foreground.unwrap_or(hl_defs.default_fg),
-            )
-        } else {
-            (
-                hl.foreground.unwrap_or(hl_defs.default_fg),
-                hl.background.unwrap_or(hl_defs.default_bg),
-            )
+        // Selected cells are drawn by the selection, "under" it is the
+        // default background.
+        let bg = match selection {
+            Some(selection) if selection.is_selected(&bg) => hl_defs.default_bg,
+            _ => bg,
         };
 
         let x = seg.start as f64 * cw;
@@ -43,57 +265,43 @@ fn put_segments(
         let w = seg.len as f64 * cw;
         let h = ch;
 
+        let (x, y, w, h) = snap_rect(cr, x, y, w, h);
+
         cr.save();
         cr.set_source_rgb(bg.r, bg.g, bg.b);
         cr.rectangle(x, y, w, h);
         cr.fill();
         cr.restore();
+    }
 
-        let attrs = pango::AttrList::new();
+    if let Some(selection) = selection {
+        selection.draw(cr, cm, row);
+    }
 
-        if hl.bold {
-            let attr = Attribute::new_weight(pango::Weight::Bold).unwrap();
-            attrs.insert(attr);
-        }
-        if hl.italic {
-            let attr = Attribute::new_style(pango::Style::Italic).unwrap();
-            attrs.insert(attr);
-        }
+    for seg in segments {
+        let hl = hl_defs.get(&seg.leaf.hl_id()).unwrap();
+        let (fg, _) = get_colors(hl, hl_defs);
+
+        let x = seg.start as f64 * cw;
+        let y = row as f64 * ch;
+        let w = seg.len as f64 * cw;
+        let h = ch;
 
         cr.save();
         cr.set_source_rgb(fg.r, fg.g, fg.b);
 
         let text = seg.leaf.text();
-        let items = pango::itemize(
-            pango_context,
-            text,
-            0,
-            text.len() as i32,
-            &attrs,
-            None,
-        );
-
-        let mut x_offset = 0.0;
-        for item in items {
-            let a = item.analysis();
-            let item_offset = item.offset() as usize;
-            let mut glyphs = pango::GlyphString::new();
-
-            pango::shape(
-                &text[item_offset..item_offset + item.length() as usize],
-                &a,
-                &mut glyphs,
-            );
-
-            cr.move_to(x + x_offset, y + cm.ascent);
-            pangocairo::functions::show_glyph_string(
-                &cr,
-                &a.font(),
-                &mut glyphs,
-            );
+        let key = (text.to_string(), hl.bold, hl.italic);
+        if glyph_cache.get_mut(&key).is_none() {
+            let shaped = shape(pango_context, seg.leaf, hl, cw, cell_widths);
+            glyph_cache.insert(key.clone(), shaped);
+        }
 
-            x_offset += item.num_chars() as f64 * cw;
-            //x_offset += glyphs.glyphs.get_width() as f64;
+        if let Some(shaped) = glyph_cache.get_mut(&key) {
+            for &mut (ref font, ref mut glyphs, x_offset) in shaped.iter_mut() {
+                cr.move_to(x + x_offset, y + cm.ascent);
+                pangocairo::functions::show_glyph_string(&cr, font, glyphs);
+            }
         }
 
         // Since we can't (for some reason) use pango attributes to draw
@@ -110,63 +318,127 @@ fn put_segments(
             );
         }
         if hl.underline {
-            let y = y + h + cm.underline_position;
-            cr.rectangle(x, y, w, cm.underline_thickness);
+            let (x, y, w, h) = snap_rect(
+                cr,
+                x,
+                y + h + cm.underline_position,
+                w,
+                cm.underline_thickness,
+            );
+            cr.rectangle(x, y, w, h);
             cr.fill();
         }
 
         cr.restore();
 
-        queue_draw_area.push((x as i32, y as i32, w as i32, h as i32));
+        damage.add(damage::enclosing(x, y, w, h));
     }
 }
 
 pub fn redraw(context: &mut Context, hl_defs: &HlDefs) {
-    for (i, row) in context.rows.iter_mut().enumerate() {
-        let segments = row.as_segments();
-
-        put_segments(
-            &context.cairo_context,
-            &context.pango_context,
-            &mut context.queue_draw_area,
-            &context.cell_metrics,
-            hl_defs,
-            segments,
-            i,
-        );
+    for i in 0..context.rows.len() {
+        redraw_row(context, hl_defs, i);
     }
 }
 
-/// Renders `line` to `context.cairo_context`.
-pub fn put_line(
-    context: &mut Context,
-    line: &GridLineSegment,
-    hl_defs: &HlDefs,
-) {
-    let row = line.row as usize;
-    let mut affected_segments = context
-        .rows
-        .get_mut(row)
-        .expect(&format!("Failed to get row {}", line.row))
-        .update(line);
-
-    // NOTE(ville): I haven't noticed any cases where a character is overflowing
-    //              to the left. Probably doesn't apply to languages that goes
-    //              from right to left, instead of left to right.
-    // Rendering the segments in reversed order fixes issues when some character
-    // is overflowing to the right.
-    affected_segments.reverse();
+/// Renders the whole `row`.
+fn redraw_row(context: &mut Context, hl_defs: &HlDefs, row: usize) {
+    let selection = row_selection(context, hl_defs, row);
+    let segments = match context.rows.get(row) {
+        Some(row) => row.as_segments(),
+        None => return,
+    };
+
     put_segments(
         &context.cairo_context,
         &context.pango_context,
-        &mut context.queue_draw_area,
+        &mut context.damage,
+        &mut context.glyph_cache,
         &context.cell_metrics,
+        &context.cell_widths,
         hl_defs,
-        affected_segments,
+        segments,
         row,
+        selection.as_ref(),
     );
 }
 
+/// Renders `line` to `context.cairo_context`.
+pub fn put_line(
+    context: &mut Context,
+    line: &GridLineSegment,
+    hl_defs: &HlDefs,
+) {
+    let row = line.row as usize;
+
+    // With rounded selection, the corners of the neighbouring rows depend
+    // on the selection on this row, so we need to know if it changes.
+    let had_selection = match context.selection_bg {
+        Some(ref bg) if context.rounded_selection => context
+            .rows
+            .get(row)
+            .map(|row| {
+                !selection_runs(&row.as_segments(), hl_defs, bg).is_empty()
+            })
+            .unwrap_or(false),
+        _ => false,
+    };
+
+    let redraw_selection = {
+        let mut affected_segments = context
+            .rows
+            .get_mut(row)
+            .expect(&format!("Failed to get row {}", line.row))
+            .update(line);
+
+        let has_selection = match context.selection_bg {
+            Some(ref bg) => {
+                !selection_runs(&affected_segments, hl_defs, bg).is_empty()
+            }
+            None => false,
+        };
+
+        if has_selection || had_selection {
+            true
+        } else {
+            // NOTE(ville): I haven't noticed any cases where a character is
+            //              overflowing to the left. Probably doesn't apply to
+            //              languages that goes from right to left, instead of
+            //              left to right.
+            // Rendering the segments in reversed order fixes issues when some
+            // character is overflowing to the right.
+            affected_segments.reverse();
+            put_segments(
+                &context.cairo_context,
+                &context.pango_context,
+                &mut context.damage,
+                &mut context.glyph_cache,
+                &context.cell_metrics,
+                &context.cell_widths,
+                hl_defs,
+                affected_segments,
+                row,
+                None,
+            );
+
+            false
+        }
+    };
+
+    // The selection is rendered as a single region, so the whole row needs
+    // to be redrawn (and its neighbours, if the corners are rounded).
+    if redraw_selection {
+        redraw_row(context, hl_defs, row);
+
+        if context.rounded_selection {
+            if row > 0 {
+                redraw_row(context, hl_defs, row - 1);
+            }
+            redraw_row(context, hl_defs, row + 1);
+        }
+    }
+}
+
 /// Clears whole `da` with `hl_defs.default_bg`.
 pub fn clear(da: &DrawingArea, ctx: &mut Context, hl_defs: &HlDefs) {
     let cr = &ctx.cairo_context;
@@ -180,7 +452,7 @@ pub fn clear(da: &DrawingArea, ctx: &mut Context, hl_defs: &HlDefs) {
     cr.fill();
     cr.restore();
 
-    ctx.queue_draw_area.push((0, 0, w, h));
+    ctx.damage.add((0, 0, w, h));
 }
 
 /// Scrolls contents in `ctx.cairo_context` and `ctx.rows`, based on `reg`.
@@ -196,38 +468,18 @@ pub fn scroll(ctx: &mut Context, hl_defs: &HlDefs, reg: [u64; 4], count: i64) {
     let left = reg[2];
     let right = reg[3];
 
-    let (src_top, src_bot, dst_top, dst_bot, clr_top, clr_bot) = if count > 0 {
+    let (src_top, dst_top, dst_bot, clr_top, clr_bot) = if count > 0 {
         let (src_top, src_bot) = ((top as i64 + count) as f64, bot as f64);
         let (dst_top, dst_bot) = (top as f64, (bot as i64 - count) as f64);
-        (src_top, src_bot, dst_top, dst_bot, dst_bot, src_bot)
+        (src_top, dst_top, dst_bot, dst_bot, src_bot)
     } else {
-        let (src_top, src_bot) = (top as f64, (bot as i64 + count) as f64);
+        let src_top = top as f64;
         let (dst_top, dst_bot) = ((top as i64 - count) as f64, bot as f64);
-        (src_top, src_bot, dst_top, dst_bot, src_top, dst_top)
+        (src_top, dst_top, dst_bot, src_top, dst_top)
     };
 
     // Modify the rows stored data of the rows.
-    let mut src = vec![];
-    for i in src_top as usize..src_bot as usize {
-        let row = ctx.rows.get(i).unwrap().clone();
-        let part = row.copy_range(left as usize, right as usize).clone();
-        src.push(part);
-    }
-    src.reverse();
-
-    for i in dst_top as usize..dst_bot as usize {
-        ctx.rows
-            .get_mut(i)
-            .unwrap()
-            .insert_rope_at(left as usize, src.pop().unwrap());
-    }
-
-    for i in clr_top as usize..clr_bot as usize {
-        ctx.rows
-            .get_mut(i)
-            .unwrap()
-            .clear_range(left as usize, right as usize);
-    }
+    row::scroll(&mut ctx.rows, reg, count);
 
     // Draw move the scrolled part on the cairo surface.
     cr.save();
@@ -257,8 +509,7 @@ pub fn scroll(ctx: &mut Context, hl_defs: &HlDefs, reg: [u64; 4], count: i64) {
     cr.set_operator(cairo::Operator::Source);
     cr.rectangle(x1, y1, w, h);
     cr.fill();
-    ctx.queue_draw_area
-        .push((x1 as i32, y1 as i32, w as i32, h as i32));
+    ctx.damage.add(damage::enclosing(x1, y1, w, h));
 
     // Clear the area that is left "dirty".
     let (x1, y1, x2, y2) = get_rect(
@@ -274,8 +525,7 @@ pub fn scroll(ctx: &mut Context, hl_defs: &HlDefs, reg: [u64; 4], count: i64) {
     cr.rectangle(x1, y1, x2 - x1, y2 - y1);
     cr.set_source_rgb(bg.r, bg.g, bg.b);
     cr.fill();
-    ctx.queue_draw_area
-        .push((x1 as i32, y1 as i32, w as i32, h as i32));
+    ctx.damage.add(damage::enclosing(x1, y1, w, h));
 
     cr.restore();
 }
@@ -298,3 +548,94 @@ pub fn get_coords(h: f64, w: f64, row: f64, col: f64) -> (f64, f64) {
     let y = row * h;
     (x, y)
 }
+
+/// Returns the size of a user space unit of `cr` in device pixels. Cell
+/// sizes come from the font metrics and are rarely whole pixels, and with
+/// (what ends up as) fractional scaling not even the scaled ones are.
+fn device_scale(cr: &cairo::Context) -> (f64, f64) {
+    let (sx, sy) = cr.user_to_device_distance(1.0, 1.0);
+    let sx = if sx.abs() > 0.0 { sx.abs() } else { 1.0 };
+    let sy = if sy.abs() > 0.0 { sy.abs() } else { 1.0 };
+    (sx, sy)
+}
+
+/// Rounds the user space coordinate `v` to the nearest device pixel, when
+/// one unit is `scale` device pixels.
+fn snap(v: f64, scale: f64) -> f64 {
+    (v * scale).round() / scale
+}
+
+/// Snaps the rectangle `(x, y, w, h)` to device pixels of `cr`, so that
+/// its edges (and thin elements like underlines and the cursor bar) aren't
+/// blurred across two pixels. The size is kept at least one device pixel,
+/// so that thin elements don't disappear.
+pub fn snap_rect(
+    cr: &cairo::Context,
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+) -> (f64, f64, f64, f64) {
+    let (sx, sy) = device_scale(cr);
+    snap_rect_scaled(x, y, w, h, sx, sy)
+}
+
+fn snap_rect_scaled(
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    sx: f64,
+    sy: f64,
+) -> (f64, f64, f64, f64) {
+    let x1 = snap(x, sx);
+    let y1 = snap(y, sy);
+    let w = (snap(x + w, sx) - x1).max(1.0 / sx);
+    let h = (snap(y + h, sy) - y1).max(1.0 / sy);
+
+    (x1, y1, w, h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_runs() {
+        assert_eq!(merge_runs(vec![]), vec![]);
+        assert_eq!(
+            merge_runs(vec![(5, 7), (0, 2), (2, 4), (7, 10)]),
+            vec![(0, 4), (5, 10)]
+        );
+        assert_eq!(merge_runs(vec![(0, 5), (3, 4)]), vec![(0, 5)]);
+    }
+
+    #[test]
+    fn test_snap_rect() {
+        // Edges are rounded to whole device pixels.
+        assert_eq!(
+            snap_rect_scaled(10.4, 17.3, 8.4, 17.3, 1.0, 1.0),
+            (10.0, 17.0, 9.0, 18.0)
+        );
+        assert_eq!(
+            snap_rect_scaled(10.4, 17.2, 8.4, 17.2, 2.0, 2.0),
+            (10.5, 17.0, 8.5, 17.5)
+        );
+
+        // Thin elements are at least one device pixel.
+        assert_eq!(
+            snap_rect_scaled(0.0, 3.2, 0.2, 0.2, 1.0, 2.0),
+            (0.0, 3.0, 1.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_covers() {
+        let runs = vec![(0, 4), (5, 10)];
+        assert!(covers(&runs, 0));
+        assert!(covers(&runs, 3));
+        assert!(!covers(&runs, 4));
+        assert!(covers(&runs, 9));
+        assert!(!covers(&runs, 10));
+    }
+}
@@ -16,21 +16,45 @@ pub struct Leaf {
     text: String,
     /// Hl id of this leaf.
     hl_id: u64,
-    /// Length of this leaf in utf8 characters.
+    /// Length of this leaf in cells.
     len: usize,
+    /// Byte offsets (in `text`) of where each cell starts. A cell can have
+    /// more than one character (e.g. combining characters), and the empty
+    /// cells after double width characters start where the next cell does.
+    cells: Vec<usize>,
 }
 
 /// Leaf of `Rope` (tree) structure.
 impl Leaf {
+    /// Creates a leaf where each character of `text` is one cell.
     fn new(text: String, hl_id: u64) -> Self {
+        let cells: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
         Leaf {
-            len: text.chars().count(),
+            len: cells.len(),
+            cells,
             text,
             hl_id,
         }
     }
 
-    /// Length of this leaf (in utf8 characters).
+    /// Creates a leaf of a cell coming from nvim, repeated `repeat` times.
+    fn from_cell(text: &str, hl_id: u64, repeat: usize) -> Self {
+        let mut leaf = Leaf {
+            text: String::with_capacity(text.len() * repeat),
+            hl_id,
+            len: repeat,
+            cells: Vec::with_capacity(repeat),
+        };
+
+        for _ in 0..repeat {
+            leaf.cells.push(leaf.text.len());
+            leaf.text.push_str(text);
+        }
+
+        leaf
+    }
+
+    /// Length of this leaf (in cells).
     #[allow(unused)] // Not used currently, but tested.
     pub fn len(&self) -> usize {
         self.len
@@ -46,29 +70,75 @@ impl Leaf {
         &self.text
     }
 
-    /// Appends `text` to this leaf.
-    pub fn append(&mut self, text: &str) {
-        self.len += text.chars().count();
-        self.text.push_str(text);
+    /// Returns the column (relative to this leaf) of the cell that has the
+    /// byte `offset` of our text.
+    pub fn col_at(&self, offset: usize) -> usize {
+        if offset >= self.text.len() {
+            return self.len;
+        }
+
+        // Empty cells share the offset with the next cell, which is the one
+        // that has the text.
+        self.cells.iter().rposition(|&c| c <= offset).unwrap_or(0)
     }
 
-    /// Splits this leaf into two (ropes).
-    #[inline]
-    fn split(self, at: usize) -> (Rope, Rope) {
-        let mut left = String::with_capacity(at);
-        let mut right = String::with_capacity(self.len - at);
-        for (i, c) in self.text.chars().enumerate() {
-            if i < at {
-                left.push(c);
+    /// Splits our text into runs (byte ranges) that are shaped together.
+    /// Cells for which `isolated` returns true are runs of their own.
+    pub fn runs<F: Fn(&str) -> bool>(
+        &self,
+        isolated: F,
+    ) -> Vec<(usize, usize)> {
+        let mut runs = vec![];
+        let mut run: Option<(usize, usize)> = None;
+
+        for (i, &start) in self.cells.iter().enumerate() {
+            let end = self.cells.get(i + 1).cloned().unwrap_or(self.text.len());
+            // Empty cell after a double width character.
+            if start == end {
+                continue;
+            }
+
+            if isolated(&self.text[start..end]) {
+                runs.extend(run.take());
+                runs.push((start, end));
             } else {
-                right.push(c);
+                run = Some((run.map(|r| r.0).unwrap_or(start), end));
             }
         }
+        runs.extend(run);
+
+        runs
+    }
+
+    /// Appends `other` to this leaf.
+    pub fn append(&mut self, other: &Leaf) {
+        let offset = self.text.len();
+        self.cells.extend(other.cells.iter().map(|c| c + offset));
+        self.len += other.len;
+        self.text.push_str(&other.text);
+    }
 
-        (
-            Rope::Leaf(Leaf::new(left, self.hl_id)),
-            Rope::Leaf(Leaf::new(right, self.hl_id)),
-        )
+    /// Splits this leaf into two (ropes) at cell `at`.
+    #[inline]
+    fn split(mut self, at: usize) -> (Rope, Rope) {
+        let offset = self.cells.get(at).cloned().unwrap_or(self.text.len());
+        let right_cells = self
+            .cells
+            .split_off(at)
+            .into_iter()
+            .map(|c| c - offset)
+            .collect::<Vec<_>>();
+        let right_text = self.text.split_off(offset);
+
+        let right = Leaf {
+            len: right_cells.len(),
+            cells: right_cells,
+            text: right_text,
+            hl_id: self.hl_id,
+        };
+        self.len = self.cells.len();
+
+        (Rope::Leaf(self), Rope::Leaf(right))
     }
 }
 
@@ -92,7 +162,7 @@ impl Rope {
         let mut rope = Rope::new(String::new(), 0);
         for cell in cells {
             let leaf =
-                Leaf::new(cell.text.repeat(cell.repeat as usize), cell.hl_id);
+                Leaf::from_cell(&cell.text, cell.hl_id, cell.repeat as usize);
             rope = rope.concat(Rope::Leaf(leaf));
         }
 
@@ -137,10 +207,7 @@ impl Rope {
                 match other {
                     Rope::Leaf(other) => {
                         if other.hl_id() == leaf.hl_id() {
-                            leaf.append(&other.text);
-                            //leaf.text.push_str(&other.text);
-                            //leaf.text += &other.text;
-                            //leaf.len += other.len;
+                            leaf.append(&other);
                             Rope::Leaf(leaf)
                         } else {
                             Rope::Node(
@@ -262,7 +329,6 @@ impl Row {
     }
 
     /// Returns the whole text of the row.
-    #[allow(unused)] // Not used currently, but tested.
     pub fn text(&self) -> String {
         self.rope.as_ref().unwrap().text()
     }
@@ -315,10 +381,18 @@ impl Row {
     /// Updates row. `line` should be coming straight from nvim's 'grid_line'.
     /// event.
     pub fn update(&mut self, line: &GridLineSegment) -> Vec<Segment> {
+        let col_start = line.col_start as usize;
+        if col_start >= self.len {
+            return vec![];
+        }
+
         // Construct a rope from give cells in `line` and insert it into us.
-        let other = Rope::from_nvim_cells(&line.cells);
+        // Whatever doesn't fit in the row is dropped.
+        let mut other = Rope::from_nvim_cells(&line.cells);
+        if other.len() > self.len - col_start {
+            other = other.split(self.len - col_start).0;
+        }
         let other_len = other.len();
-        let col_start = line.col_start as usize;
         let other_end = col_start + other_len;
         self.insert_rope_at(col_start, other);
 
@@ -381,6 +455,33 @@ impl Row {
     }
 }
 
+/// Scrolls the region `reg` (top, bot, left, right, like in `grid_scroll`)
+/// of `rows` by `count` rows, up when `count` is positive. The rows that
+/// are scrolled into the region are cleared.
+pub fn scroll(rows: &mut [Row], reg: [u64; 4], count: i64) {
+    let top = reg[0] as i64;
+    let bot = reg[1] as i64;
+    let left = reg[2] as usize;
+    let right = reg[3] as usize;
+
+    let (src_top, src_bot, dst_top, clr_top, clr_bot) = if count > 0 {
+        (top + count, bot, top, bot - count, bot)
+    } else {
+        (top, bot + count, top - count, top, top - count)
+    };
+
+    let src: Vec<Rope> = (src_top..src_bot)
+        .map(|i| rows[i as usize].copy_range(left, right))
+        .collect();
+    for (i, rope) in src.into_iter().enumerate() {
+        rows[dst_top as usize + i].insert_rope_at(left, rope);
+    }
+
+    for i in clr_top..clr_bot {
+        rows[i as usize].clear_range(left, right);
+    }
+}
+
 #[cfg(all(feature = "unstable", test))]
 mod benches {
     extern crate test;
@@ -652,6 +753,27 @@ mod tests {
         assert_eq!(row.rope.unwrap().text(), "01   56789");
     }
 
+    #[test]
+    fn test_scroll() {
+        let texts = |rows: &[Row]| -> Vec<String> {
+            rows.iter().map(|row| row.text()).collect()
+        };
+        let mut rows: Vec<Row> = ["0000", "1111", "2222", "3333"]
+            .iter()
+            .map(|text| {
+                let mut row = Row::new(4);
+                row.rope = Some(Rope::new(String::from(*text), 0));
+                row
+            })
+            .collect();
+
+        scroll(&mut rows, [0, 3, 1, 3], 1);
+        assert_eq!(texts(&rows), vec!["0110", "1221", "2  2", "3333"]);
+
+        scroll(&mut rows, [1, 4, 0, 4], -2);
+        assert_eq!(texts(&rows), vec!["0110", "    ", "    ", "1221"]);
+    }
+
     #[test]
     fn test_rope_leaf_at() {
         let rope = Rope::new(String::from("first"), 1);
@@ -743,4 +865,184 @@ mod tests {
         assert_eq!(third.start, 2);
         assert_eq!(third.len, 1);
     }
+
+    fn cell(text: &str, hl_id: u64, repeat: u64) -> nvim_bridge::Cell {
+        nvim_bridge::Cell {
+            text: String::from(text),
+            hl_id,
+            repeat,
+        }
+    }
+
+    /// Returns the hl id of each cell of `row`.
+    fn cell_hl_ids(row: &Row) -> Vec<u64> {
+        let mut ids = vec![];
+        for seg in row.as_segments() {
+            ids.extend(::std::iter::repeat(seg.leaf.hl_id).take(seg.len));
+        }
+        ids
+    }
+
+    #[test]
+    fn test_leaf_from_cell() {
+        // Combining characters are in one cell.
+        let leaf = Leaf::from_cell("e\u{301}", 1, 3);
+        assert_eq!(leaf.len, 3);
+        assert_eq!(leaf.text, "e\u{301}".repeat(3));
+
+        // Empty cells have no text, but still take space.
+        let leaf = Leaf::from_cell("", 1, 2);
+        assert_eq!(leaf.len, 2);
+        assert_eq!(leaf.text, "");
+    }
+
+    #[test]
+    fn test_leaf_split_cells() {
+        let mut leaf = Leaf::from_cell("字", 0, 1);
+        leaf.append(&Leaf::from_cell("", 0, 1));
+        leaf.append(&Leaf::from_cell("e\u{301}", 0, 1));
+        assert_eq!(leaf.len, 3);
+
+        let (left, right) = leaf.clone().split(1);
+        assert_eq!(left.text(), "字");
+        assert_eq!(left.len(), 1);
+        assert_eq!(right.text(), "e\u{301}");
+        assert_eq!(right.len(), 2);
+
+        let (left, right) = leaf.split(2);
+        assert_eq!(left.text(), "字");
+        assert_eq!(left.len(), 2);
+        assert_eq!(right.text(), "e\u{301}");
+        assert_eq!(right.len(), 1);
+    }
+
+    #[test]
+    fn test_leaf_col_at() {
+        let mut leaf = Leaf::from_cell("字", 0, 2);
+        leaf.append(&Leaf::from_cell("", 0, 1));
+        leaf.append(&Leaf::from_cell("e\u{301}", 0, 1));
+        leaf.append(&Leaf::from_cell("a", 0, 1));
+
+        assert_eq!(leaf.col_at(0), 0);
+        assert_eq!(leaf.col_at(3), 1);
+        assert_eq!(leaf.col_at(6), 3);
+        assert_eq!(leaf.col_at(9), 4);
+        assert_eq!(leaf.col_at(100), 5);
+    }
+
+    #[test]
+    fn test_leaf_runs() {
+        let mut leaf = Leaf::new(String::from("ab"), 0);
+        leaf.append(&Leaf::from_cell("字", 0, 1));
+        leaf.append(&Leaf::from_cell("", 0, 1));
+        leaf.append(&Leaf::from_cell("e\u{301}", 0, 1));
+        leaf.append(&Leaf::from_cell("字", 0, 1));
+
+        assert_eq!(leaf.runs(|_| false), vec![(0, 11)]);
+        assert_eq!(
+            leaf.runs(|cell| cell == "字"),
+            vec![(0, 2), (2, 5), (5, 8), (8, 11)]
+        );
+        assert_eq!(leaf.runs(|_| true).len(), 5);
+        assert!(Leaf::new(String::new(), 0).runs(|_| false).is_empty());
+    }
+
+    /// Hand-written `grid_line` events, in the shapes that nvim sends them
+    /// (e.g. wide and combining chars, and repeats), and the resulting rows.
+    /// See `conformance` for the tests against a real nvim.
+    #[test]
+    fn test_row_update_cases() {
+        struct Case {
+            name: &'static str,
+            len: usize,
+            lines: Vec<(u64, Vec<nvim_bridge::Cell>)>,
+            text: &'static str,
+            hl_ids: Vec<u64>,
+        }
+
+        let cases = vec![
+            Case {
+                name: "double width character",
+                len: 5,
+                lines: vec![(0, vec![cell("字", 1, 1), cell("", 1, 1)])],
+                text: "字   ",
+                hl_ids: vec![1, 1, 0, 0, 0],
+            },
+            Case {
+                name: "repeated double width characters",
+                len: 6,
+                lines: vec![(
+                    1,
+                    vec![
+                        cell("字", 2, 1),
+                        cell("", 2, 1),
+                        cell("字", 2, 1),
+                        cell("", 2, 1),
+                        cell("a", 3, 1),
+                    ],
+                )],
+                text: " 字字a",
+                hl_ids: vec![0, 2, 2, 2, 2, 3],
+            },
+            Case {
+                name: "overwriting half of double width characters",
+                len: 6,
+                lines: vec![
+                    (
+                        0,
+                        vec![
+                            cell("字", 1, 1),
+                            cell("", 1, 1),
+                            cell("字", 1, 1),
+                            cell("", 1, 1),
+                            cell("字", 1, 1),
+                            cell("", 1, 1),
+                        ],
+                    ),
+                    (1, vec![cell(" ", 2, 3)]),
+                ],
+                text: "字   字",
+                hl_ids: vec![1, 2, 2, 2, 1, 1],
+            },
+            Case {
+                name: "combining characters",
+                len: 4,
+                lines: vec![(0, vec![cell("e\u{301}", 1, 3), cell("x", 1, 1)])],
+                text: "e\u{301}e\u{301}e\u{301}x",
+                hl_ids: vec![1, 1, 1, 1],
+            },
+            Case {
+                name: "repeat with different highlights",
+                len: 6,
+                lines: vec![(0, vec![cell("-", 1, 2), cell("-", 2, 2)])],
+                text: "----  ",
+                hl_ids: vec![1, 1, 2, 2, 0, 0],
+            },
+            Case {
+                name: "cells past the end of the row",
+                len: 4,
+                lines: vec![
+                    (2, vec![cell("x", 1, 4)]),
+                    (4, vec![cell("y", 1, 1)]),
+                ],
+                text: "  xx",
+                hl_ids: vec![0, 0, 1, 1],
+            },
+        ];
+
+        for case in cases {
+            let mut row = Row::new(case.len);
+            for (col_start, cells) in case.lines {
+                row.update(&nvim_bridge::GridLineSegment {
+                    grid: 1,
+                    row: 0,
+                    col_start,
+                    cells,
+                });
+            }
+
+            assert_eq!(row.text(), case.text, "{}", case.name);
+            assert_eq!(cell_hl_ids(&row), case.hl_ids, "{}", case.name);
+        }
+    }
 }
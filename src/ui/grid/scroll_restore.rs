@@ -0,0 +1,150 @@
+use std::time::Instant;
+
+use cairo;
+
+use ui::color::Color;
+use ui::grid::context::CellMetrics;
+use ui::grid::cursor_effects::progress;
+
+/// How long the animation runs, in milliseconds.
+const DURATION: u64 = 180;
+/// Jumps of more than this many window heights are not animated. The slide
+/// wouldn't tell anything about where the view is.
+const MAX_JUMP: u64 = 10;
+/// How much of the window's height the view slides at most.
+const MAX_SLIDE: f64 = 1.0 / 3.0;
+
+/// Animation of a window's view when its buffer is switched (or the tab is
+/// switched): the new view slides in from the direction of the previous
+/// view, e.g. from below when the new view is further down than the
+/// previous one.
+#[derive(Default)]
+pub struct ScrollRestore {
+    /// The window's area (top, bot, left, right, like in `grid_scroll`)
+    /// and how many rows its view moved.
+    slide: Option<([u64; 4], i64)>,
+    /// Background of the area that the view slides away from.
+    bg: Color,
+    /// When the animation started. `None` while the new view hasn't been
+    /// flushed yet.
+    start: Option<Instant>,
+}
+
+impl ScrollRestore {
+    /// Animates the view of the window at `reg`, which moved by `rows`. The
+    /// animation starts once the new view is flushed (see `flushed`).
+    pub fn start(&mut self, reg: [u64; 4], rows: i64, bg: Color) {
+        if !is_animated(reg, rows) {
+            return;
+        }
+
+        self.slide = Some((reg, rows));
+        self.bg = bg;
+        self.start = None;
+    }
+
+    /// Tells that the grid was flushed. Returns true if the animation was
+    /// started.
+    pub fn flushed(&mut self) -> bool {
+        if self.slide.is_some() && self.start.is_none() {
+            self.start = Some(Instant::now());
+            return true;
+        }
+
+        false
+    }
+
+    pub fn is_animating(&self) -> bool {
+        progress(self.start, DURATION).is_some()
+    }
+
+    /// Draws the window's area of `surface` (our grid's content) to `cr`,
+    /// at its current position.
+    pub fn draw(
+        &self,
+        cr: &cairo::Context,
+        surface: &cairo::Surface,
+        cm: &CellMetrics,
+    ) {
+        let (reg, rows) = match self.slide {
+            Some(slide) => slide,
+            None => return,
+        };
+        let p = match progress(self.start, DURATION) {
+            Some(p) => p,
+            None => return,
+        };
+
+        let x = reg[2] as f64 * cm.width;
+        let y = reg[0] as f64 * cm.height;
+        let w = (reg[3] - reg[2]) as f64 * cm.width;
+        let h = (reg[1] - reg[0]) as f64 * cm.height;
+
+        cr.save();
+        cr.rectangle(x, y, w, h);
+        cr.clip();
+        cr.set_source_rgb(self.bg.r, self.bg.g, self.bg.b);
+        cr.paint();
+        cr.set_source_surface(surface, 0.0, offset(reg, rows, p) * cm.height);
+        cr.paint();
+        cr.restore();
+    }
+}
+
+/// If the view of the window at `reg` moving by `rows` is animated.
+fn is_animated(reg: [u64; 4], rows: i64) -> bool {
+    let height = reg[1].saturating_sub(reg[0]);
+    height > 0 && rows != 0 && rows.abs() as u64 <= height * MAX_JUMP
+}
+
+/// Returns the offset (in rows) of the view of the window at `reg`, when
+/// the view moved by `rows` and the animation is at `p` (0..1).
+fn offset(reg: [u64; 4], rows: i64, p: f64) -> f64 {
+    let height = (reg[1] - reg[0]) as f64;
+    let slide = (rows.abs() as f64).min((height * MAX_SLIDE).ceil());
+
+    // Eases out, so the view slows down as it settles.
+    slide * rows.signum() as f64 * (1.0 - p).powi(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_animated() {
+        let reg = [0, 30, 0, 80];
+        assert!(is_animated(reg, 5));
+        assert!(is_animated(reg, -300));
+        assert!(!is_animated(reg, 0));
+        assert!(!is_animated(reg, 301));
+        assert!(!is_animated([10, 10, 0, 80], 5));
+    }
+
+    #[test]
+    fn test_offset() {
+        let reg = [1, 31, 0, 80];
+        assert_eq!(offset(reg, 5, 0.0), 5.0);
+        assert_eq!(offset(reg, -5, 0.0), -5.0);
+        // Long jumps slide a third of the window.
+        assert_eq!(offset(reg, 100, 0.0), 10.0);
+        assert_eq!(offset(reg, -100, 0.0), -10.0);
+        assert_eq!(offset(reg, 100, 0.5), 1.25);
+        assert_eq!(offset(reg, 100, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_starts_on_flush() {
+        let mut restore = ScrollRestore::default();
+        assert!(!restore.flushed());
+
+        restore.start([0, 30, 0, 80], 1000, Color::default());
+        assert!(!restore.flushed());
+
+        restore.start([0, 30, 0, 80], 10, Color::default());
+        assert!(!restore.is_animating());
+        assert!(restore.flushed());
+        assert!(restore.is_animating());
+        assert!(!restore.flushed());
+    }
+}
@@ -0,0 +1,135 @@
+use ui::grid::row::Row;
+
+/// Text selected by dragging with the left button, when nvim doesn't take
+/// the mouse (see `'mouse'`). Like in terminals, the selection runs from
+/// the cell where the button was pressed to the cell under the pointer,
+/// continuing from the end of one row to the start of the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextSelection {
+    /// Cell (row, col) where the selection started.
+    start: (u64, u64),
+    /// Cell (row, col) under the pointer.
+    end: (u64, u64),
+}
+
+impl TextSelection {
+    pub fn new(row: u64, col: u64) -> Self {
+        TextSelection {
+            start: (row, col),
+            end: (row, col),
+        }
+    }
+
+    /// Moves the selection's end to the cell at `row`, `col`.
+    pub fn extend(&mut self, row: u64, col: u64) {
+        self.end = (row, col);
+    }
+
+    /// If nothing was selected, e.g. the button was just clicked.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the selected cells of each row, as (row, from, to), `to`
+    /// being exclusive. `cols` is the grid's width.
+    pub fn ranges(&self, cols: u64) -> Vec<(u64, u64, u64)> {
+        let (first, last) = if self.start <= self.end {
+            (self.start, self.end)
+        } else {
+            (self.end, self.start)
+        };
+
+        (first.0..=last.0)
+            .filter_map(|row| {
+                let from = if row == first.0 { first.1 } else { 0 };
+                let to = if row == last.0 { last.1 + 1 } else { cols };
+                let to = to.min(cols);
+
+                if from < to {
+                    Some((row, from, to))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the selected text of `rows`, without the trailing spaces of
+    /// each row.
+    pub fn text(&self, rows: &[Row]) -> String {
+        let cols = rows.first().map(|row| row.len()).unwrap_or(0);
+
+        let lines: Vec<String> = self
+            .ranges(cols as u64)
+            .into_iter()
+            .filter_map(|(row, from, to)| {
+                rows.get(row as usize).map(|row| {
+                    let text =
+                        row.copy_range(from as usize, to as usize).text();
+                    text.trim_end().to_string()
+                })
+            })
+            .collect();
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nvim_bridge::{Cell, GridLineSegment};
+
+    fn row(text: &str, len: usize) -> Row {
+        let mut row = Row::new(len);
+        row.update(&GridLineSegment {
+            grid: 1,
+            row: 0,
+            col_start: 0,
+            cells: text
+                .chars()
+                .map(|c| Cell {
+                    text: c.to_string(),
+                    hl_id: 0,
+                    repeat: 1,
+                })
+                .collect(),
+        });
+        row
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut selection = TextSelection::new(1, 4);
+        assert!(selection.is_empty());
+        assert_eq!(selection.ranges(10), vec![(1, 4, 5)]);
+
+        selection.extend(3, 2);
+        assert!(!selection.is_empty());
+        assert_eq!(
+            selection.ranges(10),
+            vec![(1, 4, 10), (2, 0, 10), (3, 0, 3)]
+        );
+
+        // Selecting backwards.
+        selection.extend(0, 8);
+        assert_eq!(selection.ranges(10), vec![(0, 8, 10), (1, 0, 5)]);
+
+        // Past the end of the grid.
+        let selection = TextSelection::new(0, 12);
+        assert!(selection.ranges(10).is_empty());
+    }
+
+    #[test]
+    fn test_text() {
+        let rows = vec![row("hello", 10), row("world foo", 10)];
+
+        let mut selection = TextSelection::new(0, 1);
+        selection.extend(1, 4);
+        assert_eq!(selection.text(&rows), "ello\nworld");
+
+        let mut selection = TextSelection::new(1, 6);
+        selection.extend(1, 20);
+        assert_eq!(selection.text(&rows), "foo");
+    }
+}
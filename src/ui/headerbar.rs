@@ -0,0 +1,217 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::neovim::Neovim;
+use neovim_lib::NeovimApiAsync;
+
+/// How many directories of the path are shown at most. The rest are
+/// replaced with "…".
+const MAX_SEGMENTS: usize = 6;
+
+/// Headerbar of the window (with `--headerbar`). Shows the current file's
+/// path as a breadcrumb, where each directory can be clicked to browse it
+/// (see `gnvim#headerbar#browse`). Without a path, nvim's title is shown.
+pub struct Headerbar {
+    headerbar: gtk::HeaderBar,
+    /// Title widget, which has either the breadcrumb or the title.
+    box_: gtk::Box,
+
+    nvim: Arc<Mutex<Neovim>>,
+
+    /// Nvim's title (see 'title').
+    title: String,
+    /// Path of the current file, empty if there is no file or the
+    /// breadcrumb is disabled.
+    path: String,
+}
+
+impl Headerbar {
+    pub fn new(
+        window: &gtk::ApplicationWindow,
+        nvim: Arc<Mutex<Neovim>>,
+    ) -> Self {
+        let headerbar = gtk::HeaderBar::new();
+        headerbar.set_show_close_button(true);
+
+        let box_ = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        headerbar.set_custom_title(Some(&box_));
+
+        window.set_titlebar(Some(&headerbar));
+
+        let headerbar = Headerbar {
+            headerbar,
+            box_,
+            nvim,
+            title: String::from("Neovim"),
+            path: String::new(),
+        };
+        headerbar.update();
+
+        headerbar
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+
+        if self.path.is_empty() {
+            self.update();
+        }
+    }
+
+    /// Adds `widget` to the end (right side) of the headerbar.
+    pub fn pack_end<P: IsA<gtk::Widget>>(&self, widget: &P) {
+        self.headerbar.pack_end(widget);
+    }
+
+    /// Sets the path of the current file. Empty `path` shows the title
+    /// instead.
+    pub fn set_path(&mut self, path: &str) {
+        if self.path != path {
+            self.path = path.to_string();
+            self.update();
+        }
+    }
+
+    fn update(&self) {
+        for child in self.box_.get_children() {
+            self.box_.remove(&child);
+        }
+
+        let path = Path::new(&self.path);
+        let file = path.file_name().map(|name| name.to_string_lossy());
+        let file = match file {
+            Some(ref file) if !self.path.is_empty() => file,
+            _ => {
+                self.add_label(&self.title, "title");
+                self.box_.show_all();
+                return;
+            }
+        };
+
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let mut crumbs = segments(path, home.as_ref().map(|h| h.as_path()));
+        if crumbs.len() > MAX_SEGMENTS {
+            let skip = crumbs.len() - MAX_SEGMENTS;
+            crumbs.drain(..skip);
+
+            self.add_label("…", "dim-label");
+        }
+
+        for (name, dir) in crumbs {
+            let button = gtk::Button::new_with_label(&name);
+            button.set_relief(gtk::ReliefStyle::None);
+            button.set_tooltip_text(Some(dir.to_string_lossy().as_ref()));
+            button.set_valign(gtk::Align::Center);
+
+            let nvim = self.nvim.clone();
+            let dir = dir.to_string_lossy().into_owned();
+            button.connect_clicked(move |_| {
+                let mut nvim = nvim.lock().unwrap();
+                nvim.call_function_async(
+                    "gnvim#headerbar#browse",
+                    vec![dir.clone().into()],
+                )
+                .cb(|res| {
+                    if let Err(err) = res {
+                        println!("Failed to browse the directory: {}", err);
+                    }
+                })
+                .call();
+            });
+
+            self.box_.pack_start(&button, false, false, 0);
+            self.add_label("›", "dim-label");
+        }
+
+        self.add_label(file, "title");
+
+        self.box_.show_all();
+    }
+
+    /// Adds a label with the style class `class` (e.g. "title").
+    fn add_label(&self, text: &str, class: &str) {
+        let label = gtk::Label::new(Some(text));
+        if let Some(style) = label.get_style_context() {
+            style.add_class(class);
+        }
+        self.box_.pack_start(&label, false, false, 0);
+    }
+}
+
+/// Returns the directories of the file `path` as the segments of a
+/// breadcrumb: the name to show and the directory. The home directory
+/// (`home`) is shown as "~".
+fn segments(path: &Path, home: Option<&Path>) -> Vec<(String, PathBuf)> {
+    let parent = match path.parent() {
+        Some(parent) => parent,
+        None => return vec![],
+    };
+
+    let mut segments = vec![];
+    let mut dir = PathBuf::new();
+
+    let rest = match home {
+        Some(home) if home.parent().is_some() && parent.starts_with(home) => {
+            dir.push(home);
+            segments.push((String::from("~"), dir.clone()));
+            parent.strip_prefix(home).unwrap()
+        }
+        _ => parent,
+    };
+
+    for component in rest.components() {
+        dir.push(component);
+
+        let name = match component {
+            Component::RootDir => {
+                // On Windows, the drive (e.g. "C:") is the first segment.
+                if !segments.is_empty() {
+                    continue;
+                }
+                String::from("/")
+            }
+            component => component.as_os_str().to_string_lossy().into_owned(),
+        };
+        segments.push((name, dir.clone()));
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(segments: &[(String, PathBuf)]) -> Vec<&str> {
+        segments.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_segments() {
+        let home = Path::new("/home/user");
+
+        let crumbs =
+            segments(Path::new("/home/user/src/gnvim/main.rs"), Some(home));
+        assert_eq!(names(&crumbs), vec!["~", "src", "gnvim"]);
+        assert_eq!(crumbs[0].1, PathBuf::from("/home/user"));
+        assert_eq!(crumbs[2].1, PathBuf::from("/home/user/src/gnvim"));
+
+        let crumbs = segments(Path::new("/etc/hosts"), Some(home));
+        assert_eq!(names(&crumbs), vec!["/", "etc"]);
+        assert_eq!(crumbs[1].1, PathBuf::from("/etc"));
+
+        // Not in the home directory, even though the name starts the same.
+        let crumbs = segments(Path::new("/home/username/a"), Some(home));
+        assert_eq!(names(&crumbs), vec!["/", "home", "username"]);
+
+        // Root as the home directory isn't abbreviated.
+        let crumbs = segments(Path::new("/tmp/a"), Some(Path::new("/")));
+        assert_eq!(names(&crumbs), vec!["/", "tmp"]);
+
+        assert!(segments(Path::new("/"), Some(home)).is_empty());
+        assert_eq!(names(&segments(Path::new("/a"), None)), vec!["/"]);
+    }
+}
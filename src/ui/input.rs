@@ -0,0 +1,30 @@
+use ui::input_queue::InputQueue;
+
+/// GUI level input hook. When set, all the input is passed through a
+/// (vimscript) function before it is sent to nvim with `nvim_input`.
+#[derive(Default)]
+pub struct InputHook {
+    /// Name of the hook function.
+    function: Option<String>,
+}
+
+impl InputHook {
+    /// Sets the hook function. `None` removes the hook.
+    pub fn set(&mut self, function: Option<String>) {
+        self.function = function;
+    }
+
+    /// Queues `input` to be sent to nvim, through the hook if it's set.
+    ///
+    /// The hook is called through `gnvim#input#process` by the queue's
+    /// writer thread, so that a slow hook doesn't block the UI, and the
+    /// hook's result stays in order with the rest of the input (e.g. mouse
+    /// input queued after it). If the hook fails or is too slow, the
+    /// vimscript side removes it.
+    pub fn process(&self, queue: &InputQueue, input: String) {
+        match self.function {
+            Some(ref function) => queue.send_hooked(function.clone(), input),
+            None => queue.send(input),
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use neovim_lib::neovim::Neovim;
+use neovim_lib::{NeovimApiAsync, Value};
+
+/// Max number of inputs waiting to be sent to nvim.
+const QUEUE_SIZE: usize = 256;
+/// Max size (in bytes) of the input that is coalesced into one call.
+const MAX_BATCH: usize = 4096;
+/// How long we wait for nvim to answer the input hook, before the keys are
+/// sent as they are.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Metrics of the input queue.
+#[derive(Default)]
+pub struct QueueStats {
+    /// Number of inputs waiting to be sent.
+    pub depth: AtomicUsize,
+    /// Number of inputs that didn't fit in the queue, and were coalesced
+    /// into the overflow buffer.
+    pub overflowed: AtomicUsize,
+    /// Number of `nvim_input` calls made.
+    pub calls: AtomicUsize,
+    /// Round trip time of the latest `nvim_input` call, in microseconds.
+    pub latency: AtomicUsize,
+}
+
+/// Input waiting to be sent to nvim.
+#[derive(Debug, PartialEq)]
+enum Input {
+    /// Keyboard or mouse input, sent as it is.
+    Raw(String),
+    /// Keys that are passed through the input hook `function` (see
+    /// `InputHook`) first.
+    Hooked { function: String, keys: String },
+}
+
+impl Input {
+    fn len(&self) -> usize {
+        match *self {
+            Input::Raw(ref keys) => keys.len(),
+            Input::Hooked { ref keys, .. } => keys.len(),
+        }
+    }
+}
+
+/// Appends `input` to `inputs`. Consecutive raw inputs are coalesced into
+/// one.
+fn push_input(inputs: &mut Vec<Input>, input: Input) {
+    if let Input::Raw(ref keys) = input {
+        if let Some(&mut Input::Raw(ref mut last)) = inputs.last_mut() {
+            last.push_str(keys);
+            return;
+        }
+    }
+
+    inputs.push(input);
+}
+
+/// Queue for the (keyboard and mouse) input we send to nvim.
+///
+/// The input is sent from a separate thread, so that the UI thread doesn't
+/// block when nvim isn't reading its input fast enough (e.g. when a key is
+/// held down while nvim is busy). Consecutive inputs are coalesced into
+/// one `nvim_input` call, which is safe because nvim just appends the input
+/// to its input buffer.
+///
+/// The queue is bounded: if nvim doesn't keep up and the queue fills up,
+/// new input is appended to an overflow buffer (and counted in
+/// `QueueStats::overflowed`) instead of blocking the UI. Raw input is
+/// coalesced in the overflow, so holding a key down grows it by a few
+/// bytes per key instead of by a queue entry. No input is dropped.
+///
+/// Keys for the input hook are passed through it by the writer thread
+/// too, so that they stay in order with the rest of the input.
+#[derive(Clone)]
+pub struct InputQueue {
+    tx: SyncSender<Input>,
+    /// Input that didn't fit in the queue, and the number of inputs in it.
+    overflow: Arc<Mutex<(Vec<Input>, usize)>>,
+    stats: Arc<QueueStats>,
+}
+
+impl InputQueue {
+    pub fn new(nvim: Arc<Mutex<Neovim>>) -> Self {
+        let (tx, rx) = sync_channel(QUEUE_SIZE);
+        let overflow = Arc::new(Mutex::new((vec![], 0)));
+        let stats = Arc::new(QueueStats::default());
+
+        let overflow_ref = overflow.clone();
+        let stats_ref = stats.clone();
+        thread::spawn(move || writer(rx, overflow_ref, nvim, stats_ref));
+
+        InputQueue {
+            tx,
+            overflow,
+            stats,
+        }
+    }
+
+    /// Queues `input` to be sent to nvim.
+    pub fn send(&self, input: String) {
+        self.push(Input::Raw(input));
+    }
+
+    /// Queues `keys` to be passed through the input hook `function`, and
+    /// then sent to nvim.
+    pub fn send_hooked(&self, function: String, keys: String) {
+        self.push(Input::Hooked { function, keys });
+    }
+
+    fn push(&self, input: Input) {
+        self.stats.depth.fetch_add(1, Ordering::SeqCst);
+
+        // Once something is in the overflow, the rest of the input goes
+        // there too until the writer has taken it, to keep the order.
+        let mut overflow = self.overflow.lock().unwrap();
+        if overflow.1 > 0 {
+            push_input(&mut overflow.0, input);
+            overflow.1 += 1;
+            self.stats.overflowed.fetch_add(1, Ordering::SeqCst);
+            return;
+        }
+
+        match self.tx.try_send(input) {
+            Ok(()) => {}
+            Err(TrySendError::Full(input)) => {
+                push_input(&mut overflow.0, input);
+                overflow.1 += 1;
+                self.stats.overflowed.fetch_add(1, Ordering::SeqCst);
+            }
+            // Writer is gone, e.g. nvim has exited.
+            Err(TrySendError::Disconnected(_)) => {
+                self.stats.depth.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> Arc<QueueStats> {
+        self.stats.clone()
+    }
+}
+
+fn writer(
+    rx: Receiver<Input>,
+    overflow: Arc<Mutex<(Vec<Input>, usize)>>,
+    nvim: Arc<Mutex<Neovim>>,
+    stats: Arc<QueueStats>,
+) {
+    while let Some(batch) = next_batch(&rx, &overflow, &stats) {
+        let input: String = batch
+            .into_iter()
+            .map(|input| match input {
+                Input::Raw(keys) => keys,
+                Input::Hooked { function, keys } => {
+                    run_hook(&nvim, &function, keys)
+                }
+            })
+            .collect();
+        // The hook might have swallowed all of the input.
+        if input.is_empty() {
+            continue;
+        }
+
+        stats.calls.fetch_add(1, Ordering::SeqCst);
+
+        let sent = Instant::now();
+        let stats_ref = stats.clone();
+        let mut nvim = nvim.lock().unwrap();
+        nvim.input_async(&input)
+            .cb(move |res| {
+                let latency = sent.elapsed();
+                stats_ref.latency.store(
+                    latency.as_secs() as usize * 1_000_000
+                        + latency.subsec_micros() as usize,
+                    Ordering::SeqCst,
+                );
+
+                if let Err(err) = res {
+                    eprintln!("Failed to send input: {}", err);
+                }
+            })
+            .call();
+    }
+}
+
+/// Passes `keys` through the input hook `function` (with
+/// `gnvim#input#process`), and returns the result. We wait for nvim here
+/// instead of on the UI thread, without holding the lock on `nvim`. If
+/// the hook fails, or nvim doesn't answer in time, the keys are returned
+/// as they are.
+fn run_hook(nvim: &Arc<Mutex<Neovim>>, function: &str, keys: String) -> String {
+    let (tx, rx) = mpsc::channel();
+    {
+        let mut nvim = nvim.lock().unwrap();
+        nvim.call_function_async(
+            "gnvim#input#process",
+            vec![Value::from(function), Value::from(keys.as_str())],
+        )
+        .cb(move |res| {
+            let _ = tx.send(res);
+        })
+        .call();
+    }
+
+    match rx.recv_timeout(HOOK_TIMEOUT) {
+        Ok(Ok(res)) => res.as_str().map(String::from).unwrap_or(keys),
+        Ok(Err(err)) => {
+            eprintln!("Failed to call input hook: {}", err);
+            keys
+        }
+        Err(_) => {
+            eprintln!("Input hook didn't answer in time");
+            keys
+        }
+    }
+}
+
+/// Returns the next input to send, coalesced from the queue and the
+/// overflow. Blocks until there is input, and returns `None` once the
+/// queue is disconnected and empty.
+fn next_batch(
+    rx: &Receiver<Input>,
+    overflow: &Mutex<(Vec<Input>, usize)>,
+    stats: &QueueStats,
+) -> Option<Vec<Input>> {
+    let mut batch = vec![];
+    let mut len = 0;
+
+    loop {
+        if let Ok(next) = rx.try_recv() {
+            stats.depth.fetch_sub(1, Ordering::SeqCst);
+            len += next.len();
+            push_input(&mut batch, next);
+            if len >= MAX_BATCH {
+                return Some(batch);
+            }
+            continue;
+        }
+
+        // While we hold the lock, no new input comes in. Whatever is still
+        // in the queue came before the overflow.
+        {
+            let mut overflow = overflow.lock().unwrap();
+            while let Ok(next) = rx.try_recv() {
+                stats.depth.fetch_sub(1, Ordering::SeqCst);
+                push_input(&mut batch, next);
+            }
+            stats.depth.fetch_sub(overflow.1, Ordering::SeqCst);
+            for input in overflow.0.drain(..) {
+                push_input(&mut batch, input);
+            }
+            overflow.1 = 0;
+        }
+
+        if !batch.is_empty() {
+            return Some(batch);
+        }
+
+        // Both are empty, and the overflow only fills up once the queue is
+        // full, so it's safe to wait for the queue.
+        let next = rx.recv().ok()?;
+        stats.depth.fetch_sub(1, Ordering::SeqCst);
+        len += next.len();
+        push_input(&mut batch, next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue() -> (InputQueue, Receiver<Input>) {
+        let (tx, rx) = sync_channel(QUEUE_SIZE);
+        let queue = InputQueue {
+            tx,
+            overflow: Arc::new(Mutex::new((vec![], 0))),
+            stats: Arc::new(QueueStats::default()),
+        };
+        (queue, rx)
+    }
+
+    fn raw(keys: &str) -> Input {
+        Input::Raw(keys.to_string())
+    }
+
+    #[test]
+    fn test_next_batch_full() {
+        let (queue, rx) = queue();
+
+        // Fills the queue with exactly MAX_BATCH bytes of input, so that
+        // the last input goes to the overflow.
+        let input = "<LeftDrag><0,12>";
+        assert_eq!(input.len() * QUEUE_SIZE, MAX_BATCH);
+        for _ in 0..QUEUE_SIZE + 1 {
+            queue.send(input.to_string());
+        }
+        assert_eq!(queue.stats.overflowed.load(Ordering::SeqCst), 1);
+
+        let batch = next_batch(&rx, &queue.overflow, &queue.stats);
+        assert_eq!(batch, Some(vec![raw(&input.repeat(QUEUE_SIZE))]));
+        // The queue is empty now, but the overflow isn't.
+        let batch = next_batch(&rx, &queue.overflow, &queue.stats);
+        assert_eq!(batch, Some(vec![raw(input)]));
+
+        // New input goes to the queue again.
+        queue.send(String::from("a"));
+        let batch = next_batch(&rx, &queue.overflow, &queue.stats);
+        assert_eq!(batch, Some(vec![raw("a")]));
+        assert_eq!(queue.stats.depth.load(Ordering::SeqCst), 0);
+
+        drop(queue);
+        assert_eq!(
+            next_batch(&rx, &Mutex::default(), &QueueStats::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_next_batch_hooked() {
+        let (queue, rx) = queue();
+
+        queue.send(String::from("<LeftMouse><0,0>"));
+        queue.send_hooked(String::from("Hook"), String::from("x"));
+        queue.send(String::from("a"));
+        queue.send(String::from("b"));
+
+        let batch = next_batch(&rx, &queue.overflow, &queue.stats);
+        assert_eq!(
+            batch,
+            Some(vec![
+                raw("<LeftMouse><0,0>"),
+                Input::Hooked {
+                    function: String::from("Hook"),
+                    keys: String::from("x"),
+                },
+                raw("ab"),
+            ])
+        );
+    }
+}
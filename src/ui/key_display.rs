@@ -0,0 +1,261 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use glib;
+use gtk;
+use gtk::prelude::*;
+
+use ui::simulate::split_keys;
+
+/// Max number of keys shown at once.
+const MAX_KEYS: usize = 12;
+/// How long (in milliseconds) the keys are shown after the last key.
+const HIDE_DELAY: u32 = 1500;
+/// Interval (in milliseconds) and step of the fade-out.
+const FADE_INTERVAL: u32 = 30;
+const FADE_STEP: f64 = 0.1;
+
+/// Latest keys, with repeats of the same key collapsed into one entry.
+#[derive(Default)]
+struct Keys {
+    entries: VecDeque<(String, usize)>,
+}
+
+impl Keys {
+    fn push(&mut self, label: String) {
+        if let Some(last) = self.entries.back_mut() {
+            if last.0 == label {
+                last.1 += 1;
+                return;
+            }
+        }
+
+        if self.entries.len() == MAX_KEYS {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((label, 1));
+    }
+
+    fn text(&self) -> String {
+        let keys: Vec<String> = self
+            .entries
+            .iter()
+            .map(|&(ref label, count)| {
+                if count > 1 {
+                    format!("{}×{}", label, count)
+                } else {
+                    label.clone()
+                }
+            })
+            .collect();
+        keys.join(" ")
+    }
+}
+
+struct Inner {
+    keys: Keys,
+    /// If the keyboard input is shown (see `KeyDisplay::input`).
+    enabled: bool,
+    /// Source of the timeout that hides (or fades out) the display.
+    hide_source: Option<glib::SourceId>,
+}
+
+/// On-screen display of the latest keys, in the bottom right corner of the
+/// window (e.g. for screencasts). The keys are shown as they were sent to
+/// nvim, so they match what nvim got (e.g. after the input hook).
+#[derive(Clone)]
+pub struct KeyDisplay {
+    label: gtk::Label,
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl KeyDisplay {
+    pub fn new(parent: &gtk::Overlay) -> Self {
+        let css_provider = gtk::CssProvider::new();
+
+        let label = gtk::Label::new(None);
+        label.set_halign(gtk::Align::End);
+        label.set_valign(gtk::Align::End);
+        label.set_margin_end(24);
+        label.set_margin_bottom(48);
+        label.set_no_show_all(true);
+
+        add_css_provider!(&css_provider, label);
+        let css = if gtk::get_minor_version() < 20 {
+            "GtkLabel {
+                padding: 8px 12px;
+                font-size: 20px;
+                color: #ffffff;
+                background-color: rgba(0, 0, 0, 0.6);
+                border-radius: 6px;
+            }"
+        } else {
+            "label {
+                padding: 8px 12px;
+                font-size: 20px;
+                color: #ffffff;
+                background-color: rgba(0, 0, 0, 0.6);
+                border-radius: 6px;
+            }"
+        };
+        CssProviderExt::load_from_data(&css_provider, css.as_bytes()).unwrap();
+
+        parent.add_overlay(&label);
+        parent.set_overlay_pass_through(&label, true);
+
+        KeyDisplay {
+            label,
+            inner: Rc::new(RefCell::new(Inner {
+                keys: Keys::default(),
+                enabled: false,
+                hide_source: None,
+            })),
+        }
+    }
+
+    /// Sets if the keyboard input is shown.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.borrow_mut().enabled = enabled;
+        if !enabled {
+            self.clear();
+        }
+    }
+
+    /// Shows `input` that was sent to nvim, if the display is enabled.
+    pub fn input(&self, input: &str) {
+        if self.inner.borrow().enabled {
+            self.push(input);
+        }
+    }
+
+    /// Shows `input` (in nvim's key notation) after the previous keys.
+    pub fn push(&self, input: &str) {
+        let mut inner = self.inner.borrow_mut();
+
+        for key in split_keys(input) {
+            inner.keys.push(key_label(&key));
+        }
+
+        self.label.set_text(&inner.keys.text());
+        self.label.set_opacity(1.0);
+        self.label.show();
+
+        if let Some(source) = inner.hide_source.take() {
+            glib::source::source_remove(source);
+        }
+
+        let display = self.clone();
+        inner.hide_source = Some(gtk::timeout_add(HIDE_DELAY, move || {
+            display.fade_out();
+            gtk::Continue(false)
+        }));
+    }
+
+    /// Fades the display out, and clears it once it's not visible.
+    fn fade_out(&self) {
+        let display = self.clone();
+        let id = gtk::timeout_add(FADE_INTERVAL, move || {
+            let opacity = display.label.get_opacity() - FADE_STEP;
+            if opacity > 0.0 {
+                display.label.set_opacity(opacity);
+                return gtk::Continue(true);
+            }
+
+            display.inner.borrow_mut().hide_source = None;
+            display.clear();
+            gtk::Continue(false)
+        });
+
+        self.inner.borrow_mut().hide_source = Some(id);
+    }
+
+    /// Hides the display and forgets the shown keys.
+    pub fn clear(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.keys = Keys::default();
+        if let Some(source) = inner.hide_source.take() {
+            glib::source::source_remove(source);
+        }
+
+        self.label.hide();
+    }
+}
+
+/// Returns the label of `key` (in nvim's key notation), with symbols for
+/// the modifiers and some of the special keys. E.g. "<C-S-Left>" is "⌃⇧←".
+fn key_label(key: &str) -> String {
+    if key == " " {
+        return String::from("␣");
+    }
+    if !(key.len() > 2 && key.starts_with('<') && key.ends_with('>')) {
+        return key.to_string();
+    }
+
+    let mut name = &key[1..key.len() - 1];
+    let mut label = String::new();
+
+    // Modifiers, e.g. "C-" and "S-". Single "-" is the minus key.
+    while name.len() > 2 && name.as_bytes()[1] == b'-' {
+        label.push_str(match &name[..1] {
+            "C" | "c" => "⌃",
+            "S" | "s" => "⇧",
+            "A" | "a" | "M" | "m" => "⌥",
+            "D" | "d" => "⌘",
+            m => m,
+        });
+        name = &name[2..];
+    }
+
+    label.push_str(match name.to_lowercase().as_str() {
+        "cr" | "enter" | "return" => "⏎",
+        "esc" => "Esc",
+        "bs" => "⌫",
+        "del" => "⌦",
+        "tab" => "⇥",
+        "space" => "␣",
+        "lt" => "<",
+        "up" => "↑",
+        "down" => "↓",
+        "left" => "←",
+        "right" => "→",
+        _ => name,
+    });
+
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_label() {
+        assert_eq!(key_label("a"), "a");
+        assert_eq!(key_label(" "), "␣");
+        assert_eq!(key_label("<lt>"), "<");
+        assert_eq!(key_label("<CR>"), "⏎");
+        assert_eq!(key_label("<C-w>"), "⌃w");
+        assert_eq!(key_label("<C-S-Left>"), "⌃⇧←");
+        assert_eq!(key_label("<M-->"), "⌥-");
+        assert_eq!(key_label("<D-s>"), "⌘s");
+        assert_eq!(key_label("<F12>"), "F12");
+    }
+
+    #[test]
+    fn test_keys_collapse_repeats() {
+        let mut keys = Keys::default();
+        keys.push(String::from("j"));
+        keys.push(String::from("j"));
+        keys.push(String::from("j"));
+        keys.push(String::from("k"));
+        keys.push(String::from("j"));
+        assert_eq!(keys.text(), "j×3 k j");
+
+        for i in 0..MAX_KEYS {
+            keys.push(i.to_string());
+        }
+        assert_eq!(keys.entries.len(), MAX_KEYS);
+        assert_eq!(keys.entries[0].0, "0");
+    }
+}
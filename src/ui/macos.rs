@@ -0,0 +1,150 @@
+use std::sync::{Arc, Mutex};
+
+use gdk;
+use gio;
+use gio::prelude::*;
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::neovim::Neovim;
+use neovim_lib::NeovimApiAsync;
+
+use thread_guard::ThreadGuard;
+use ui::context_menu::{Action, ContextMenu};
+use ui::input_queue::InputQueue;
+
+/// Edit menu entries: label, action name, the action and its shortcut.
+const EDIT_ACTIONS: [(&str, &str, Action, &str); 4] = [
+    ("Cut", "cut", Action::Cut, "<Primary>x"),
+    ("Copy", "copy", Action::Copy, "<Primary>c"),
+    ("Paste", "paste", Action::Paste, "<Primary>v"),
+    ("Select All", "select-all", Action::SelectAll, "<Primary>a"),
+];
+
+/// Sets up the application menu (About, Preferences and Quit) and the Edit
+/// menu of the menu bar, and their Cmd shortcuts. The menus' actions are
+/// window actions, so they act on the focused window.
+pub fn setup_menus(
+    app: &gtk::Application,
+    window: &gtk::ApplicationWindow,
+    nvim: Arc<Mutex<Neovim>>,
+    input_queue: InputQueue,
+    context_menu: Arc<ThreadGuard<ContextMenu>>,
+) {
+    let app_menu = gio::Menu::new();
+    app_menu.append("About GNvim", "win.about");
+    app_menu.append("Preferences", "win.preferences");
+    app_menu.append("Quit GNvim", "win.quit");
+    app.set_app_menu(&app_menu);
+
+    let edit_menu = gio::Menu::new();
+    for (label, name, _, _) in EDIT_ACTIONS.iter() {
+        edit_menu.append(*label, format!("win.{}", name).as_str());
+    }
+    let menubar = gio::Menu::new();
+    menubar.append_submenu("Edit", &edit_menu);
+    app.set_menubar(&menubar);
+
+    let about = gio::SimpleAction::new("about", None);
+    let window_ref = window.clone();
+    about.connect_activate(move |_, _| {
+        let dialog = gtk::AboutDialog::new();
+        dialog.set_transient_for(&window_ref);
+        dialog.set_program_name("GNvim");
+        dialog.set_version(::VERSION);
+        dialog.set_logo_icon_name("gnvim");
+        dialog.connect_response(|dialog, _| dialog.destroy());
+        dialog.show_all();
+    });
+    window.add_action(&about);
+
+    let preferences = gio::SimpleAction::new("preferences", None);
+    let nvim_ref = nvim.clone();
+    preferences.connect_activate(move |_, _| {
+        let mut nvim = nvim_ref.lock().unwrap();
+        nvim.command_async("call gnvim#preferences#show()")
+            .cb(|res| {
+                if let Err(err) = res {
+                    println!("Failed to show the preferences: {}", err);
+                }
+            })
+            .call();
+    });
+    window.add_action(&preferences);
+
+    // Closing the windows (instead of quitting the application right away)
+    // goes through our confirmation of unsaved changes.
+    let quit = gio::SimpleAction::new("quit", None);
+    let app_ref = app.clone();
+    quit.connect_activate(move |_, _| {
+        for window in app_ref.get_windows() {
+            window.close();
+        }
+    });
+    window.add_action(&quit);
+    app.set_accels_for_action("win.quit", &["<Primary>q"]);
+
+    let close = gio::SimpleAction::new("close", None);
+    let window_ref = window.clone();
+    close.connect_activate(move |_, _| window_ref.close());
+    window.add_action(&close);
+    app.set_accels_for_action("win.close", &["<Primary>w"]);
+
+    for (_, name, action, accel) in EDIT_ACTIONS.iter() {
+        let simple = gio::SimpleAction::new(name, None);
+        let action = *action;
+        let queue = input_queue.clone();
+        let menu_ref = context_menu.clone();
+        simple.connect_activate(move |_, _| {
+            if let Some(input) = menu_ref.borrow().action_input(action) {
+                queue.send(input.to_string());
+            }
+        });
+        window.add_action(&simple);
+        app.set_accels_for_action(&format!("win.{}", name), &[*accel]);
+    }
+}
+
+/// Returns the keyval and the modifiers that should be sent to nvim for
+/// `e`, and if Cmd was held down. `None` means that the event should be
+/// left to GTK, e.g. to activate one of our shortcuts.
+///
+/// If `option_as_meta` is set, the Option key is used as Meta (e.g.
+/// `<M-a>`), instead of composing characters (e.g. "å").
+pub fn translate_key(
+    app: &gtk::Application,
+    e: &gdk::EventKey,
+    option_as_meta: bool,
+) -> Option<(u32, gdk::ModifierType, bool)> {
+    let keymap = gdk::Keymap::get_default()?;
+    let cmd_mask =
+        keymap.get_modifier_mask(gdk::ModifierIntent::PrimaryAccelerator);
+
+    let mut keyval = e.get_keyval();
+    let mut state = e.get_state();
+
+    let cmd = state.intersects(cmd_mask);
+    if cmd {
+        let accel = gtk::accelerator_name(keyval, state)?;
+        if !app.get_actions_for_accel(&accel).is_empty() {
+            return None;
+        }
+        state.remove(cmd_mask);
+    }
+
+    if state.contains(gdk::ModifierType::MOD1_MASK) {
+        if option_as_meta {
+            // Use the key without Option's composition.
+            let (unmodified, _, _, _) = keymap.translate_keyboard_state(
+                u32::from(e.get_hardware_keycode()),
+                state - gdk::ModifierType::MOD1_MASK,
+                i32::from(e.get_group()),
+            )?;
+            keyval = unmodified;
+        } else {
+            // The keyval is already the composed character.
+            state.remove(gdk::ModifierType::MOD1_MASK);
+        }
+    }
+
+    Some((keyval, state, cmd))
+}
@@ -13,14 +13,60 @@ macro_rules! add_css_provider {
     );
 }
 
+mod animation;
+mod cell_width;
 mod cmdline;
 pub mod color;
 mod common;
+mod context_menu;
 mod cursor_tooltip;
+mod debug_console;
 mod font;
+mod gesture;
+mod ghost_text;
 mod grid;
+mod headerbar;
+mod input;
+mod input_queue;
+mod key_display;
+#[cfg(target_os = "macos")]
+mod macos;
+mod mouse;
+mod notification;
+mod perf_hud;
+mod placement;
 mod popupmenu;
+mod preferences;
+mod print;
+mod project;
+mod quickfix;
+mod quit;
+mod recording;
+mod replace_preview;
+mod resources;
+mod resume;
+mod runtime_check;
+mod scroll_routing;
+pub mod session;
+mod simulate;
+mod snap;
+pub mod startup_error;
 mod tabline;
+mod task;
+mod trust;
 mod ui;
+mod unicode_input;
+mod user_css;
 mod wildmenu;
+mod window_geometry;
+mod wm_class;
+pub use self::placement::Placement;
+pub use self::preferences::{
+    default_cell_widths, default_guifont, default_placement,
+    default_scroll_restore,
+};
+pub use self::project::project_dir;
+pub use self::resources::{runtime_path, set_default_icon};
 pub use self::ui::UI;
+pub use self::window_geometry::WindowGeometry;
+pub use self::wm_class::project_name;
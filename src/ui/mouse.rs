@@ -0,0 +1,93 @@
+/// Nvim's `'mouse'` option and the current mode, which tell if nvim takes
+/// the mouse. When it doesn't, our mouse events are not sent to nvim, and
+/// dragging with the left button selects text instead (see
+/// `Grid::set_mouse_enabled`).
+pub struct Mouse {
+    /// Value of `'mouse'`. `None` until nvim tells us, in which case the
+    /// mouse is enabled.
+    option: Option<String>,
+    /// Name of the current mode (from `mode_change`).
+    mode: String,
+}
+
+impl Default for Mouse {
+    fn default() -> Self {
+        Mouse {
+            option: None,
+            mode: String::from("normal"),
+        }
+    }
+}
+
+impl Mouse {
+    pub fn set_option(&mut self, option: &str) {
+        self.option = Some(option.to_string());
+    }
+
+    pub fn set_mode(&mut self, mode: &str) {
+        self.mode = mode.to_string();
+    }
+
+    /// If nvim takes the mouse in the current mode.
+    pub fn is_enabled(&self) -> bool {
+        let option = match self.option {
+            Some(ref option) => option,
+            None => return true,
+        };
+
+        if option.contains('a') {
+            return true;
+        }
+
+        match flag(&self.mode) {
+            Some(flag) => option.contains(flag),
+            // E.g. dragging the statusline, which is only possible if the
+            // mouse is enabled in some mode.
+            None => !option.is_empty(),
+        }
+    }
+}
+
+/// Returns the flag of `'mouse'` for `mode`.
+fn flag(mode: &str) -> Option<char> {
+    match mode {
+        "normal" | "operator" => Some('n'),
+        "visual" | "visual_select" => Some('v'),
+        "insert" | "replace" | "showmatch" => Some('i'),
+        "more" | "more_lastline" => Some('r'),
+        _ if mode.starts_with("cmdline_") => Some('c'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_enabled() {
+        let mut mouse = Mouse::default();
+        assert!(mouse.is_enabled());
+
+        mouse.set_option("");
+        assert!(!mouse.is_enabled());
+
+        mouse.set_option("a");
+        assert!(mouse.is_enabled());
+
+        mouse.set_option("nv");
+        assert!(mouse.is_enabled());
+        mouse.set_mode("visual");
+        assert!(mouse.is_enabled());
+        mouse.set_mode("insert");
+        assert!(!mouse.is_enabled());
+        mouse.set_mode("cmdline_normal");
+        assert!(!mouse.is_enabled());
+        mouse.set_mode("vsep_drag");
+        assert!(mouse.is_enabled());
+
+        mouse.set_option("c");
+        mouse.set_mode("cmdline_insert");
+        assert!(mouse.is_enabled());
+    }
+}
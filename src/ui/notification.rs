@@ -0,0 +1,143 @@
+use gio;
+use gio::prelude::*;
+use glib;
+use gtk;
+use gtk::prelude::*;
+
+/// Name of the application action that presents the window which sent a
+/// notification. Takes the window's id as its parameter.
+const FOCUS_ACTION: &str = "focus-window";
+
+/// Level of a notification, same as the levels in nvim's `vim.log.levels`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn from_u64(level: u64) -> Self {
+        match level {
+            0 => Level::Trace,
+            1 => Level::Debug,
+            2 => Level::Info,
+            3 => Level::Warn,
+            _ => Level::Error,
+        }
+    }
+
+    fn icon_name(self) -> &'static str {
+        match self {
+            Level::Trace | Level::Debug | Level::Info => "dialog-information",
+            Level::Warn => "dialog-warning",
+            Level::Error => "dialog-error",
+        }
+    }
+
+    fn priority(self) -> gio::NotificationPriority {
+        match self {
+            Level::Trace | Level::Debug => gio::NotificationPriority::Low,
+            Level::Info | Level::Warn => gio::NotificationPriority::Normal,
+            Level::Error => gio::NotificationPriority::High,
+        }
+    }
+}
+
+/// Shows messages from nvim (e.g. from `vim.notify()`) as desktop
+/// notifications, when our window isn't focused. Clicking a notification
+/// focuses the window.
+pub struct Notifier {
+    window: gtk::ApplicationWindow,
+    /// Messages below this level are not shown.
+    min_level: Level,
+}
+
+impl Notifier {
+    pub fn new(window: &gtk::ApplicationWindow) -> Self {
+        // The action is shared by all of our windows.
+        if let Some(app) = window.get_application() {
+            if app.lookup_action(FOCUS_ACTION).is_none() {
+                add_focus_action(&app);
+            }
+        }
+
+        Notifier {
+            window: window.clone(),
+            min_level: Level::Warn,
+        }
+    }
+
+    pub fn set_min_level(&mut self, level: Level) {
+        self.min_level = level;
+    }
+
+    pub fn notify(&self, msg: &str, level: Level) {
+        if level >= self.min_level {
+            self.send(msg, level);
+        }
+    }
+
+    /// Shows `msg` regardless of the minimum level (e.g. when a task
+    /// finishes), if the window isn't focused.
+    pub fn send(&self, msg: &str, level: Level) {
+        if self.window.is_active() {
+            return;
+        }
+
+        let app = match self.window.get_application() {
+            Some(app) => app,
+            None => return,
+        };
+
+        // First line is the title, the rest is the body.
+        let mut lines = msg.trim().splitn(2, '\n');
+        let title = lines.next().unwrap_or_default();
+        let notification = gio::Notification::new(title);
+        if let Some(body) = lines.next() {
+            notification.set_body(body);
+        }
+        notification.set_icon(&gio::ThemedIcon::new_with_default_fallbacks(
+            level.icon_name(),
+        ));
+        notification.set_priority(level.priority());
+        notification.set_default_action_and_target_value(
+            &format!("app.{}", FOCUS_ACTION),
+            Some(&self.window.get_id().to_variant()),
+        );
+
+        app.send_notification(None, &notification);
+    }
+}
+
+fn add_focus_action(app: &gtk::Application) {
+    let action =
+        gio::SimpleAction::new(FOCUS_ACTION, glib::VariantTy::new("u").ok());
+    let app_ref = app.clone();
+    action.connect_activate(move |_, id| {
+        let window = id
+            .as_ref()
+            .and_then(|id| id.get::<u32>())
+            .and_then(|id| app_ref.get_window_by_id(id));
+        if let Some(window) = window {
+            window.present();
+        }
+    });
+    app.add_action(&action);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_from_u64() {
+        assert_eq!(Level::from_u64(0), Level::Trace);
+        assert_eq!(Level::from_u64(3), Level::Warn);
+        // Unknown levels are treated as errors.
+        assert_eq!(Level::from_u64(10), Level::Error);
+        assert!(Level::Info < Level::Warn);
+    }
+}
@@ -0,0 +1,313 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cairo;
+use glib;
+use gtk;
+use gtk::prelude::*;
+
+use ui::input_queue::QueueStats;
+
+/// How often (in milliseconds) the metrics are sampled.
+const SAMPLE_INTERVAL: u32 = 250;
+/// How many samples are kept (and drawn) for each metric.
+const SAMPLES: usize = 60;
+
+const WIDTH: i32 = 300;
+const ROW_HEIGHT: f64 = 22.0;
+const PADDING: f64 = 8.0;
+/// Width of the metrics' labels, the rest is for the sparklines.
+const LABEL_WIDTH: f64 = 150.0;
+
+/// Latest samples of a single metric.
+#[derive(Default)]
+struct Series {
+    values: VecDeque<f64>,
+}
+
+impl Series {
+    fn push(&mut self, value: f64) {
+        if self.values.len() == SAMPLES {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    fn last(&self) -> f64 {
+        self.values.back().cloned().unwrap_or(0.0)
+    }
+
+    fn max(&self) -> f64 {
+        self.values.iter().cloned().fold(0.0, f64::max)
+    }
+}
+
+/// Metrics shown in the HUD, and what is accumulated between the samples.
+#[derive(Default)]
+struct Metrics {
+    fps: Series,
+    /// Longest time spent on a redraw batch, in milliseconds.
+    batch: Series,
+    /// Number of cells damaged.
+    damage: Series,
+    /// Round trip time of our input, in milliseconds.
+    latency: Series,
+    /// Number of inputs waiting to be sent.
+    queue: Series,
+
+    batch_max: Duration,
+    damaged: usize,
+    /// Frame count of the current grid, as of the last batch.
+    frames: u64,
+    /// Frame count on the last sample.
+    sampled_frames: u64,
+}
+
+impl Metrics {
+    /// Takes a sample, `elapsed` seconds after the previous one.
+    fn sample(&mut self, elapsed: f64, depth: usize, latency: usize) {
+        let frames = self.frames.saturating_sub(self.sampled_frames);
+        self.sampled_frames = self.frames;
+        self.fps.push(if elapsed > 0.0 {
+            frames as f64 / elapsed
+        } else {
+            0.0
+        });
+
+        let batch = self.batch_max;
+        self.batch.push(
+            batch.as_secs() as f64 * 1000.0
+                + batch.subsec_nanos() as f64 / 1_000_000.0,
+        );
+        self.batch_max = Duration::default();
+
+        self.damage.push(self.damaged as f64);
+        self.damaged = 0;
+
+        self.latency.push(latency as f64 / 1000.0);
+        self.queue.push(depth as f64);
+    }
+
+    /// Returns the rows of the HUD: labels and the series to draw.
+    fn rows(&self) -> Vec<(String, &Series)> {
+        vec![
+            (format!("FPS      {:6.1}", self.fps.last()), &self.fps),
+            (format!("Redraw {:6.2}ms", self.batch.last()), &self.batch),
+            (format!("Damage   {:6}", self.damage.last()), &self.damage),
+            (
+                format!("RPC    {:6.2}ms", self.latency.last()),
+                &self.latency,
+            ),
+            (format!("Queue    {:6}", self.queue.last()), &self.queue),
+        ]
+    }
+}
+
+/// Overlay in the top right corner of the window that shows the frame rate,
+/// the time spent on the redraw batches, the number of damaged cells, the
+/// round trip time of our input and the depth of our input queue. The
+/// latest samples of each are drawn as sparklines.
+pub struct PerfHud {
+    da: gtk::DrawingArea,
+    metrics: Rc<RefCell<Metrics>>,
+    queue_stats: Arc<QueueStats>,
+    /// Source of the sampling timeout, if the HUD is shown.
+    source: Option<glib::SourceId>,
+}
+
+impl PerfHud {
+    pub fn new(parent: &gtk::Overlay, queue_stats: Arc<QueueStats>) -> Self {
+        let metrics = Rc::new(RefCell::new(Metrics::default()));
+
+        let da = gtk::DrawingArea::new();
+        let rows = metrics.borrow().rows().len();
+        da.set_size_request(
+            WIDTH,
+            (rows as f64 * ROW_HEIGHT + PADDING * 2.0) as i32,
+        );
+        da.set_halign(gtk::Align::End);
+        da.set_valign(gtk::Align::Start);
+        da.set_margin_top(12);
+        da.set_margin_end(12);
+        da.set_no_show_all(true);
+
+        let metrics_ref = metrics.clone();
+        da.connect_draw(move |da, cr| {
+            draw(da, cr, &metrics_ref.borrow());
+            Inhibit(false)
+        });
+
+        parent.add_overlay(&da);
+        parent.set_overlay_pass_through(&da, true);
+
+        PerfHud {
+            da,
+            metrics,
+            queue_stats,
+            source: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// Shows or hides the HUD. The metrics are only sampled while the HUD
+    /// is shown.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled == self.is_enabled() {
+            return;
+        }
+
+        if !enabled {
+            glib::source::source_remove(self.source.take().unwrap());
+            self.da.hide();
+            return;
+        }
+
+        *self.metrics.borrow_mut() = Metrics::default();
+
+        let da = self.da.clone();
+        let metrics = self.metrics.clone();
+        let queue_stats = self.queue_stats.clone();
+        let mut last = Instant::now();
+        self.source = Some(gtk::timeout_add(SAMPLE_INTERVAL, move || {
+            let elapsed = last.elapsed();
+            last = Instant::now();
+
+            metrics.borrow_mut().sample(
+                elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9,
+                queue_stats.depth.load(Ordering::SeqCst),
+                queue_stats.latency.load(Ordering::SeqCst),
+            );
+            da.queue_draw();
+
+            gtk::Continue(true)
+        }));
+
+        self.da.show();
+    }
+
+    /// Records a handled redraw batch: how long it took and the frame count
+    /// of the current grid after it.
+    pub fn record_batch(&self, duration: Duration, frames: u64) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut metrics = self.metrics.borrow_mut();
+        metrics.batch_max = metrics.batch_max.max(duration);
+        metrics.frames = frames;
+    }
+
+    /// Records the number of cells damaged on a flush.
+    pub fn record_damage(&self, cells: usize) {
+        if self.is_enabled() {
+            self.metrics.borrow_mut().damaged += cells;
+        }
+    }
+}
+
+fn draw(da: &gtk::DrawingArea, cr: &cairo::Context, metrics: &Metrics) {
+    let w = da.get_allocated_width() as f64;
+    let h = da.get_allocated_height() as f64;
+
+    cr.set_source_rgba(0.0, 0.0, 0.0, 0.7);
+    cr.rectangle(0.0, 0.0, w, h);
+    cr.fill();
+
+    cr.select_font_face(
+        "monospace",
+        cairo::FontSlant::Normal,
+        cairo::FontWeight::Normal,
+    );
+    cr.set_font_size(12.0);
+
+    let graph_w = w - LABEL_WIDTH - PADDING * 2.0;
+    for (i, (label, series)) in metrics.rows().into_iter().enumerate() {
+        let y = PADDING + i as f64 * ROW_HEIGHT;
+
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.move_to(PADDING, y + ROW_HEIGHT * 0.7);
+        cr.show_text(&label);
+
+        sparkline(
+            cr,
+            series,
+            (PADDING + LABEL_WIDTH, y + 3.0, graph_w, ROW_HEIGHT - 6.0),
+        );
+    }
+}
+
+/// Draws the samples of `series` in `area` (x, y, width, height), scaled to
+/// the largest sample.
+fn sparkline(cr: &cairo::Context, series: &Series, area: (f64, f64, f64, f64)) {
+    let (x, y, w, h) = area;
+    let max = series.max();
+    let step = w / (SAMPLES - 1) as f64;
+    // Newest sample is on the right edge.
+    let start = x + (SAMPLES - series.values.len()) as f64 * step;
+
+    cr.set_source_rgba(1.0, 1.0, 1.0, 0.15);
+    cr.rectangle(x, y, w, h);
+    cr.fill();
+
+    cr.set_source_rgb(0.4, 0.8, 1.0);
+    cr.set_line_width(1.0);
+    for (i, value) in series.values.iter().enumerate() {
+        let scaled = if max > 0.0 { value / max } else { 0.0 };
+        let px = start + i as f64 * step;
+        let py = y + h - scaled * h;
+        if i == 0 {
+            cr.move_to(px, py);
+        } else {
+            cr.line_to(px, py);
+        }
+    }
+    cr.stroke();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_series_capacity() {
+        let mut series = Series::default();
+        assert_eq!(series.last(), 0.0);
+
+        for i in 0..SAMPLES + 10 {
+            series.push(i as f64);
+        }
+        assert_eq!(series.values.len(), SAMPLES);
+        assert_eq!(series.values[0], 10.0);
+        assert_eq!(series.last(), (SAMPLES + 9) as f64);
+        assert_eq!(series.max(), (SAMPLES + 9) as f64);
+    }
+
+    #[test]
+    fn test_metrics_sample() {
+        let mut metrics = Metrics::default();
+        metrics.frames = 30;
+        metrics.batch_max = Duration::from_micros(2500);
+        metrics.damaged = 120;
+
+        metrics.sample(0.5, 3, 1500);
+        assert_eq!(metrics.fps.last(), 60.0);
+        assert_eq!(metrics.batch.last(), 2.5);
+        assert_eq!(metrics.damage.last(), 120.0);
+        assert_eq!(metrics.latency.last(), 1.5);
+        assert_eq!(metrics.queue.last(), 3.0);
+
+        // Accumulated values are reset on each sample.
+        metrics.frames = 45;
+        metrics.sample(0.5, 0, 1500);
+        assert_eq!(metrics.fps.last(), 30.0);
+        assert_eq!(metrics.batch.last(), 0.0);
+        assert_eq!(metrics.damage.last(), 0.0);
+    }
+}
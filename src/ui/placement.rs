@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+use gdk;
+use gdk::prelude::*;
+use gtk;
+use gtk::prelude::*;
+
+use ui::window_geometry::WindowGeometry;
+
+/// Where a new window is placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    /// Where the window was when gnvim was last closed.
+    Restore,
+    /// At the pointer, on the monitor under it.
+    Pointer,
+    /// Centered on the monitor under the pointer.
+    Center,
+    /// Where the window of the current project was when it was last closed.
+    Project,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement::Restore
+    }
+}
+
+impl FromStr for Placement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "restore" => Ok(Placement::Restore),
+            "pointer" => Ok(Placement::Pointer),
+            "center" => Ok(Placement::Center),
+            "project" => Ok(Placement::Project),
+            _ => Err(format!(
+                "Invalid placement \"{}\" (restore, pointer, center or \
+                 project)",
+                s
+            )),
+        }
+    }
+}
+
+/// Places `window`, which is about to be shown with `geometry`. The window
+/// is moved where `placement` tells, and the same is set as gtk's hint
+/// (`gtk::WindowPosition`). Where the platform doesn't let us position our
+/// window (e.g. wayland), the hint is all that the window manager gets.
+pub fn place(
+    window: &gtk::ApplicationWindow,
+    placement: Placement,
+    geometry: &WindowGeometry,
+) {
+    let size = (geometry.width, geometry.height);
+
+    match placement {
+        Placement::Restore | Placement::Project => {
+            if let Some((x, y)) = geometry.position {
+                window.move_(x, y);
+            }
+        }
+        Placement::Pointer => {
+            window.set_position(gtk::WindowPosition::Mouse);
+            if let Some((pointer, area)) = pointer_workarea() {
+                let (x, y) = centered_at(pointer, size, area);
+                window.move_(x, y);
+            }
+        }
+        Placement::Center => {
+            window.set_position(gtk::WindowPosition::Center);
+            if let Some((_, area)) = pointer_workarea() {
+                let center = (area.0 + area.2 / 2, area.1 + area.3 / 2);
+                let (x, y) = centered_at(center, size, area);
+                window.move_(x, y);
+            }
+        }
+    }
+}
+
+/// Returns the position of the pointer, and the work area (x, y, width,
+/// height) of the monitor under it.
+fn pointer_workarea() -> Option<((i32, i32), (i32, i32, i32, i32))> {
+    let display = gdk::Display::get_default()?;
+    let pointer = display.get_device_manager()?.get_client_pointer()?;
+    let (screen, x, y) = pointer.get_position();
+
+    let monitor = screen.get_monitor_at_point(x, y);
+    let area = screen.get_monitor_workarea(monitor);
+    Some(((x, y), (area.x, area.y, area.width, area.height)))
+}
+
+/// Returns the position of a window of `size` (width, height) that is
+/// centered at `at`, but kept in `area` (x, y, width, height). If the
+/// window doesn't fit in `area`, its top left corner is kept in `area`.
+fn centered_at(
+    at: (i32, i32),
+    size: (i32, i32),
+    area: (i32, i32, i32, i32),
+) -> (i32, i32) {
+    let clamp = |pos: i32, size: i32, start: i32, len: i32| {
+        pos.min(start + len - size).max(start)
+    };
+
+    (
+        clamp(at.0 - size.0 / 2, size.0, area.0, area.2),
+        clamp(at.1 - size.1 / 2, size.1, area.1, area.3),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("restore".parse(), Ok(Placement::Restore));
+        assert_eq!("pointer".parse(), Ok(Placement::Pointer));
+        assert_eq!("center".parse(), Ok(Placement::Center));
+        assert_eq!("project".parse(), Ok(Placement::Project));
+        assert!("Center".parse::<Placement>().is_err());
+    }
+
+    #[test]
+    fn test_centered_at() {
+        // Second monitor, right of a 1920 pixels wide one.
+        let area = (1920, 0, 2560, 1400);
+
+        assert_eq!(centered_at((3200, 700), (800, 600), area), (2800, 400));
+        // Near the edges.
+        assert_eq!(centered_at((1930, 10), (800, 600), area), (1920, 0));
+        assert_eq!(centered_at((4470, 1390), (800, 600), area), (3680, 800));
+        // Bigger than the monitor.
+        assert_eq!(centered_at((3200, 700), (3000, 1500), area), (1920, 0));
+    }
+}
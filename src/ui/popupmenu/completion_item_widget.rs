@@ -1,3 +1,9 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use cairo;
+use gdk;
+use gdk::ContextExt;
 use gtk;
 use gtk::prelude::*;
 
@@ -21,6 +27,11 @@ pub struct CompletionItemWidgetWrap {
     pub menu: gtk::Label,
     /// Image of the item in the row.
     pub kind: gtk::Image,
+    /// Color of the `kind` image, for rendering it again when the scale
+    /// factor changes.
+    kind_fg: Rc<Cell<Color>>,
+    /// Size of the `kind` image.
+    size: f64,
     /// Root container.
     pub row: gtk::ListBoxRow,
 }
@@ -37,8 +48,17 @@ impl CompletionItemWidgetWrap {
         let grid = gtk::Grid::new();
         grid.set_column_spacing(10);
 
-        let buf = get_icon_pixbuf(&item.kind.as_str(), icon_fg, size);
-        let kind = gtk::Image::new_from_pixbuf(&buf);
+        let kind = gtk::Image::new();
+        set_icon(&kind, &item.kind, icon_fg, size);
+        // The icon is rendered for the scale factor, so it needs to be
+        // rendered again for a new one (e.g. when the window is moved to a
+        // monitor with a different scale).
+        let kind_fg = Rc::new(Cell::new(*icon_fg));
+        let kind_fg_ref = kind_fg.clone();
+        let kind_name = item.kind.clone();
+        kind.connect_property_scale_factor_notify(move |kind| {
+            set_icon(kind, &kind_name, &kind_fg_ref.get(), size);
+        });
         kind.set_tooltip_text(format!("kind: '{}'", item.kind).as_str());
         kind.set_margin_start(margin);
         grid.attach(&kind, 0, 0, 1, 1);
@@ -79,9 +99,17 @@ impl CompletionItemWidgetWrap {
             info,
             row,
             kind,
+            kind_fg,
+            size,
             menu,
         }
     }
+
+    /// Sets the color of the `kind` image.
+    pub fn set_kind_fg(&self, color: &Color) {
+        self.kind_fg.set(*color);
+        set_icon(&self.kind, &self.item.kind, color, self.size);
+    }
 }
 
 /// Returns first line of `info`.
@@ -91,18 +119,22 @@ fn shorten_info(info: &String) -> String {
     first_line.to_string()
 }
 
-pub fn get_icon_pixbuf(
-    kind: &str,
-    color: &Color,
-    size: f64,
-) -> gdk_pixbuf::Pixbuf {
-    let contents = get_icon_name_for_kind(kind, &color, size);
+/// Sets the icon for `kind` to `image`. The icon is rendered at `image`'s
+/// scale factor, so that it's sharp on HiDPI monitors too.
+fn set_icon(image: &gtk::Image, kind: &str, color: &Color, size: f64) {
+    let scale = image.get_scale_factor();
+    let contents = get_icon_name_for_kind(kind, &color, size * scale as f64);
     let stream = gio::MemoryInputStream::new_from_bytes(&glib::Bytes::from(
         contents.as_bytes(),
     ));
     let buf = gdk_pixbuf::Pixbuf::new_from_stream(&stream, None).unwrap();
 
-    buf
+    let surface = cairo::Context::cairo_surface_create_from_pixbuf(
+        &buf,
+        scale,
+        None::<&gdk::Window>,
+    );
+    image.set_from_surface(surface.as_ref());
 }
 
 fn get_icon_name_for_kind(kind: &str, color: &Color, size: f64) -> String {
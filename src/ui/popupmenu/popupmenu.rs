@@ -5,17 +5,18 @@ use gtk;
 use gtk::prelude::*;
 use neovim_lib::neovim::Neovim;
 use neovim_lib::neovim_api::NeovimApi;
+use neovim_lib::Value;
 use pango;
 
 use nvim_bridge::{CompletionItem, PmenuColors};
 use thread_guard::ThreadGuard;
 use ui::common::calc_line_space;
 use ui::common::{
-    get_preferred_horizontal_position, get_preferred_vertical_position,
+    get_preferred_horizontal_position, get_preferred_vertical_position_below,
 };
 use ui::font::{Font, FontUnit};
-use ui::popupmenu::get_icon_pixbuf;
 use ui::popupmenu::LazyLoader;
+use ui::scroll_routing::ScrollArea;
 use ui::ui::HlDefs;
 
 /// Maximum height of completion menu.
@@ -36,6 +37,12 @@ struct State {
 
     width_no_details: i32,
     width_with_details: i32,
+
+    /// Maximum number of visible rows. Zero means no limit (other than
+    /// `MAX_HEIGHT`).
+    max_rows: i32,
+    /// Number of visible rows we've told nvim about.
+    visible_rows: i32,
 }
 
 impl State {
@@ -53,6 +60,9 @@ impl State {
             current_width: DEFAULT_WIDTH_NO_DETAILS,
             width_no_details: DEFAULT_WIDTH_NO_DETAILS,
             width_with_details: DEFAULT_WIDTH_WITH_DETAILS,
+
+            max_rows: 0,
+            visible_rows: 0,
         }
     }
 }
@@ -127,12 +137,16 @@ impl Popupmenu {
         scrolled_list.add(&list);
         scrolled_list
             .set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        // Always show the scrollbar when the list doesn't fit, so the user can
+        // see that there is more items.
+        scrolled_list.set_overlay_scrolling(false);
 
         let box_ = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         box_.pack_start(&scrolled_list, true, true, 0);
         box_.pack_start(&scrolled_info, true, true, 0);
         box_.set_size_request(DEFAULT_WIDTH_NO_DETAILS, MAX_HEIGHT);
         box_.set_homogeneous(true);
+        gtk::WidgetExt::set_name(&box_, "gnvim-popupmenu");
 
         add_css_provider!(
             &css_provider,
@@ -198,30 +212,65 @@ impl Popupmenu {
 
         let state_ref = state.clone();
         let layout_ref = layout.clone();
+        let list_ref = list.clone();
         let scrolled_list_ref = scrolled_list.clone();
         let scrolled_info_ref = scrolled_info.clone();
+        let nvim_ref = nvim.clone();
         box_.connect_size_allocate(move |box_, alloc| {
-            let state = state_ref.borrow();
+            let mut state = state_ref.borrow_mut();
             let layout = layout_ref.clone();
 
             if let Some(area) = state.available_size {
                 let pos = state.anchor;
+                let row_height = get_row_height(&list_ref);
+
+                let max_height = match row_height {
+                    Some(row_height) if state.max_rows > 0 => {
+                        (row_height * state.max_rows).min(MAX_HEIGHT)
+                    }
+                    _ => MAX_HEIGHT,
+                };
 
                 let (x, width) = get_preferred_horizontal_position(
                     &area,
                     &pos,
                     state.current_width,
                 );
-                let (y, height) = get_preferred_vertical_position(
+                let (y, height) = get_preferred_vertical_position_below(
                     &area,
                     &pos,
-                    alloc.height.min(MAX_HEIGHT),
+                    alloc.height.min(max_height),
                 );
 
                 layout.move_(box_, x, y);
 
                 box_.set_size_request(width, height);
 
+                // Tell nvim how many items we're showing, so that paging
+                // (e.g. <PageDown>) in the popupmenu works as expected.
+                if let Some(row_height) = row_height {
+                    let visible_rows = height / row_height;
+                    if visible_rows > 0 && visible_rows != state.visible_rows {
+                        state.visible_rows = visible_rows;
+
+                        let mut nvim = nvim_ref.lock().unwrap();
+                        nvim.session
+                            .call_async::<Value>(
+                                "nvim_ui_pum_set_height",
+                                vec![Value::from(visible_rows)],
+                            )
+                            .cb(|res| {
+                                if let Err(err) = res {
+                                    println!(
+                                        "Failed to set pum height: {}",
+                                        err
+                                    );
+                                }
+                            })
+                            .call();
+                    }
+                }
+
                 // If we moved the popupmenu above the achor position, make
                 // sure our contents are aligned to the bottom so there is not
                 // cap between the achor and the content it self.
@@ -272,6 +321,15 @@ impl Popupmenu {
         }
     }
 
+    /// Returns our area for routing the wheel events: the list and the
+    /// documentation (see `toggle_show_info`) scroll under the pointer.
+    pub fn scroll_area(&self) -> ScrollArea {
+        ScrollArea::new(
+            self.box_.clone().upcast(),
+            vec![self.scrolled_list.clone(), self.scrolled_info.clone()],
+        )
+    }
+
     pub fn is_above_anchor(&self) -> bool {
         self.scrolled_list.get_child().unwrap().get_valign() == gtk::Align::End
     }
@@ -335,6 +393,15 @@ impl Popupmenu {
         self.ensure_container_width();
     }
 
+    /// Sets the maximum number of visible rows. Zero removes the limit.
+    pub fn set_max_rows(&mut self, rows: i32) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.max_rows = rows;
+        }
+        self.ensure_container_width();
+    }
+
     pub fn set_width_details(&mut self, w: i32) {
         {
             let mut state = self.state.borrow_mut();
@@ -376,7 +443,6 @@ impl Popupmenu {
         let scrolled_list = self.scrolled_list.clone();
         let fg = self.colors.fg.unwrap_or(hl_defs.default_fg).clone();
         let fg_sel = self.colors.sel_fg.unwrap_or(hl_defs.default_fg).clone();
-        let font_height = self.font.height as f64;
         let list = self.list.clone();
         let info_label = self.info_label.clone();
         let info_shown = self.info_shown;
@@ -389,8 +455,7 @@ impl Popupmenu {
                 prev.menu.set_visible(false);
 
                 // Update the `kind` icon with default fg color.
-                let buf = get_icon_pixbuf(&prev.item.kind, &fg, font_height);
-                prev.kind.set_from_pixbuf(&buf);
+                prev.set_kind_fg(&fg);
             }
 
             state.selected = item_num;
@@ -443,9 +508,7 @@ impl Popupmenu {
                 }
 
                 // Update the `kind` icon with "selected" fg color.
-                let buf =
-                    get_icon_pixbuf(&item.item.kind, &fg_sel, font_height);
-                item.kind.set_from_pixbuf(&buf);
+                item.set_kind_fg(&fg_sel);
                 let newline =
                     if item.item.menu.len() > 0 && item.item.info.len() > 0 {
                         "\n"
@@ -586,6 +649,13 @@ impl Popupmenu {
     }
 }
 
+/// Returns the height of a single row in `list`, if there is any rows.
+fn get_row_height(list: &gtk::ListBox) -> Option<i32> {
+    list.get_row_at_index(0)
+        .map(|row| row.get_preferred_height().1)
+        .filter(|height| *height > 0)
+}
+
 fn ensure_row_visible(list: &gtk::ListBox, row: &gtk::ListBoxRow) {
     if let Some(adj) = list.get_adjustment() {
         let alloc = row.get_allocation();
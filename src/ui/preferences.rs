@@ -0,0 +1,489 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use gio;
+use gio::SettingsExt;
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::neovim::Neovim;
+use neovim_lib::{NeovimApiAsync, Value};
+
+use thread_guard::ThreadGuard;
+use ui::cell_width;
+use ui::font::Font;
+use ui::placement::Placement;
+
+const SCHEMA_ID: &str = "com.github.vhakulinen.gnvim";
+
+/// Returns gnvim's settings, or `None` if our schema is not installed (e.g.
+/// when gnvim is run from the source tree).
+pub fn settings() -> Option<gio::Settings> {
+    let source = gio::SettingsSchemaSource::get_default()?;
+    source.lookup(SCHEMA_ID, true)?;
+
+    Some(gio::Settings::new(SCHEMA_ID))
+}
+
+/// Returns the font from the settings, in the `guifont` format.
+pub fn default_guifont() -> Option<String> {
+    settings()
+        .and_then(|settings| settings.get_string("font"))
+        .map(|font| font.to_string())
+        .filter(|font| !font.is_empty())
+}
+
+/// Returns the cell widths from the settings, as a vim list in the format of
+/// `setcellwidths()`.
+pub fn default_cell_widths() -> Option<String> {
+    let widths = settings()
+        .and_then(|settings| settings.get_string("cell-widths"))
+        .map(|widths| widths.to_string())
+        .filter(|widths| !widths.is_empty())?;
+
+    match cell_width::parse_overrides(&widths) {
+        Ok(overrides) => Some(cell_width::overrides_to_vim(&overrides)),
+        Err(err) => {
+            eprintln!("Invalid cell widths in the preferences: {}", err);
+            None
+        }
+    }
+}
+
+/// Returns if the scroll animation (`g:gnvim_scroll_restore`) is enabled in
+/// the settings.
+pub fn default_scroll_restore() -> bool {
+    settings()
+        .map(|settings| settings.get_boolean("scroll-restore"))
+        .unwrap_or(false)
+}
+
+/// Returns if the zoom shortcuts are enabled in `settings`. They are when
+/// there are no settings.
+pub fn zoom_shortcuts(settings: Option<&gio::Settings>) -> bool {
+    settings.map_or(true, |settings| settings.get_boolean("zoom-shortcuts"))
+}
+
+/// Returns where new windows are placed, from the settings.
+pub fn default_placement() -> Option<Placement> {
+    let placement = settings()
+        .and_then(|settings| settings.get_string("window-placement"))
+        .map(|placement| placement.to_string())?;
+
+    match placement.parse() {
+        Ok(placement) => Some(placement),
+        Err(err) => {
+            eprintln!("Invalid window placement in the preferences: {}", err);
+            None
+        }
+    }
+}
+
+/// Writes `value` to `key`, if it differs from the current value.
+pub fn store_bool(settings: Option<&gio::Settings>, key: &str, value: bool) {
+    if let Some(settings) = settings {
+        if settings.get_boolean(key) != value {
+            settings.set_boolean(key, value);
+        }
+    }
+}
+
+/// Writes `value` to `key`, if it differs from the current value.
+pub fn store_uint(settings: Option<&gio::Settings>, key: &str, value: u32) {
+    if let Some(settings) = settings {
+        if settings.get_uint(key) != value {
+            settings.set_uint(key, value);
+        }
+    }
+}
+
+/// Writes `value` to `key`, if it differs from the current value.
+pub fn store_string(settings: Option<&gio::Settings>, key: &str, value: &str) {
+    if let Some(settings) = settings {
+        let current = settings.get_string(key).map(|s| s.to_string());
+        if current.as_ref().map(|s| s.as_str()) != Some(value) {
+            settings.set_string(key, value);
+        }
+    }
+}
+
+/// Applies the changes to `settings` (e.g. from the preferences window)
+/// live.
+///
+/// The settings are gnvim's defaults: values set from nvim (`guifont` or the
+/// gnvim#* functions) take precedence. Those values are written back to the
+/// settings (see `store_*`), so that the settings always reflect what is in
+/// use. `guifont` is the current `guifont`, used to tell if a change to the
+/// font came from nvim in the first place.
+pub fn connect_changed(
+    settings: &gio::Settings,
+    window: gtk::ApplicationWindow,
+    nvim: Arc<Mutex<Neovim>>,
+    guifont: Arc<ThreadGuard<String>>,
+    max_fps: Arc<AtomicUsize>,
+) {
+    settings.connect_changed(move |settings, key| {
+        let cmd = match key {
+            "font" => {
+                let font = settings
+                    .get_string(key)
+                    .map(|s| s.to_string())
+                    .unwrap_or_default();
+                if font.is_empty() || font == *guifont.borrow() {
+                    return;
+                }
+
+                let mut nvim = nvim.lock().unwrap();
+                nvim.set_option_async("guifont", Value::from(font))
+                    .cb(|res| {
+                        if let Err(err) = res {
+                            eprintln!("Failed to set guifont: {}", err);
+                        }
+                    })
+                    .call();
+                return;
+            }
+            "cell-widths" => {
+                let widths = settings
+                    .get_string(key)
+                    .map(|s| s.to_string())
+                    .unwrap_or_default();
+                let overrides = match cell_width::parse_overrides(&widths) {
+                    Ok(overrides) => overrides,
+                    Err(err) => {
+                        eprintln!("Invalid cell widths: {}", err);
+                        return;
+                    }
+                };
+
+                let mut nvim = nvim.lock().unwrap();
+                nvim.call_function_async(
+                    "gnvim#cell_widths#set",
+                    vec![cell_width::overrides_to_value(&overrides)],
+                )
+                .cb(|res| {
+                    if let Err(err) = res {
+                        eprintln!("Failed to set the cell widths: {}", err);
+                    }
+                })
+                .call();
+                return;
+            }
+            "max-fps" => {
+                let fps = settings.get_uint(key);
+                max_fps.store(fps as usize, Ordering::SeqCst);
+                return;
+            }
+            "opacity" => {
+                window.set_opacity(settings.get_double(key));
+                return;
+            }
+            // The rest are handled through nvim, so nvim stays up to
+            // date too.
+            "rounded-selection" => format!(
+                "call gnvim#grid#set_rounded_selection({})",
+                settings.get_boolean(key) as u8
+            ),
+            "ghost-text" => format!(
+                "call gnvim#popupmenu#set_ghost_text({})",
+                settings.get_boolean(key) as u8
+            ),
+            "cursor-beacon" => format!(
+                "call gnvim#cursor#set_beacon({})",
+                settings.get_uint(key)
+            ),
+            "cursor-mode-flash" => format!(
+                "call gnvim#cursor#set_mode_flash({})",
+                settings.get_boolean(key) as u8
+            ),
+            "scroll-restore" => format!(
+                "let g:gnvim_scroll_restore = {}",
+                settings.get_boolean(key) as u8
+            ),
+            _ => return,
+        };
+
+        let mut nvim = nvim.lock().unwrap();
+        nvim.command_async(&cmd)
+            .cb(|res| {
+                if let Err(err) = res {
+                    eprintln!("Failed to apply a setting: {}", err);
+                }
+            })
+            .call();
+    });
+}
+
+/// Preferences window, which edits our settings.
+pub struct Preferences {
+    win: gtk::Window,
+    settings: gio::Settings,
+
+    font: gtk::FontButton,
+    rounded_selection: gtk::CheckButton,
+    ghost_text: gtk::CheckButton,
+    max_fps: gtk::SpinButton,
+    cell_widths: gtk::Entry,
+    placement: gtk::ComboBoxText,
+    cursor_beacon: gtk::SpinButton,
+    cursor_mode_flash: gtk::CheckButton,
+    scroll_restore: gtk::CheckButton,
+    opacity: gtk::SpinButton,
+    zoom_shortcuts: gtk::CheckButton,
+}
+
+impl Preferences {
+    pub fn new(
+        parent: &gtk::ApplicationWindow,
+        settings: gio::Settings,
+    ) -> Self {
+        let win = gtk::Window::new(gtk::WindowType::Toplevel);
+        win.set_title("Gnvim Preferences");
+        win.set_transient_for(Some(parent));
+        win.set_resizable(false);
+
+        let font = gtk::FontButton::new();
+        let rounded_selection =
+            gtk::CheckButton::new_with_label("Rounded selection");
+        let ghost_text =
+            gtk::CheckButton::new_with_label("Completion ghost text");
+        let max_fps = gtk::SpinButton::new_with_range(0.0, 240.0, 1.0);
+        let cell_widths = gtk::Entry::new();
+        cell_widths.set_placeholder_text("e.g. e000-f8ff:2, 1f600:1");
+        cell_widths.set_tooltip_text(
+            "Widths (in cells) of characters, e.g. of the icons of an icon \
+             font. Passed to neovim's setcellwidths().",
+        );
+
+        let cursor_beacon = gtk::SpinButton::new_with_range(0.0, 100.0, 1.0);
+        cursor_beacon.set_tooltip_text(
+            "Minimum jump of the cursor, in rows, that shows a beacon at the \
+             cursor.",
+        );
+        let cursor_mode_flash =
+            gtk::CheckButton::new_with_label("Flash the cursor on mode change");
+        let scroll_restore = gtk::CheckButton::new_with_label(
+            "Animate the view when the buffer is switched",
+        );
+        let opacity = gtk::SpinButton::new_with_range(0.1, 1.0, 0.05);
+        opacity.set_digits(2);
+        let zoom_shortcuts = gtk::CheckButton::new_with_label(
+            "Zoom shortcuts (ctrl and +, - or 0)",
+        );
+
+        let placement = gtk::ComboBoxText::new();
+        placement.append(Some("restore"), "Where it was last time");
+        placement.append(Some("pointer"), "At the pointer");
+        placement.append(Some("center"), "Centered on the current monitor");
+        placement.append(Some("project"), "Where the project's window was");
+
+        let grid = gtk::Grid::new();
+        grid.set_border_width(12);
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(12);
+
+        let font_label = gtk::Label::new("Font");
+        font_label.set_halign(gtk::Align::Start);
+        let max_fps_label = gtk::Label::new("Frame rate cap (0 for none)");
+        max_fps_label.set_halign(gtk::Align::Start);
+        let cell_widths_label = gtk::Label::new("Cell widths");
+        cell_widths_label.set_halign(gtk::Align::Start);
+        let placement_label = gtk::Label::new("New windows open");
+        placement_label.set_halign(gtk::Align::Start);
+        let cursor_beacon_label = gtk::Label::new("Cursor beacon (0 for none)");
+        cursor_beacon_label.set_halign(gtk::Align::Start);
+        let opacity_label = gtk::Label::new("Window opacity");
+        opacity_label.set_halign(gtk::Align::Start);
+
+        grid.attach(&font_label, 0, 0, 1, 1);
+        grid.attach(&font, 1, 0, 1, 1);
+        grid.attach(&max_fps_label, 0, 1, 1, 1);
+        grid.attach(&max_fps, 1, 1, 1, 1);
+        grid.attach(&cell_widths_label, 0, 2, 1, 1);
+        grid.attach(&cell_widths, 1, 2, 1, 1);
+        grid.attach(&placement_label, 0, 3, 1, 1);
+        grid.attach(&placement, 1, 3, 1, 1);
+        grid.attach(&cursor_beacon_label, 0, 4, 1, 1);
+        grid.attach(&cursor_beacon, 1, 4, 1, 1);
+        grid.attach(&opacity_label, 0, 5, 1, 1);
+        grid.attach(&opacity, 1, 5, 1, 1);
+        grid.attach(&rounded_selection, 0, 6, 2, 1);
+        grid.attach(&ghost_text, 0, 7, 2, 1);
+        grid.attach(&cursor_mode_flash, 0, 8, 2, 1);
+        grid.attach(&scroll_restore, 0, 9, 2, 1);
+        grid.attach(&zoom_shortcuts, 0, 10, 2, 1);
+        win.add(&grid);
+
+        // Keep the window around, so that it can be shown again.
+        win.connect_delete_event(|win, _| {
+            win.hide();
+            Inhibit(true)
+        });
+
+        let settings_ref = settings.clone();
+        font.connect_font_set(move |font| {
+            let name = font.get_font_name().map(|s| s.to_string());
+            if let Some(font) = name.and_then(|n| Font::from_font_name(&n).ok())
+            {
+                settings_ref.set_string("font", &font.as_guifont());
+            }
+        });
+
+        let settings_ref = settings.clone();
+        rounded_selection.connect_toggled(move |button| {
+            store_bool(
+                Some(&settings_ref),
+                "rounded-selection",
+                button.get_active(),
+            );
+        });
+
+        let settings_ref = settings.clone();
+        ghost_text.connect_toggled(move |button| {
+            store_bool(Some(&settings_ref), "ghost-text", button.get_active());
+        });
+
+        let settings_ref = settings.clone();
+        max_fps.connect_value_changed(move |spin| {
+            store_uint(
+                Some(&settings_ref),
+                "max-fps",
+                spin.get_value_as_int() as u32,
+            );
+        });
+
+        let settings_ref = settings.clone();
+        cursor_beacon.connect_value_changed(move |spin| {
+            store_uint(
+                Some(&settings_ref),
+                "cursor-beacon",
+                spin.get_value_as_int() as u32,
+            );
+        });
+
+        let settings_ref = settings.clone();
+        cursor_mode_flash.connect_toggled(move |button| {
+            store_bool(
+                Some(&settings_ref),
+                "cursor-mode-flash",
+                button.get_active(),
+            );
+        });
+
+        let settings_ref = settings.clone();
+        scroll_restore.connect_toggled(move |button| {
+            store_bool(
+                Some(&settings_ref),
+                "scroll-restore",
+                button.get_active(),
+            );
+        });
+
+        let settings_ref = settings.clone();
+        opacity.connect_value_changed(move |spin| {
+            if settings_ref.get_double("opacity") != spin.get_value() {
+                settings_ref.set_double("opacity", spin.get_value());
+            }
+        });
+
+        let settings_ref = settings.clone();
+        zoom_shortcuts.connect_toggled(move |button| {
+            store_bool(
+                Some(&settings_ref),
+                "zoom-shortcuts",
+                button.get_active(),
+            );
+        });
+
+        let settings_ref = settings.clone();
+        placement.connect_changed(move |combo| {
+            if let Some(id) = combo.get_active_id() {
+                store_string(Some(&settings_ref), "window-placement", &id);
+            }
+        });
+
+        // Stored once the editing is done, since a half written value is
+        // likely invalid.
+        let settings_ref = settings.clone();
+        cell_widths.connect_activate(move |entry| {
+            store_cell_widths(&settings_ref, entry);
+        });
+        let settings_ref = settings.clone();
+        cell_widths.connect_focus_out_event(move |entry, _| {
+            store_cell_widths(&settings_ref, entry);
+            Inhibit(false)
+        });
+
+        Preferences {
+            win,
+            settings,
+            font,
+            rounded_selection,
+            ghost_text,
+            max_fps,
+            cell_widths,
+            placement,
+            cursor_beacon,
+            cursor_mode_flash,
+            scroll_restore,
+            opacity,
+            zoom_shortcuts,
+        }
+    }
+
+    /// Shows the window with the current settings.
+    pub fn show(&self) {
+        let settings = &self.settings;
+
+        let font = settings
+            .get_string("font")
+            .and_then(|font| Font::from_guifont(&font).ok())
+            .unwrap_or_default();
+        self.font.set_font_name(&font.as_font_name());
+        self.rounded_selection
+            .set_active(settings.get_boolean("rounded-selection"));
+        self.ghost_text
+            .set_active(settings.get_boolean("ghost-text"));
+        self.max_fps.set_value(settings.get_uint("max-fps") as f64);
+        let cell_widths = settings
+            .get_string("cell-widths")
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        self.cell_widths.set_text(&cell_widths);
+        let placement = settings
+            .get_string("window-placement")
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        self.placement.set_active_id(Some(placement.as_str()));
+        self.cursor_beacon
+            .set_value(settings.get_uint("cursor-beacon") as f64);
+        self.cursor_mode_flash
+            .set_active(settings.get_boolean("cursor-mode-flash"));
+        self.scroll_restore
+            .set_active(settings.get_boolean("scroll-restore"));
+        self.opacity.set_value(settings.get_double("opacity"));
+        self.zoom_shortcuts
+            .set_active(settings.get_boolean("zoom-shortcuts"));
+
+        self.win.show_all();
+        self.win.present();
+    }
+}
+
+/// Writes the cell widths from `entry` to `settings`, if they're valid.
+/// Invalid ones are marked as an error.
+fn store_cell_widths(settings: &gio::Settings, entry: &gtk::Entry) {
+    let text = entry.get_text().unwrap_or_default();
+    let valid = cell_width::parse_overrides(&text).is_ok();
+
+    if let Some(style) = entry.get_style_context() {
+        if valid {
+            style.remove_class("error");
+        } else {
+            style.add_class("error");
+        }
+    }
+
+    if valid {
+        store_string(Some(settings), "cell-widths", text.trim());
+    }
+}
@@ -0,0 +1,329 @@
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+
+use gtk;
+use gtk::prelude::*;
+use pango;
+use pango::LayoutExt;
+use pangocairo;
+
+use nvim_bridge::PrintJob;
+use ui::color::{Color, Highlight};
+
+/// Space between the header and the text, in lines.
+const HEADER_SPACING: f64 = 1.0;
+/// Space between the line numbers and the text, in characters.
+const GUTTER_SPACING: usize = 2;
+
+/// Prints `job` with `font`, either through the print dialog or by writing
+/// it straight to the job's PDF file.
+pub fn print(
+    parent: &gtk::Window,
+    job: PrintJob,
+    font: pango::FontDescription,
+) -> Result<(), String> {
+    let op = gtk::PrintOperation::new();
+    op.set_job_name(&job.title);
+    op.set_embed_page_setup(true);
+
+    let export = !job.pdf.is_empty();
+    if export {
+        op.set_export_filename(&job.pdf);
+    } else {
+        op.set_allow_async(true);
+    }
+
+    let job = Rc::new(job);
+    // Lines on each page, updated in begin-print.
+    let pages: Rc<RefCell<Vec<Range<usize>>>> = Rc::new(RefCell::new(vec![]));
+
+    let job_ref = job.clone();
+    let pages_ref = pages.clone();
+    let font_ref = font.clone();
+    op.connect_begin_print(move |op, ctx| {
+        let (header, _) = header_layouts(ctx, &job_ref, &font_ref, 1, 1);
+        let line_height = line_height(ctx, &font_ref);
+        let page_height = ctx.get_height()
+            - layout_height(&header)
+            - line_height * HEADER_SPACING;
+
+        let width = text_width(ctx, &job_ref, &font_ref);
+        let heights: Vec<f64> = (0..job_ref.lines.len())
+            .map(|i| {
+                layout_height(&line_layout(ctx, &job_ref, &font_ref, i, width))
+            })
+            .collect();
+
+        let mut pages = pages_ref.borrow_mut();
+        *pages = paginate(&heights, page_height);
+        op.set_n_pages(pages.len().max(1) as i32);
+    });
+
+    op.connect_draw_page(move |_, ctx, page| {
+        let pages = pages.borrow();
+        let n_pages = pages.len().max(1);
+        let lines = pages.get(page as usize).cloned().unwrap_or(0..0);
+        draw_page(ctx, &job, &font, lines, page as usize + 1, n_pages);
+    });
+
+    let action = if export {
+        gtk::PrintOperationAction::Export
+    } else {
+        gtk::PrintOperationAction::PrintDialog
+    };
+
+    op.run(action, Some(parent))
+        .map(|_| ())
+        .map_err(|err| format!("Failed to print: {}", err))
+}
+
+/// Splits lines of `heights` to pages of `page_height`. Returns the range
+/// of lines on each page. Lines that are taller than a page get a page of
+/// their own.
+fn paginate(heights: &[f64], page_height: f64) -> Vec<Range<usize>> {
+    let mut pages = vec![];
+    let mut start = 0;
+    let mut y = 0.0;
+
+    for (i, &height) in heights.iter().enumerate() {
+        if y + height > page_height && i > start {
+            pages.push(start..i);
+            start = i;
+            y = 0.0;
+        }
+        y += height;
+    }
+
+    if start < heights.len() {
+        pages.push(start..heights.len());
+    }
+
+    pages
+}
+
+fn draw_page(
+    ctx: &gtk::PrintContext,
+    job: &PrintJob,
+    font: &pango::FontDescription,
+    lines: Range<usize>,
+    page: usize,
+    n_pages: usize,
+) {
+    let cr = match ctx.get_cairo_context() {
+        Some(cr) => cr,
+        None => return,
+    };
+
+    let normal = &job.hls[0];
+    let (fg, bg) = colors(normal, normal);
+
+    // Page background, so that the colors look like they do in nvim.
+    cr.set_source_rgb(bg.r, bg.g, bg.b);
+    cr.rectangle(0.0, 0.0, ctx.get_width(), ctx.get_height());
+    cr.fill();
+
+    // Header: the title on the left and the page on the right, with a
+    // rule under them.
+    let (title, number) = header_layouts(ctx, job, font, page, n_pages);
+    let header_height = layout_height(&title);
+    cr.set_source_rgb(fg.r, fg.g, fg.b);
+    cr.move_to(0.0, 0.0);
+    pangocairo::functions::show_layout(&cr, &title);
+    let (number_width, _) = number.get_pixel_size();
+    cr.move_to(ctx.get_width() - number_width as f64, 0.0);
+    pangocairo::functions::show_layout(&cr, &number);
+
+    let rule_y = header_height + line_height(ctx, font) * HEADER_SPACING / 2.0;
+    cr.set_line_width(0.5);
+    cr.move_to(0.0, rule_y);
+    cr.line_to(ctx.get_width(), rule_y);
+    cr.stroke();
+
+    let width = text_width(ctx, job, font);
+    let text_x = ctx.get_width() - width;
+    let mut y = header_height + line_height(ctx, font) * HEADER_SPACING;
+
+    for i in lines {
+        let layout = line_layout(ctx, job, font, i, width);
+
+        if let Some(first_lnum) = job.first_lnum {
+            let (fg, _) = colors(&job.hls[1], normal);
+            let lnum =
+                simple_layout(ctx, font, &(first_lnum + i as u64).to_string());
+            let (w, _) = lnum.get_pixel_size();
+            cr.set_source_rgb(fg.r, fg.g, fg.b);
+            cr.move_to(
+                text_x
+                    - w as f64
+                    - char_width(ctx, font) * GUTTER_SPACING as f64,
+                y,
+            );
+            pangocairo::functions::show_layout(&cr, &lnum);
+        }
+
+        cr.set_source_rgb(fg.r, fg.g, fg.b);
+        cr.move_to(text_x, y);
+        pangocairo::functions::show_layout(&cr, &layout);
+
+        y += layout_height(&layout);
+    }
+}
+
+/// Returns the (fg, bg) of `hl`, falling back to `normal`'s colors.
+fn colors(hl: &Highlight, normal: &Highlight) -> (Color, Color) {
+    let fg = hl
+        .foreground
+        .or(normal.foreground)
+        .unwrap_or(Color::from_u64(0));
+    let bg = hl
+        .background
+        .or(normal.background)
+        .unwrap_or(Color::from_u64(0xffffff));
+
+    if hl.reverse {
+        (bg, fg)
+    } else {
+        (fg, bg)
+    }
+}
+
+fn simple_layout(
+    ctx: &gtk::PrintContext,
+    font: &pango::FontDescription,
+    text: &str,
+) -> pango::Layout {
+    let layout = ctx.create_pango_layout().unwrap();
+    layout.set_font_description(Some(font));
+    layout.set_text(text);
+    layout
+}
+
+/// Returns the layouts of the header's title and page number.
+fn header_layouts(
+    ctx: &gtk::PrintContext,
+    job: &PrintJob,
+    font: &pango::FontDescription,
+    page: usize,
+    n_pages: usize,
+) -> (pango::Layout, pango::Layout) {
+    let mut bold = font.clone();
+    bold.set_weight(pango::Weight::Bold);
+
+    (
+        simple_layout(ctx, &bold, &job.title),
+        simple_layout(ctx, font, &format!("{} / {}", page, n_pages)),
+    )
+}
+
+fn layout_height(layout: &pango::Layout) -> f64 {
+    layout.get_pixel_size().1 as f64
+}
+
+fn line_height(ctx: &gtk::PrintContext, font: &pango::FontDescription) -> f64 {
+    layout_height(&simple_layout(ctx, font, " "))
+}
+
+fn char_width(ctx: &gtk::PrintContext, font: &pango::FontDescription) -> f64 {
+    simple_layout(ctx, font, " ").get_pixel_size().0 as f64
+}
+
+/// Returns the width of the text area: the page without the line numbers.
+fn text_width(
+    ctx: &gtk::PrintContext,
+    job: &PrintJob,
+    font: &pango::FontDescription,
+) -> f64 {
+    match job.first_lnum {
+        Some(first_lnum) => {
+            let last = first_lnum + job.lines.len() as u64;
+            let cols = last.to_string().len() + GUTTER_SPACING;
+            ctx.get_width() - char_width(ctx, font) * cols as f64
+        }
+        None => ctx.get_width(),
+    }
+}
+
+/// Returns the layout of line `i` of `job`, with its highlights. Long lines
+/// are wrapped to `width`.
+fn line_layout(
+    ctx: &gtk::PrintContext,
+    job: &PrintJob,
+    font: &pango::FontDescription,
+    i: usize,
+    width: f64,
+) -> pango::Layout {
+    let normal = &job.hls[0];
+    let attrs = pango::AttrList::new();
+    let mut text = String::new();
+
+    for &(ref chunk, hl) in job.lines[i].iter() {
+        let start = text.len() as u32;
+        text.push_str(chunk);
+        let end = text.len() as u32;
+
+        let hl = &job.hls[hl];
+        let (fg, bg) = colors(hl, normal);
+        let (normal_fg, normal_bg) = colors(normal, normal);
+
+        let mut chunk_attrs = vec![];
+        if fg != normal_fg {
+            let (r, g, b) = to_u16(fg);
+            chunk_attrs.push(pango::Attribute::new_foreground(r, g, b));
+        }
+        if bg != normal_bg {
+            let (r, g, b) = to_u16(bg);
+            chunk_attrs.push(pango::Attribute::new_background(r, g, b));
+        }
+        if hl.bold {
+            chunk_attrs.push(pango::Attribute::new_weight(pango::Weight::Bold));
+        }
+        if hl.italic {
+            chunk_attrs.push(pango::Attribute::new_style(pango::Style::Italic));
+        }
+        if hl.underline {
+            chunk_attrs.push(pango::Attribute::new_underline(
+                pango::Underline::Single,
+            ));
+        }
+        if hl.undercurl {
+            chunk_attrs
+                .push(pango::Attribute::new_underline(pango::Underline::Error));
+        }
+
+        for mut attr in chunk_attrs.into_iter().filter_map(|attr| attr) {
+            attr.set_start_index(start);
+            attr.set_end_index(end);
+            attrs.insert(attr);
+        }
+    }
+
+    let layout = simple_layout(ctx, font, &text);
+    layout.set_attributes(Some(&attrs));
+    layout.set_width((width * pango::SCALE as f64) as i32);
+    layout.set_wrap(pango::WrapMode::Char);
+    layout
+}
+
+fn to_u16(color: Color) -> (u16, u16, u16) {
+    let c = |v: f64| (v * 65535.0).round() as u16;
+    (c(color.r), c(color.g), c(color.b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        assert_eq!(paginate(&[], 10.0), vec![]);
+        assert_eq!(paginate(&[2.0; 5], 10.0), vec![0..5]);
+        assert_eq!(paginate(&[2.0; 6], 10.0), vec![0..5, 5..6]);
+
+        // Wrapped lines take more room.
+        assert_eq!(paginate(&[2.0, 6.0, 4.0, 2.0], 10.0), vec![0..2, 2..4]);
+
+        // Lines taller than a page get their own page.
+        assert_eq!(paginate(&[2.0, 12.0, 2.0], 10.0), vec![0..1, 1..2, 2..3]);
+    }
+}
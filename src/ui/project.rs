@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use key_value;
+use ui::color::Color;
+
+/// Project's settings file, in the project's root.
+pub const SETTINGS_FILE: &str = ".gnvim.toml";
+/// Project's CSS, applied on top of the user's CSS.
+pub const CSS_FILE: &str = ".gnvim.css";
+
+/// Project local configuration files, which are only used if the project
+/// is trusted (see `trust::trust`).
+const FILES: [&str; 2] = [SETTINGS_FILE, CSS_FILE];
+
+/// Per project overrides of our settings, from a `.gnvim.toml` file.
+///
+/// Only a small subset of TOML is supported: `key = value` pairs, where
+/// the value is a string or an integer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectSettings {
+    /// Font size, in points.
+    pub font_size: Option<usize>,
+    /// Space around the grid, in pixels.
+    pub padding: Option<i32>,
+    /// Accent color, available to the user's CSS as `@gnvim_accent`.
+    pub accent_color: Option<Color>,
+}
+
+impl ProjectSettings {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read_to_string(path)
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        ProjectSettings::parse(&data)
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    fn parse(data: &str) -> Result<Self, String> {
+        let mut settings = ProjectSettings::default();
+
+        for (i, line) in data.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            // Tables aren't supported, but don't fail on their headers.
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+
+            let (key, value) = match key_value::split(line) {
+                Some(pair) => pair,
+                None => {
+                    return Err(format!("line {}: expected key = value", i + 1))
+                }
+            };
+
+            let err =
+                |what| format!("line {}: {} expected for {}", i + 1, what, key);
+            match key {
+                "font_size" => {
+                    settings.font_size =
+                        Some(value.parse().map_err(|_| err("integer"))?);
+                }
+                "padding" => {
+                    settings.padding =
+                        Some(value.parse().map_err(|_| err("integer"))?);
+                }
+                "accent_color" => {
+                    let hex =
+                        parse_string(value).ok_or_else(|| err("string"))?;
+                    settings.accent_color = Some(
+                        Color::from_hex_string(hex.to_string())
+                            .map_err(|_| err("color (e.g. \"#ff8000\")"))?,
+                    );
+                }
+                _ => println!("Unknown project setting: {}", key),
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Removes a `#` comment from the end of `line`, unless the `#` is in a
+/// string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// Parses a basic (double quoted) TOML string, without escapes.
+fn parse_string(value: &str) -> Option<&str> {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        Some(&value[1..value.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Finds the root of the project that `dir` is in: `dir` or its closest
+/// parent that has project local configuration.
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|dir| !files(dir).is_empty())
+        .map(Path::to_path_buf)
+}
+
+/// Returns the project that `dir` is in: the closest directory with project
+/// local configuration (see `find_root`), or with a git repository.
+/// Otherwise it's `dir` itself.
+pub fn project_dir(dir: &Path) -> PathBuf {
+    find_root(dir)
+        .or_else(|| {
+            dir.ancestors()
+                .find(|dir| dir.join(".git").exists())
+                .map(Path::to_path_buf)
+        })
+        .unwrap_or_else(|| PathBuf::from(dir))
+}
+
+/// Returns the project local configuration files that `root` has.
+pub fn files(root: &Path) -> Vec<&'static str> {
+    FILES
+        .iter()
+        .cloned()
+        .filter(|file| root.join(file).is_file())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let settings = ProjectSettings::parse(
+            "# Our project\n\
+             font_size = 14\n\
+             accent_color = \"#ff8000\" # orange\n\
+             \n\
+             [other]\n\
+             padding=4\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings,
+            ProjectSettings {
+                font_size: Some(14),
+                padding: Some(4),
+                accent_color: Some(Color::from_u64(0xff8000)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(ProjectSettings::parse("font_size = big").is_err());
+        assert!(ProjectSettings::parse("accent_color = ff8000").is_err());
+        assert!(ProjectSettings::parse("padding").is_err());
+    }
+}
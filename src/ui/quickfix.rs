@@ -0,0 +1,313 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::neovim::Neovim;
+use neovim_lib::NeovimApiAsync;
+use pango;
+
+use nvim_bridge::{QuickfixItem, QuickfixList};
+
+const HEIGHT: i32 = 200;
+
+/// Columns of our tree store.
+const COLUMN_ICON: u32 = 0;
+const COLUMN_LOCATION: u32 = 1;
+const COLUMN_TEXT: u32 = 2;
+/// Index of the item in the list, plus one. Zero for the file rows.
+const COLUMN_INDEX: u32 = 3;
+
+/// Severity of a quickfix item, from the item's type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+    Info,
+    None,
+}
+
+impl Severity {
+    fn from_kind(kind: &str) -> Self {
+        match kind {
+            "E" | "e" => Severity::Error,
+            "W" | "w" => Severity::Warning,
+            "I" | "i" | "N" | "n" => Severity::Info,
+            _ => Severity::None,
+        }
+    }
+
+    fn icon_name(self) -> &'static str {
+        match self {
+            Severity::Error => "dialog-error-symbolic",
+            Severity::Warning => "dialog-warning-symbolic",
+            Severity::Info => "dialog-information-symbolic",
+            Severity::None => "",
+        }
+    }
+}
+
+/// Groups `items` by their file, in the order in which the files first
+/// appear in the list. Items without a file (e.g. continuation lines) go
+/// with the item before them. Items which don't contain `filter` in their
+/// file or text (ignoring case) are left out. Returns the indexes of the
+/// items.
+fn group_by_file<'a>(
+    items: &'a [QuickfixItem],
+    filter: &str,
+) -> Vec<(&'a str, Vec<usize>)> {
+    let filter = filter.to_lowercase();
+    let mut groups: Vec<(&str, Vec<usize>)> = vec![];
+    let mut file = "";
+
+    for (i, item) in items.iter().enumerate() {
+        if !item.filename.is_empty() {
+            file = &item.filename;
+        }
+
+        if !filter.is_empty()
+            && !file.to_lowercase().contains(&filter)
+            && !item.text.to_lowercase().contains(&filter)
+        {
+            continue;
+        }
+
+        match groups.iter_mut().find(|(f, _)| *f == file) {
+            Some((_, indexes)) => indexes.push(i),
+            None => groups.push((file, vec![i])),
+        }
+    }
+
+    groups
+}
+
+/// Fills `store` with `list`'s items matching `filter`.
+fn fill(store: &gtk::TreeStore, list: &QuickfixList, filter: &str) {
+    store.clear();
+
+    let columns = [COLUMN_ICON, COLUMN_LOCATION, COLUMN_TEXT, COLUMN_INDEX];
+    for (file, indexes) in group_by_file(&list.items, filter) {
+        let location = format!("{} ({})", file, indexes.len());
+        let parent = store.insert_with_values(
+            None,
+            None,
+            &columns,
+            &[&"", &location, &"", &0u64],
+        );
+
+        for i in indexes {
+            let item = &list.items[i];
+            let location = match (item.lnum, item.col) {
+                (0, _) => String::new(),
+                (lnum, 0) => format!("{}", lnum),
+                (lnum, col) => format!("{}:{}", lnum, col),
+            };
+            let text = item.text.replace('\n', " ");
+
+            store.insert_with_values(
+                Some(&parent),
+                None,
+                &columns,
+                &[
+                    &Severity::from_kind(&item.kind).icon_name(),
+                    &location,
+                    &text,
+                    &(i as u64 + 1),
+                ],
+            );
+        }
+    }
+}
+
+/// Native panel for the quickfix (or location) list, shown below the
+/// grid. The items are grouped by their file, and activating an item jumps
+/// to it with `:cc` (or `:ll`).
+pub struct QuickfixPanel {
+    box_: gtk::Box,
+    title: gtk::Label,
+    search: gtk::SearchEntry,
+    store: gtk::TreeStore,
+    tree: gtk::TreeView,
+
+    list: Rc<RefCell<Option<QuickfixList>>>,
+}
+
+impl QuickfixPanel {
+    pub fn new(nvim: Arc<Mutex<Neovim>>) -> Self {
+        let title = gtk::Label::new(None);
+        title.set_halign(gtk::Align::Start);
+        title.set_ellipsize(pango::EllipsizeMode::End);
+
+        let search = gtk::SearchEntry::new();
+        search.set_placeholder_text("Filter");
+
+        let close = gtk::Button::new_from_icon_name(
+            "window-close-symbolic",
+            gtk::IconSize::Menu.into(),
+        );
+        close.set_relief(gtk::ReliefStyle::None);
+
+        let header = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        header.set_border_width(3);
+        header.pack_start(&title, true, true, 0);
+        header.pack_start(&search, false, false, 0);
+        header.pack_start(&close, false, false, 0);
+
+        let store = gtk::TreeStore::new(&[
+            gtk::Type::String,
+            gtk::Type::String,
+            gtk::Type::String,
+            gtk::Type::U64,
+        ]);
+
+        let tree = gtk::TreeView::new_with_model(&store);
+        tree.set_headers_visible(false);
+        tree.set_activate_on_single_click(true);
+
+        let column = gtk::TreeViewColumn::new();
+        let cell = gtk::CellRendererPixbuf::new();
+        column.pack_start(&cell, false);
+        column.add_attribute(&cell, "icon-name", COLUMN_ICON as i32);
+        let cell = gtk::CellRendererText::new();
+        column.pack_start(&cell, false);
+        column.add_attribute(&cell, "text", COLUMN_LOCATION as i32);
+        tree.append_column(&column);
+
+        let column = gtk::TreeViewColumn::new();
+        let cell = gtk::CellRendererText::new();
+        cell.set_property_ellipsize(pango::EllipsizeMode::End);
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", COLUMN_TEXT as i32);
+        tree.append_column(&column);
+
+        let scrolled = gtk::ScrolledWindow::new(None, None);
+        scrolled
+            .set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+        scrolled.set_size_request(-1, HEIGHT);
+        scrolled.add(&tree);
+
+        let box_ = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        gtk::WidgetExt::set_name(&box_, "gnvim-quickfix");
+        box_.pack_start(&header, false, false, 0);
+        box_.pack_start(&scrolled, true, true, 0);
+
+        let list: Rc<RefCell<Option<QuickfixList>>> =
+            Rc::new(RefCell::new(None));
+
+        let list_ref = list.clone();
+        tree.connect_row_activated(move |tree, path, _| {
+            let model = match tree.get_model() {
+                Some(model) => model,
+                None => return,
+            };
+            let index = model
+                .get_iter(path)
+                .and_then(|iter| {
+                    model.get_value(&iter, COLUMN_INDEX as i32).get::<u64>()
+                })
+                .unwrap_or(0);
+            if index == 0 {
+                // File row.
+                return;
+            }
+
+            let cmd = match *list_ref.borrow() {
+                Some(ref list) if list.loclist => format!("ll {}", index),
+                Some(_) => format!("cc {}", index),
+                None => return,
+            };
+
+            let mut nvim = nvim.lock().unwrap();
+            nvim.command_async(&cmd)
+                .cb(|res| {
+                    if let Err(err) = res {
+                        println!("Failed to jump to quickfix item: {}", err);
+                    }
+                })
+                .call();
+        });
+
+        let list_ref = list.clone();
+        let store_ref = store.clone();
+        let tree_ref = tree.clone();
+        search.connect_search_changed(move |search| {
+            if let Some(ref list) = *list_ref.borrow() {
+                let filter = search.get_text().unwrap_or_default();
+                fill(&store_ref, list, &filter);
+                tree_ref.expand_all();
+            }
+        });
+
+        let box_ref = box_.clone();
+        close.connect_clicked(move |_| box_ref.hide());
+
+        QuickfixPanel {
+            box_,
+            title,
+            search,
+            store,
+            tree,
+            list,
+        }
+    }
+
+    pub fn widget(&self) -> gtk::Widget {
+        self.box_.clone().upcast()
+    }
+
+    pub fn set_list(&self, list: QuickfixList) {
+        self.title.set_text(&match list.title.as_str() {
+            "" if list.loclist => "Location list",
+            "" => "Quickfix list",
+            title => title,
+        });
+
+        let filter = self.search.get_text().unwrap_or_default();
+        fill(&self.store, &list, &filter);
+        self.tree.expand_all();
+
+        *self.list.borrow_mut() = Some(list);
+    }
+
+    pub fn show(&self) {
+        self.box_.show_all();
+    }
+
+    pub fn hide(&self) {
+        self.box_.hide();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(filename: &str, text: &str) -> QuickfixItem {
+        QuickfixItem {
+            filename: filename.to_string(),
+            lnum: 1,
+            col: 1,
+            kind: String::from("E"),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_group_by_file() {
+        let items = vec![
+            item("a.rs", "first"),
+            item("b.rs", "second"),
+            item("", "second, continued"),
+            item("a.rs", "third"),
+        ];
+
+        assert_eq!(
+            group_by_file(&items, ""),
+            vec![("a.rs", vec![0, 3]), ("b.rs", vec![1, 2])]
+        );
+        assert_eq!(group_by_file(&items, "THIRD"), vec![("a.rs", vec![3])]);
+        assert_eq!(group_by_file(&items, "b.rs"), vec![("b.rs", vec![1, 2])]);
+        assert_eq!(group_by_file(&items, "nothing"), vec![]);
+    }
+}
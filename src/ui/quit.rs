@@ -0,0 +1,106 @@
+use gtk;
+use gtk::prelude::*;
+
+use neovim_lib::neovim::Neovim;
+use neovim_lib::{NeovimApiAsync, Value};
+
+/// Dialog responses. Positive response ids are free for applications to
+/// use.
+const RESPONSE_SAVE: i32 = 1;
+const RESPONSE_DISCARD: i32 = 2;
+const RESPONSE_CANCEL: i32 = 3;
+
+/// Gets the names of the modified buffers in nvim, without blocking. `f` is
+/// called with the names once nvim responds. Note that `f` is called from
+/// nvim's event loop thread, not from the main thread.
+pub fn modified_buffers<F>(nvim: &mut Neovim, f: F)
+where
+    F: FnOnce(Result<Vec<String>, String>) + Send + 'static,
+{
+    let opts = Value::from(vec![(Value::from("bufmodified"), Value::from(1))]);
+    nvim.call_function_async("getbufinfo", vec![opts])
+        .cb(move |res| {
+            f(res
+                .map_err(|err| err.to_string())
+                .and_then(|bufs| buffer_names(&bufs)))
+        })
+        .call();
+}
+
+fn buffer_names(bufs: &Value) -> Result<Vec<String>, String> {
+    let bufs = bufs.as_array().ok_or("Invalid buffer info")?;
+    let names = bufs
+        .iter()
+        .filter_map(|buf| buf.as_map())
+        .map(|buf| {
+            buf.iter()
+                .find(|(key, _)| key.as_str() == Some("name"))
+                .and_then(|(_, name)| name.as_str())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("[No Name]")
+                .to_string()
+        })
+        .collect();
+
+    Ok(names)
+}
+
+/// Asks the user what to do with the modified `buffers` before quitting.
+/// `buffers` is an error if we couldn't get the modified buffers from nvim.
+/// Once the user responds, `f` is called with the nvim command to quit with,
+/// unless the user canceled.
+///
+/// The dialog doesn't run a main loop of its own (e.g. `gtk::Dialog::run`),
+/// so we keep rendering and handling nvim's events while it is open.
+pub fn confirm<F>(
+    parent: &gtk::ApplicationWindow,
+    buffers: &Result<Vec<String>, String>,
+    f: F,
+) where
+    F: Fn(&'static str) + 'static,
+{
+    let (msg, details) = match buffers {
+        Ok(buffers) => (
+            "Save changes before closing?",
+            format!(
+                "The following buffers have unsaved changes:\n\n{}",
+                buffers.join("\n")
+            ),
+        ),
+        Err(err) => (
+            "Quit without checking for unsaved changes?",
+            format!("Failed to get the modified buffers from nvim: {}", err),
+        ),
+    };
+
+    let dialog = gtk::MessageDialog::new(
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        msg,
+    );
+    dialog.set_property_secondary_text(Some(details.as_str()));
+
+    dialog.add_button("Cancel", RESPONSE_CANCEL);
+    if buffers.is_ok() {
+        dialog.add_button("Discard", RESPONSE_DISCARD);
+        dialog.add_button("Save All", RESPONSE_SAVE);
+        dialog.set_default_response(RESPONSE_SAVE);
+    } else {
+        dialog.add_button("Quit Anyway", RESPONSE_DISCARD);
+        dialog.set_default_response(RESPONSE_CANCEL);
+    }
+
+    dialog.connect_response(move |dialog, res| {
+        dialog.destroy();
+
+        match res {
+            RESPONSE_SAVE => f("wall | qall"),
+            RESPONSE_DISCARD => f("qall!"),
+            _ => {}
+        }
+    });
+
+    dialog.show_all();
+}
@@ -0,0 +1,57 @@
+use gtk;
+use gtk::prelude::*;
+
+/// Indicator in the bottom left corner of the window that is shown while a
+/// macro is being recorded, with the register it is recorded to.
+pub struct RecordingIndicator {
+    label: gtk::Label,
+}
+
+impl RecordingIndicator {
+    pub fn new(parent: &gtk::Overlay) -> Self {
+        let css_provider = gtk::CssProvider::new();
+
+        let label = gtk::Label::new(None);
+        label.set_halign(gtk::Align::Start);
+        label.set_valign(gtk::Align::End);
+        label.set_margin_start(12);
+        label.set_margin_bottom(12);
+        label.set_no_show_all(true);
+
+        add_css_provider!(&css_provider, label);
+        let css = if gtk::get_minor_version() < 20 {
+            "GtkLabel {
+                padding: 2px 8px;
+                font-weight: bold;
+                color: #ffffff;
+                background-color: rgba(204, 0, 0, 0.85);
+                border-radius: 4px;
+            }"
+        } else {
+            "label {
+                padding: 2px 8px;
+                font-weight: bold;
+                color: #ffffff;
+                background-color: rgba(204, 0, 0, 0.85);
+                border-radius: 4px;
+            }"
+        };
+        CssProviderExt::load_from_data(&css_provider, css.as_bytes()).unwrap();
+
+        parent.add_overlay(&label);
+        parent.set_overlay_pass_through(&label, true);
+
+        RecordingIndicator { label }
+    }
+
+    /// Shows that a macro is being recorded to `register`. Empty register
+    /// hides the indicator.
+    pub fn set(&self, register: &str) {
+        if register.is_empty() {
+            self.label.hide();
+        } else {
+            self.label.set_text(&format!("● REC @{}", register));
+            self.label.show();
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::neovim::Neovim;
+use neovim_lib::{NeovimApiAsync, Value};
+use pango;
+
+use nvim_bridge::ReplacePreview;
+use ui::scroll_routing::ScrollArea;
+
+const WIDTH: i32 = 420;
+
+/// Preview and the check buttons of its matches.
+type Current = Option<(ReplacePreview, Vec<gtk::CheckButton>)>;
+
+/// Side panel that shows the preview of a search and replace (see
+/// `gnvim#replace#preview`), with a check button for each match. The checked
+/// matches are replaced with a single call to `gnvim#replace#apply`, so
+/// that the replacement is a single change (and undo step) in nvim.
+pub struct ReplacePanel {
+    frame: gtk::Frame,
+    title: gtk::Label,
+    scrolled: gtk::ScrolledWindow,
+    list: gtk::ListBox,
+    apply: gtk::Button,
+
+    current: Rc<RefCell<Current>>,
+}
+
+impl ReplacePanel {
+    pub fn new(parent: &gtk::Overlay, nvim: Arc<Mutex<Neovim>>) -> Self {
+        let title = gtk::Label::new(None);
+        title.set_halign(gtk::Align::Start);
+        title.set_ellipsize(pango::EllipsizeMode::End);
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::None);
+
+        let scrolled = gtk::ScrolledWindow::new(None, None);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.set_vexpand(true);
+        scrolled.add(&list);
+
+        let apply = gtk::Button::new_with_label("Replace");
+        let cancel = gtk::Button::new_with_label("Cancel");
+
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        buttons.pack_end(&apply, false, false, 0);
+        buttons.pack_end(&cancel, false, false, 0);
+
+        let box_ = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        box_.set_border_width(6);
+        box_.pack_start(&title, false, false, 0);
+        box_.pack_start(&scrolled, true, true, 0);
+        box_.pack_start(&buttons, false, false, 0);
+
+        let frame = gtk::Frame::new(None);
+        gtk::WidgetExt::set_name(&frame, "gnvim-replace-preview");
+        frame.set_halign(gtk::Align::End);
+        frame.set_size_request(WIDTH, -1);
+        frame.add(&box_);
+
+        parent.add_overlay(&frame);
+
+        let current: Rc<RefCell<Current>> = Rc::new(RefCell::new(None));
+
+        let current_ref = current.clone();
+        let frame_ref = frame.clone();
+        apply.connect_clicked(move |_| {
+            frame_ref.hide();
+
+            let (preview, checks) = match current_ref.borrow_mut().take() {
+                Some(current) => current,
+                None => return,
+            };
+
+            let lnums: Vec<Value> = preview
+                .matches
+                .iter()
+                .zip(checks.iter())
+                .filter(|(_, check)| check.get_active())
+                .map(|(m, _)| Value::from(m.lnum))
+                .collect();
+            if lnums.is_empty() {
+                return;
+            }
+
+            let mut nvim = nvim.lock().unwrap();
+            nvim.call_function_async(
+                "gnvim#replace#apply",
+                vec![
+                    Value::from(preview.bufnr),
+                    Value::from(preview.changedtick),
+                    Value::from(preview.pattern),
+                    Value::from(preview.replacement),
+                    Value::from(lnums),
+                ],
+            )
+            .cb(|res| {
+                if let Err(err) = res {
+                    println!("Failed to apply the replacement: {}", err);
+                }
+            })
+            .call();
+        });
+
+        let current_ref = current.clone();
+        let frame_ref = frame.clone();
+        cancel.connect_clicked(move |_| {
+            current_ref.borrow_mut().take();
+            frame_ref.hide();
+        });
+
+        ReplacePanel {
+            frame,
+            title,
+            scrolled,
+            list,
+            apply,
+            current,
+        }
+    }
+
+    pub fn show(&self, preview: ReplacePreview) {
+        for child in self.list.get_children() {
+            self.list.remove(&child);
+        }
+
+        let mut checks = vec![];
+        for m in preview.matches.iter() {
+            let label = gtk::Label::new(
+                format!("{}: {}", m.lnum, m.replaced.trim()).as_str(),
+            );
+            label.set_ellipsize(pango::EllipsizeMode::End);
+
+            let check = gtk::CheckButton::new();
+            check.add(&label);
+            check.set_active(true);
+            check.set_tooltip_text(
+                format!("- {}\n+ {}", m.line, m.replaced).as_str(),
+            );
+
+            self.list.add(&check);
+            checks.push(check);
+        }
+
+        self.title.set_text(&match preview.matches.len() {
+            0 => format!("No matches for {}", preview.pattern),
+            n => format!(
+                "Replace {} with {} ({} matches)",
+                preview.pattern, preview.replacement, n
+            ),
+        });
+        self.apply.set_sensitive(!checks.is_empty());
+
+        *self.current.borrow_mut() = Some((preview, checks));
+
+        self.frame.show_all();
+    }
+
+    /// Returns our area for routing the wheel events.
+    pub fn scroll_area(&self) -> ScrollArea {
+        ScrollArea::new(
+            self.frame.clone().upcast(),
+            vec![self.scrolled.clone()],
+        )
+    }
+
+    pub fn hide(&self) {
+        self.frame.hide();
+    }
+}
@@ -0,0 +1,198 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use gdk_pixbuf::{self, PixbufLoaderExt};
+use gtk;
+use gtk::prelude::*;
+
+use paths;
+use ui::trust::fnv1a;
+
+// Defines RUNTIME_FILES, the names (relative to the runtime directory) and
+// the contents of our runtime files. Generated by build.rs.
+include!(concat!(env!("OUT_DIR"), "/runtime_files.rs"));
+
+/// Our icon, for when it's not installed in the icon theme (e.g. when we're
+/// run from an AppImage or installed with cargo install). In all the sizes
+/// that we have, so that the window manager can pick a sharp one for any
+/// scale factor.
+const ICONS: &[&[u8]] = &[
+    include_bytes!("../../desktop/gnvim_16.png"),
+    include_bytes!("../../desktop/gnvim_24.png"),
+    include_bytes!("../../desktop/gnvim_32.png"),
+    include_bytes!("../../desktop/gnvim_48.png"),
+    include_bytes!("../../desktop/gnvim_64.png"),
+    include_bytes!("../../desktop/gnvim_128.png"),
+    include_bytes!("../../desktop/gnvim_256.png"),
+    include_bytes!("../../desktop/gnvim_512.png"),
+];
+
+/// Where `make install` puts the runtime files. Used if the embedded files
+/// can't be extracted.
+const INSTALLED_RUNTIME: &str = "/usr/local/share/gnvim/runtime";
+
+/// File in the extracted runtime directory. Written once the files are
+/// extracted, and touched every time the directory is used.
+const MARKER: &str = ".gnvim-runtime";
+
+/// Extracted runtime directories that haven't been used for this long are
+/// removed. Other gnvim versions (that might still be running) use their
+/// own directories.
+const MAX_UNUSED: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Returns the path of our runtime files, to be added to nvim's
+/// 'runtimepath'. That is `path` if it's given (e.g. `--gnvim-rtp`, when
+/// working on the runtime files), otherwise the runtime files embedded in
+/// the binary, extracted to our state directory. This way the runtime
+/// always matches the binary, and the binary can be run from anywhere.
+pub fn runtime_path(path: Option<&str>) -> String {
+    if let Some(path) = path {
+        return path.to_string();
+    }
+
+    match extract_runtime() {
+        Ok(dir) => dir.to_string_lossy().into_owned(),
+        Err(err) => {
+            eprintln!("Failed to extract the runtime files: {}", err);
+            INSTALLED_RUNTIME.to_string()
+        }
+    }
+}
+
+/// Sets our embedded icon as the windows' default icon, unless the icon
+/// theme has our icon (e.g. it's installed with `make install`).
+pub fn set_default_icon() {
+    let installed = gtk::IconTheme::get_default()
+        .map(|theme| theme.has_icon("gnvim"))
+        .unwrap_or(false);
+    if installed {
+        return;
+    }
+
+    let icons: Vec<gdk_pixbuf::Pixbuf> = ICONS
+        .iter()
+        .filter_map(|data| {
+            let loader = gdk_pixbuf::PixbufLoader::new();
+            loader.write(data).ok()?;
+            loader.close().ok()?;
+            loader.get_pixbuf()
+        })
+        .collect();
+
+    gtk::Window::set_default_icon_list(&icons);
+}
+
+/// Extracts the embedded runtime files to our state directory, unless
+/// they're already there. Each version of the files has its own directory,
+/// named by the hash of the files.
+fn extract_runtime() -> io::Result<PathBuf> {
+    let base = paths::state_dir().ok_or_else(paths::no_state_dir)?;
+
+    let dir = base.join(format!("runtime-{:016x}", hash(RUNTIME_FILES)));
+    if !dir.join(MARKER).is_file() {
+        write_files(&dir, RUNTIME_FILES)?;
+    }
+    // Marks the files as complete, or as used.
+    fs::write(dir.join(MARKER), "")?;
+
+    remove_unused(&base, &dir);
+
+    Ok(dir)
+}
+
+fn hash(files: &[(&str, &[u8])]) -> u64 {
+    let mut data = vec![];
+    for (name, content) in files {
+        data.extend_from_slice(name.as_bytes());
+        data.push(0);
+        data.extend_from_slice(content);
+        data.push(0);
+    }
+
+    fnv1a(&data)
+}
+
+fn write_files(dir: &Path, files: &[(&str, &[u8])]) -> io::Result<()> {
+    for (name, content) in files {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+
+    Ok(())
+}
+
+/// Removes the extracted runtime directories in `base` (other than
+/// `current`) that haven't been used in a while.
+fn remove_unused(base: &Path, current: &Path) {
+    let entries = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let dir = entry.path();
+        let is_runtime =
+            entry.file_name().to_string_lossy().starts_with("runtime-");
+        if !is_runtime || dir == current {
+            continue;
+        }
+
+        let unused = fs::metadata(dir.join(MARKER))
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| SystemTime::now().duration_since(time).ok())
+            .map(|age| age > MAX_UNUSED)
+            // Incomplete extraction, or not ours.
+            .unwrap_or(false);
+        if unused {
+            if let Err(err) = fs::remove_dir_all(&dir) {
+                eprintln!("Failed to remove {}: {}", dir.display(), err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_runtime_files() {
+        let names: Vec<&str> = RUNTIME_FILES.iter().map(|(n, _)| *n).collect();
+        assert!(names.contains(&"plugin/gnvim.vim"));
+        assert!(names.contains(&"doc/tags"));
+        assert!(names.iter().all(|name| !name.contains('\\')));
+    }
+
+    #[test]
+    fn test_hash() {
+        let a: &[(&str, &[u8])] = &[("a", b"bc")];
+        let b: &[(&str, &[u8])] = &[("a", b"bc")];
+        let c: &[(&str, &[u8])] = &[("ab", b"c")];
+        let d: &[(&str, &[u8])] = &[("a", b"bd")];
+        assert_eq!(hash(a), hash(b));
+        assert_ne!(hash(a), hash(c));
+        assert_ne!(hash(a), hash(d));
+    }
+
+    #[test]
+    fn test_write_files() {
+        let dir = env::temp_dir()
+            .join(format!("gnvim-test-runtime-{}", ::std::process::id()));
+        let files: &[(&str, &[u8])] =
+            &[("plugin/a.vim", b"a"), ("autoload/gnvim/b.vim", b"b")];
+
+        write_files(&dir, files).unwrap();
+        assert_eq!(fs::read(dir.join("plugin/a.vim")).unwrap(), b"a");
+        assert_eq!(fs::read(dir.join("autoload/gnvim/b.vim")).unwrap(), b"b");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
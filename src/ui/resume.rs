@@ -0,0 +1,70 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+use gdk;
+use gdk::prelude::*;
+use gtk;
+
+/// How often (in milliseconds) we check if the system has been suspended.
+const CHECK_INTERVAL: u32 = 2000;
+
+/// How much more the wall clock has to advance than the monotonic clock
+/// between our checks, for the system to have been suspended. Leaves room
+/// for small adjustments of the wall clock (e.g. by NTP).
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// Calls `f` when the system resumes from a suspend, and when the
+/// compositor changes (e.g. it's restarted). After both, our grids'
+/// surfaces might have lost their content (e.g. with GPU drivers that
+/// don't preserve the memory over a suspend), and the window stays black
+/// or corrupted until it's redrawn.
+///
+/// A suspend is detected from the clocks: the monotonic clock doesn't
+/// advance while the system is suspended, but the wall clock does.
+pub fn connect_resume<F: Fn() + 'static>(f: F) {
+    let f = Rc::new(f);
+
+    if let Some(screen) = gdk::Screen::get_default() {
+        let f = f.clone();
+        screen.connect_composited_changed(move |_| f());
+    }
+
+    let mut last = (Instant::now(), SystemTime::now());
+    gtk::timeout_add(CHECK_INTERVAL, move || {
+        let now = (Instant::now(), SystemTime::now());
+        let monotonic = now.0.duration_since(last.0);
+        // Wall clock going backwards is not a suspend.
+        let wall = now.1.duration_since(last.1).unwrap_or_default();
+        last = now;
+
+        if is_suspend(monotonic, wall) {
+            f();
+        }
+
+        gtk::Continue(true)
+    });
+}
+
+/// If the system was suspended, when the monotonic clock advanced by
+/// `monotonic` while the wall clock advanced by `wall`.
+fn is_suspend(monotonic: Duration, wall: Duration) -> bool {
+    wall > monotonic + SUSPEND_THRESHOLD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_suspend() {
+        let secs = Duration::from_secs;
+
+        assert!(!is_suspend(secs(2), secs(2)));
+        // Our main loop was blocked, the clocks still agree.
+        assert!(!is_suspend(secs(60), secs(60)));
+        // Clock adjusted a bit.
+        assert!(!is_suspend(secs(2), secs(4)));
+        assert!(is_suspend(secs(2), secs(3600)));
+        assert!(!is_suspend(secs(2), secs(0)));
+    }
+}
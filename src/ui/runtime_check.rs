@@ -0,0 +1,109 @@
+use gtk;
+use gtk::prelude::*;
+
+/// Version of our runtime files (see `g:gnvim_runtime_version` in
+/// runtime/plugin/gnvim.vim) that this binary works with. Bumped together
+/// with the runtime's version whenever one of them starts to depend on a
+/// change in the other (e.g. a new gnvim event).
+pub const RUNTIME_VERSION: u64 = 1;
+
+/// Warning banner at the top of the window, shown when our runtime files
+/// don't match the binary (e.g. after a package upgrade, or with a wrong
+/// `--gnvim-rtp`). That would otherwise show up as confusing errors from
+/// the runtime's functions, or as features that silently don't work.
+pub struct RuntimeCheck {
+    infobar: gtk::InfoBar,
+    label: gtk::Label,
+}
+
+impl RuntimeCheck {
+    pub fn new(parent: &gtk::Box) -> Self {
+        let infobar = gtk::InfoBar::new();
+        infobar.set_message_type(gtk::MessageType::Warning);
+        infobar.set_show_close_button(true);
+        infobar.set_no_show_all(true);
+        infobar.connect_response(|infobar, _| infobar.hide());
+
+        let label = gtk::Label::new(None);
+        label.set_line_wrap(true);
+        label.set_xalign(0.0);
+        label.show();
+        if let Some(area) = infobar.get_content_area() {
+            if let Ok(area) = area.downcast::<gtk::Container>() {
+                area.add(&label);
+            }
+        }
+
+        parent.pack_start(&infobar, false, false, 0);
+
+        RuntimeCheck { infobar, label }
+    }
+
+    /// Shows the banner if the runtime that nvim loaded from `path` (empty
+    /// if nvim didn't load our runtime) has a different `version` than
+    /// what we expect. Runtime files before the versioning have version 0.
+    pub fn check(&self, version: u64, path: &str) {
+        if let Some(msg) = mismatch_message(version, path) {
+            self.label.set_text(&msg);
+            self.infobar.show();
+        }
+    }
+}
+
+/// Returns what the user is told, if the runtime `version` (from `path`)
+/// doesn't match the binary.
+fn mismatch_message(version: u64, path: &str) -> Option<String> {
+    let fix = "Reinstall gnvim, or point --gnvim-rtp to the runtime files \
+               that came with this gnvim.";
+
+    if path.is_empty() {
+        return Some(format!(
+            "Gnvim's runtime files were not loaded by neovim. {}",
+            fix
+        ));
+    }
+
+    if version == RUNTIME_VERSION {
+        None
+    } else if version < RUNTIME_VERSION {
+        Some(format!(
+            "Gnvim's runtime files in {} are older than gnvim ({}). {}",
+            path,
+            ::VERSION,
+            fix
+        ))
+    } else {
+        Some(format!(
+            "Gnvim's runtime files in {} are newer than gnvim ({}). {}",
+            path,
+            ::VERSION,
+            fix
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_version_in_sync() {
+        let plugin = include_str!("../../runtime/plugin/gnvim.vim");
+        let stamp =
+            format!("let g:gnvim_runtime_version = {}", RUNTIME_VERSION);
+        assert!(plugin.lines().any(|line| line == stamp));
+    }
+
+    #[test]
+    fn test_mismatch_message() {
+        let path = "/usr/share/gnvim/runtime";
+        assert_eq!(mismatch_message(RUNTIME_VERSION, path), None);
+
+        let msg = mismatch_message(0, path).unwrap();
+        assert!(msg.contains("older") && msg.contains(path));
+        let msg = mismatch_message(RUNTIME_VERSION + 1, path).unwrap();
+        assert!(msg.contains("newer"));
+        let msg = mismatch_message(RUNTIME_VERSION, "").unwrap();
+        assert!(msg.contains("not loaded"));
+    }
+}
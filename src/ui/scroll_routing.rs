@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gdk;
+use gdk::prelude::*;
+use gtk;
+use gtk::prelude::*;
+
+/// Element on top of the grid (e.g. the popupmenu) that takes the wheel
+/// events over it, instead of nvim's window under it.
+#[derive(Clone)]
+pub struct ScrollArea {
+    /// Widget that covers the element.
+    widget: gtk::Widget,
+    /// Scrolled windows of the element. The one under the pointer is
+    /// scrolled.
+    scrolled: Vec<gtk::ScrolledWindow>,
+}
+
+impl ScrollArea {
+    pub fn new(
+        widget: gtk::Widget,
+        scrolled: Vec<gtk::ScrolledWindow>,
+    ) -> Self {
+        ScrollArea { widget, scrolled }
+    }
+}
+
+/// Routes the wheel events to the element under the pointer.
+///
+/// Gtk delivers the wheel events to the widget under the pointer, but that
+/// isn't always the element that the user sees there: the cursor tooltip
+/// lets the events pass through to the grid, and while a button is held
+/// down, the grid gets the events wherever the pointer is. In the other
+/// direction, the overlays that cover the whole grid (e.g. the popupmenu's
+/// layout) get the events where they have nothing, and those are sent to
+/// nvim for the grid under them (see `widget_position`). Nvim scrolls the
+/// window under the pointer itself, floating windows included.
+#[derive(Clone, Default)]
+pub struct ScrollRouter {
+    /// The areas, topmost first.
+    areas: Rc<RefCell<Vec<ScrollArea>>>,
+}
+
+impl ScrollRouter {
+    /// Adds `area`, below the areas added before it.
+    pub fn add(&self, area: ScrollArea) {
+        self.areas.borrow_mut().push(area);
+    }
+
+    /// Scrolls the area that is at `root` (in root window coordinates) in
+    /// `dir`. The focus stays where it is. Returns false if there is no
+    /// area at `root`, in which case the event is nvim's.
+    pub fn scroll(&self, root: (f64, f64), dir: gdk::ScrollDirection) -> bool {
+        let areas = self.areas.borrow();
+        let area = match areas.iter().find(|area| {
+            area.widget.get_mapped() && contains(&area.widget, root)
+        }) {
+            Some(area) => area,
+            None => return false,
+        };
+
+        let delta = match dir {
+            gdk::ScrollDirection::Up => -1.0,
+            gdk::ScrollDirection::Down => 1.0,
+            _ => return true,
+        };
+
+        // An area without anything to scroll (e.g. the popupmenu when all
+        // its items fit) still takes the events, so the text under it
+        // doesn't move.
+        let adj = area
+            .scrolled
+            .iter()
+            .find(|scrolled| {
+                scrolled.get_mapped() && contains(scrolled.upcast_ref(), root)
+            })
+            .and_then(|scrolled| scrolled.get_vadjustment());
+        if let Some(adj) = adj {
+            adj.set_value(scrolled_value(
+                adj.get_value(),
+                adj.get_lower(),
+                adj.get_upper(),
+                adj.get_page_size(),
+                delta,
+            ));
+        }
+
+        true
+    }
+}
+
+/// Returns the position of `root` (in root window coordinates) in
+/// `widget`'s coordinates, e.g. for sending wheel events that other
+/// widgets got to the grid.
+pub fn widget_position(
+    widget: &gtk::Widget,
+    root: (f64, f64),
+) -> Option<(f64, f64)> {
+    let toplevel = widget.get_toplevel()?;
+    let (_, x, y) = toplevel.get_window()?.get_origin();
+
+    toplevel
+        .translate_coordinates(widget, root.0 as i32 - x, root.1 as i32 - y)
+        .map(|(x, y)| (x as f64, y as f64))
+}
+
+/// If the point at `root` (in root window coordinates) is on `widget`.
+fn contains(widget: &gtk::Widget, root: (f64, f64)) -> bool {
+    match widget_position(widget, root) {
+        Some((x, y)) => {
+            x >= 0.0
+                && y >= 0.0
+                && x < widget.get_allocated_width() as f64
+                && y < widget.get_allocated_height() as f64
+        }
+        None => false,
+    }
+}
+
+/// Returns the value of an adjustment (`value`, `lower`, `upper`,
+/// `page_size`) after scrolling a wheel step in `delta`'s direction. Like
+/// in gtk, the step grows with the page size. The step is in whole pixels.
+fn scrolled_value(
+    value: f64,
+    lower: f64,
+    upper: f64,
+    page_size: f64,
+    delta: f64,
+) -> f64 {
+    let step = page_size.powf(2.0 / 3.0).round();
+    (value + delta * step).min(upper - page_size).max(lower)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrolled_value() {
+        assert_eq!(scrolled_value(0.0, 0.0, 1000.0, 125.0, 1.0), 25.0);
+        assert_eq!(scrolled_value(25.0, 0.0, 1000.0, 125.0, -1.0), 0.0);
+        // The ends.
+        assert_eq!(scrolled_value(10.0, 0.0, 1000.0, 125.0, -1.0), 0.0);
+        assert_eq!(scrolled_value(870.0, 0.0, 1000.0, 125.0, 1.0), 875.0);
+        // Nothing to scroll.
+        assert_eq!(scrolled_value(0.0, 0.0, 100.0, 125.0, 1.0), 0.0);
+    }
+}
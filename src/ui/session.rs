@@ -0,0 +1,97 @@
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::neovim::Neovim;
+use neovim_lib::NeovimApiAsync;
+
+use paths;
+use ui::project;
+use ui::trust;
+
+/// Session files (see `:h :mksession`) in our state directory, one per
+/// project. Written by `gnvim#session#save` when nvim exits.
+const FILE_NAME: &str = "session";
+
+/// Responses of our restore dialog.
+const RESPONSE_RESTORE: i32 = 1;
+const RESPONSE_SKIP: i32 = 2;
+
+/// Returns the path of the session file of the project that gnvim (and
+/// so nvim) was started in, like `session-<hash>.vim`. Like with the
+/// trust, the project's path is hashed. The file might not exist.
+pub fn path() -> Option<PathBuf> {
+    let dir = env::current_dir().ok()?;
+    let root = project::project_dir(&dir);
+    paths::state_dir().map(|dir| {
+        dir.join(format!("{}-{}.vim", FILE_NAME, trust::hash_path(&root)))
+    })
+}
+
+/// Returns the path of the session file, if the project has a saved
+/// session.
+pub fn saved() -> Option<PathBuf> {
+    path().filter(|path| path.is_file())
+}
+
+/// Asks the user if the previous session should be restored. `f` is called
+/// if it should.
+pub fn ask_restore<F: 'static>(parent: &gtk::Window, f: F)
+where
+    F: Fn(),
+{
+    let dialog = gtk::MessageDialog::new(
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        "Restore previous session?",
+    );
+    dialog.set_property_secondary_text(Some(
+        "The files, windows and the working directory from the last time \
+         gnvim was closed can be restored.",
+    ));
+    dialog.add_button("Don't Restore", RESPONSE_SKIP);
+    dialog.add_button("Restore", RESPONSE_RESTORE);
+    dialog.set_default_response(RESPONSE_RESTORE);
+
+    dialog.connect_response(move |dialog, res| {
+        dialog.destroy();
+
+        if res == RESPONSE_RESTORE {
+            f();
+        }
+    });
+
+    dialog.show_all();
+}
+
+/// Saves the session when the desktop's session manager tells that the
+/// user is logging out, since nvim might be killed before it gets to save
+/// the session on its own.
+pub fn save_on_logout(app: &gtk::Application, nvim: Arc<Mutex<Neovim>>) {
+    app.set_property_register_session(true);
+
+    // Older GTK versions don't have the signal, in which case we just rely
+    // on nvim.
+    let res = app.connect("query-end", false, move |_| {
+        // Don't block the main loop while nvim might be busy. The session
+        // manager gives the applications some time before ending the
+        // session, which is plenty for writing the session file.
+        let mut nvim = nvim.lock().unwrap();
+        nvim.command_async("call gnvim#session#save()")
+            .cb(|res| {
+                if let Err(err) = res {
+                    eprintln!("Failed to save the session: {}", err);
+                }
+            })
+            .call();
+
+        None
+    });
+    if let Err(err) = res {
+        eprintln!("Failed to connect to the session manager: {}", err);
+    }
+}
@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use glib;
+use gtk;
+
+use thread_guard::ThreadGuard;
+use ui::input::InputHook;
+use ui::input_queue::InputQueue;
+use ui::key_display::KeyDisplay;
+
+/// Feeds scripted input to nvim, one key at a time, as if it was typed
+/// (e.g. for demos and reproducible bug recordings). The keys go through
+/// the input hook and the input queue, like our keyboard input.
+pub struct Simulator {
+    queue: InputQueue,
+    input_hook: Arc<ThreadGuard<InputHook>>,
+    key_display: KeyDisplay,
+    /// Source of the timeout that sends the next key, if a simulation is
+    /// running.
+    source: Rc<RefCell<Option<glib::SourceId>>>,
+}
+
+impl Simulator {
+    pub fn new(
+        queue: InputQueue,
+        input_hook: Arc<ThreadGuard<InputHook>>,
+        key_display: KeyDisplay,
+    ) -> Self {
+        Simulator {
+            queue,
+            input_hook,
+            key_display,
+            source: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Sends `sequence` (in nvim's key notation) with `delay` milliseconds
+    /// between the keys. If `show` is set, the keys are shown on the screen
+    /// as they are sent, even if the key display is not enabled. Stops the
+    /// previous simulation, if any.
+    pub fn run(&self, sequence: &str, delay: u64, show: bool) {
+        self.stop();
+
+        let mut keys: VecDeque<String> = split_keys(sequence).into();
+        if keys.is_empty() {
+            return;
+        }
+
+        let queue = self.queue.clone();
+        let input_hook = self.input_hook.clone();
+        let key_display = self.key_display.clone();
+        let source = self.source.clone();
+        let interval = delay.max(1) as u32;
+
+        let id = gtk::timeout_add(interval, move || {
+            // Without a delay, all the keys are sent at once.
+            let n = if delay == 0 { keys.len() } else { 1 };
+
+            for key in keys.drain(..n) {
+                if show {
+                    key_display.push(&key);
+                } else {
+                    key_display.input(&key);
+                }
+                input_hook.borrow().process(&queue, key);
+            }
+
+            if keys.is_empty() {
+                source.borrow_mut().take();
+                return gtk::Continue(false);
+            }
+
+            gtk::Continue(true)
+        });
+
+        *self.source.borrow_mut() = Some(id);
+    }
+
+    /// Stops the running simulation. The keys that are not sent yet are
+    /// dropped.
+    pub fn stop(&self) {
+        if let Some(id) = self.source.borrow_mut().take() {
+            glib::source::source_remove(id);
+        }
+    }
+}
+
+/// Splits `sequence` to keys in nvim's key notation. Special keys (e.g.
+/// "<C-w>") stay as one key, and a "<" that doesn't start a special key is
+/// turned to "<lt>".
+pub fn split_keys(sequence: &str) -> Vec<String> {
+    let mut keys = vec![];
+    let mut rest = sequence;
+
+    while let Some(c) = rest.chars().next() {
+        let len = if c == '<' {
+            match special_key_len(&rest[1..]) {
+                Some(len) => len + 1,
+                None => {
+                    keys.push(String::from("<lt>"));
+                    rest = &rest[1..];
+                    continue;
+                }
+            }
+        } else {
+            c.len_utf8()
+        };
+
+        keys.push(rest[..len].to_string());
+        rest = &rest[len..];
+    }
+
+    keys
+}
+
+/// Returns the length of the special key at the start of `s` (which
+/// follows a "<"), including the closing ">".
+fn special_key_len(s: &str) -> Option<usize> {
+    let mut i = 0;
+
+    // Modifiers, e.g. "C-" and "S-".
+    loop {
+        let mut chars = s[i..].chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(m), Some('-'), Some(_)) if m.is_ascii_alphabetic() => i += 2,
+            _ => break,
+        }
+    }
+
+    // Key name (e.g. "Esc" or "F12"), or any single character.
+    let name_len: usize = s[i..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .map(|c| c.len_utf8())
+        .sum();
+    i += match name_len {
+        0 => s[i..].chars().next()?.len_utf8(),
+        len => len,
+    };
+
+    if s[i..].starts_with('>') {
+        Some(i + 1)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_keys() {
+        assert_eq!(split_keys(""), Vec::<String>::new());
+        assert_eq!(split_keys("iä<Esc>"), vec!["i", "ä", "<Esc>"]);
+        assert_eq!(
+            split_keys("<C-w>v:q<CR>"),
+            vec!["<C-w>", "v", ":", "q", "<CR>"]
+        );
+        assert_eq!(split_keys("<lt><C->>"), vec!["<lt>", "<C->>"]);
+        assert_eq!(split_keys("a<b"), vec!["a", "<lt>", "b"]);
+        assert_eq!(split_keys("<>"), vec!["<lt>", ">"]);
+        assert_eq!(split_keys("a < b>"), vec!["a", " ", "<lt>", " ", "b", ">"]);
+        assert_eq!(split_keys("<<Esc>"), vec!["<lt>", "<Esc>"]);
+        assert_eq!(split_keys("<C-S-Left><A-->"), vec!["<C-S-Left>", "<A-->"]);
+    }
+}
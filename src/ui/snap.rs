@@ -0,0 +1,141 @@
+use gdk;
+use gdk::prelude::*;
+use gtk;
+use gtk::prelude::*;
+
+/// Part of the screen that the window is snapped to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Snap {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Snap {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "left" => Some(Snap::Left),
+            "right" => Some(Snap::Right),
+            "top" => Some(Snap::Top),
+            "bottom" => Some(Snap::Bottom),
+            "top-left" => Some(Snap::TopLeft),
+            "top-right" => Some(Snap::TopRight),
+            "bottom-left" => Some(Snap::BottomLeft),
+            "bottom-right" => Some(Snap::BottomRight),
+            _ => None,
+        }
+    }
+
+    /// Returns the rectangle for the snap in the monitor's `workarea`.
+    fn rect(self, workarea: &gdk::Rectangle) -> gdk::Rectangle {
+        let half_w = workarea.width / 2;
+        let half_h = workarea.height / 2;
+
+        // Halves and quarters as (x, y, width, height), relative to the
+        // workarea. The right and bottom ones take the odd pixel.
+        let (x, y, width, height) = match self {
+            Snap::Left => (0, 0, half_w, workarea.height),
+            Snap::Right => {
+                (half_w, 0, workarea.width - half_w, workarea.height)
+            }
+            Snap::Top => (0, 0, workarea.width, half_h),
+            Snap::Bottom => {
+                (0, half_h, workarea.width, workarea.height - half_h)
+            }
+            Snap::TopLeft => (0, 0, half_w, half_h),
+            Snap::TopRight => (half_w, 0, workarea.width - half_w, half_h),
+            Snap::BottomLeft => (0, half_h, half_w, workarea.height - half_h),
+            Snap::BottomRight => (
+                half_w,
+                half_h,
+                workarea.width - half_w,
+                workarea.height - half_h,
+            ),
+        };
+
+        gdk::Rectangle {
+            x: workarea.x + x,
+            y: workarea.y + y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Returns true if the compositor (or window manager) has tiled `window`.
+pub fn is_tiled(window: &gtk::Window) -> bool {
+    window
+        .get_window()
+        .map(|win| win.get_state().contains(gdk::WindowState::TILED))
+        .unwrap_or(false)
+}
+
+/// Moves and resizes `window` to the part of its current monitor that
+/// `snap` tells. Some platforms (e.g. wayland) don't let us position our
+/// window, in which case only the size changes.
+pub fn snap(window: &gtk::Window, snap: Snap) {
+    let gdk_window = match window.get_window() {
+        Some(win) => win,
+        None => return,
+    };
+
+    let screen = gdk_window.get_screen();
+    let monitor = screen.get_monitor_at_window(&gdk_window);
+    let rect = snap.rect(&screen.get_monitor_workarea(monitor));
+
+    window.unfullscreen();
+    window.unmaximize();
+    window.move_(rect.x, rect.y);
+    window.resize(rect.width, rect.height);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap_from_str() {
+        assert_eq!(Snap::from_str("left"), Some(Snap::Left));
+        assert_eq!(Snap::from_str("bottom-right"), Some(Snap::BottomRight));
+        assert_eq!(Snap::from_str("middle"), None);
+    }
+
+    #[test]
+    fn test_snap_rect() {
+        let workarea = gdk::Rectangle {
+            x: 100,
+            y: 20,
+            width: 1921,
+            height: 1081,
+        };
+
+        let rect = Snap::Left.rect(&workarea);
+        assert_eq!(
+            (rect.x, rect.y, rect.width, rect.height),
+            (100, 20, 960, 1081)
+        );
+
+        let rect = Snap::Right.rect(&workarea);
+        assert_eq!(
+            (rect.x, rect.y, rect.width, rect.height),
+            (1060, 20, 961, 1081)
+        );
+
+        let rect = Snap::BottomLeft.rect(&workarea);
+        assert_eq!(
+            (rect.x, rect.y, rect.width, rect.height),
+            (100, 560, 960, 541)
+        );
+
+        let rect = Snap::TopRight.rect(&workarea);
+        assert_eq!(
+            (rect.x, rect.y, rect.width, rect.height),
+            (1060, 20, 961, 540)
+        );
+    }
+}
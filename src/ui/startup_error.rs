@@ -0,0 +1,143 @@
+use std::io::{self, Read, Write};
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use gdk;
+use gtk;
+use gtk::prelude::*;
+
+/// Max size (in bytes) of the output kept, from the end of the output.
+const MAX_OUTPUT: usize = 64 * 1024;
+/// How long we wait for the rest of nvim's output after it failed to
+/// start. Nvim might still be running, or its jobs might hold the stderr
+/// open.
+const OUTPUT_TIMEOUT: Duration = Duration::from_millis(500);
+
+const RESPONSE_COPY: i32 = 1;
+const RESPONSE_RETRY: i32 = 2;
+const RESPONSE_QUIT: i32 = 3;
+
+/// Nvim's stderr, captured so that we can show it if nvim fails to start.
+/// The output is also passed on to our own stderr, so that it's seen when
+/// gnvim is started from a terminal. Only the end of the output is kept,
+/// in memory, so that every gnvim instance has its own.
+pub struct Stderr {
+    output: Arc<Mutex<Vec<u8>>>,
+    done: mpsc::Receiver<()>,
+}
+
+impl Stderr {
+    /// Returns where nvim's stderr should go, and the captured output. If
+    /// the output can't be captured, nvim writes to our stderr directly.
+    pub fn capture() -> (Stdio, Self) {
+        let output = Arc::new(Mutex::new(vec![]));
+        let (tx, done) = mpsc::channel();
+        let stderr = Stderr { output, done };
+
+        let (mut reader, writer) = match io::pipe() {
+            Ok(pipe) => pipe,
+            Err(err) => {
+                eprintln!("Failed to capture nvim's stderr: {}", err);
+                return (Stdio::inherit(), stderr);
+            }
+        };
+
+        // Nvim never blocks on writing its stderr, since we read it all the
+        // time.
+        let output = stderr.output.clone();
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            loop {
+                let n = match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+
+                let _ = io::stderr().write_all(&buf[..n]);
+
+                let mut output = output.lock().unwrap();
+                output.extend_from_slice(&buf[..n]);
+                if output.len() > MAX_OUTPUT {
+                    let excess = output.len() - MAX_OUTPUT;
+                    output.drain(..excess);
+                }
+            }
+
+            let _ = tx.send(());
+        });
+
+        (Stdio::from(writer), stderr)
+    }
+
+    /// Returns what nvim has written to its stderr. Waits for nvim to
+    /// close its stderr (e.g. when it exits), for a while.
+    pub fn output(&self) -> String {
+        let _ = self.done.recv_timeout(OUTPUT_TIMEOUT);
+
+        let output = self.output.lock().unwrap();
+        String::from_utf8_lossy(&output).trim_end().to_string()
+    }
+}
+
+/// Shows that nvim failed to start because of `error`, with nvim's stderr
+/// `output`. `retry` is called if the user wants to retry with `--clean`
+/// (e.g. without their config).
+pub fn show<F>(app: &gtk::Application, error: &str, output: &str, retry: F)
+where
+    F: Fn() + 'static,
+{
+    let dialog = gtk::MessageDialog::new(
+        None::<&gtk::Window>,
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Error,
+        gtk::ButtonsType::None,
+        "Neovim failed to start",
+    );
+    // Keeps the application running while the dialog is open.
+    dialog.set_application(app);
+    dialog.set_property_secondary_text(Some(error));
+
+    let text = if output.is_empty() {
+        String::from("(no output)")
+    } else {
+        output.to_string()
+    };
+
+    let buffer = gtk::TextBuffer::new(None);
+    buffer.set_text(&text);
+    let view = gtk::TextView::new_with_buffer(&buffer);
+    view.set_editable(false);
+    view.set_monospace(true);
+
+    let scrolled = gtk::ScrolledWindow::new(None, None);
+    scrolled.add(&view);
+    scrolled.set_size_request(600, 300);
+    scrolled.set_vexpand(true);
+    dialog
+        .get_content_area()
+        .pack_start(&scrolled, true, true, 0);
+
+    dialog.add_button("Copy to Clipboard", RESPONSE_COPY);
+    dialog.add_button("Quit", RESPONSE_QUIT);
+    dialog.add_button("Retry with --clean", RESPONSE_RETRY);
+    dialog.set_default_response(RESPONSE_RETRY);
+
+    let details = format!("{}\n\n{}", error, text);
+    dialog.connect_response(move |dialog, res| match res {
+        RESPONSE_COPY => {
+            gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(&details);
+        }
+        RESPONSE_RETRY => {
+            // Retry before the dialog is gone, so the application doesn't
+            // quit in between.
+            retry();
+            dialog.destroy();
+        }
+        _ => dialog.destroy(),
+    });
+
+    dialog.show_all();
+}
@@ -5,10 +5,7 @@ use std::sync::{Arc, Mutex};
 use glib;
 use gtk;
 use gtk::prelude::*;
-use neovim_lib::{
-    neovim_api::{NeovimApi, Tabpage},
-    Neovim,
-};
+use neovim_lib::{neovim_api::Tabpage, Neovim, NeovimApiAsync};
 use pango;
 
 use nvim_bridge;
@@ -35,6 +32,7 @@ impl Tabline {
     pub fn new(nvim: Arc<Mutex<Neovim>>) -> Self {
         let notebook = gtk::Notebook::new();
         notebook.set_show_border(false);
+        gtk::WidgetExt::set_name(&notebook, "gnvim-tabline");
 
         let css_provider = gtk::CssProvider::new();
         add_css_provider!(&css_provider, notebook);
@@ -47,7 +45,13 @@ impl Tabline {
                 let pages = tabpage_data_ref.borrow();
                 if let Some(ref page) = pages.get(page_num as usize) {
                     let mut nvim = nvim_ref.lock().unwrap();
-                    nvim.set_current_tabpage(&page).unwrap();
+                    nvim.set_current_tabpage_async(&page)
+                        .cb(|res| {
+                            if let Err(err) = res {
+                                println!("Failed to switch tab page: {}", err);
+                            }
+                        })
+                        .call();
                 } else {
                     println!("Failed to get tab page {}", page_num);
                 }
@@ -109,10 +113,6 @@ impl Tabline {
         glib::signal_handler_unblock(&self.notebook, &self.switch_tab_signal);
     }
 
-    pub fn get_height(&self) -> i32 {
-        self.notebook.get_preferred_height().0
-    }
-
     pub fn set_font(&mut self, font: Font, hl_defs: &HlDefs) {
         self.font = font;
         self.set_styles(hl_defs);
@@ -0,0 +1,256 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use glib;
+use gtk;
+use gtk::prelude::*;
+
+use ui::headerbar::Headerbar;
+
+/// How long (in milliseconds) a finished task is shown.
+const HIDE_DELAY: u32 = 5000;
+
+/// How a task finished.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Success,
+    Failure,
+}
+
+impl Status {
+    /// Parses `status` from `gnvim#task#end`. Anything else than "success"
+    /// is a failure.
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "success" => Status::Success,
+            _ => Status::Failure,
+        }
+    }
+}
+
+/// Task (e.g. `:make`) that a plugin tells us about.
+#[derive(Debug, Clone, PartialEq)]
+struct Task {
+    name: String,
+    /// Progress in percents, if known.
+    progress: Option<u64>,
+    /// Set once the task has finished.
+    status: Option<Status>,
+}
+
+struct Inner {
+    /// Nvim's title (see 'title').
+    title: String,
+    task: Option<Task>,
+    /// Source of the timeout that hides a finished task.
+    hide_source: Option<glib::SourceId>,
+}
+
+/// Progress of the current task (see `gnvim#task#begin`), shown in the
+/// window's title (e.g. "[make 40%] Neovim"), and in the headerbar if we
+/// have one.
+#[derive(Clone)]
+pub struct TaskIndicator {
+    window: gtk::ApplicationWindow,
+
+    box_: gtk::Box,
+    label: gtk::Label,
+    spinner: gtk::Spinner,
+    progress: gtk::ProgressBar,
+
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl TaskIndicator {
+    pub fn new(
+        window: &gtk::ApplicationWindow,
+        headerbar: Option<&Headerbar>,
+    ) -> Self {
+        let box_ = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        box_.set_no_show_all(true);
+
+        let spinner = gtk::Spinner::new();
+        let progress = gtk::ProgressBar::new();
+        progress.set_valign(gtk::Align::Center);
+        progress.set_size_request(60, -1);
+        let label = gtk::Label::new(None);
+
+        box_.pack_start(&spinner, false, false, 0);
+        box_.pack_start(&progress, false, false, 0);
+        box_.pack_start(&label, false, false, 0);
+
+        if let Some(headerbar) = headerbar {
+            headerbar.pack_end(&box_);
+        }
+
+        TaskIndicator {
+            window: window.clone(),
+            box_,
+            label,
+            spinner,
+            progress,
+            inner: Rc::new(RefCell::new(Inner {
+                title: String::from("Neovim"),
+                task: None,
+                hide_source: None,
+            })),
+        }
+    }
+
+    /// Sets nvim's title, which is shown after the task.
+    pub fn set_title(&self, title: &str) {
+        self.inner.borrow_mut().title = title.to_string();
+        self.update();
+    }
+
+    /// Starts showing the task `name`, replacing the previous task.
+    pub fn begin(&self, name: &str) {
+        {
+            let mut inner = self.inner.borrow_mut();
+            if let Some(source) = inner.hide_source.take() {
+                glib::source::source_remove(source);
+            }
+            inner.task = Some(Task {
+                name: name.to_string(),
+                progress: None,
+                status: None,
+            });
+        }
+
+        self.update();
+    }
+
+    /// Sets the progress of the current task, in percents.
+    pub fn set_progress(&self, progress: u64) {
+        if let Some(ref mut task) = self.inner.borrow_mut().task {
+            if task.status.is_none() {
+                task.progress = Some(progress.min(100));
+            }
+        }
+
+        self.update();
+    }
+
+    /// Finishes the current task with `status`. The result is shown for a
+    /// moment. Returns the finished task's name, if there was a task.
+    pub fn end(&self, status: Status) -> Option<String> {
+        let name = {
+            let mut inner = self.inner.borrow_mut();
+            let name = match inner.task {
+                Some(ref mut task) if task.status.is_none() => {
+                    task.status = Some(status);
+                    task.name.clone()
+                }
+                _ => return None,
+            };
+
+            let indicator = self.clone();
+            inner.hide_source = Some(gtk::timeout_add(HIDE_DELAY, move || {
+                {
+                    let mut inner = indicator.inner.borrow_mut();
+                    inner.hide_source = None;
+                    inner.task = None;
+                }
+                indicator.update();
+                gtk::Continue(false)
+            }));
+
+            name
+        };
+
+        self.update();
+        Some(name)
+    }
+
+    fn update(&self) {
+        let inner = self.inner.borrow();
+        self.window
+            .set_title(&title(&inner.title, inner.task.as_ref()));
+
+        let task = match inner.task {
+            Some(ref task) => task,
+            None => {
+                self.spinner.stop();
+                self.box_.hide();
+                return;
+            }
+        };
+
+        self.label.set_text(&task.name);
+        if let Some(style) = self.label.get_style_context() {
+            style.remove_class("success");
+            style.remove_class("error");
+            match task.status {
+                Some(Status::Success) => style.add_class("success"),
+                Some(Status::Failure) => style.add_class("error"),
+                None => {}
+            }
+        }
+
+        let running = task.status.is_none();
+        match task.progress {
+            Some(progress) if running => {
+                self.progress.set_fraction(progress as f64 / 100.0);
+                self.progress.show();
+            }
+            _ => self.progress.hide(),
+        }
+        if running && task.progress.is_none() {
+            self.spinner.start();
+            self.spinner.show();
+        } else {
+            self.spinner.stop();
+            self.spinner.hide();
+        }
+
+        self.label.show();
+        self.box_.show();
+    }
+}
+
+/// Returns the window title for nvim's `title` and `task`.
+fn title(title: &str, task: Option<&Task>) -> String {
+    let task = match task {
+        Some(task) => task,
+        None => return title.to_string(),
+    };
+
+    let state = match (task.status, task.progress) {
+        (Some(Status::Success), _) => String::from(" ✓"),
+        (Some(Status::Failure), _) => String::from(" ✗"),
+        (None, Some(progress)) => format!(" {}%", progress),
+        (None, None) => String::new(),
+    };
+
+    format!("[{}{}] {}", task.name, state, title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_parse() {
+        assert_eq!(Status::parse("success"), Status::Success);
+        assert_eq!(Status::parse("failure"), Status::Failure);
+        assert_eq!(Status::parse("whatever"), Status::Failure);
+    }
+
+    #[test]
+    fn test_title() {
+        let mut task = Task {
+            name: String::from("make"),
+            progress: None,
+            status: None,
+        };
+
+        assert_eq!(title("Neovim", None), "Neovim");
+        assert_eq!(title("Neovim", Some(&task)), "[make] Neovim");
+        task.progress = Some(40);
+        assert_eq!(title("Neovim", Some(&task)), "[make 40%] Neovim");
+        task.status = Some(Status::Success);
+        assert_eq!(title("Neovim", Some(&task)), "[make ✓] Neovim");
+        task.status = Some(Status::Failure);
+        assert_eq!(title("Neovim", Some(&task)), "[make ✗] Neovim");
+    }
+}
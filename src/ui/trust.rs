@@ -0,0 +1,153 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use gtk;
+use gtk::prelude::*;
+
+use key_value;
+use paths;
+
+/// The user's decisions of which directories are trusted, in our state
+/// directory.
+const FILE_NAME: &str = "trust";
+
+/// Responses of our trust dialog.
+const RESPONSE_TRUST: i32 = 1;
+const RESPONSE_DISTRUST: i32 = 2;
+
+/// The user's decision of whether to use a directory's (e.g. a project's)
+/// local configuration. Nothing from a directory (settings, CSS etc.) may
+/// be used unless the directory is trusted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trust {
+    Trusted,
+    Untrusted,
+    /// The user hasn't been asked yet.
+    Unknown,
+}
+
+/// Returns the user's decision for `dir`.
+pub fn trust(dir: &Path) -> Trust {
+    let data = path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+
+    parse(&data, &hash_path(dir))
+}
+
+/// Remembers the user's decision for `dir`.
+pub fn set_trust(dir: &Path, trusted: bool) -> io::Result<()> {
+    let path = path().ok_or_else(paths::no_state_dir)?;
+
+    let mut data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    data.push_str(&format!("{}={}\n", hash_path(dir), trusted));
+
+    fs::write(path, data)
+}
+
+/// Asks the user if `dir` is trusted. `files` are the directory's files
+/// that we'd use, and are listed in the dialog. `f` is called with the
+/// answer, unless the dialog is dismissed. The answer is not remembered,
+/// see `set_trust`.
+pub fn ask<F: 'static>(parent: &gtk::Window, dir: &Path, files: &[&str], f: F)
+where
+    F: Fn(bool),
+{
+    let dialog = gtk::MessageDialog::new(
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        "Trust this directory?",
+    );
+    dialog.set_property_secondary_text(Some(
+        format!(
+            "{} has configuration for gnvim ({}). Only trust the \
+             directory if you trust its contents, e.g. if it isn't a \
+             repository you've just cloned.",
+            dir.display(),
+            files.join(", ")
+        )
+        .as_str(),
+    ));
+    dialog.add_button("Don't Trust", RESPONSE_DISTRUST);
+    dialog.add_button("Trust", RESPONSE_TRUST);
+    dialog.set_default_response(RESPONSE_DISTRUST);
+
+    dialog.connect_response(move |dialog, res| {
+        dialog.destroy();
+
+        match res {
+            RESPONSE_TRUST => f(true),
+            RESPONSE_DISTRUST => f(false),
+            _ => {}
+        }
+    });
+
+    dialog.show_all();
+}
+
+/// Parses the decision for `hash` from `hash=bool` lines. The last line for
+/// the hash wins.
+fn parse(data: &str, hash: &str) -> Trust {
+    key_value::lines(data)
+        .filter(|(h, _)| *h == hash)
+        .filter_map(|(_, value)| value.parse().ok())
+        .last()
+        .map(|trusted| {
+            if trusted {
+                Trust::Trusted
+            } else {
+                Trust::Untrusted
+            }
+        })
+        .unwrap_or(Trust::Unknown)
+}
+
+/// Returns a hash of `dir`'s canonical path, so that we don't keep a list
+/// of the user's directories around. The hash (64 bit FNV-1a) must stay
+/// the same across our versions.
+pub fn hash_path(dir: &Path) -> String {
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    format!("{:016x}", fnv1a(dir.to_string_lossy().as_bytes()))
+}
+
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = "0123=true\n\
+                    4567=false\n\
+                    0123=false\n\
+                    89ab=true\n";
+
+        assert_eq!(parse(data, "0123"), Trust::Untrusted);
+        assert_eq!(parse(data, "89ab"), Trust::Trusted);
+        assert_eq!(parse(data, "cdef"), Trust::Unknown);
+        assert_eq!(parse("0123=maybe", "0123"), Trust::Unknown);
+    }
+}
@@ -1,10 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time;
 
 use gdk;
+use gio;
 use glib;
 use gtk;
 use neovim_lib::neovim::Neovim;
@@ -12,19 +15,68 @@ use neovim_lib::neovim_api::NeovimApi;
 use neovim_lib::NeovimApiAsync;
 use neovim_lib::Value;
 
+use gdk::prelude::*;
+use gio::SettingsExt;
 use gtk::prelude::*;
 
 use nvim_bridge::{
     GnvimEvent, Message, ModeInfo, Notify, OptionSet, RedrawEvent, Request,
 };
+use paths;
+use profiler;
+use rpc_trace::RpcTrace;
+use startup;
 use thread_guard::ThreadGuard;
+use ui::animation::Animations;
+use ui::cell_width::CellWidths;
 use ui::cmdline::Cmdline;
 use ui::color::{Color, Highlight};
+use ui::context_menu::ContextMenu;
 use ui::cursor_tooltip::{CursorTooltip, Gravity};
+use ui::debug_console::DebugConsole;
 use ui::font::Font;
-use ui::grid::Grid;
+use ui::gesture::{GestureResult, Gestures};
+use ui::ghost_text::GhostText;
+use ui::grid::{Grid, MouseButton};
+use ui::headerbar::Headerbar;
+use ui::input::InputHook;
+use ui::input_queue::InputQueue;
+use ui::key_display::KeyDisplay;
+#[cfg(target_os = "macos")]
+use ui::macos;
+use ui::mouse::Mouse;
+use ui::notification::{self, Notifier};
+use ui::perf_hud::PerfHud;
+use ui::placement::{self, Placement};
 use ui::popupmenu::Popupmenu;
+use ui::preferences::{self, Preferences};
+use ui::print;
+use ui::project::{self, ProjectSettings};
+use ui::quickfix::QuickfixPanel;
+use ui::quit;
+use ui::recording::RecordingIndicator;
+use ui::replace_preview::ReplacePanel;
+use ui::resume;
+use ui::runtime_check::RuntimeCheck;
+use ui::scroll_routing::ScrollRouter;
+use ui::session;
+use ui::simulate::Simulator;
+use ui::snap::{self, Snap};
 use ui::tabline::Tabline;
+use ui::task::{self, TaskIndicator};
+use ui::trust::{self, Trust};
+use ui::unicode_input::UnicodeInput;
+use ui::user_css::UserCss;
+use ui::window_geometry::WindowGeometry;
+use ui::wm_class;
+use watchdog::{self, Stall, Watchdog};
+
+/// Seconds after which the watchdog reports a stall.
+const WATCHDOG_TIMEOUT: u64 = 5;
+
+/// Frame interval (in microseconds) to use until we know the refresh rate
+/// of the monitor.
+const DEFAULT_FRAME_INTERVAL: u64 = 16_667;
 
 type Grids = HashMap<u64, Grid>;
 
@@ -67,6 +119,8 @@ struct UIState {
     cmdline: Cmdline,
     tabline: Tabline,
     cursor_tooltip: CursorTooltip,
+    replace_panel: ReplacePanel,
+    quickfix: QuickfixPanel,
 
     /// Overlay contains our grid(s) and popupmenu.
     #[allow(unused)]
@@ -74,6 +128,80 @@ struct UIState {
 
     /// Source id for delayed call to ui_try_resize.
     resize_source_id: Arc<Mutex<Option<glib::SourceId>>>,
+
+    /// Handler for `GnvimEvent::NewWindow`, called with the nvim server
+    /// address.
+    new_window_handler: Option<Box<Fn(&str)>>,
+
+    /// Input hook, shared with our keyboard input handlers.
+    input_hook: Arc<ThreadGuard<InputHook>>,
+    /// If the Option key is used as Meta on macOS (see
+    /// `macos::translate_key`), shared with our keyboard input handler.
+    option_as_meta: Arc<AtomicBool>,
+    /// Simulated input, for scripted demos.
+    simulator: Simulator,
+    /// On-screen display of the keys, shared with our keyboard input
+    /// handlers.
+    key_display: KeyDisplay,
+    /// Mouse gestures, shared with our mouse input handlers.
+    gestures: Arc<ThreadGuard<Gestures>>,
+    /// Indicator of the macro recording.
+    recording: RecordingIndicator,
+    /// Animations of the window's properties.
+    animations: Animations,
+    /// Context menu, shared with our mouse input handlers.
+    context_menu: Arc<ThreadGuard<ContextMenu>>,
+    /// If nvim takes the mouse, shared with our mouse input handlers.
+    mouse: Arc<ThreadGuard<Mouse>>,
+    /// Popover for entering characters by their codepoint or name.
+    unicode_input: UnicodeInput,
+    /// Headerbar, if we have one (with `--headerbar`).
+    headerbar: Option<Headerbar>,
+    /// Progress of the current task (e.g. `:make`), in the title and the
+    /// headerbar.
+    task: TaskIndicator,
+    /// Banner for a mismatch between our runtime files and the binary.
+    runtime_check: RuntimeCheck,
+
+    /// Background of the selection (visual highlight) and if the selection
+    /// has rounded corners. Passed to the grids.
+    selection_bg: Option<Color>,
+    rounded_selection: bool,
+
+    /// Ghost text of the selected completion item.
+    ghost_text: GhostText,
+
+    /// Frame rate cap, zero for no cap. Shared with the event loop in
+    /// `UI::start`.
+    max_fps: Arc<AtomicUsize>,
+
+    /// Trace of the rpc messages, shared with our nvim bridge.
+    rpc_trace: Arc<Mutex<RpcTrace>>,
+    debug_console: DebugConsole,
+    /// Performance metrics overlay.
+    perf_hud: PerfHud,
+
+    /// Desktop notifications of messages from nvim.
+    notifier: Notifier,
+
+    /// Our settings, if the schema is installed. Values set from nvim are
+    /// written back to the settings.
+    settings: Option<gio::Settings>,
+    preferences: Option<Preferences>,
+    /// Current guifont, shared with the settings' change handler and our
+    /// zoom shortcuts.
+    guifont: Arc<ThreadGuard<String>>,
+    /// State of the zoom shortcuts.
+    zoom: Arc<ThreadGuard<Zoom>>,
+
+    /// Guifont before the project's font size was applied, if the current
+    /// project has a font size (see `apply_project_settings`).
+    project_base_font: Option<String>,
+    /// Directories the user has been asked to trust during this session.
+    trust_asked: HashSet<PathBuf>,
+
+    /// User's CSS, if we have a config directory.
+    user_css: Option<UserCss>,
 }
 
 /// Main UI structure.
@@ -87,6 +215,11 @@ pub struct UI {
     /// Our internal state, containing basically everything we manipulate
     /// when we receive an event from nvim.
     state: Arc<ThreadGuard<UIState>>,
+    /// Set when our window is closed while nvim keeps running (see
+    /// `detach_on_close`).
+    closed: Arc<AtomicBool>,
+    /// Frame rate cap, zero for no cap.
+    max_fps: Arc<AtomicUsize>,
 }
 
 impl UI {
@@ -96,20 +229,82 @@ impl UI {
     /// * `rx` - Channel to receive nvim UI events.
     /// * `nvim` - Neovim instance to use. Should be the same that is the source
     ///            of `rx` events.
+    /// * `geometry` - Initial geometry of the window. The geometry is saved
+    ///                when the window is closed.
+    /// * `placement` - Where the window is placed.
+    /// * `headerbar` - If the window has a headerbar (which shows the current
+    ///                 file's path) instead of the system's title bar.
     pub fn init(
         app: &gtk::Application,
         rx: Receiver<Message>,
         nvim: Arc<Mutex<Neovim>>,
+        geometry: WindowGeometry,
+        placement: Placement,
+        headerbar: bool,
+        rpc_trace: Arc<Mutex<RpcTrace>>,
     ) -> Self {
         // Create the main window.
         let window = gtk::ApplicationWindow::new(app);
         window.set_title("Neovim");
-        window.set_default_size(1280, 720);
+        // The titlebar can't be changed after the window is shown.
+        let headerbar = if headerbar {
+            Some(Headerbar::new(&window, nvim.clone()))
+        } else {
+            None
+        };
+        window.set_default_size(geometry.width, geometry.height);
+        placement::place(&window, placement, &geometry);
+        if geometry.maximized {
+            window.maximize();
+        }
+        if geometry.fullscreen {
+            window.fullscreen();
+        }
+
+        // Keep track of our geometry, so it can be saved when we're closed.
+        let geometry = Arc::new(ThreadGuard::new(geometry));
+        let geometry_ref = geometry.clone();
+        window.connect_configure_event(move |window, _| {
+            let mut geometry = geometry_ref.borrow_mut();
+            // Only remember the "normal" size and position, so that they're
+            // right when the window is unmaximized (or untiled) after a
+            // restart.
+            if !geometry.maximized
+                && !geometry.fullscreen
+                && !snap::is_tiled(window.upcast_ref())
+            {
+                let (width, height) = window.get_size();
+                geometry.width = width;
+                geometry.height = height;
+                geometry.position = Some(window.get_position());
+            }
+
+            false
+        });
+
+        let geometry_ref = geometry.clone();
+        window.connect_window_state_event(move |_, e| {
+            let mut geometry = geometry_ref.borrow_mut();
+            let state = e.get_new_window_state();
+            geometry.maximized = state.contains(gdk::WindowState::MAXIMIZED);
+            geometry.fullscreen = state.contains(gdk::WindowState::FULLSCREEN);
+
+            Inhibit(false)
+        });
+
+        let geometry_ref = geometry.clone();
+        window.connect_destroy(move |_| {
+            if let Err(err) = geometry_ref.borrow().save() {
+                eprintln!("Failed to save window geometry: {}", err);
+            }
+        });
 
         // Top level widget.
         let b = gtk::Box::new(gtk::Orientation::Vertical, 0);
         window.add(&b);
 
+        let runtime_check = RuntimeCheck::new(&b);
+
         let tabline = Tabline::new(nvim.clone());
         b.pack_start(&tabline.get_widget(), false, false, 0);
 
@@ -120,6 +315,9 @@ impl UI {
         let box_ = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         overlay.add(&box_);
 
+        let quickfix = QuickfixPanel::new(nvim.clone());
+        b.pack_start(&quickfix.widget(), false, false, 0);
+
         // Create hl defs and initialize 0th element because we'll need to have
         // something that is accessible for the default grid that we're gonna
         // make next.
@@ -137,7 +335,14 @@ impl UI {
         let source_id = Arc::new(Mutex::new(None));
         let source_id_ref = source_id.clone();
         let nvim_ref = nvim.clone();
+        let geometry_ref = geometry.clone();
         grid.connect_da_resize(move |rows, cols| {
+            {
+                let mut geometry = geometry_ref.borrow_mut();
+                geometry.rows = rows;
+                geometry.cols = cols;
+            }
+
             let nvim_ref = nvim_ref.clone();
 
             let source_id_moved = source_id_ref.clone();
@@ -176,67 +381,293 @@ impl UI {
             false
         });
 
+        // All the keyboard and mouse input goes to nvim through this queue.
+        let input_queue = InputQueue::new(nvim.clone());
+
+        // Mouse gestures, performed with the right mouse button.
+        let gestures = Arc::new(ThreadGuard::new(Gestures::new(&overlay)));
+        let recording = RecordingIndicator::new(&overlay);
+
+        // Context menu, which takes over the right mouse button when
+        // 'mousemodel' is "popup".
+        let context_menu = Arc::new(ThreadGuard::new(ContextMenu::new(
+            &grid.widget(),
+            nvim.clone(),
+            input_queue.clone(),
+        )));
+
+        // Application menu, menu bar and the Cmd shortcuts on macOS.
+        #[cfg(target_os = "macos")]
+        macos::setup_menus(
+            app,
+            &window,
+            nvim.clone(),
+            input_queue.clone(),
+            context_menu.clone(),
+        );
+
+        // Nvim's 'mouse' option. When nvim doesn't take the mouse, we don't
+        // send it mouse events that it would ignore.
+        let mouse = Arc::new(ThreadGuard::new(Mouse::default()));
+
+        // The context menu is shown where the right button was pressed.
+        let menu_ref = context_menu.clone();
+        grid.widget().connect_button_press_event(move |_, e| {
+            if e.get_button() == 3 {
+                menu_ref.borrow_mut().set_pointer(e.get_position());
+            }
+
+            Inhibit(false)
+        });
+
         // Mouse button press event.
-        let nvim_ref = nvim.clone();
+        let queue_ref = input_queue.clone();
+        let gestures_ref = gestures.clone();
+        let menu_ref = context_menu.clone();
+        let mouse_ref = mouse.clone();
         grid.connect_mouse_button_press_events(move |button, row, col| {
-            let mut nvim = nvim_ref.lock().unwrap();
-            let input = format!("<{}Mouse><{},{}>", button, col, row);
-            nvim.input(&input).expect("Couldn't send mouse input");
+            if let MouseButton::Right = button {
+                // If there are gestures mapped, the actual mouse press is
+                // only sent (or the context menu shown) after we know that
+                // there was no gesture.
+                let mut gestures = gestures_ref.borrow_mut();
+                if gestures.is_enabled() {
+                    gestures.begin(row, col);
+                    return Inhibit(false);
+                }
+
+                let menu = menu_ref.borrow();
+                if menu.is_enabled() {
+                    menu.popup(row, col);
+                    return Inhibit(false);
+                }
+            }
+
+            if !mouse_ref.borrow().is_enabled() {
+                return Inhibit(false);
+            }
+
+            queue_ref.send(format!("<{}Mouse><{},{}>", button, col, row));
 
             Inhibit(false)
         });
 
         // Mouse button release events.
-        let nvim_ref = nvim.clone();
+        let queue_ref = input_queue.clone();
+        let gestures_ref = gestures.clone();
+        let menu_ref = context_menu.clone();
+        let mouse_ref = mouse.clone();
         grid.connect_mouse_button_release_events(move |button, row, col| {
-            let mut nvim = nvim_ref.lock().unwrap();
-            let input = format!("<{}Release><{},{}>", button, col, row);
-            nvim.input(&input).expect("Couldn't send mouse input");
+            let enabled = mouse_ref.borrow().is_enabled();
+
+            if let MouseButton::Right = button {
+                let menu = menu_ref.borrow();
+                let mut gestures = gestures_ref.borrow_mut();
+                if gestures.is_active() {
+                    let input = match gestures.end() {
+                        GestureResult::Click(row, col) if menu.is_enabled() => {
+                            menu.popup(row, col);
+                            return Inhibit(false);
+                        }
+                        GestureResult::Click(..) if !enabled => {
+                            return Inhibit(false)
+                        }
+                        GestureResult::Click(row, col) => format!(
+                            "<RightMouse><{c},{r}><RightRelease><{c},{r}>",
+                            c = col,
+                            r = row
+                        ),
+                        GestureResult::Input(input) => input,
+                        GestureResult::Unmapped => return Inhibit(false),
+                    };
+                    queue_ref.send(input);
+
+                    return Inhibit(false);
+                }
+
+                // The press showed the context menu.
+                if menu.is_enabled() {
+                    return Inhibit(false);
+                }
+            }
+
+            if !enabled {
+                return Inhibit(false);
+            }
+
+            queue_ref.send(format!("<{}Release><{},{}>", button, col, row));
 
             Inhibit(false)
         });
 
         // Mouse drag events.
-        let nvim_ref = nvim.clone();
+        let queue_ref = input_queue.clone();
+        let gestures_ref = gestures.clone();
+        let menu_ref = context_menu.clone();
+        let mouse_ref = mouse.clone();
         grid.connect_motion_events_for_drag(move |button, row, col| {
-            let mut nvim = nvim_ref.lock().unwrap();
-            let input = format!("<{}Drag><{},{}>", button, col, row);
-            nvim.input(&input).expect("Couldn't send mouse input");
+            if let MouseButton::Right = button {
+                let mut gestures = gestures_ref.borrow_mut();
+                if gestures.is_active() {
+                    gestures.motion(row, col);
+                    return Inhibit(false);
+                }
+
+                if menu_ref.borrow().is_enabled() {
+                    return Inhibit(false);
+                }
+            }
+
+            if !mouse_ref.borrow().is_enabled() {
+                return Inhibit(false);
+            }
+
+            queue_ref.send(format!("<{}Drag><{},{}>", button, col, row));
 
             Inhibit(false)
         });
 
-        // Scrolling events.
+        // Current guifont and the state of the zoom (see `zoom`).
+        let guifont = Arc::new(ThreadGuard::new(String::new()));
+        let zoom_state = Arc::new(ThreadGuard::new(Zoom::default()));
+
+        // Ctrl + scroll zooms, instead of scrolling nvim.
         let nvim_ref = nvim.clone();
+        let guifont_ref = guifont.clone();
+        let zoom_ref = zoom_state.clone();
+        grid.widget().connect_scroll_event(move |_, e| {
+            if !e.get_state().contains(gdk::ModifierType::CONTROL_MASK) {
+                return Inhibit(false);
+            }
+
+            let step = match e.get_direction() {
+                gdk::ScrollDirection::Up => 1,
+                gdk::ScrollDirection::Down => -1,
+                _ => return Inhibit(false),
+            };
+            zoom(&nvim_ref, &guifont_ref, &zoom_ref, step);
+
+            Inhibit(true)
+        });
+
+        // Wheel events over our own elements (e.g. the popupmenu) scroll
+        // them, even when the grid gets the events (see `ScrollRouter`).
+        let scroll_router = ScrollRouter::default();
+        let router_ref = scroll_router.clone();
+        grid.widget().connect_scroll_event(move |_, e| {
+            Inhibit(router_ref.scroll(e.get_root(), e.get_direction()))
+        });
+
+        // Scrolling events. These are not propagated further, so that the
+        // overlay doesn't send them again (see below).
+        let queue_ref = input_queue.clone();
         grid.connect_scroll_events(move |dir, row, col| {
-            let mut nvim = nvim_ref.lock().unwrap();
-            let input = format!("<{}><{},{}>", dir, col, row);
-            nvim.input(&input).expect("Couldn't send mouse input");
+            queue_ref.send(format!("<{}><{},{}>", dir, col, row));
 
-            Inhibit(false)
+            Inhibit(true)
         });
 
+        // Input hook that all the keyboard input goes through.
+        let input_hook = Arc::new(ThreadGuard::new(InputHook::default()));
+        let option_as_meta = Arc::new(AtomicBool::new(false));
+
+        // On-screen display of the keys we send to nvim.
+        let key_display = KeyDisplay::new(&overlay);
+        let simulator = Simulator::new(
+            input_queue.clone(),
+            input_hook.clone(),
+            key_display.clone(),
+        );
+
         // IMMulticontext is used to handle most of the inputs.
         let im_context = gtk::IMMulticontext::new();
-        let nvim_ref = nvim.clone();
+        let queue_ref = input_queue.clone();
+        let input_hook_ref = input_hook.clone();
+        let key_display_ref = key_display.clone();
         im_context.set_use_preedit(false);
         im_context.connect_commit(move |_, input| {
             // "<" needs to be escaped for nvim.input()
             let nvim_input = input.replace("<", "<lt>");
 
-            let mut nvim = nvim_ref.lock().unwrap();
-            nvim.input(&nvim_input).expect("Couldn't send input");
+            key_display_ref.input(&nvim_input);
+            input_hook_ref.borrow().process(&queue_ref, nvim_input);
         });
 
+        let cmdline = Cmdline::new(&overlay, nvim.clone(), input_queue.clone());
+
         let im_ref = im_context.clone();
         let nvim_ref = nvim.clone();
-        window.connect_key_press_event(move |_, e| {
+        let queue_ref = input_queue.clone();
+        let input_hook_ref = input_hook.clone();
+        let key_display_ref = key_display.clone();
+        let cmdline_entry = cmdline.entry_widget();
+        let guifont_ref = guifont.clone();
+        let zoom_ref = zoom_state.clone();
+        let settings = preferences::settings();
+        let settings_ref = settings.clone();
+        #[cfg(target_os = "macos")]
+        let app_ref = app.clone();
+        #[cfg(target_os = "macos")]
+        let option_as_meta_ref = option_as_meta.clone();
+        window.connect_key_press_event(move |window, e| {
+            if let Some(step) = zoom_step(e) {
+                if preferences::zoom_shortcuts(settings_ref.as_ref()) {
+                    zoom(&nvim_ref, &guifont_ref, &zoom_ref, step);
+                    return Inhibit(true);
+                }
+            }
+
+            // The cmdline entry (see `Cmdline::set_entry_mode`) handles its
+            // own input.
+            if let Some(focus) = window.get_focus() {
+                if focus == cmdline_entry && focus.is_visible() {
+                    return Inhibit(false);
+                }
+            }
+
+            // Ctrl+Shift+U would otherwise be taken by the input method,
+            // for its own unicode input. Nvim tells us where the cursor
+            // is (see `gnvim#unicode#input`).
+            if is_unicode_input_shortcut(e) {
+                let mut nvim = nvim_ref.lock().unwrap();
+                nvim.command_async("call gnvim#unicode#input()")
+                    .cb(|res| {
+                        if let Err(err) = res {
+                            println!("Failed to show unicode input: {}", err);
+                        }
+                    })
+                    .call();
+                return Inhibit(true);
+            }
+
+            // On macOS, our Cmd shortcuts are left to GTK, and the Option
+            // key is either Meta or composes characters.
+            #[cfg(target_os = "macos")]
+            let input = match macos::translate_key(
+                &app_ref,
+                e,
+                option_as_meta_ref.load(Ordering::SeqCst),
+            ) {
+                Some((keyval, state, cmd)) => {
+                    keyval_to_nvim_input(keyval, state).map(|input| {
+                        if cmd {
+                            format!("<D-{}", &input[1..])
+                        } else {
+                            input
+                        }
+                    })
+                }
+                None => return Inhibit(false),
+            };
+            #[cfg(not(target_os = "macos"))]
+            let input = event_to_nvim_input(e);
+
             if im_ref.filter_keypress(e) {
                 Inhibit(true)
             } else {
-                if let Some(input) = event_to_nvim_input(e) {
-                    let mut nvim = nvim_ref.lock().unwrap();
-                    nvim.input(input.as_str()).expect("Couldn't send input");
+                if let Some(input) = input {
+                    key_display_ref.input(&input);
+                    input_hook_ref.borrow().process(&queue_ref, input);
                     return Inhibit(true);
                 } else {
                     println!(
@@ -267,8 +698,55 @@ impl UI {
             Inhibit(false)
         });
 
-        let cmdline = Cmdline::new(&overlay, nvim.clone());
+        // Our grid recreates its surface and cell metrics when the scale
+        // factor or the screen's resolution changes. Nvim needs to know our
+        // new size, and to redraw the content for the new scale.
+        let nvim_ref = nvim.clone();
+        grid.connect_scale_changed(move |rows, cols| {
+            let mut nvim = nvim_ref.lock().unwrap();
+            nvim.ui_try_resize_async(cols as i64, rows as i64)
+                .cb(|res| {
+                    if let Err(err) = res {
+                        eprintln!("Failed to resize nvim: {}", err);
+                    }
+                })
+                .call();
+            nvim.command_async("redraw!")
+                .cb(|res| {
+                    if let Err(err) = res {
+                        eprintln!("Failed to redraw: {}", err);
+                    }
+                })
+                .call();
+        });
+
         let cursor_tooltip = CursorTooltip::new(&overlay);
+        let unicode_input =
+            UnicodeInput::new(&overlay.clone().upcast(), nvim.clone());
+        let replace_panel = ReplacePanel::new(&overlay, nvim.clone());
+
+        // Wheel events that the overlays (e.g. the popupmenu's layout) get
+        // where they have nothing to scroll are sent to nvim for the cell
+        // under them. The overlays' windows take the overlay's event mask.
+        overlay.add_events(gdk::EventMask::SCROLL_MASK.bits() as i32);
+        let router_ref = scroll_router.clone();
+        overlay.connect_scroll_event(move |_, e| {
+            // Zooming is only done over the grid.
+            if e.get_state().contains(gdk::ModifierType::CONTROL_MASK) {
+                return Inhibit(true);
+            }
+
+            Inhibit(router_ref.scroll(e.get_root(), e.get_direction()))
+        });
+        let queue_ref = input_queue.clone();
+        grid.connect_scroll_events_of(
+            overlay.upcast_ref(),
+            move |dir, row, col| {
+                queue_ref.send(format!("<{}><{},{}>", dir, col, row));
+
+                Inhibit(true)
+            },
+        );
 
         window.show_all();
 
@@ -276,10 +754,53 @@ impl UI {
 
         cmdline.hide();
         cursor_tooltip.hide();
+        replace_panel.hide();
+        quickfix.hide();
+
+        // The popupmenu hides itself, so it's created after the rest are
+        // shown.
+        let popupmenu = Popupmenu::new(&overlay, nvim.clone());
+
+        // Topmost first.
+        scroll_router.add(popupmenu.scroll_area());
+        scroll_router.add(cursor_tooltip.scroll_area());
+        scroll_router.add(replace_panel.scroll_area());
 
         let mut grids = HashMap::new();
         grids.insert(1, grid);
 
+        let max_fps = Arc::new(AtomicUsize::new(0));
+        let debug_console =
+            DebugConsole::new(&window, rpc_trace.clone(), input_queue.stats());
+        let perf_hud = PerfHud::new(&overlay, input_queue.stats());
+
+        let notifier = Notifier::new(&window);
+        let task = TaskIndicator::new(&window, headerbar.as_ref());
+
+        let mut rounded_selection = false;
+        let mut ghost_text = GhostText::default();
+        let preferences = settings.as_ref().map(|settings| {
+            rounded_selection = settings.get_boolean("rounded-selection");
+            ghost_text.enabled = settings.get_boolean("ghost-text");
+            max_fps
+                .store(settings.get_uint("max-fps") as usize, Ordering::SeqCst);
+            for grid in grids.values() {
+                grid.set_beacon(settings.get_uint("cursor-beacon") as u64);
+                grid.set_mode_flash(settings.get_boolean("cursor-mode-flash"));
+            }
+            window.set_opacity(settings.get_double("opacity"));
+
+            preferences::connect_changed(
+                settings,
+                window.clone(),
+                nvim.clone(),
+                guifont.clone(),
+                max_fps.clone(),
+            );
+
+            Preferences::new(&window, settings.clone())
+        });
+
         UI {
             win: Arc::new(ThreadGuard::new(window)),
             rx,
@@ -287,18 +808,153 @@ impl UI {
                 grids: grids,
                 mode_infos: vec![],
                 current_grid: 1,
-                popupmenu: Popupmenu::new(&overlay, nvim.clone()),
+                popupmenu,
                 cmdline,
                 overlay,
                 tabline,
                 cursor_tooltip,
+                replace_panel,
+                quickfix,
                 resize_source_id: source_id,
                 hl_defs,
+                new_window_handler: None,
+                input_hook,
+                option_as_meta,
+                simulator,
+                key_display,
+                gestures,
+                recording,
+                animations: Animations::default(),
+                context_menu,
+                mouse,
+                unicode_input,
+                headerbar,
+                task,
+                runtime_check,
+                selection_bg: None,
+                rounded_selection,
+                ghost_text,
+                max_fps: max_fps.clone(),
+                rpc_trace,
+                debug_console,
+                perf_hud,
+                notifier,
+                settings,
+                preferences,
+                guifont,
+                zoom: zoom_state,
+                project_base_font: None,
+                trust_asked: HashSet::new(),
+                user_css: paths::config_dir()
+                    .map(|dir| UserCss::new(dir.join("gnvim.css"))),
             })),
             nvim,
+            closed: Arc::new(AtomicBool::new(false)),
+            max_fps,
         }
     }
 
+    /// Limits how often the UI is rendered (and the cursor animated) to
+    /// `fps` frames per second. Zero removes the limit, in which case we
+    /// follow the refresh rate of the monitor.
+    pub fn set_max_fps(&self, fps: u32) {
+        self.max_fps.store(fps as usize, Ordering::SeqCst);
+    }
+
+    /// Sets `f` to be called when nvim asks for a new window. `f` receives
+    /// nvim's server address.
+    pub fn connect_new_window<F: 'static>(&self, f: F)
+    where
+        F: Fn(&str),
+    {
+        let mut state = self.state.borrow_mut();
+        state.new_window_handler = Some(Box::new(f));
+    }
+
+    /// Detaches the UI from nvim when the window is closed, instead of
+    /// waiting nvim to exit. Used for windows that are not the first UI
+    /// attached to nvim.
+    ///
+    /// After detaching, nvim is asked to close our `channel`. That ends
+    /// our connection to nvim, and with it the threads that receive the
+    /// messages from nvim (see `start`), which would otherwise keep the
+    /// connection alive.
+    pub fn detach_on_close(&self, channel: u64) {
+        let nvim = self.nvim.clone();
+        let closed = self.closed.clone();
+        self.win.borrow().connect_delete_event(move |_, _| {
+            closed.store(true, Ordering::SeqCst);
+
+            let mut nvim = nvim.lock().unwrap();
+            if let Err(err) = nvim.ui_detach() {
+                eprintln!("Failed to detach from nvim: {}", err);
+            }
+            // Nvim closes the channel before it answers, so there's no
+            // answer to wait for.
+            nvim.command_async(&format!("call chanclose({})", channel))
+                .call();
+
+            Inhibit(false)
+        });
+    }
+
+    /// Asks the user what to do with the unsaved changes when the window is
+    /// closed, instead of just killing nvim. Used for the first UI attached
+    /// to nvim.
+    pub fn confirm_quit_on_close(&self) {
+        let nvim = self.nvim.clone();
+        let win_ref = self.win.clone();
+        self.win.borrow().connect_delete_event(move |_, _| {
+            let nvim_ref = nvim.clone();
+            let win = win_ref.clone();
+
+            // Don't block the main loop while waiting for nvim, it might be
+            // busy.
+            let mut nvim = nvim.lock().unwrap();
+            quit::modified_buffers(&mut nvim, move |buffers| {
+                glib::idle_add(move || {
+                    match buffers {
+                        Ok(ref buffers) if buffers.is_empty() => {
+                            quit_nvim(&nvim_ref, "qall")
+                        }
+                        _ => {
+                            let nvim = nvim_ref.clone();
+                            quit::confirm(
+                                &win.borrow(),
+                                &buffers,
+                                move |cmd| quit_nvim(&nvim, cmd),
+                            );
+                        }
+                    }
+
+                    glib::Continue(false)
+                });
+            });
+
+            // Our window is destroyed once nvim exits.
+            Inhibit(true)
+        });
+    }
+
+    /// Offers to restore the previous session, if there is one.
+    pub fn offer_session_restore(&self) {
+        if session::saved().is_none() {
+            return;
+        }
+
+        let nvim = self.nvim.clone();
+        session::ask_restore(self.win.borrow().upcast_ref(), move || {
+            let mut nvim = nvim.lock().unwrap();
+            nvim.command_async("call gnvim#session#restore()")
+                .cb(|res| {
+                    if let Err(err) = res {
+                        eprintln!("Failed to restore the session: {}", err);
+                    }
+                })
+                .call();
+        });
+    }
+
     /// Starts to listen events from `rx` (e.g. from nvim) and processing those.
     /// Think this as the "main" function of the UI.
     pub fn start(self) {
@@ -306,15 +962,95 @@ impl UI {
         let state = self.state.clone();
         let win = self.win.clone();
         let nvim = self.nvim.clone();
+        let closed = self.closed.clone();
+        let max_fps = self.max_fps.clone();
+        let rpc_trace = self.state.borrow().rpc_trace.clone();
+
+        // Duration of one frame of our window, in microseconds. Updated by
+        // the UI thread from the window's frame clock.
+        let frame_interval =
+            Arc::new(AtomicUsize::new(DEFAULT_FRAME_INTERVAL as usize));
+
+        // After a suspend, our surfaces might have lost their content. Nvim
+        // redraws everything to the new ones.
+        let state_ref = state.clone();
+        let nvim_ref = nvim.clone();
+        resume::connect_resume(move || {
+            for grid in state_ref.borrow().grids.values() {
+                grid.reset_surface();
+            }
+
+            let mut nvim = nvim_ref.lock().unwrap();
+            nvim.command_async("redraw!")
+                .cb(|res| {
+                    if let Err(err) = res {
+                        eprintln!("Failed to redraw: {}", err);
+                    }
+                })
+                .call();
+        });
 
         thread::spawn(move || {
             let timeout = time::Duration::from_millis(33);
 
+            let mut watchdog =
+                Watchdog::new(time::Duration::from_secs(WATCHDOG_TIMEOUT));
+            // Number of notifies we've passed to the UI thread and the number
+            // of notifies it has handled. Used to notice if the UI is stuck.
+            let mut scheduled = 0;
+            let handled = Arc::new(AtomicUsize::new(0));
+
+            // Notifies are batched and passed to the UI thread at most once
+            // per frame, so that multiple flushes (e.g. from fast output in
+            // a terminal) results in only one render.
+            let mut batch = vec![];
+            let mut last_dispatch = time::Instant::now();
+            // When the first notify of the current batch was received.
+            let mut batch_start = time::Instant::now();
+
             loop {
+                let mut frame = time::Duration::from_micros(
+                    frame_interval.load(Ordering::SeqCst) as u64,
+                );
+
+                // Obey the frame rate cap, both for rendering and for the
+                // tick.
+                let mut timeout = timeout;
+                let fps = max_fps.load(Ordering::SeqCst) as u32;
+                if fps > 0 {
+                    frame = frame.max(time::Duration::from_secs(1) / fps);
+                    timeout = timeout.max(frame);
+                }
+
                 // Use timeout, so we can use this loop to "tick" the current
-                // grid (mainly to just blink the cursor).
+                // grid (mainly to just blink the cursor). If we have a batch
+                // waiting, wake up when it is time to dispatch it.
+                let timeout = if batch.is_empty() {
+                    timeout
+                } else {
+                    frame
+                        .checked_sub(last_dispatch.elapsed())
+                        .unwrap_or_default()
+                };
                 let message = rx.recv_timeout(timeout);
 
+                // If our window is closed, there is nothing to do with the
+                // messages anymore.
+                if closed.load(Ordering::SeqCst) {
+                    batch.clear();
+
+                    match message {
+                        Err(RecvTimeoutError::Disconnected) => break,
+                        Ok(Message::Request(tx, _)) => {
+                            tx.send(Err("Window closed".into()))
+                                .expect("Failed to respond to a request");
+                        }
+                        _ => {}
+                    }
+
+                    continue;
+                }
+
                 match message {
                     // If the sender disconnects, then neovim exited. This
                     // means that we need to exit too.
@@ -322,8 +1058,9 @@ impl UI {
                         break;
                     }
                     // If we 'just' got a timeout, then we should tick the
-                    // current grid (e.g. blink the cursor).
-                    Err(RecvTimeoutError::Timeout) => {
+                    // current grid (e.g. blink the cursor). If there is a
+                    // batch waiting, it is dispatched below.
+                    Err(RecvTimeoutError::Timeout) if batch.is_empty() => {
                         // TODO(ville): Can we combine this with Ok(Message::Notify(notify))?
                         let state = state.clone();
                         glib::idle_add(move || {
@@ -335,31 +1072,39 @@ impl UI {
                             glib::Continue(false)
                         });
                     }
+                    Err(RecvTimeoutError::Timeout) => {}
                     // Handle a notify.
                     Ok(Message::Notify(notify)) => {
-                        let state = state.clone();
-                        let nvim = nvim.clone();
-                        let win = win.clone();
-                        glib::idle_add(move || {
-                            let mut state = state.borrow_mut();
-
-                            handle_notify(
-                                &win.borrow(),
-                                &notify,
-                                &mut state,
-                                nvim.clone(),
-                            );
-
-                            // Tick the current active grid.
-                            let grid =
-                                state.grids.get(&state.current_grid).unwrap();
-                            grid.tick();
+                        if let Notify::RedrawEvent(ref events) = notify {
+                            let now = time::Instant::now();
+                            for event in events {
+                                watchdog.redraw_event(event, now);
+                            }
+                        }
 
-                            glib::Continue(false)
-                        });
+                        if batch.is_empty() {
+                            batch_start = time::Instant::now();
+                        }
+                        batch.push(notify);
                     }
                     // Handle a request.
                     Ok(Message::Request(tx, request)) => {
+                        // Requests might depend on the earlier notifies, so
+                        // handle those first.
+                        if !batch.is_empty() {
+                            scheduled += 1;
+                            profiler::record("batch", "batch", batch_start);
+                            dispatch_notifies(
+                                batch.split_off(0),
+                                state.clone(),
+                                win.clone(),
+                                nvim.clone(),
+                                handled.clone(),
+                                frame_interval.clone(),
+                            );
+                            last_dispatch = time::Instant::now();
+                        }
+
                         let state = state.clone();
 
                         glib::idle_add(move || {
@@ -372,17 +1117,181 @@ impl UI {
                         });
                     }
                 }
+
+                if !batch.is_empty() && last_dispatch.elapsed() >= frame {
+                    scheduled += 1;
+                    profiler::record("batch", "batch", batch_start);
+                    dispatch_notifies(
+                        batch.split_off(0),
+                        state.clone(),
+                        win.clone(),
+                        nvim.clone(),
+                        handled.clone(),
+                        frame_interval.clone(),
+                    );
+                    last_dispatch = time::Instant::now();
+                }
+
+                let now = time::Instant::now();
+                let behind = handled.load(Ordering::SeqCst) != scheduled;
+                watchdog.ui_progress(behind, now);
+
+                match watchdog.check(now) {
+                    // If the UI thread is stuck, we can't get anything out of
+                    // it so just write what we have.
+                    Some(Stall::Ui) => report_stall(
+                        &Stall::Ui,
+                        &watchdog.trace(),
+                        &watchdog::rpc_report(&rpc_trace),
+                        None,
+                        None,
+                    ),
+                    // Nvim is stuck, but the UI isn't so we can capture the
+                    // grid and let the user know about the stall.
+                    Some(Stall::Flush) => {
+                        let trace = watchdog.trace();
+                        let rpc = watchdog::rpc_report(&rpc_trace);
+                        let state = state.clone();
+                        let nvim = nvim.clone();
+                        glib::idle_add(move || {
+                            let state = state.borrow();
+                            let grid =
+                                state.grids.get(&state.current_grid).unwrap();
+                            report_stall(
+                                &Stall::Flush,
+                                &trace,
+                                &rpc,
+                                Some(&grid.text_snapshot()),
+                                Some(nvim.clone()),
+                            );
+
+                            glib::Continue(false)
+                        });
+                    }
+                    None => {}
+                }
             }
 
-            // Close the window once the recv loop exits.
+            // Close the window once the recv loop exits. The window is
+            // destroyed directly, because closing would ask for confirmation
+            // from nvim which is not there anymore.
             glib::idle_add(move || {
-                win.borrow().close();
+                if !closed.load(Ordering::SeqCst) {
+                    win.borrow().destroy();
+                }
                 glib::Continue(false)
             });
         });
     }
 }
 
+/// Passes a batch of notifies to the UI thread. Once the batch is handled,
+/// `handled` is incremented and `frame_interval` is updated from the
+/// window's frame clock.
+fn dispatch_notifies(
+    batch: Vec<Notify>,
+    state: Arc<ThreadGuard<UIState>>,
+    win: Arc<ThreadGuard<gtk::ApplicationWindow>>,
+    nvim: Arc<Mutex<Neovim>>,
+    handled: Arc<AtomicUsize>,
+    frame_interval: Arc<AtomicUsize>,
+) {
+    let scheduled = time::Instant::now();
+
+    glib::idle_add(move || {
+        // Time it took for the UI thread to get to the batch.
+        profiler::record("ui", "wait", scheduled);
+        let _frame = profiler::frame();
+
+        let mut state = state.borrow_mut();
+        let win = win.borrow();
+
+        let start = time::Instant::now();
+        for notify in batch.iter() {
+            let _span = profiler::span(
+                "apply",
+                match notify {
+                    Notify::RedrawEvent(_) => "redraw",
+                    Notify::GnvimEvent(_) => "gnvim",
+                },
+            );
+            handle_notify(&win, notify, &mut state, nvim.clone());
+        }
+
+        // Tick the current active grid.
+        let grid = state.grids.get(&state.current_grid).unwrap();
+        grid.tick();
+        state
+            .perf_hud
+            .record_batch(start.elapsed(), grid.frame_count());
+
+        // Follow the refresh rate of the monitor our window is on.
+        if let Some(clock) = win.get_frame_clock() {
+            let (interval, _) = clock.get_refresh_info(0);
+            if interval > 0 {
+                frame_interval.store(interval as usize, Ordering::SeqCst);
+            }
+        }
+
+        handled.fetch_add(1, Ordering::SeqCst);
+
+        glib::Continue(false)
+    });
+}
+
+/// Writes a stall report to the disk and notifies the user about it.
+fn report_stall(
+    stall: &Stall,
+    trace: &str,
+    rpc: &str,
+    grid: Option<&str>,
+    nvim: Option<Arc<Mutex<Neovim>>>,
+) {
+    let msg = match watchdog::write_report(stall, trace, rpc, grid) {
+        Ok(path) => format!(
+            "Gnvim watchdog: {}, trace written to {}",
+            stall,
+            path.display()
+        ),
+        Err(err) => {
+            format!("Gnvim watchdog: {}, failed to write trace: {}", stall, err)
+        }
+    };
+
+    eprintln!("{}", msg);
+
+    if let Some(nvim) = nvim {
+        echom_error(&nvim, &msg);
+    }
+}
+
+/// Shows `msg` to the user with `:echom`, e.g. to tell that one of our
+/// functions failed.
+fn echom_error(nvim: &Arc<Mutex<Neovim>>, msg: &str) {
+    let mut nvim = nvim.lock().unwrap();
+    nvim.command_async(&format!(
+        "echom \"{}\"",
+        msg.replace("\\", "\\\\").replace("\"", "\\\"")
+    ))
+    .cb(|res| {
+        if let Err(err) = res {
+            eprintln!("Failed to execute nvim command: {}", err)
+        }
+    })
+    .call();
+}
+
+fn quit_nvim(nvim: &Arc<Mutex<Neovim>>, cmd: &str) {
+    let mut nvim = nvim.lock().unwrap();
+    nvim.command_async(cmd)
+        .cb(|res| {
+            if let Err(err) = res {
+                println!("Failed to quit nvim: {}", err);
+            }
+        })
+        .call();
+}
+
 fn handle_request(
     request: &Request,
     state: &mut UIState,
@@ -412,18 +1321,10 @@ fn handle_notify(
         Notify::GnvimEvent(event) => match event {
             Ok(event) => handle_gnvim_event(event, state, nvim),
             Err(err) => {
-                let mut nvim = nvim.lock().unwrap();
-                nvim.command_async(&format!(
-                    "echom \"Failed to parse gnvim notify: '{}'\"",
-                    err
-                ))
-                .cb(|res| match res {
-                    Ok(_) => {}
-                    Err(err) => {
-                        println!("Failed to execute nvim command: {}", err)
-                    }
-                })
-                .call();
+                echom_error(
+                    &nvim,
+                    &format!("Failed to parse gnvim notify: '{}'", err),
+                );
             }
         },
     }
@@ -442,34 +1343,31 @@ fn handle_gnvim_event(
             state
                 .cmdline
                 .wildmenu_set_colors(&colors.wildmenu, &state.hl_defs);
+
+            state.selection_bg = colors.visual_bg;
+            update_selection_style(state);
+
+            if let Some(ref user_css) = state.user_css {
+                user_css
+                    .update_palette(|palette| palette.set_gui_colors(colors));
+            }
         }
         GnvimEvent::CompletionMenuToggleInfo => {
             state.popupmenu.toggle_show_info()
         }
         GnvimEvent::CursorTooltipLoadStyle(path) => {
             if let Err(err) = state.cursor_tooltip.load_style(path.clone()) {
-                let mut nvim = nvim.lock().unwrap();
-                nvim.command_async(&format!(
-                    "echom \"Cursor tooltip load style failed: '{}'\"",
-                    err
-                ))
-                .cb(|res| match res {
-                    Ok(_) => {}
-                    Err(err) => {
-                        println!("Failed to execute nvim command: {}", err)
-                    }
-                })
-                .call();
+                echom_error(
+                    &nvim,
+                    &format!("Cursor tooltip load style failed: '{}'", err),
+                );
             }
         }
         GnvimEvent::CursorTooltipShow(content, row, col) => {
             state.cursor_tooltip.show(content.clone());
 
             let grid = state.grids.get(&state.current_grid).unwrap();
-            let mut rect = grid.get_rect_for_cell(*row, *col);
-
-            let extra_h = state.tabline.get_height();
-            rect.y -= extra_h;
+            let rect = overlay_rect(&state.overlay, grid, *row, *col);
 
             state.cursor_tooltip.move_to(&rect);
         }
@@ -483,12 +1381,335 @@ fn handle_gnvim_event(
         GnvimEvent::PopupmenuWidthDetails(width) => {
             state.popupmenu.set_width_details(*width as i32);
         }
+        GnvimEvent::PopupmenuMaxRows(rows) => {
+            state.popupmenu.set_max_rows(*rows as i32);
+        }
+        GnvimEvent::CmdlineEntryMode(enabled) => {
+            state.cmdline.set_entry_mode(*enabled);
+        }
+        GnvimEvent::ReplacePreview(preview) => {
+            state.replace_panel.show(preview.clone());
+        }
+        GnvimEvent::ProjectDirChanged(dir) => {
+            update_project(state, &nvim, Path::new(dir));
+        }
+        GnvimEvent::QuickfixSet(list) => {
+            state.quickfix.set_list(list.clone());
+        }
+        GnvimEvent::QuickfixShow(show) => {
+            if *show {
+                state.quickfix.show();
+            } else {
+                state.quickfix.hide();
+            }
+        }
+        GnvimEvent::Print(job) => {
+            let window = state
+                .overlay
+                .get_toplevel()
+                .and_then(|w| w.downcast::<gtk::Window>().ok());
+            if let Some(window) = window {
+                let font = Font::from_guifont(&state.guifont.borrow())
+                    .unwrap_or(Font::default());
+
+                if let Err(err) =
+                    print::print(&window, job.clone(), font.as_pango_font())
+                {
+                    echom_error(&nvim, &err);
+                }
+            }
+        }
+        GnvimEvent::PopupmenuGhostText(enabled) => {
+            state.ghost_text.enabled = *enabled;
+            preferences::store_bool(
+                state.settings.as_ref(),
+                "ghost-text",
+                *enabled,
+            );
+        }
+        GnvimEvent::NewWindow(address) => {
+            if let Some(ref handler) = state.new_window_handler {
+                handler(address.as_str());
+            }
+        }
+        GnvimEvent::WindowSnap(snap) => match Snap::from_str(snap) {
+            Some(snap) => {
+                let window = state
+                    .overlay
+                    .get_toplevel()
+                    .and_then(|w| w.downcast::<gtk::Window>().ok());
+                if let Some(window) = window {
+                    snap::snap(&window, snap);
+                }
+            }
+            None => println!("Unknown window snap: {}", snap),
+        },
+        GnvimEvent::WindowAnimate(anim) => {
+            let window = state
+                .overlay
+                .get_toplevel()
+                .and_then(|w| w.downcast::<gtk::Window>().ok());
+            let res = match window {
+                Some(window) => state.animations.animate(&window, anim),
+                None => Ok(()),
+            };
+
+            if let Err(err) = res {
+                let msg = format!("Gnvim: failed to animate ({})", err);
+                echom_error(&nvim, &msg);
+            }
+        }
+        GnvimEvent::InputHookSet(function) => {
+            state.input_hook.borrow_mut().set(function.clone());
+        }
+        GnvimEvent::OptionAsMeta(enabled) => {
+            state.option_as_meta.store(*enabled, Ordering::SeqCst);
+        }
+        GnvimEvent::InputSimulate(sequence, delay, show) => {
+            state.simulator.run(sequence, *delay, *show);
+        }
+        GnvimEvent::InputSimulateStop => {
+            state.simulator.stop();
+        }
+        GnvimEvent::KeyDisplay(enabled) => {
+            state.key_display.set_enabled(*enabled);
+        }
+        GnvimEvent::GestureSet(gesture, input) => {
+            state
+                .gestures
+                .borrow_mut()
+                .set_mapping(gesture.clone(), input.clone());
+        }
+        GnvimEvent::MouseModel(mousemodel) => {
+            state.context_menu.borrow_mut().set_mousemodel(mousemodel);
+        }
+        GnvimEvent::Mouse(mouse) => {
+            state.mouse.borrow_mut().set_option(mouse);
+            update_mouse(state);
+        }
+        GnvimEvent::MenuEntrySet(label, command) => {
+            state
+                .context_menu
+                .borrow_mut()
+                .set_entry(label.clone(), command.clone());
+        }
+        GnvimEvent::RoundedSelection(rounded) => {
+            state.rounded_selection = *rounded;
+            update_selection_style(state);
+            preferences::store_bool(
+                state.settings.as_ref(),
+                "rounded-selection",
+                *rounded,
+            );
+        }
+        GnvimEvent::RpcTrace(enabled) => {
+            state.rpc_trace.lock().unwrap().set_enabled(*enabled);
+        }
+        GnvimEvent::Profile(enabled) => {
+            let was_enabled = profiler::is_enabled();
+            profiler::set_enabled(*enabled);
+
+            if was_enabled && !enabled {
+                let msg = match profiler::export() {
+                    Ok(path) => {
+                        format!("Profile written to {}", path.display())
+                    }
+                    Err(err) => format!("Failed to write the profile: {}", err),
+                };
+
+                echom_error(&nvim, &msg);
+            }
+        }
+        GnvimEvent::DesktopNotify(msg, level) => {
+            state
+                .notifier
+                .notify(msg, notification::Level::from_u64(*level));
+        }
+        GnvimEvent::DesktopNotifyLevel(level) => {
+            state
+                .notifier
+                .set_min_level(notification::Level::from_u64(*level));
+        }
+        GnvimEvent::ShowDebugConsole => {
+            state.debug_console.show();
+        }
+        GnvimEvent::PerfHud(enabled) => {
+            state.perf_hud.set_enabled(*enabled);
+        }
+        GnvimEvent::ShowPreferences => {
+            if let Some(ref preferences) = state.preferences {
+                preferences.show();
+            } else {
+                let mut nvim = nvim.lock().unwrap();
+                nvim.command_async(
+                    "echoerr 'Preferences are not available (the GSettings \
+                     schema is not installed)'",
+                )
+                .cb(|res| {
+                    if let Err(err) = res {
+                        eprintln!("Failed to show an error: {}", err);
+                    }
+                })
+                .call();
+            }
+        }
+        GnvimEvent::SetMaxFps(fps) => {
+            state.max_fps.store(*fps as usize, Ordering::SeqCst);
+            preferences::store_uint(
+                state.settings.as_ref(),
+                "max-fps",
+                *fps as u32,
+            );
+        }
+        GnvimEvent::ShowDamage(show) => {
+            for grid in state.grids.values() {
+                grid.set_show_damage(*show);
+            }
+        }
+        GnvimEvent::Dithering(enabled) => {
+            for grid in state.grids.values() {
+                grid.set_dithering(*enabled);
+            }
+        }
+        GnvimEvent::CursorCrosshair(enabled) => {
+            for grid in state.grids.values() {
+                grid.set_crosshair(*enabled);
+            }
+        }
+        GnvimEvent::ScrollRestore(reg, rows) => {
+            let grid = state.grids.get(&1).unwrap();
+            grid.scroll_restore(*reg, *rows, state.hl_defs.default_bg);
+        }
+        GnvimEvent::CursorBeacon(rows) => {
+            for grid in state.grids.values() {
+                grid.set_beacon(*rows);
+            }
+            preferences::store_uint(
+                state.settings.as_ref(),
+                "cursor-beacon",
+                *rows as u32,
+            );
+        }
+        GnvimEvent::CursorModeFlash(enabled) => {
+            for grid in state.grids.values() {
+                grid.set_mode_flash(*enabled);
+            }
+            preferences::store_bool(
+                state.settings.as_ref(),
+                "cursor-mode-flash",
+                *enabled,
+            );
+        }
+        GnvimEvent::ImageShow(id, path, row, col, max_cols, max_rows, clip) => {
+            let grid = state.grids.get(&1).unwrap();
+            if let Err(err) = grid
+                .show_image(*id, path, *row, *col, *max_cols, *max_rows, *clip)
+            {
+                echom_error(&nvim, &err);
+            }
+        }
+        GnvimEvent::ImageHide(id) => {
+            state.grids.get(&1).unwrap().hide_image(*id);
+        }
+        GnvimEvent::ImageClear => {
+            state.grids.get(&1).unwrap().clear_images();
+        }
+        GnvimEvent::RecordingSet(register) => {
+            state.recording.set(register);
+        }
+        GnvimEvent::UnicodeInputShow(row, col) => {
+            let grid = state.grids.get(&state.current_grid).unwrap();
+            let rect = overlay_rect(&state.overlay, grid, *row, *col);
+
+            state.unicode_input.show(&rect);
+        }
+        GnvimEvent::RuntimeVersion(version, path) => {
+            state.runtime_check.check(*version, path);
+        }
+        GnvimEvent::CellWidths(widths, ambiwidth_double) => {
+            let widths = CellWidths::new(widths.clone(), *ambiwidth_double);
+            state.ghost_text.set_cell_widths(widths.clone());
+            for grid in state.grids.values() {
+                grid.set_cell_widths(widths.clone());
+                grid.redraw(&state.hl_defs);
+            }
+        }
+        GnvimEvent::SetWmClass(name) => {
+            let window = state
+                .overlay
+                .get_toplevel()
+                .and_then(|w| w.downcast::<gtk::Window>().ok());
+            if let Some(window) = window {
+                if let Err(err) = wm_class::set(&window, name) {
+                    echom_error(&nvim, &err);
+                }
+            }
+        }
+        GnvimEvent::TaskBegin(name) => {
+            state.task.begin(name);
+        }
+        GnvimEvent::TaskUpdate(progress) => {
+            state.task.set_progress(*progress);
+        }
+        GnvimEvent::TaskEnd(status) => {
+            let status = task::Status::parse(status);
+            if let Some(name) = state.task.end(status) {
+                let (msg, level) = match status {
+                    task::Status::Success => (
+                        format!("{} finished", name),
+                        notification::Level::Info,
+                    ),
+                    task::Status::Failure => {
+                        (format!("{} failed", name), notification::Level::Error)
+                    }
+                };
+                state.notifier.send(&msg, level);
+            }
+        }
+        GnvimEvent::HeaderbarPath(path) => {
+            if let Some(ref mut headerbar) = state.headerbar {
+                headerbar.set_path(path);
+            }
+        }
+        GnvimEvent::CopySelection(target, text) => {
+            let selection = match target.as_str() {
+                "primary" => Some(gdk::SELECTION_PRIMARY),
+                "clipboard" => Some(gdk::SELECTION_CLIPBOARD),
+                _ => None,
+            };
+
+            if let Some(selection) = selection {
+                gtk::Clipboard::get(&selection).set_text(text);
+            } else {
+                let msg = format!(
+                    "Gnvim: unknown selection '{}' in g:gnvim_copy_on_select",
+                    target
+                );
+                echom_error(&nvim, &msg);
+            }
+        }
         GnvimEvent::Unknown(msg) => {
             println!("Received unknown GnvimEvent: {}", msg);
         }
     }
 }
 
+/// Tells the grids if nvim takes the mouse.
+fn update_mouse(state: &UIState) {
+    let enabled = state.mouse.borrow().is_enabled();
+    for grid in state.grids.values() {
+        grid.set_mouse_enabled(enabled);
+    }
+}
+
+/// Passes the selection style from `state` to the grids.
+fn update_selection_style(state: &UIState) {
+    for grid in state.grids.values() {
+        grid.set_selection_style(state.selection_bg, state.rounded_selection);
+        grid.redraw(&state.hl_defs);
+    }
+}
+
 fn handle_redraw_event(
     window: &gtk::ApplicationWindow,
     events: &Vec<RedrawEvent>,
@@ -498,7 +1719,17 @@ fn handle_redraw_event(
     for event in events {
         match event {
             RedrawEvent::SetTitle(title) => {
-                window.set_title(title);
+                state.task.set_title(title);
+                if let Some(ref mut headerbar) = state.headerbar {
+                    headerbar.set_title(title);
+                }
+            }
+            RedrawEvent::SetIcon(icon) => {
+                // This is the name shown when the window is minimized, not
+                // the icon image.
+                if let Some(win) = window.get_window() {
+                    win.set_icon_name(Some(icon.as_str()));
+                }
             }
             RedrawEvent::GridLine(lines) => {
                 for line in lines {
@@ -531,6 +1762,7 @@ fn handle_redraw_event(
                 grid.cursor_goto(*row, *col);
             }
             RedrawEvent::GridResize(grid, width, height) => {
+                let _span = profiler::span("layout", "resize");
                 let grid = state.grids.get(grid).unwrap();
                 grid.resize(*width, *height);
             }
@@ -538,6 +1770,18 @@ fn handle_redraw_event(
                 let grid = state.grids.get(grid).unwrap();
                 grid.clear(&state.hl_defs);
             }
+            RedrawEvent::GridDestroy(id) => {
+                // The default grid (1) is never destroyed by nvim, and we
+                // can't live without it.
+                if *id != 1 {
+                    if let Some(grid) = state.grids.remove(id) {
+                        grid.widget().destroy();
+                    }
+                    if state.current_grid == *id {
+                        state.current_grid = 1;
+                    }
+                }
+            }
             RedrawEvent::GridScroll(grid, reg, rows, cols) => {
                 let grid = state.grids.get(grid).unwrap();
                 grid.scroll(*reg, *rows, *cols, &state.hl_defs);
@@ -570,6 +1814,12 @@ fn handle_redraw_event(
                 }
 
                 state.cursor_tooltip.set_colors(*fg, *bg);
+
+                if let Some(ref user_css) = state.user_css {
+                    user_css.update_palette(|palette| {
+                        palette.set_default_colors(*fg, *bg, *sp)
+                    });
+                }
             }
             RedrawEvent::HlAttrDefine(defs) => {
                 for (id, hl) in defs {
@@ -580,6 +1830,23 @@ fn handle_redraw_event(
                 for opt in opts {
                     match opt {
                         OptionSet::GuiFont(font) => {
+                            state
+                                .zoom
+                                .borrow_mut()
+                                .guifont_changed(&state.guifont.borrow(), font);
+                            // Update the current font before the settings,
+                            // so that the settings' change handler doesn't
+                            // set it back to nvim.
+                            *state.guifont.borrow_mut() = font.clone();
+                            // Project's font size is not our preference.
+                            if state.project_base_font.is_none() {
+                                preferences::store_string(
+                                    state.settings.as_ref(),
+                                    "font",
+                                    font,
+                                );
+                            }
+
                             let font = Font::from_guifont(font)
                                 .unwrap_or(Font::default());
                             let pango_font = font.as_pango_font();
@@ -653,7 +1920,11 @@ fn handle_redraw_event(
             RedrawEvent::ModeInfoSet(_cursor_shape_enabled, infos) => {
                 state.mode_infos = infos.clone();
             }
-            RedrawEvent::ModeChange(_name, idx) => {
+            RedrawEvent::ModeChange(name, idx) => {
+                state.context_menu.borrow_mut().set_mode(name);
+                state.mouse.borrow_mut().set_mode(name);
+                update_mouse(state);
+
                 let mode = state.mode_infos.get(*idx as usize).unwrap();
                 // Broadcast the mode change to all grids.
                 // TODO(ville): It might be enough to just set the mode to the
@@ -661,6 +1932,10 @@ fn handle_redraw_event(
                 for grid in state.grids.values() {
                     grid.set_mode(mode);
                 }
+
+                if let Some(grid) = state.grids.get(&state.current_grid) {
+                    grid.flash_mode();
+                }
             }
             RedrawEvent::SetBusy(busy) => {
                 for grid in state.grids.values() {
@@ -668,21 +1943,41 @@ fn handle_redraw_event(
                 }
             }
             RedrawEvent::Flush() => {
+                let _span = profiler::span("layout", "flush");
+
+                // Update the ghost text now that the cursor's row is up to
+                // date.
+                if let Some(grid) = state.grids.get(&state.current_grid) {
+                    let (line, col) = grid.cursor_line();
+                    let text = state.ghost_text.text(&line, col);
+                    grid.set_ghost_text(text, &state.hl_defs);
+                }
+
                 for grid in state.grids.values() {
-                    grid.flush(&state.hl_defs);
+                    let damaged = grid.flush(&state.hl_defs);
+                    state.perf_hud.record_damage(damaged);
                 }
+
+                startup::mark(startup::Phase::FirstFlush);
             }
             RedrawEvent::PopupmenuShow(popupmenu) => {
+                state.ghost_text.show(
+                    popupmenu.items.iter().map(|i| i.word.clone()).collect(),
+                    popupmenu.col,
+                    popupmenu.selected,
+                );
+
                 state
                     .popupmenu
                     .set_items(popupmenu.items.clone(), &state.hl_defs);
 
                 let grid = state.grids.get(&state.current_grid).unwrap();
-                let mut rect =
-                    grid.get_rect_for_cell(popupmenu.row, popupmenu.col);
-
-                let extra_h = state.tabline.get_height();
-                rect.y -= extra_h;
+                let rect = overlay_rect(
+                    &state.overlay,
+                    grid,
+                    popupmenu.row,
+                    popupmenu.col,
+                );
 
                 state.popupmenu.set_anchor(rect);
                 state.popupmenu.show();
@@ -704,6 +1999,7 @@ fn handle_redraw_event(
             }
             RedrawEvent::PopupmenuHide() => {
                 state.popupmenu.hide();
+                state.ghost_text.hide();
 
                 // Undo any force positioning of cursor tool tip that might
                 // have occured on popupmenu show.
@@ -712,6 +2008,7 @@ fn handle_redraw_event(
             }
             RedrawEvent::PopupmenuSelect(selected) => {
                 state.popupmenu.select(*selected as i32, &state.hl_defs);
+                state.ghost_text.select(*selected);
             }
             RedrawEvent::TablineUpdate(cur, tabs) => {
                 state.tabline.update(cur.clone(), tabs.clone());
@@ -799,14 +2096,263 @@ fn keyname_to_nvim_key(s: &str) -> Option<&str> {
     }
 }
 
+/// Returns the zoom step for our zoom shortcuts: ctrl and =/+ or - to zoom
+/// in or out, and ctrl and 0 to reset the zoom.
+fn zoom_step(e: &gdk::EventKey) -> Option<i64> {
+    if !e.get_state().contains(gdk::ModifierType::CONTROL_MASK) {
+        return None;
+    }
+
+    match gdk::keyval_name(e.get_keyval())?.as_str() {
+        "equal" | "plus" | "KP_Add" => Some(1),
+        "minus" | "KP_Subtract" => Some(-1),
+        "0" | "KP_0" => Some(0),
+        _ => None,
+    }
+}
+
+/// Returns the position (+ width and height) of the cell (row, col) of
+/// `grid`, relative to `overlay` (e.g. for the widgets in it). The overlay
+/// is below the tabline, and the headerbar if we have one.
+fn overlay_rect(
+    overlay: &gtk::Overlay,
+    grid: &Grid,
+    row: u64,
+    col: u64,
+) -> gdk::Rectangle {
+    let mut rect = grid.get_rect_for_cell(row, col);
+
+    let offset = overlay
+        .get_toplevel()
+        .and_then(|toplevel| overlay.translate_coordinates(&toplevel, 0, 0));
+    if let Some((x, y)) = offset {
+        rect.x -= x;
+        rect.y -= y;
+    }
+
+    rect
+}
+
+/// If `e` is Ctrl+Shift+U, which opens our unicode input.
+fn is_unicode_input_shortcut(e: &gdk::EventKey) -> bool {
+    let state = e.get_state();
+    if !state.contains(
+        gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+    ) {
+        return false;
+    }
+
+    match gdk::keyval_name(e.get_keyval()) {
+        Some(name) => name == "U" || name == "u",
+        None => false,
+    }
+}
+
+/// Applies the local configuration of the project that `dir` is in (see
+/// `project::find_root`), or removes the previous project's configuration.
+/// The user is asked first if they trust the project's directory.
+fn update_project(state: &mut UIState, nvim: &Arc<Mutex<Neovim>>, dir: &Path) {
+    let root = match project::find_root(dir) {
+        Some(root) => root,
+        None => {
+            apply_project(state, nvim, None);
+            return;
+        }
+    };
+
+    match trust::trust(&root) {
+        Trust::Trusted => apply_project(state, nvim, Some(&root)),
+        Trust::Unknown if state.trust_asked.insert(root.clone()) => {
+            let window = state
+                .overlay
+                .get_toplevel()
+                .and_then(|w| w.downcast::<gtk::Window>().ok());
+            if let Some(window) = window {
+                let nvim = nvim.clone();
+                let root_ref = root.clone();
+                let files = project::files(&root);
+                trust::ask(&window, &root, &files, move |trusted| {
+                    if let Err(err) = trust::set_trust(&root_ref, trusted) {
+                        eprintln!("Failed to save trust: {}", err);
+                    }
+
+                    // Ask nvim for the current directory again, now that
+                    // the project is trusted.
+                    if trusted {
+                        let mut nvim = nvim.lock().unwrap();
+                        nvim.command_async("call gnvim#project#update()")
+                            .cb(|res| {
+                                if let Err(err) = res {
+                                    eprintln!(
+                                        "Failed to update project: {}",
+                                        err
+                                    );
+                                }
+                            })
+                            .call();
+                    }
+                });
+            }
+
+            apply_project(state, nvim, None);
+        }
+        _ => apply_project(state, nvim, None),
+    }
+}
+
+/// Applies the local configuration of the project in `root`, which must be
+/// trusted. `None` removes the previous project's configuration.
+fn apply_project(
+    state: &mut UIState,
+    nvim: &Arc<Mutex<Neovim>>,
+    root: Option<&Path>,
+) {
+    let settings = root
+        .map(|root| root.join(project::SETTINGS_FILE))
+        .filter(|path| path.is_file())
+        .map(|path| {
+            ProjectSettings::load(&path).unwrap_or_else(|err| {
+                eprintln!("Failed to load project settings: {}", err);
+                ProjectSettings::default()
+            })
+        })
+        .unwrap_or_default();
+
+    if let Some(ref user_css) = state.user_css {
+        user_css.set_project_css(
+            root.map(|root| root.join(project::CSS_FILE))
+                .filter(|path| path.is_file()),
+        );
+    }
+
+    apply_project_settings(state, nvim, settings);
+}
+
+/// Applies the project's `settings`. Settings that the project doesn't have
+/// are reset to our own.
+fn apply_project_settings(
+    state: &mut UIState,
+    nvim: &Arc<Mutex<Neovim>>,
+    settings: ProjectSettings,
+) {
+    if let Some(grid) = state.grids.get(&1) {
+        let padding = settings.padding.unwrap_or(0).max(0);
+        let widget = grid.widget();
+        widget.set_margin_start(padding);
+        widget.set_margin_end(padding);
+        widget.set_margin_top(padding);
+        widget.set_margin_bottom(padding);
+    }
+
+    if let Some(ref user_css) = state.user_css {
+        user_css.update_palette(|palette| {
+            palette.set_accent(settings.accent_color)
+        });
+    }
+
+    // Like zooming, the font size is changed through 'guifont'.
+    let new = match settings.font_size {
+        Some(size) => {
+            if state.project_base_font.is_none() {
+                state.project_base_font = Some(state.guifont.borrow().clone());
+            }
+            let base = state.project_base_font.clone().unwrap_or_default();
+            let font = Font::from_guifont(&base).unwrap_or_default();
+            font.zoom(size as i64 - font.height as i64).as_guifont()
+        }
+        None => match state.project_base_font.take() {
+            Some(base) => base,
+            None => return,
+        },
+    };
+
+    if new == *state.guifont.borrow() {
+        return;
+    }
+
+    let mut nvim = nvim.lock().unwrap();
+    nvim.set_option_async("guifont", Value::from(new))
+        .cb(|res| {
+            if let Err(err) = res {
+                eprintln!("Failed to set the project's font: {}", err);
+            }
+        })
+        .call();
+}
+
+/// State of our zoom shortcuts (see `zoom`).
+#[derive(Default)]
+struct Zoom {
+    /// Guifont before zooming, restored when the zoom is reset.
+    base: Option<String>,
+    /// Guifont we've last set to nvim, to tell our own changes from the
+    /// others (e.g. `:set guifont=`) when nvim echoes it back.
+    requested: Option<String>,
+}
+
+impl Zoom {
+    /// Nvim's guifont changed from `old` to `new`. If the change didn't
+    /// come from us, `new` is the font the zoom is reset to.
+    fn guifont_changed(&mut self, old: &str, new: &str) {
+        let ours = self.requested.take().map_or(false, |font| font == new);
+        if !ours && old != new {
+            self.base = None;
+        }
+    }
+}
+
+/// Changes the font size by `step` points, or resets it to the size before
+/// zooming if `step` is zero. The font is changed by setting nvim's
+/// 'guifont', so that 'guifont' is always in sync with our font.
+fn zoom(
+    nvim: &Arc<Mutex<Neovim>>,
+    guifont: &Arc<ThreadGuard<String>>,
+    zoom: &Arc<ThreadGuard<Zoom>>,
+    step: i64,
+) {
+    let current = guifont.borrow().clone();
+    let mut zoom = zoom.borrow_mut();
+
+    let new = if step == 0 {
+        match zoom.base.take() {
+            Some(base) => base,
+            None => return,
+        }
+    } else {
+        if zoom.base.is_none() {
+            zoom.base = Some(current.clone());
+        }
+
+        Font::from_guifont(&current)
+            .unwrap_or_default()
+            .zoom(step)
+            .as_guifont()
+    };
+    zoom.requested = Some(new.clone());
+
+    let mut nvim = nvim.lock().unwrap();
+    nvim.set_option_async("guifont", Value::from(new))
+        .cb(|res| {
+            if let Err(err) = res {
+                eprintln!("Failed to zoom: {}", err);
+            }
+        })
+        .call();
+}
+
+#[cfg(not(target_os = "macos"))]
 fn event_to_nvim_input(e: &gdk::EventKey) -> Option<String> {
+    keyval_to_nvim_input(e.get_keyval(), e.get_state())
+}
+
+fn keyval_to_nvim_input(
+    keyval: u32,
+    state: gdk::ModifierType,
+) -> Option<String> {
     let mut input = String::from("");
 
-    let keyval = e.get_keyval();
     let keyname = gdk::keyval_name(keyval)?;
 
-    let state = e.get_state();
-
     if state.contains(gdk::ModifierType::SHIFT_MASK) {
         input.push_str("S-");
     }
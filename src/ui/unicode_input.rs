@@ -0,0 +1,391 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use gdk;
+use gtk;
+use gtk::prelude::*;
+use neovim_lib::neovim::Neovim;
+use neovim_lib::{NeovimApiAsync, Value};
+
+use paths;
+
+/// Recently inserted characters, in our state directory.
+const FILE_NAME: &str = "unicode-recent";
+
+/// How many recent characters are kept.
+const MAX_RECENT: usize = 12;
+/// How many characters are listed for a search.
+const MAX_RESULTS: usize = 50;
+
+/// Locations of the Unicode Character Database's UnicodeData.txt, which
+/// has the characters' names. Without it, only codepoints can be entered.
+const UNICODE_DATA: &[&str] = &[
+    "/usr/share/unicode/UnicodeData.txt",
+    "/usr/share/unicode-data/UnicodeData.txt",
+    "/usr/share/unicode/ucd/UnicodeData.txt",
+];
+
+struct State {
+    nvim: Arc<Mutex<Neovim>>,
+
+    /// Characters and their names, loaded on first search.
+    names: Option<Vec<(char, String)>>,
+    /// Recently inserted characters, the latest first.
+    recent: Vec<char>,
+    /// Characters listed in the results, in order.
+    matches: Vec<char>,
+}
+
+/// Popover (Ctrl+Shift+U) for entering a character by its codepoint (e.g.
+/// "20ac" or "U+20AC") or by searching its name (e.g. "euro"). The selected
+/// character is inserted at the cursor with `nvim_put`.
+pub struct UnicodeInput {
+    popover: gtk::Popover,
+    entry: gtk::SearchEntry,
+    /// Buttons of the recent characters.
+    recent_box: gtk::Box,
+
+    state: Rc<RefCell<State>>,
+}
+
+impl UnicodeInput {
+    pub fn new(relative_to: &gtk::Widget, nvim: Arc<Mutex<Neovim>>) -> Self {
+        let popover = gtk::Popover::new(Some(relative_to));
+        popover.set_position(gtk::PositionType::Bottom);
+
+        let box_ = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        box_.set_border_width(6);
+
+        let entry = gtk::SearchEntry::new();
+        entry.set_placeholder_text("Codepoint or name");
+        entry.set_width_chars(30);
+        box_.pack_start(&entry, false, false, 0);
+
+        let recent_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        box_.pack_start(&recent_box, false, false, 0);
+
+        let results = gtk::ListBox::new();
+        results.set_activate_on_single_click(true);
+        let scroll = gtk::ScrolledWindow::new(None, None);
+        scroll.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scroll.set_min_content_height(200);
+        scroll.add(&results);
+        box_.pack_start(&scroll, true, true, 0);
+
+        box_.show_all();
+        popover.add(&box_);
+
+        let state = Rc::new(RefCell::new(State {
+            nvim,
+            names: None,
+            recent: load_recent(),
+            matches: vec![],
+        }));
+
+        let state_ref = state.clone();
+        let results_ref = results.clone();
+        entry.connect_search_changed(move |entry| {
+            let query = entry.get_text().unwrap_or_default();
+            update_results(&results_ref, &mut state_ref.borrow_mut(), &query);
+        });
+
+        let state_ref = state.clone();
+        let popover_ref = popover.clone();
+        entry.connect_activate(move |_| {
+            let ch = state_ref.borrow().matches.first().cloned();
+            if let Some(ch) = ch {
+                insert(&popover_ref, &state_ref, ch);
+            }
+        });
+
+        let state_ref = state.clone();
+        let popover_ref = popover.clone();
+        results.connect_row_activated(move |_, row| {
+            let ch = state_ref
+                .borrow()
+                .matches
+                .get(row.get_index() as usize)
+                .cloned();
+            if let Some(ch) = ch {
+                insert(&popover_ref, &state_ref, ch);
+            }
+        });
+
+        UnicodeInput {
+            popover,
+            entry,
+            recent_box,
+            state,
+        }
+    }
+
+    /// Shows the popover, pointing to `rect` (relative to the widget the
+    /// popover was created for).
+    pub fn show(&self, rect: &gdk::Rectangle) {
+        for child in self.recent_box.get_children() {
+            self.recent_box.remove(&child);
+        }
+
+        for ch in self.state.borrow().recent.iter().cloned() {
+            let button = gtk::Button::new_with_label(&ch.to_string());
+            button.set_relief(gtk::ReliefStyle::None);
+            button.set_tooltip_text(Some(
+                format!("U+{:04X}", ch as u32).as_str(),
+            ));
+
+            let state = self.state.clone();
+            let popover = self.popover.clone();
+            button.connect_clicked(move |_| insert(&popover, &state, ch));
+
+            self.recent_box.pack_start(&button, false, false, 0);
+        }
+        self.recent_box.show_all();
+
+        self.entry.set_text("");
+        self.popover.set_pointing_to(rect);
+        self.popover.show();
+        self.entry.grab_focus();
+    }
+}
+
+/// Lists the characters matching `query` in `results`.
+fn update_results(results: &gtk::ListBox, state: &mut State, query: &str) {
+    for child in results.get_children() {
+        results.remove(&child);
+    }
+
+    if state.names.is_none() {
+        state.names = Some(load_names());
+    }
+    let names = state.names.as_ref().unwrap();
+
+    state.matches = search(names, query, MAX_RESULTS);
+
+    for ch in state.matches.iter().cloned() {
+        let label = gtk::Label::new(None);
+        label.set_halign(gtk::Align::Start);
+        label.set_text(&format!(
+            "{}    U+{:04X} {}",
+            ch,
+            ch as u32,
+            name_of(names, ch).unwrap_or("")
+        ));
+        results.add(&label);
+    }
+
+    results.show_all();
+}
+
+/// Hides the popover and inserts `ch` at nvim's cursor.
+fn insert(popover: &gtk::Popover, state: &Rc<RefCell<State>>, ch: char) {
+    popover.hide();
+
+    let mut state = state.borrow_mut();
+    push_recent(&mut state.recent, ch);
+    if let Err(err) = save_recent(&state.recent) {
+        println!("Failed to save the recent characters: {}", err);
+    }
+
+    let mut nvim = state.nvim.lock().unwrap();
+    nvim.call_function_async(
+        "nvim_put",
+        vec![
+            Value::Array(vec![ch.to_string().into()]),
+            "c".into(),
+            false.into(),
+            true.into(),
+        ],
+    )
+    .cb(|res| {
+        if let Err(err) = res {
+            println!("Failed to insert the character: {}", err);
+        }
+    })
+    .call();
+}
+
+/// Parses a codepoint, e.g. "20ac", "U+20AC" or "0x20ac".
+fn parse_codepoint(s: &str) -> Option<char> {
+    let s = s.trim();
+    let hex = ["U+", "u+", "0x", "0X"]
+        .iter()
+        .find(|prefix| s.starts_with(*prefix))
+        .map_or(s, |prefix| &s[prefix.len()..]);
+
+    if hex.is_empty() || hex.len() > 6 {
+        return None;
+    }
+
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .and_then(::std::char::from_u32)
+}
+
+/// Parses the characters and their names from UnicodeData.txt. Control
+/// characters and the ranges (e.g. CJK ideographs) don't have names there,
+/// and are left out.
+fn parse_names(data: &str) -> Vec<(char, String)> {
+    data.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(';');
+            let cp = fields.next()?;
+            let name = fields.next()?;
+            if name.starts_with('<') {
+                return None;
+            }
+
+            let ch = u32::from_str_radix(cp, 16)
+                .ok()
+                .and_then(::std::char::from_u32)?;
+            Some((ch, name.to_string()))
+        })
+        .collect()
+}
+
+fn load_names() -> Vec<(char, String)> {
+    UNICODE_DATA
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .next()
+        .map(|data| parse_names(&data))
+        .unwrap_or_default()
+}
+
+/// Returns the name of `ch`. `names` is sorted by the characters, as they
+/// are in UnicodeData.txt.
+fn name_of(names: &[(char, String)], ch: char) -> Option<&str> {
+    names
+        .binary_search_by_key(&ch, |(c, _)| *c)
+        .ok()
+        .map(|i| names[i].1.as_str())
+}
+
+/// Returns (at most `max`) characters matching `query`: the character of
+/// the codepoint in `query`, followed by the characters whose name has all
+/// the words of `query`. The character of the exact name comes first.
+fn search(names: &[(char, String)], query: &str, max: usize) -> Vec<char> {
+    let query = query.trim().to_uppercase();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let mut matches: Vec<char> = parse_codepoint(&query).into_iter().collect();
+
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let mut found: Vec<&(char, String)> = names
+        .iter()
+        .filter(|(_, name)| words.iter().all(|w| name.contains(w)))
+        .collect();
+    // Stable sort, so the rest stay in the codepoint order.
+    found.sort_by_key(|(_, name)| *name != query);
+
+    for (ch, _) in found {
+        if matches.len() >= max {
+            break;
+        }
+        if !matches.contains(ch) {
+            matches.push(*ch);
+        }
+    }
+
+    matches
+}
+
+/// Moves (or adds) `ch` to the front of `recent`.
+fn push_recent(recent: &mut Vec<char>, ch: char) {
+    recent.retain(|c| *c != ch);
+    recent.insert(0, ch);
+    recent.truncate(MAX_RECENT);
+}
+
+fn path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+fn load_recent() -> Vec<char> {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|data| {
+            data.trim_end_matches('\n')
+                .chars()
+                .take(MAX_RECENT)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_recent(recent: &[char]) -> ::std::io::Result<()> {
+    let path = path().ok_or_else(paths::no_state_dir)?;
+
+    let mut data: String = recent.iter().collect();
+    data.push('\n');
+    fs::write(path, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = "\
+0000;<control>;Cc;0;BN;;;;;N;NULL;;;;
+0041;LATIN CAPITAL LETTER A;Lu;0;L;;;;;N;;;;0061;
+00E9;LATIN SMALL LETTER E WITH ACUTE;Ll;0;L;0065 0301;;;;N;;;00C9;;00C9
+20A0;EURO-CURRENCY SIGN;Sc;0;ET;;;;;N;;;;;
+20AC;EURO SIGN;Sc;0;ET;;;;;N;;;;;
+4E00;<CJK Ideograph, First>;Lo;0;L;;;;;N;;;;;
+";
+
+    #[test]
+    fn test_parse_codepoint() {
+        assert_eq!(parse_codepoint("20ac"), Some('€'));
+        assert_eq!(parse_codepoint("U+1F600"), Some('😀'));
+        assert_eq!(parse_codepoint(" 0x41 "), Some('A'));
+        assert_eq!(parse_codepoint("U+"), None);
+        assert_eq!(parse_codepoint("euro"), None);
+        // Surrogates aren't characters.
+        assert_eq!(parse_codepoint("d800"), None);
+        assert_eq!(parse_codepoint("1234567"), None);
+    }
+
+    #[test]
+    fn test_parse_names() {
+        let names = parse_names(DATA);
+        assert_eq!(names.len(), 4);
+        assert_eq!(
+            name_of(&names, 'é'),
+            Some("LATIN SMALL LETTER E WITH ACUTE")
+        );
+        assert_eq!(name_of(&names, '\0'), None);
+    }
+
+    #[test]
+    fn test_search() {
+        let names = parse_names(DATA);
+        assert_eq!(search(&names, "sign", 10), vec!['₠', '€']);
+        // Exact name first, then in the codepoint order.
+        assert_eq!(search(&names, "euro sign", 10), vec!['€', '₠']);
+        assert_eq!(search(&names, "currency", 10), vec!['₠']);
+        assert_eq!(search(&names, "acute e", 10), vec!['é']);
+        // Codepoint and the names that match it.
+        assert_eq!(search(&names, "a", 10), vec!['\n', 'A', 'é']);
+        assert_eq!(search(&names, "20ac", 10), vec!['€']);
+        assert_eq!(search(&names, "sign", 1), vec!['₠']);
+        assert!(search(&names, "  ", 10).is_empty());
+    }
+
+    #[test]
+    fn test_push_recent() {
+        let mut recent = vec!['a', 'b'];
+        push_recent(&mut recent, 'b');
+        assert_eq!(recent, vec!['b', 'a']);
+
+        for ch in "0123456789abcdef".chars() {
+            push_recent(&mut recent, ch);
+        }
+        assert_eq!(recent.len(), MAX_RECENT);
+        assert_eq!(recent[0], 'f');
+    }
+}
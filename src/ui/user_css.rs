@@ -0,0 +1,272 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use gdk;
+use gio;
+use gio::prelude::*;
+use gtk;
+use gtk::prelude::*;
+
+use nvim_bridge::SetGuiColors;
+use ui::color::Color;
+
+/// Nvim's colors, exposed to the user's CSS as named colors (e.g.
+/// `@gnvim_normal_fg`).
+///
+/// All the colors are always defined, so that the user's CSS doesn't fail
+/// to load. Colors that nvim doesn't have (e.g. `Pmenu` has no `guibg`)
+/// fall back to the default foreground or background.
+pub struct Palette {
+    colors: BTreeMap<&'static str, Color>,
+    /// Colors which fall back to another color.
+    aliases: BTreeMap<&'static str, &'static str>,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        let mut palette = Palette {
+            colors: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+        };
+
+        palette.set_default_colors(
+            Color::default(),
+            Color::default(),
+            Color::default(),
+        );
+        palette.set_gui_colors(&SetGuiColors::default());
+        palette.set_accent(None);
+
+        palette
+    }
+
+    /// Sets `name` to `color`, or to `fallback` if there is no color.
+    fn set(
+        &mut self,
+        name: &'static str,
+        color: Option<Color>,
+        fallback: &'static str,
+    ) {
+        match color {
+            Some(color) => {
+                self.aliases.remove(name);
+                self.colors.insert(name, color);
+            }
+            None => {
+                self.colors.remove(name);
+                self.aliases.insert(name, fallback);
+            }
+        }
+    }
+
+    pub fn set_default_colors(&mut self, fg: Color, bg: Color, sp: Color) {
+        self.colors.insert("normal_fg", fg);
+        self.colors.insert("normal_bg", bg);
+        self.colors.insert("special", sp);
+    }
+
+    pub fn set_gui_colors(&mut self, colors: &SetGuiColors) {
+        let (fg, bg) = ("normal_fg", "normal_bg");
+
+        self.set("pmenu_fg", colors.pmenu.fg, fg);
+        self.set("pmenu_bg", colors.pmenu.bg, bg);
+        self.set("pmenu_sel_fg", colors.pmenu.sel_fg, fg);
+        self.set("pmenu_sel_bg", colors.pmenu.sel_bg, bg);
+
+        self.set("tabline_fg", colors.tabline.fg, fg);
+        self.set("tabline_bg", colors.tabline.bg, bg);
+        self.set("tabline_fill_fg", colors.tabline.fill_fg, fg);
+        self.set("tabline_fill_bg", colors.tabline.fill_bg, bg);
+        self.set("tabline_sel_fg", colors.tabline.sel_fg, fg);
+        self.set("tabline_sel_bg", colors.tabline.sel_bg, bg);
+
+        self.set("cmdline_fg", colors.cmdline.fg, fg);
+        self.set("cmdline_bg", colors.cmdline.bg, bg);
+        self.set("cmdline_border", colors.cmdline.border, fg);
+
+        self.set("wildmenu_fg", colors.wildmenu.fg, fg);
+        self.set("wildmenu_bg", colors.wildmenu.bg, bg);
+        self.set("wildmenu_sel_fg", colors.wildmenu.sel_fg, fg);
+        self.set("wildmenu_sel_bg", colors.wildmenu.sel_bg, bg);
+
+        self.set("visual_bg", colors.visual_bg, bg);
+    }
+
+    /// Sets the accent color (e.g. from the project's settings).
+    pub fn set_accent(&mut self, color: Option<Color>) {
+        self.set("accent", color, "normal_fg");
+    }
+
+    /// Returns the colors as `@define-color` rules.
+    fn to_css(&self) -> String {
+        let colors = self.colors.iter().map(|(name, color)| {
+            format!("@define-color gnvim_{} #{};\n", name, color.to_hex())
+        });
+        // Aliases refer to the colors, so they're defined last.
+        let aliases = self.aliases.iter().map(|(name, fallback)| {
+            format!("@define-color gnvim_{} @gnvim_{};\n", name, fallback)
+        });
+
+        colors.chain(aliases).collect()
+    }
+}
+
+/// User's own CSS (`gnvim.css` in gnvim's config directory), which is
+/// applied on top of our styles. The file is watched, and reloaded when it
+/// changes.
+pub struct UserCss {
+    provider: gtk::CssProvider,
+    path: PathBuf,
+    palette: Rc<RefCell<Palette>>,
+
+    /// The current project's CSS (see `set_project_css`), applied on top of
+    /// the user's CSS.
+    project_provider: gtk::CssProvider,
+    project_path: RefCell<Option<PathBuf>>,
+
+    /// Kept around, so that we keep watching the file.
+    #[allow(unused)]
+    monitor: Option<gio::FileMonitor>,
+}
+
+impl UserCss {
+    pub fn new(path: PathBuf) -> Self {
+        let provider = gtk::CssProvider::new();
+        // Added after the user's provider, so that it takes precedence.
+        let project_provider = gtk::CssProvider::new();
+        if let Some(screen) = gdk::Screen::get_default() {
+            for provider in [&provider, &project_provider].iter() {
+                gtk::StyleContext::add_provider_for_screen(
+                    &screen,
+                    *provider,
+                    gtk::STYLE_PROVIDER_PRIORITY_USER,
+                );
+            }
+        }
+
+        let palette = Rc::new(RefCell::new(Palette::new()));
+
+        let file = gio::File::new_for_path(&path);
+        let monitor = match file.monitor_file(gio::FileMonitorFlags::NONE, None)
+        {
+            Ok(monitor) => {
+                let provider_ref = provider.clone();
+                let path_ref = path.clone();
+                let palette_ref = palette.clone();
+                monitor.connect_changed(move |_, _, _, event| match event {
+                    // Editors might replace the file instead of writing
+                    // to it, so we'll look for those events too.
+                    gio::FileMonitorEvent::ChangesDoneHint
+                    | gio::FileMonitorEvent::Created
+                    | gio::FileMonitorEvent::Deleted => {
+                        load(&provider_ref, &path_ref, &palette_ref.borrow())
+                    }
+                    _ => {}
+                });
+
+                Some(monitor)
+            }
+            Err(err) => {
+                eprintln!("Failed to watch {}: {}", path.display(), err);
+                None
+            }
+        };
+
+        load(&provider, &path, &palette.borrow());
+
+        UserCss {
+            provider,
+            path,
+            palette,
+            project_provider,
+            project_path: RefCell::new(None),
+            monitor,
+        }
+    }
+
+    /// Updates the palette with `f`, and reloads the CSS with the new
+    /// colors.
+    pub fn update_palette<F: FnOnce(&mut Palette)>(&self, f: F) {
+        f(&mut self.palette.borrow_mut());
+        load(&self.provider, &self.path, &self.palette.borrow());
+        self.load_project_css();
+    }
+
+    /// Sets the current project's CSS file. `None` removes the previous
+    /// project's CSS. The project must be trusted (see `trust::trust`).
+    pub fn set_project_css(&self, path: Option<PathBuf>) {
+        *self.project_path.borrow_mut() = path;
+        self.load_project_css();
+    }
+
+    fn load_project_css(&self) {
+        match *self.project_path.borrow() {
+            Some(ref path) => {
+                load(&self.project_provider, path, &self.palette.borrow())
+            }
+            None => {
+                if let Err(err) =
+                    CssProviderExt::load_from_data(&self.project_provider, b"")
+                {
+                    eprintln!("Failed to unload project's CSS: {}", err);
+                }
+            }
+        }
+    }
+}
+
+fn load(provider: &gtk::CssProvider, path: &Path, palette: &Palette) {
+    let css = match fs::read_to_string(path) {
+        Ok(css) => css,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            eprintln!("Failed to read {}: {}", path.display(), err);
+            return;
+        }
+    };
+
+    let css = palette.to_css() + &css;
+    if let Err(err) = CssProviderExt::load_from_data(provider, css.as_bytes()) {
+        eprintln!("Failed to load {}: {}", path.display(), err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_to_css() {
+        let mut palette = Palette::new();
+        palette.set_default_colors(
+            Color::from_u64(0xffffff),
+            Color::from_u64(0x000000),
+            Color::from_u64(0xff0000),
+        );
+
+        let css = palette.to_css();
+        assert!(css.starts_with(
+            "@define-color gnvim_normal_bg #000000;\n\
+             @define-color gnvim_normal_fg #ffffff;\n\
+             @define-color gnvim_special #ff0000;\n"
+        ));
+        assert!(
+            css.contains("@define-color gnvim_visual_bg @gnvim_normal_bg;\n")
+        );
+
+        let mut colors = SetGuiColors::default();
+        colors.visual_bg = Some(Color::from_u64(0x00ff00));
+        palette.set_gui_colors(&colors);
+
+        let css = palette.to_css();
+        assert!(css.contains("@define-color gnvim_visual_bg #00ff00;\n"));
+        assert!(!css.contains("gnvim_visual_bg @"));
+        assert!(
+            css.contains("@define-color gnvim_pmenu_fg @gnvim_normal_fg;\n")
+        );
+    }
+}
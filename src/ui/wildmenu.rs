@@ -5,7 +5,7 @@ use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 use neovim_lib::neovim::Neovim;
-use neovim_lib::neovim_api::NeovimApi;
+use neovim_lib::NeovimApiAsync;
 
 use nvim_bridge;
 use ui::ui::HlDefs;
@@ -31,6 +31,7 @@ impl Wildmenu {
         let css_provider = gtk::CssProvider::new();
 
         let frame = gtk::Frame::new(None);
+        gtk::WidgetExt::set_name(&frame, "gnvim-wildmenu");
 
         let list = gtk::ListBox::new();
         list.set_selection_mode(gtk::SelectionMode::Single);
@@ -73,7 +74,13 @@ impl Wildmenu {
                 // NOTE(ville): nvim doesn't like single input with many
                 //              tabs in it, so we'll have to send each
                 //              individually.
-                nvim.input(&op).unwrap();
+                nvim.input_async(&op)
+                    .cb(|res| {
+                        if let Err(err) = res {
+                            println!("Failed to send input: {}", err);
+                        }
+                    })
+                    .call();
             }
         });
 
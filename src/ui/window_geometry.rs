@@ -0,0 +1,190 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use key_value;
+use paths;
+use ui::trust;
+
+const FILE_NAME: &str = "window";
+
+/// Window geometry that is persisted across restarts.
+///
+/// The position also tells which monitor the window was on. Some platforms
+/// (e.g. wayland) don't allow us to position our window, in which case
+/// the position is ignored by gtk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowGeometry {
+    /// Size of the window when it isn't maximized (or fullscreen).
+    pub width: i32,
+    pub height: i32,
+    /// Position of the window when it isn't maximized (or fullscreen).
+    pub position: Option<(i32, i32)>,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    /// Size of the main grid (in cells). Used when we attach to nvim, so
+    /// that we don't need to resize the grid right after we've started.
+    pub cols: u64,
+    pub rows: u64,
+    /// Project that the geometry is remembered for (see `load_project`).
+    pub project: Option<PathBuf>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        WindowGeometry {
+            width: 1280,
+            height: 720,
+            position: None,
+            maximized: false,
+            fullscreen: false,
+            cols: 80,
+            rows: 30,
+            project: None,
+        }
+    }
+}
+
+impl WindowGeometry {
+    /// Loads the geometry from gnvim's state directory. If there is no saved
+    /// geometry, the default one is returned.
+    pub fn load() -> Self {
+        path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|data| WindowGeometry::parse(&data))
+            .unwrap_or_default()
+    }
+
+    /// Loads the geometry that the window of the project in `root` had
+    /// when it was last closed. If the project has no saved geometry, the
+    /// last window's geometry is used. The geometry is saved for the
+    /// project too.
+    pub fn load_project(root: &Path) -> Self {
+        let mut geometry = project_path(root)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|data| WindowGeometry::parse(&data))
+            .unwrap_or_else(WindowGeometry::load);
+        geometry.project = Some(root.to_path_buf());
+        geometry
+    }
+
+    /// Saves the geometry to gnvim's state directory.
+    pub fn save(&self) -> io::Result<()> {
+        let data = self.serialize();
+        fs::write(path().ok_or_else(paths::no_state_dir)?, &data)?;
+        if let Some(ref root) = self.project {
+            fs::write(
+                project_path(root).ok_or_else(paths::no_state_dir)?,
+                &data,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses the geometry from `key=value` lines. Invalid or missing values
+    /// are left to their default values.
+    fn parse(data: &str) -> Self {
+        let mut geometry = WindowGeometry::default();
+        let mut x = None;
+        let mut y = None;
+
+        for (key, value) in key_value::lines(data) {
+            match key {
+                "width" => set_parsed(&mut geometry.width, value),
+                "height" => set_parsed(&mut geometry.height, value),
+                "x" => x = value.parse().ok(),
+                "y" => y = value.parse().ok(),
+                "maximized" => set_parsed(&mut geometry.maximized, value),
+                "fullscreen" => set_parsed(&mut geometry.fullscreen, value),
+                "cols" => set_parsed(&mut geometry.cols, value),
+                "rows" => set_parsed(&mut geometry.rows, value),
+                _ => {}
+            }
+        }
+
+        if let (Some(x), Some(y)) = (x, y) {
+            geometry.position = Some((x, y));
+        }
+
+        // Don't let bogus values to make our window unusable.
+        if geometry.width <= 0 || geometry.height <= 0 {
+            geometry.width = WindowGeometry::default().width;
+            geometry.height = WindowGeometry::default().height;
+        }
+        if geometry.cols == 0 || geometry.rows == 0 {
+            geometry.cols = WindowGeometry::default().cols;
+            geometry.rows = WindowGeometry::default().rows;
+        }
+
+        geometry
+    }
+
+    fn serialize(&self) -> String {
+        let mut data = String::new();
+        data.push_str(&format!(
+            "width={}\nheight={}\n",
+            self.width, self.height
+        ));
+        data.push_str(&format!("maximized={}\n", self.maximized));
+        data.push_str(&format!("fullscreen={}\n", self.fullscreen));
+        data.push_str(&format!("cols={}\nrows={}\n", self.cols, self.rows));
+
+        if let Some((x, y)) = self.position {
+            data.push_str(&format!("x={}\ny={}\n", x, y));
+        }
+
+        data
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+/// Returns the path of the geometry of the project in `root`. Like with
+/// the trust, the path is hashed.
+fn project_path(root: &Path) -> Option<PathBuf> {
+    paths::state_dir().map(|dir| {
+        dir.join(format!("{}-{}", FILE_NAME, trust::hash_path(root)))
+    })
+}
+
+fn set_parsed<T: ::std::str::FromStr>(target: &mut T, value: &str) {
+    if let Ok(value) = value.parse() {
+        *target = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_serialize() {
+        let geometry = WindowGeometry {
+            width: 800,
+            height: 600,
+            position: Some((-10, 20)),
+            maximized: true,
+            fullscreen: false,
+            cols: 100,
+            rows: 40,
+            project: None,
+        };
+
+        assert_eq!(WindowGeometry::parse(&geometry.serialize()), geometry);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let geometry =
+            WindowGeometry::parse("width=foo\nheight=-1\nx=10\nrows=0\nbar");
+        assert_eq!(geometry, WindowGeometry::default());
+
+        let geometry = WindowGeometry::parse("maximized=true\nx=1\ny=2");
+        assert_eq!(geometry.maximized, true);
+        assert_eq!(geometry.position, Some((1, 2)));
+        assert_eq!(geometry.width, WindowGeometry::default().width);
+    }
+}
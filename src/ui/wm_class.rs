@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use gdk;
+use gdk::prelude::*;
+use glib::prelude::*;
+use gtk;
+use gtk::prelude::*;
+
+use ui::project;
+
+/// Returns the name of the project that `dir` is in (e.g. "myproject"),
+/// for the window class. See `project::project_dir`.
+pub fn project_name(dir: &Path) -> String {
+    let name = project::project_dir(dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    sanitize(&name)
+}
+
+/// Returns `name` as a valid element of an application id (see
+/// `g_application_id_is_valid`): only ASCII letters, digits, '_' and '-',
+/// not starting with a digit.
+fn sanitize(name: &str) -> String {
+    let mut name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    name = name.trim_matches('-').to_string();
+
+    if name.is_empty() {
+        name.push_str("none");
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+
+    name
+}
+
+/// Sets the window class (WM_CLASS) of `window` to `name`. Before the
+/// window is shown, this works everywhere on X11. After that, the class
+/// property is changed directly, which window managers that follow the
+/// changes pick up. On wayland, the app id can't be changed after the
+/// window is shown.
+pub fn set(window: &gtk::Window, name: &str) -> Result<(), String> {
+    let gdk_window = match window.get_window() {
+        Some(gdk_window) => gdk_window,
+        None => {
+            window.set_wmclass(name, name);
+            return Ok(());
+        }
+    };
+
+    let is_x11 = gdk_window.get_display().get_type().name() == "GdkX11Display";
+    if !is_x11 {
+        return Err(String::from(
+            "the window class can only be changed on X11 (see --class-from-cwd)",
+        ));
+    }
+
+    // Instance and class, both null terminated.
+    let data = format!("{}\0{}\0", name, name);
+    gdk::property_change(
+        &gdk_window,
+        &gdk::Atom::intern("WM_CLASS"),
+        &gdk::Atom::intern("STRING"),
+        8,
+        gdk::PropMode::Replace,
+        gdk::ChangeData::UChars(data.as_bytes()),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("gnvim"), "gnvim");
+        assert_eq!(sanitize("My Project"), "my-project");
+        assert_eq!(sanitize("foo.bar_baz"), "foo-bar_baz");
+        assert_eq!(sanitize("2048"), "_2048");
+        assert_eq!(sanitize("..."), "none");
+        assert_eq!(sanitize("äö"), "none");
+    }
+}
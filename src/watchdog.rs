@@ -0,0 +1,272 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use nvim_bridge::RedrawEvent;
+use paths;
+use rpc_trace::{as_micros, RpcTrace};
+
+/// How many of the latest redraw events are kept for the trace.
+const RECENT_EVENTS: usize = 256;
+/// How many of the latest rpc messages are written to the report.
+const RECENT_RPC: usize = 256;
+
+/// Kind of a stall detected by the `Watchdog`.
+#[derive(Debug, PartialEq)]
+pub enum Stall {
+    /// Redraw events have been received from nvim, but no `flush` has
+    /// followed them.
+    Flush,
+    /// Messages have been passed to the UI thread, but it hasn't handled
+    /// them.
+    Ui,
+}
+
+impl fmt::Display for Stall {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stall::Flush => write!(fmt, "redraw events without a flush"),
+            Stall::Ui => write!(fmt, "UI thread not handling events"),
+        }
+    }
+}
+
+/// Watchdog keeps track of the redraw events coming from nvim and notices
+/// when either nvim or the UI (thread) seems to be stuck.
+pub struct Watchdog {
+    /// How long we'll wait until a stall is reported.
+    timeout: Duration,
+    /// When the first redraw event after the latest flush was received.
+    redraw_since: Option<Instant>,
+    /// When the UI thread fell behind in handling our messages.
+    ui_behind_since: Option<Instant>,
+    /// Flags to ensure that we report each stall only once.
+    flush_reported: bool,
+    ui_reported: bool,
+    /// Names of the latest redraw events.
+    recent: VecDeque<String>,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Watchdog {
+            timeout,
+            redraw_since: None,
+            ui_behind_since: None,
+            flush_reported: false,
+            ui_reported: false,
+            recent: VecDeque::with_capacity(RECENT_EVENTS),
+        }
+    }
+
+    /// Records a redraw event received at `now`.
+    pub fn redraw_event(&mut self, event: &RedrawEvent, now: Instant) {
+        if self.recent.len() == RECENT_EVENTS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(event.to_string());
+
+        match event {
+            RedrawEvent::Flush() => {
+                self.redraw_since = None;
+                self.flush_reported = false;
+            }
+            _ => {
+                if self.redraw_since.is_none() {
+                    self.redraw_since = Some(now);
+                }
+            }
+        }
+    }
+
+    /// Records if the UI thread is `behind` in handling our messages at `now`.
+    pub fn ui_progress(&mut self, behind: bool, now: Instant) {
+        if !behind {
+            self.ui_behind_since = None;
+            self.ui_reported = false;
+        } else if self.ui_behind_since.is_none() {
+            self.ui_behind_since = Some(now);
+        }
+    }
+
+    /// Checks if there is a stall to be reported at `now`. Each stall is
+    /// reported only once.
+    pub fn check(&mut self, now: Instant) -> Option<Stall> {
+        if let Some(since) = self.ui_behind_since {
+            if !self.ui_reported && now.duration_since(since) >= self.timeout {
+                self.ui_reported = true;
+                return Some(Stall::Ui);
+            }
+        }
+
+        if let Some(since) = self.redraw_since {
+            if !self.flush_reported && now.duration_since(since) >= self.timeout
+            {
+                self.flush_reported = true;
+                return Some(Stall::Flush);
+            }
+        }
+
+        None
+    }
+
+    /// Returns the latest redraw events, one per line.
+    pub fn trace(&self) -> String {
+        let mut trace = String::new();
+        for name in self.recent.iter() {
+            trace.push_str(name);
+            trace.push('\n');
+        }
+        trace
+    }
+}
+
+/// Returns the latest messages of the rpc trace (see `RpcTrace`) for a
+/// stall report, one per line.
+pub fn rpc_report(trace: &Mutex<RpcTrace>) -> String {
+    // Don't get stuck ourselves, if the stuck thread holds the lock.
+    let trace = match trace.try_lock() {
+        Ok(trace) => trace,
+        Err(_) => return String::from("(rpc trace is locked)\n"),
+    };
+    if !trace.is_enabled() {
+        return String::from(
+            "(rpc trace is disabled, enable it with --rpc-trace)\n",
+        );
+    }
+
+    let entries: Vec<_> = trace.entries().collect();
+    let skip = entries.len().saturating_sub(RECENT_RPC);
+
+    let mut report = String::new();
+    for e in entries.into_iter().skip(skip) {
+        report.push_str(&format!(
+            "{}us {} {} {} bytes",
+            as_micros(e.time),
+            e.kind,
+            e.method,
+            e.size
+        ));
+        if let Some(latency) = e.latency {
+            report.push_str(&format!(", {}us latency", as_micros(latency)));
+        }
+        report.push('\n');
+    }
+    report
+}
+
+/// Writes a stall report to gnvim's state directory, with the latest
+/// redraw events (`trace`) and rpc messages (`rpc`, see `rpc_report`).
+/// Returns the path of the written file.
+pub fn write_report(
+    stall: &Stall,
+    trace: &str,
+    rpc: &str,
+    grid: Option<&str>,
+) -> io::Result<PathBuf> {
+    let dir = paths::state_dir().ok_or_else(paths::no_state_dir)?;
+
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("watchdog-{}.txt", secs));
+
+    let mut f = File::create(&path)?;
+    writeln!(f, "Stall: {}", stall)?;
+    writeln!(f, "\nLatest redraw events (oldest first):\n{}", trace)?;
+    writeln!(f, "Latest rpc messages (oldest first):\n{}", rpc)?;
+    if let Some(grid) = grid {
+        writeln!(f, "Grid contents:\n{}", grid)?;
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rpc_trace::Kind;
+
+    #[test]
+    fn test_flush_stall() {
+        let start = Instant::now();
+        let mut wd = Watchdog::new(Duration::from_secs(5));
+
+        wd.redraw_event(&RedrawEvent::SetBusy(true), start);
+        assert_eq!(wd.check(start + Duration::from_secs(1)), None);
+        assert_eq!(
+            wd.check(start + Duration::from_secs(5)),
+            Some(Stall::Flush)
+        );
+        // Same stall is only reported once.
+        assert_eq!(wd.check(start + Duration::from_secs(6)), None);
+
+        // Flush resets the watchdog.
+        wd.redraw_event(&RedrawEvent::Flush(), start + Duration::from_secs(7));
+        assert_eq!(wd.check(start + Duration::from_secs(20)), None);
+    }
+
+    #[test]
+    fn test_ui_stall() {
+        let start = Instant::now();
+        let mut wd = Watchdog::new(Duration::from_secs(5));
+
+        wd.ui_progress(true, start);
+        wd.ui_progress(true, start + Duration::from_secs(4));
+        assert_eq!(wd.check(start + Duration::from_secs(4)), None);
+        assert_eq!(wd.check(start + Duration::from_secs(5)), Some(Stall::Ui));
+        assert_eq!(wd.check(start + Duration::from_secs(6)), None);
+
+        wd.ui_progress(false, start + Duration::from_secs(7));
+        assert_eq!(wd.check(start + Duration::from_secs(20)), None);
+    }
+
+    #[test]
+    fn test_trace() {
+        let now = Instant::now();
+        let mut wd = Watchdog::new(Duration::from_secs(5));
+
+        for _ in 0..RECENT_EVENTS + 10 {
+            wd.redraw_event(&RedrawEvent::SetBusy(false), now);
+        }
+        wd.redraw_event(&RedrawEvent::Flush(), now);
+
+        let trace = wd.trace();
+        assert_eq!(trace.lines().count(), RECENT_EVENTS);
+        assert_eq!(trace.lines().last(), Some("Flush"));
+    }
+
+    #[test]
+    fn test_rpc_report() {
+        let trace = Mutex::new(RpcTrace::new(false));
+        assert!(rpc_report(&trace).contains("disabled"));
+
+        trace.lock().unwrap().set_enabled(true);
+        for _ in 0..RECENT_RPC + 10 {
+            trace.lock().unwrap().record(
+                Kind::Notification,
+                "redraw",
+                &[],
+                None,
+            );
+        }
+        trace.lock().unwrap().record(
+            Kind::Response,
+            "Gnvim",
+            &[],
+            Some(Duration::from_millis(2)),
+        );
+
+        let report = rpc_report(&trace);
+        assert_eq!(report.lines().count(), RECENT_RPC);
+        let last = report.lines().last().unwrap();
+        assert!(last.ends_with("response Gnvim 0 bytes, 2000us latency"));
+    }
+}